@@ -0,0 +1,214 @@
+//! Animated image exports, for short loops that are easier to share than videos. Every frame is
+//! rendered into memory first, so these are meant for animations of a few hundred small frames
+//! rather than long ones at full resolution. Animated WebPs are encoded by ffmpeg, as videos are,
+//! in the `video` module.
+//!
+//! APNGs keep every frame exactly as rendered, alpha included, and loop forever. Older viewers
+//! show only the first frame.
+//!
+//! GIFs hold at most 256 colors. A single palette is chosen for the whole animation with NeuQuant,
+//! from pixels sampled evenly across all of its frames so that colors don't flicker from one frame
+//! to the next, and each frame is dithered onto it with Floyd-Steinberg error diffusion to hide
+//! the banding. GIF frame delays are whole hundredths of a second, so the delays alternate to keep
+//! the average rate, and most viewers play anything faster than 50 frames per second slower than
+//! that. GIFs loop forever and are always opaque.
+
+use color_quant::NeuQuant;
+
+use gif::SetParameter;
+
+use image::imageops::colorops::{self, ColorMap};
+use image::{Rgba, RgbaImage};
+
+use crate::atomic::write_atomic;
+use crate::{ImgDimensions, JuliaContext, JuliaData, JuliaExportError, Progress};
+
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+/// The most pixels the GIF palette is chosen from. Larger animations are sampled evenly.
+const PALETTE_SAMPLES: usize = 1 << 22;
+
+/// How thoroughly NeuQuant learns the palette, from 1 for the best palette to 30 for the fastest.
+const PALETTE_QUALITY: i32 = 10;
+
+/// A format of animated images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnimatedFormat {
+    Gif,
+    Apng,
+}
+
+impl AnimatedFormat {
+    /// The format named by the extension of `path`, if any. Both `.apng` and `.png` name APNGs.
+    pub fn from_path(path: &Path) -> Option<AnimatedFormat> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "gif" => Some(AnimatedFormat::Gif),
+            "apng" | "png" => Some(AnimatedFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+impl Display for AnimatedFormat {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            AnimatedFormat::Gif => "gif",
+            AnimatedFormat::Apng => "apng",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Renders `frames` at `dims` and writes them to `filename` as an animated image in `format`,
+/// playing at `fps` frames per second. The file is replaced atomically. `progress` is called as
+/// each frame is rendered.
+pub fn export_animated<F>(
+    context: &JuliaContext,
+    dims: ImgDimensions,
+    frames: &[JuliaData],
+    filename: &Path,
+    format: AnimatedFormat,
+    fps: f32,
+    mut progress: F,
+) -> Result<(), JuliaExportError>
+where
+    F: FnMut(Progress),
+{
+    let mut pixels = Vec::with_capacity(frames.len());
+    for (i, data) in frames.iter().enumerate() {
+        pixels.push(context.render_rgba(dims, data)?);
+
+        progress(Progress {
+            done: i as u32 + 1,
+            total: frames.len() as u32,
+        });
+    }
+
+    match format {
+        AnimatedFormat::Gif => write_gif(filename, dims, &pixels, fps)?,
+        AnimatedFormat::Apng => write_apng(filename, dims, &pixels, fps)?,
+    }
+    Ok(())
+}
+
+/// The delays between frames, in hundredths of a second, that keep to `fps` on average: each is
+/// the time from the rounded start of its frame to the rounded start of the next.
+fn delays(count: usize, fps: f32) -> Vec<u16> {
+    let start = |i: usize| (i as f64 * 100.0 / fps.max(0.01) as f64).round();
+    (0..count)
+        .map(|i| (start(i + 1) - start(i)).max(1.0).min(u16::MAX as f64) as u16)
+        .collect()
+}
+
+/// Writes 8-bit RGBA frames of `dims` as a looping GIF, dithered onto one palette.
+fn write_gif(filename: &Path, dims: ImgDimensions, frames: &[Vec<u8>], fps: f32) -> io::Result<()> {
+    if dims.width > u16::MAX as u32 || dims.height > u16::MAX as u32 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "GIFs can't be larger than 65535 pixels along either side",
+        ));
+    }
+
+    let quantizer = Palette::learn(frames);
+    let palette = quantizer.0.color_map_rgb();
+
+    write_atomic(filename, |path| {
+        let out = BufWriter::new(File::create(path)?);
+        let (width, height) = (dims.width as u16, dims.height as u16);
+        let mut encoder = gif::Encoder::new(out, width, height, &palette)?;
+        encoder.set(gif::Repeat::Infinite)?;
+
+        for (rgba, delay) in frames.iter().zip(delays(frames.len(), fps)) {
+            let mut image = RgbaImage::from_raw(dims.width, dims.height, rgba.clone())
+                .expect("frame has the wrong size");
+            // Fully opaque, so that transparent pixels don't take palette entries of their own
+            for pixel in image.pixels_mut() {
+                pixel[3] = 255;
+            }
+            colorops::dither(&mut image, &quantizer);
+            let indices = colorops::index_colors(&image, &quantizer);
+
+            let frame = gif::Frame {
+                width,
+                height,
+                delay,
+                buffer: Cow::Owned(indices.into_raw()),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Writes 8-bit RGBA frames of `dims` as a looping APNG. Frame delays are fractions of a second,
+/// so hundredths of a frame per second are kept.
+fn write_apng(
+    filename: &Path,
+    dims: ImgDimensions,
+    frames: &[Vec<u8>],
+    fps: f32,
+) -> io::Result<()> {
+    let rate = (fps * 100.0).round().max(1.0).min(u16::MAX as f32) as u16;
+
+    write_atomic(filename, |path| {
+        let out = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(out, dims.width, dims.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        // No limit on the number of plays
+        encoder
+            .set_animated(frames.len() as u32, 0)
+            .map_err(png_err)?;
+        encoder.set_frame_delay(100, rate).map_err(png_err)?;
+
+        let mut writer = encoder.write_header().map_err(png_err)?;
+        for rgba in frames {
+            writer.write_image_data(rgba).map_err(png_err)?;
+        }
+        writer.finish().map_err(png_err)
+    })
+}
+
+fn png_err(err: png::EncodingError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
+
+/// A GIF palette of 256 colors.
+struct Palette(NeuQuant);
+
+impl Palette {
+    /// The palette learned from pixels sampled evenly across every frame.
+    fn learn(frames: &[Vec<u8>]) -> Palette {
+        let pixels: usize = frames.iter().map(|f| f.len() / 4).sum();
+        let step = (pixels / PALETTE_SAMPLES).max(1);
+
+        let samples: Vec<u8> = frames
+            .iter()
+            .flat_map(|f| f.chunks_exact(4))
+            .step_by(step)
+            .flat_map(|p| vec![p[0], p[1], p[2], 255])
+            .collect();
+
+        Palette(NeuQuant::new(PALETTE_QUALITY, 256, &samples))
+    }
+}
+
+impl ColorMap for Palette {
+    type Color = Rgba<u8>;
+
+    fn index_of(&self, color: &Rgba<u8>) -> usize {
+        self.0.index_of(&color.0)
+    }
+
+    fn map_color(&self, color: &mut Rgba<u8>) {
+        self.0.map_pixel(&mut color.0)
+    }
+}
@@ -0,0 +1,637 @@
+//! Animations: views that change over time, rendered as a numbered sequence of frames to be put
+//! together by an external encoder such as ffmpeg. Frames are taken at a fixed rate from the start
+//! of an animation to its end, and numbered from 1. Their file names are given by a pattern in
+//! which `%d` stands for the frame number, or `%05d` for the number padded with zeros to five
+//! digits, as in ffmpeg's own patterns:
+//!
+//! ```text
+//! ffmpeg -framerate 30 -i frames/frame_%05d.png out.mp4
+//! ```
+//!
+//! A range of frame numbers can be rendered on its own, so that a long animation can be split over
+//! several runs, or one that failed picked up where it stopped.
+
+use gramit::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::path::CPath;
+use crate::{ExportJob, ImgDimensions, JuliaData};
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A view that changes over time.
+pub trait Animation {
+    /// The length of the animation, in seconds.
+    fn duration(&self) -> f32;
+
+    /// The view `time` seconds from the start, as changed from `base`.
+    fn view(&self, base: &JuliaData, time: f32) -> JuliaData;
+}
+
+/// A recorded path moves `c`, keeping the pace of the recording.
+impl Animation for CPath {
+    fn duration(&self) -> f32 {
+        CPath::duration(self)
+    }
+
+    fn view(&self, base: &JuliaData, time: f32) -> JuliaData {
+        JuliaData {
+            c: self.at(time),
+            ..*base
+        }
+    }
+}
+
+/// How an animated parameter moves between two keyframes: a function from the fraction of the time
+/// between them that has passed to the fraction of the way the parameter has moved, both from 0
+/// to 1. Serialized as the string it is parsed from.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Easing {
+    /// A steady pace.
+    Linear,
+    /// Speeding up from rest and slowing down to rest, along `3t² - 2t³`.
+    Smoothstep,
+    /// Speeding up and slowing down exponentially, which makes for much sharper starts and stops
+    /// than `Smoothstep`.
+    Exponential,
+    /// The curve of a cubic Bezier from `(0, 0)` to `(1, 1)` through the control points `(x1, y1)`
+    /// and `(x2, y2)`, as CSS's `cubic-bezier(x1, y1, x2, y2)`. The `x`s are kept between 0 and 1.
+    CubicBezier(f32, f32, f32, f32),
+}
+
+impl Easing {
+    /// The fraction of the way moved when the fraction `t` of the time has passed.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.max(0.0).min(1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::Smoothstep => t * t * (3.0 - 2.0 * t),
+            Easing::Exponential => {
+                if t <= 0.0 || t >= 1.0 {
+                    t
+                } else if t < 0.5 {
+                    2f32.powf(20.0 * t - 10.0) / 2.0
+                } else {
+                    1.0 - 2f32.powf(10.0 - 20.0 * t) / 2.0
+                }
+            }
+            Easing::CubicBezier(x1, y1, x2, y2) => {
+                let (x1, x2) = (x1.max(0.0).min(1.0), x2.max(0.0).min(1.0));
+                let bezier = |a: f32, b: f32, s: f32| {
+                    let r = 1.0 - s;
+                    3.0 * r * r * s * a + 3.0 * r * s * s * b + s * s * s
+                };
+
+                // With the `x`s between 0 and 1, `x` only grows along the curve, so the point at
+                // `x = t` can be found by bisection
+                let (mut low, mut high) = (0.0, 1.0);
+                for _ in 0..32 {
+                    let mid = (low + high) / 2.0;
+                    if bezier(x1, x2, mid) < t {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                bezier(y1, y2, (low + high) / 2.0)
+            }
+        }
+    }
+}
+
+impl Default for Easing {
+    fn default() -> Easing {
+        Easing::Linear
+    }
+}
+
+impl From<Easing> for String {
+    fn from(easing: Easing) -> String {
+        easing.to_string()
+    }
+}
+
+impl TryFrom<String> for Easing {
+    type Error = ParseEasingError;
+
+    fn try_from(s: String) -> Result<Easing, ParseEasingError> {
+        s.parse()
+    }
+}
+
+/// Formats the easing as it is parsed: `linear`, `smoothstep`, `exponential` or
+/// `cubic-bezier(x1,y1,x2,y2)`.
+impl Display for Easing {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Easing::Linear => write!(f, "linear"),
+            Easing::Smoothstep => write!(f, "smoothstep"),
+            Easing::Exponential => write!(f, "exponential"),
+            Easing::CubicBezier(x1, y1, x2, y2) => {
+                write!(f, "cubic-bezier({},{},{},{})", x1, y1, x2, y2)
+            }
+        }
+    }
+}
+
+impl FromStr for Easing {
+    type Err = ParseEasingError;
+
+    fn from_str(s: &str) -> Result<Easing, ParseEasingError> {
+        match s.trim() {
+            "linear" => Ok(Easing::Linear),
+            "smoothstep" => Ok(Easing::Smoothstep),
+            "exponential" | "expo" => Ok(Easing::Exponential),
+            s if s.starts_with("cubic-bezier(") && s.ends_with(')') => {
+                let points = s["cubic-bezier(".len()..s.len() - 1]
+                    .split(',')
+                    .map(|n| n.trim().parse())
+                    .collect::<Result<Vec<f32>, _>>()
+                    .map_err(|_| ParseEasingError)?;
+
+                match points[..] {
+                    [x1, y1, x2, y2] => Ok(Easing::CubicBezier(x1, y1, x2, y2)),
+                    _ => Err(ParseEasingError),
+                }
+            }
+            _ => Err(ParseEasingError),
+        }
+    }
+}
+
+/// An easing that isn't `linear`, `smoothstep`, `exponential` or `cubic-bezier(x1,y1,x2,y2)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseEasingError;
+
+impl Display for ParseEasingError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "expected linear, smoothstep, exponential or cubic-bezier(x1,y1,x2,y2)"
+        )
+    }
+}
+
+impl std::error::Error for ParseEasingError {}
+
+/// A zoom toward a point, which stays at the center. The extent shrinks exponentially, so that
+/// every frame zooms in by the same factor, and iterations are added as the zoom deepens, since
+/// finer detail takes more of them to resolve. A `to_extent` larger than `from_extent` zooms out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Zoom {
+    pub target: Vec2,
+    /// The extent of the shorter image dimension at the start.
+    pub from_extent: f32,
+    /// The extent of the shorter image dimension at the end.
+    pub to_extent: f32,
+    /// The length of the zoom, in seconds.
+    pub duration: f32,
+    /// The iterations added to those of the base view for every tenfold zoom in.
+    pub iters_per_decade: f32,
+    /// How the zoom speeds up and slows down. Linear keeps the same pace all the way in.
+    pub easing: Easing,
+}
+
+impl Zoom {
+    /// The extent of the shorter image dimension `time` seconds from the start.
+    pub fn extent(&self, time: f32) -> f32 {
+        let t = if self.duration > 0.0 {
+            self.easing.apply(time / self.duration)
+        } else {
+            1.0
+        };
+        self.from_extent * (self.to_extent / self.from_extent).powf(t)
+    }
+}
+
+impl Animation for Zoom {
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn view(&self, base: &JuliaData, time: f32) -> JuliaData {
+        let extent = self.extent(time);
+        let decades = (self.from_extent / extent).log10().max(0.0);
+        let iters = base.iters as f32 + self.iters_per_decade * decades;
+
+        // The longer dimension keeps its share of the extents of the base view
+        let shorter = f32::min(base.extents.x, base.extents.y);
+        JuliaData {
+            center: self.target,
+            extents: (extent / shorter) * base.extents,
+            iters: iters.round().max(1.0) as u32,
+            ..*base
+        }
+    }
+}
+
+/// `c` moving around a circle about the origin, `c = r·e^(iθ)`, with the angle `θ` sweeping at a
+/// steady rate: the classic loop of a Julia set morphing through the shapes around the Mandelbrot
+/// set. Angles are in degrees, counterclockwise from the positive real axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircleOrbit {
+    /// The distance of `c` from the origin.
+    pub radius: f32,
+    /// The angle of `c` at the start.
+    pub from_angle: f32,
+    /// The angle `c` sweeps toward.
+    pub to_angle: f32,
+    /// The time `c` takes to sweep from `from_angle` to `to_angle`, in seconds.
+    pub sweep: f32,
+    /// The length of the animation, in seconds. Ending one frame short of a full turn makes the
+    /// animation loop without repeating a frame.
+    pub duration: f32,
+    /// How the sweep speeds up and slows down.
+    pub easing: Easing,
+}
+
+impl CircleOrbit {
+    /// The value of `c` `time` seconds from the start.
+    pub fn at(&self, time: f32) -> Vec2 {
+        let t = if self.sweep > 0.0 {
+            self.easing.apply(time / self.sweep)
+        } else {
+            1.0
+        };
+        let angle = (self.from_angle + (self.to_angle - self.from_angle) * t).to_radians();
+        vec2!(self.radius * angle.cos(), self.radius * angle.sin())
+    }
+}
+
+impl Animation for CircleOrbit {
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn view(&self, base: &JuliaData, time: f32) -> JuliaData {
+        JuliaData {
+            c: self.at(time),
+            ..*base
+        }
+    }
+}
+
+/// A point a waypoint path passes through, with the control points that shape the curve into and
+/// out of it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Waypoint {
+    /// The time `c` passes through the waypoint, in seconds.
+    pub time: f32,
+    #[serde(with = "point")]
+    pub c: Vec2,
+    /// The control point of the curve from the previous waypoint.
+    #[serde(rename = "in", default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "optional_point")]
+    pub control_in: Option<Vec2>,
+    /// The control point of the curve to the next waypoint.
+    #[serde(rename = "out", default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "optional_point")]
+    pub control_out: Option<Vec2>,
+    /// How `c` speeds up and slows down along the curve to the next waypoint.
+    #[serde(default, skip_serializing_if = "is_linear")]
+    pub easing: Easing,
+}
+
+pub(crate) fn is_linear(easing: &Easing) -> bool {
+    *easing == Easing::Linear
+}
+
+/// Complex numbers serialized as arrays of their real and imaginary parts.
+pub(crate) mod point {
+    use gramit::Vec2;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(p: &Vec2, serializer: S) -> Result<S::Ok, S::Error> {
+        [p.x, p.y].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec2, D::Error> {
+        let [x, y] = <[f32; 2]>::deserialize(deserializer)?;
+        Ok(vec2!(x, y))
+    }
+}
+
+/// Optional complex numbers, serialized as `point` serializes them.
+pub(crate) mod optional_point {
+    use gramit::Vec2;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(p: &Option<Vec2>, serializer: S) -> Result<S::Ok, S::Error> {
+        p.map(|p| [p.x, p.y]).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Vec2>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let p = Option::<[f32; 2]>::deserialize(deserializer)?;
+        Ok(p.map(|[x, y]| vec2!(x, y)))
+    }
+}
+
+/// `c` following a piecewise cubic Bezier curve through waypoints, such as one tracing the
+/// boundary of the main cardioid. Each piece runs from one waypoint to the next, shaped by the
+/// control point out of the first and into the second. A missing control point lies a third of
+/// the way along the straight line between the two, so a piece without any is a straight line,
+/// crossed at a steady pace unless its first waypoint eases it. `c` holds at the first waypoint
+/// until its time. Serialized as the list of its waypoints.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<Waypoint>", into = "Vec<Waypoint>")]
+pub struct WaypointPath {
+    waypoints: Vec<Waypoint>,
+}
+
+impl WaypointPath {
+    /// The path through `waypoints`, in order of their times.
+    pub fn new(mut waypoints: Vec<Waypoint>) -> WaypointPath {
+        waypoints.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(Ordering::Equal));
+        WaypointPath { waypoints }
+    }
+
+    pub fn waypoints(&self) -> &[Waypoint] {
+        &self.waypoints
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waypoints.is_empty()
+    }
+
+    /// The value of `c` at the given time, held constant before the first waypoint and after the
+    /// last.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path is empty.
+    pub fn at(&self, time: f32) -> Vec2 {
+        let next = self.waypoints.iter().position(|w| w.time > time);
+
+        match next {
+            Some(0) => self.waypoints[0].c,
+            Some(i) => {
+                let (a, b) = (self.waypoints[i - 1], self.waypoints[i]);
+                let t = a.easing.apply((time - a.time) / (b.time - a.time));
+                let p1 = a.control_out.unwrap_or(a.c + (1.0 / 3.0) * (b.c - a.c));
+                let p2 = b.control_in.unwrap_or(b.c + (1.0 / 3.0) * (a.c - b.c));
+
+                let s = 1.0 - t;
+                (s * s * s) * a.c
+                    + (3.0 * s * s * t) * p1
+                    + (3.0 * s * t * t) * p2
+                    + (t * t * t) * b.c
+            }
+            None => self.waypoints.last().expect("the path is empty").c,
+        }
+    }
+}
+
+impl From<Vec<Waypoint>> for WaypointPath {
+    fn from(waypoints: Vec<Waypoint>) -> WaypointPath {
+        WaypointPath::new(waypoints)
+    }
+}
+
+impl From<WaypointPath> for Vec<Waypoint> {
+    fn from(path: WaypointPath) -> Vec<Waypoint> {
+        path.waypoints
+    }
+}
+
+/// A waypoint path moves `c`, from the start of the animation to the time of its last waypoint.
+impl Animation for WaypointPath {
+    fn duration(&self) -> f32 {
+        self.waypoints.last().map_or(0.0, |w| w.time.max(0.0))
+    }
+
+    fn view(&self, base: &JuliaData, time: f32) -> JuliaData {
+        if self.is_empty() {
+            return *base;
+        }
+
+        JuliaData {
+            c: self.at(time),
+            ..*base
+        }
+    }
+}
+
+/// The file names of numbered frames: the text around a `%d` or `%0Nd` that stands for the frame
+/// number.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FramePattern {
+    prefix: String,
+    width: usize,
+    suffix: String,
+}
+
+impl FramePattern {
+    /// The file name of frame `number`.
+    pub fn frame(&self, number: u32) -> String {
+        format!(
+            "{}{:0width$}{}",
+            self.prefix,
+            number,
+            self.suffix,
+            width = self.width
+        )
+    }
+}
+
+/// `frame_%05d.png`.
+impl Default for FramePattern {
+    fn default() -> FramePattern {
+        FramePattern {
+            prefix: String::from("frame_"),
+            width: 5,
+            suffix: String::from(".png"),
+        }
+    }
+}
+
+impl Display for FramePattern {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.width > 0 {
+            write!(f, "{}%0{}d{}", self.prefix, self.width, self.suffix)
+        } else {
+            write!(f, "{}%d{}", self.prefix, self.suffix)
+        }
+    }
+}
+
+impl FromStr for FramePattern {
+    type Err = ParseFramePatternError;
+
+    fn from_str(s: &str) -> Result<FramePattern, ParseFramePatternError> {
+        let percent = s.find('%').ok_or(ParseFramePatternError)?;
+        let rest = &s[percent + 1..];
+        let d = rest.find('d').ok_or(ParseFramePatternError)?;
+
+        let width = match &rest[..d] {
+            "" => 0,
+            digits if digits.starts_with('0') => {
+                digits.parse().map_err(|_| ParseFramePatternError)?
+            }
+            _ => return Err(ParseFramePatternError),
+        };
+
+        let suffix = &rest[d + 1..];
+        if suffix.contains('%') {
+            return Err(ParseFramePatternError);
+        }
+
+        Ok(FramePattern {
+            prefix: s[..percent].to_string(),
+            width,
+            suffix: suffix.to_string(),
+        })
+    }
+}
+
+/// A frame pattern without exactly one `%d` or `%0Nd`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseFramePatternError;
+
+impl Display for ParseFramePatternError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "expected one %d or %0Nd for the frame number")
+    }
+}
+
+impl std::error::Error for ParseFramePatternError {}
+
+/// The frame numbers to render, both included, given as `FIRST..LAST`. Either end may be left out
+/// to start at the first frame or stop at the last, and a single number renders one frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameRange {
+    pub first: u32,
+    pub last: Option<u32>,
+}
+
+impl FrameRange {
+    /// The frame numbers of the range among `count` frames.
+    fn frames(&self, count: u32) -> std::ops::RangeInclusive<u32> {
+        let last = self.last.map_or(count, |last| last.min(count));
+        self.first.max(1)..=last
+    }
+}
+
+/// Every frame.
+impl Default for FrameRange {
+    fn default() -> FrameRange {
+        FrameRange {
+            first: 1,
+            last: None,
+        }
+    }
+}
+
+impl FromStr for FrameRange {
+    type Err = ParseFrameRangeError;
+
+    fn from_str(s: &str) -> Result<FrameRange, ParseFrameRangeError> {
+        let number = |s: &str| s.trim().parse().map_err(|_| ParseFrameRangeError);
+
+        match s.find("..") {
+            Some(dots) => {
+                let (first, last) = (s[..dots].trim(), s[dots + 2..].trim());
+                Ok(FrameRange {
+                    first: if first.is_empty() { 1 } else { number(first)? },
+                    last: if last.is_empty() {
+                        None
+                    } else {
+                        Some(number(last)?)
+                    },
+                })
+            }
+            None => {
+                let frame = number(s)?;
+                Ok(FrameRange {
+                    first: frame,
+                    last: Some(frame),
+                })
+            }
+        }
+    }
+}
+
+/// A malformed frame range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseFrameRangeError;
+
+impl Display for ParseFrameRangeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "expected FIRST..LAST, FIRST.., ..LAST or a frame number")
+    }
+}
+
+impl std::error::Error for ParseFrameRangeError {}
+
+/// Where and how often the frames of an animation are written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameSequence {
+    /// The directory the frames are written to.
+    pub dir: PathBuf,
+    pub pattern: FramePattern,
+    /// Frames per second of animation time.
+    pub fps: f32,
+    pub range: FrameRange,
+}
+
+impl FrameSequence {
+    /// The number of frames of an animation `duration` seconds long: one at the start and one
+    /// every `1 / fps` seconds after it, up to the end.
+    pub fn frame_count(&self, duration: f32) -> u32 {
+        // Allow for rounding, so that a duration of a whole number of frames gets all of them
+        (duration.max(0.0) * self.fps + 1e-3).floor() as u32 + 1
+    }
+
+    /// The file frame `number` is written to.
+    pub fn path(&self, number: u32) -> PathBuf {
+        self.dir.join(self.pattern.frame(number))
+    }
+
+    /// The numbers of the frames of `animation` in the range, each with the view at its time as
+    /// changed from `base`.
+    pub fn frames<A>(&self, animation: &A, base: &JuliaData) -> Vec<(u32, JuliaData)>
+    where
+        A: Animation + ?Sized,
+    {
+        let count = self.frame_count(animation.duration());
+
+        self.range
+            .frames(count)
+            .map(|number| {
+                let time = (number - 1) as f32 / self.fps;
+                (number, animation.view(base, time))
+            })
+            .collect()
+    }
+
+    /// The jobs exporting the frames of `animation` in the range as images of `dims`.
+    pub fn jobs<A>(
+        &self,
+        animation: &A,
+        base: &JuliaData,
+        dims: ImgDimensions,
+        potential: bool,
+    ) -> Vec<ExportJob>
+    where
+        A: Animation + ?Sized,
+    {
+        self.frames(animation, base)
+            .into_iter()
+            .map(|(number, data)| ExportJob {
+                dims,
+                data,
+                filename: self.path(number),
+                potential,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,47 @@
+//! Crash-safe file replacement. Every file julia writes, exports included, goes through
+//! `write_atomic`, which library users can call too for files of their own.
+
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Writes `path` by handing `write` a temporary path in the same directory and renaming the
+/// temporary file over `path` once `write` succeeds and the data is on disk. If `write` fails, the
+/// temporary file is removed and `path` is left untouched, so an interrupted write never leaves a
+/// truncated file behind under the final name.
+///
+/// The temporary file keeps the extension of `path`, so writers that pick a format from the file
+/// name behave the same as they would for `path` itself.
+pub fn write_atomic<F>(path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&Path) -> io::Result<()>,
+{
+    let tmp_path = temp_path(path)?;
+
+    let res = write(&tmp_path).and_then(|()| {
+        OpenOptions::new().write(true).open(&tmp_path)?.sync_all()?;
+        fs::rename(&tmp_path, path)
+    });
+
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    res
+}
+
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file path", path.display()),
+        )
+    })?;
+
+    let mut tmp_name = OsString::from(format!(".julia-{}-", process::id()));
+    tmp_name.push(file_name);
+
+    Ok(path.with_file_name(tmp_name))
+}
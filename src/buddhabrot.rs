@@ -0,0 +1,326 @@
+//! Buddhabrot renders, which show how often escaping orbits pass through each pixel rather than
+//! how quickly each pixel escapes, and Nebulabrots, which combine three Buddhabrots made with
+//! different iteration limits.
+
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, DeviceLocalBuffer, ImmutableBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, StorageImage};
+use vulkano::sync::GpuFuture;
+
+use crate::shaders::{
+    julia_buddhabrot_comp, julia_buddhabrot_tonemap_comp, julia_nebulabrot_tonemap_comp,
+};
+use crate::{ImgDimensions, JuliaContext, JuliaData};
+
+use std::iter;
+use std::sync::Arc;
+
+/// Orbits traced by each invocation of the accumulation shader in one dispatch.
+pub(crate) const SAMPLES_PER_INVOCATION: u32 = 64;
+
+/// Invocations of the accumulation shader per dispatch. Accumulation is split into dispatches of
+/// this size, each waited on before the next, so that none of them runs long enough to trip the
+/// driver's watchdog.
+pub(crate) const INVOCATIONS_PER_DISPATCH: u32 = 64 * 1024;
+
+/// Settings for Buddhabrot renders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BuddhabrotOptions {
+    /// The number of orbits sampled per output pixel. Rounded up to a whole number of dispatches.
+    pub samples: u32,
+    /// Multiplies the normalized hit counts before they are mapped into the gradient.
+    pub exposure: f32,
+    /// The exponent applied to the normalized hit counts. Values below 1 bring out rarely visited
+    /// pixels.
+    pub gamma: f32,
+    /// Seeds the random sampling, so that renders can be repeated.
+    pub seed: u32,
+}
+
+impl Default for BuddhabrotOptions {
+    fn default() -> BuddhabrotOptions {
+        BuddhabrotOptions {
+            samples: 50,
+            exposure: 1.0,
+            gamma: 0.5,
+            seed: 0,
+        }
+    }
+}
+
+/// Renders the Buddhabrot of `data` at the given dimensions, returning linear RGBA pixels. If
+/// `data.nebulabrot` is set, renders a Nebulabrot instead.
+pub(crate) fn render(
+    dims: ImgDimensions,
+    data: &JuliaData,
+    options: &BuddhabrotOptions,
+    context: &JuliaContext,
+) -> Vec<u8> {
+    if let Some(iters) = data.nebulabrot {
+        return render_nebulabrot(dims, data, iters, options, context);
+    }
+
+    let hits = accumulate(dims, data, options, context);
+    tonemap(dims, data, hits, options, context)
+}
+
+/// Maps a buffer of hit counts, as made by `accumulate`, into the gradient of `data`. Returns
+/// linear RGBA pixels.
+pub(crate) fn tonemap(
+    dims: ImgDimensions,
+    data: &JuliaData,
+    hits: Arc<DeviceLocalBuffer<[u32]>>,
+    options: &BuddhabrotOptions,
+    context: &JuliaContext,
+) -> Vec<u8> {
+    let device = context.device();
+    let queue = context.export_queue();
+
+    let (uniform, upload) =
+        ImmutableBuffer::from_data(data.into_shader_data(), BufferUsage::all(), queue.clone())
+            .unwrap();
+
+    let tonemap = context.buddhabrot_tonemap_pipeline().clone();
+    let (image, output_buffer) = output_image(dims, context);
+    let tonemap_set = Arc::new(
+        PersistentDescriptorSet::start(tonemap.clone(), 0)
+            .add_image(image.clone())
+            .unwrap()
+            .add_buffer(uniform)
+            .unwrap()
+            .add_buffer(hits)
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+    let params = julia_buddhabrot_tonemap_comp::ty::Tonemap {
+        exposure: options.exposure,
+        gamma: options.gamma,
+    };
+
+    let command_buffer =
+        AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+            .unwrap()
+            .dispatch(
+                [dims.width / 8, dims.height / 8, 1],
+                tonemap,
+                tonemap_set,
+                params,
+            )
+            .unwrap()
+            .copy_image_to_buffer(image, output_buffer.clone())
+            .unwrap()
+            .build()
+            .unwrap();
+
+    upload
+        .then_execute(queue.clone(), command_buffer)
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    let img_contents = output_buffer.read().unwrap();
+    img_contents.to_vec()
+}
+
+/// Renders a Nebulabrot: three Buddhabrots of `data`, with the given iteration limits, in the red,
+/// green and blue channels. Returns linear RGBA pixels.
+fn render_nebulabrot(
+    dims: ImgDimensions,
+    data: &JuliaData,
+    iters: [u32; 3],
+    options: &BuddhabrotOptions,
+    context: &JuliaContext,
+) -> Vec<u8> {
+    let device = context.device();
+    let queue = context.export_queue();
+
+    let hits: Vec<_> = iters
+        .iter()
+        .map(|&iters| {
+            let data = JuliaData { iters, ..*data };
+            accumulate(dims, &data, options, context)
+        })
+        .collect();
+
+    let tonemap = context.nebulabrot_tonemap_pipeline().clone();
+    let (image, output_buffer) = output_image(dims, context);
+    let tonemap_set = Arc::new(
+        PersistentDescriptorSet::start(tonemap.clone(), 0)
+            .add_image(image.clone())
+            .unwrap()
+            .add_buffer(hits[0].clone())
+            .unwrap()
+            .add_buffer(hits[1].clone())
+            .unwrap()
+            .add_buffer(hits[2].clone())
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+    let params = julia_nebulabrot_tonemap_comp::ty::Tonemap {
+        exposure: options.exposure,
+        gamma: options.gamma,
+    };
+
+    AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+        .unwrap()
+        .dispatch(
+            [dims.width / 8, dims.height / 8, 1],
+            tonemap,
+            tonemap_set,
+            params,
+        )
+        .unwrap()
+        .copy_image_to_buffer(image, output_buffer.clone())
+        .unwrap()
+        .build()
+        .unwrap()
+        .execute(queue.clone())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    let img_contents = output_buffer.read().unwrap();
+    img_contents.to_vec()
+}
+
+/// Samples the orbits of `data` into a new buffer of hit counts, which holds the largest count
+/// followed by the count of every pixel.
+fn accumulate(
+    dims: ImgDimensions,
+    data: &JuliaData,
+    options: &BuddhabrotOptions,
+    context: &JuliaContext,
+) -> Arc<DeviceLocalBuffer<[u32]>> {
+    let device = context.device();
+    let queue = context.export_queue();
+
+    let (uniform, upload) =
+        ImmutableBuffer::from_data(data.into_shader_data(), BufferUsage::all(), queue.clone())
+            .unwrap();
+
+    let hits = hits_buffer(dims, context);
+
+    upload
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    let accumulate = context.buddhabrot_pipeline().clone();
+    let accumulate_set = Arc::new(
+        PersistentDescriptorSet::start(accumulate.clone(), 0)
+            .add_buffer(hits.clone())
+            .unwrap()
+            .add_buffer(uniform)
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+
+    for i in 0..dispatch_count(dims, options) {
+        let sampling = julia_buddhabrot_comp::ty::Sampling {
+            size: [dims.width, dims.height],
+            seed: options.seed.wrapping_add(i as u32),
+            samples: SAMPLES_PER_INVOCATION,
+        };
+
+        AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+            .unwrap()
+            .dispatch(
+                [INVOCATIONS_PER_DISPATCH / 64, 1, 1],
+                accumulate.clone(),
+                accumulate_set.clone(),
+                sampling,
+            )
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+    }
+
+    hits
+}
+
+/// The number of dispatches of `INVOCATIONS_PER_DISPATCH` invocations, each taking
+/// `SAMPLES_PER_INVOCATION` samples, needed for at least `options.samples` samples per pixel.
+pub(crate) fn dispatch_count(dims: ImgDimensions, options: &BuddhabrotOptions) -> u64 {
+    let total = u64::from(options.samples) * u64::from(dims.width * dims.height);
+    let per_dispatch = u64::from(SAMPLES_PER_INVOCATION * INVOCATIONS_PER_DISPATCH);
+    (total + per_dispatch - 1) / per_dispatch
+}
+
+/// A zeroed buffer of hit counts for an image of the given dimensions, with room for the largest
+/// count followed by the count of every pixel.
+pub(crate) fn hits_buffer(
+    dims: ImgDimensions,
+    context: &JuliaContext,
+) -> Arc<DeviceLocalBuffer<[u32]>> {
+    let device = context.device();
+    let queue = context.export_queue();
+
+    let hits = DeviceLocalBuffer::<[u32]>::array(
+        device.clone(),
+        (dims.width * dims.height) as usize + 1,
+        BufferUsage {
+            storage_buffer: true,
+            transfer_destination: true,
+            ..BufferUsage::none()
+        },
+        iter::once(queue.family()),
+    )
+    .unwrap();
+
+    AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+        .unwrap()
+        .fill_buffer(hits.clone(), 0)
+        .unwrap()
+        .build()
+        .unwrap()
+        .execute(queue.clone())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    hits
+}
+
+/// The image that a tonemapping pass writes to, and the buffer it's copied into for reading back.
+fn output_image(
+    dims: ImgDimensions,
+    context: &JuliaContext,
+) -> (Arc<StorageImage<Format>>, Arc<CpuAccessibleBuffer<[u8]>>) {
+    let image = StorageImage::new(
+        context.device().clone(),
+        Dimensions::Dim2d {
+            width: dims.width,
+            height: dims.height,
+        },
+        Format::R8G8B8A8Unorm,
+        Some(context.export_queue().family()),
+    )
+    .unwrap();
+
+    let output_buffer = CpuAccessibleBuffer::from_iter(
+        context.device().clone(),
+        BufferUsage::all(),
+        (0..dims.width * dims.height * 4).map(|_| 0u8),
+    )
+    .unwrap();
+
+    (image, output_buffer)
+}
@@ -0,0 +1,478 @@
+//! Periodic checkpoints of the interactive state, appended to a plain-text history file with one
+//! checkpoint per line.
+
+use gramit::Vec2;
+
+use crate::{
+    Channels, ColoringMode, FractalKind, Gradient, GradientStop, ImgDimensions, Interpolation,
+    JuliaData, Light, Relief, Tone, ToneCurve, DEFAULT_STRIPE_DENSITY,
+};
+
+use std::env;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A saved interface state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Checkpoint {
+    pub time: SystemTime,
+    pub data: JuliaData,
+    pub export_dimensions: ImgDimensions,
+}
+
+/// The history file that checkpoints are appended to, and how often the interface saves them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckpointLog {
+    path: PathBuf,
+    interval: Duration,
+}
+
+impl CheckpointLog {
+    pub fn new<P: Into<PathBuf>>(path: P, interval: Duration) -> CheckpointLog {
+        CheckpointLog {
+            path: path.into(),
+            interval,
+        }
+    }
+
+    /// `$XDG_DATA_HOME/julia/checkpoints`, falling back to `~/.local/share/julia/checkpoints`, or
+    /// to `.julia-checkpoints` in the working directory if neither variable is set.
+    pub fn default_path() -> PathBuf {
+        let data_home = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| Path::new(&h).join(".local/share")));
+
+        match data_home {
+            Some(dir) => dir.join("julia").join("checkpoints"),
+            None => PathBuf::from(".julia-checkpoints"),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Appends a checkpoint of the given state, stamped with the current time.
+    pub fn append(&self, data: &JuliaData, export_dimensions: ImgDimensions) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            if !dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+
+        let checkpoint = Checkpoint {
+            time: SystemTime::now(),
+            data: *data,
+            export_dimensions,
+        };
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", encode(&checkpoint))?;
+        file.sync_data()
+    }
+
+    /// Reads all checkpoints in the history file, oldest first. Lines that can't be parsed are
+    /// skipped, so a partially written line from a crash doesn't hide the rest of the history.
+    pub fn read(&self) -> io::Result<Vec<Checkpoint>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        Ok(contents.lines().filter_map(decode).collect())
+    }
+}
+
+impl Display for Checkpoint {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let data = &self.data;
+        let extent = f32::max(data.extents.x, data.extents.y);
+
+        write!(f, "{}  ", fmt_utc(self.time))?;
+        match data.kind {
+            FractalKind::Julia => write!(f, "x^{} + ({}, {})", data.n, data.c.x, data.c.y)?,
+            FractalKind::Mandelbrot => write!(f, "Mandelbrot x^{} + c", data.n)?,
+            FractalKind::BurningShip => write!(f, "Burning Ship x^{} + c", data.n)?,
+            FractalKind::Tricorn => write!(f, "Tricorn x^{} + c", data.n)?,
+            FractalKind::Newton => write!(f, "Newton x^{} - 1", data.n)?,
+        }
+        if let Some(p) = data.plane {
+            write!(f, "  {}-plane", p)?;
+        }
+        write!(
+            f,
+            "  center ({}, {})  extent {}  {} iterations",
+            data.center.x, data.center.y, extent, data.iters,
+        )?;
+        if let Some(p) = &data.poly {
+            write!(f, "  P = {}", p)?;
+        }
+        if let Some(q) = &data.denom {
+            write!(f, "  Q = {}", q)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn encode(checkpoint: &Checkpoint) -> String {
+    let data = &checkpoint.data;
+    let secs = checkpoint
+        .time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let stops = data.gradient.stops();
+    let colors = stops
+        .iter()
+        .map(|s| format!("{},{},{},{}", s.color.x, s.color.y, s.color.z, s.color.w))
+        .collect::<Vec<_>>()
+        .join(";");
+    let midpts = stops
+        .iter()
+        .map(|s| s.position.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let mut optional = String::new();
+    if let Some(p) = &data.poly {
+        optional.push_str(&format!(" poly={}", p));
+    }
+    if let Some(q) = &data.denom {
+        optional.push_str(&format!(" denom={}", q));
+    }
+    if let Some(h) = &data.hybrid {
+        optional.push_str(&format!(" hybrid={}", h));
+    }
+    if data.z0 != Vec2::zeros() {
+        optional.push_str(&format!(" z0={},{}", data.z0.x, data.z0.y));
+    }
+    if let Some(l) = &data.shading {
+        optional.push_str(&format!(" light={},{}", l.azimuth, l.elevation));
+        if l.relief != Relief::default() {
+            optional.push_str(&format!(" relief={}", l.relief));
+        }
+    }
+    if let Some([r, g, b]) = data.nebulabrot {
+        optional.push_str(&format!(" nebulabrot={},{},{}", r, g, b));
+    }
+    if let Some(p) = data.plane {
+        optional.push_str(&format!(" plane={}", p));
+    }
+    if !data.periodicity {
+        optional.push_str(" periodicity=false");
+    }
+    if !data.interior_checks {
+        optional.push_str(" interior_checks=false");
+    }
+    if data.rotation != 0.0 {
+        optional.push_str(&format!(" rotation={}", data.rotation));
+    }
+    if data.coloring != ColoringMode::Iterations {
+        optional.push_str(&format!(" coloring={}", data.coloring));
+    }
+    if data.stripe_density != DEFAULT_STRIPE_DENSITY {
+        optional.push_str(&format!(" stripe_density={}", data.stripe_density));
+    }
+    if !data.channels.is_identity() {
+        let Channels { scale, offset } = data.channels;
+        optional.push_str(&format!(
+            " channels={},{},{},{},{},{}",
+            scale.x, scale.y, scale.z, offset.x, offset.y, offset.z
+        ));
+    }
+    if data.equalize {
+        optional.push_str(" equalize=true");
+    }
+    if let Some(p) = data.color_period {
+        optional.push_str(&format!(" color_period={}", p));
+    }
+    if data.color_phase != 0.0 {
+        optional.push_str(&format!(" color_phase={}", data.color_phase));
+    }
+    if data.interpolation != Interpolation::Rgb {
+        optional.push_str(&format!(" interpolation={}", data.interpolation));
+    }
+    if let Some(c) = data.interior_color {
+        optional.push_str(&format!(" interior_color={},{},{},{}", c.x, c.y, c.z, c.w));
+    }
+    if !data.tone.is_identity() {
+        let t = data.tone;
+        optional.push_str(&format!(" tone={},{},{}", t.exposure, t.gamma, t.contrast));
+        if t.curve != ToneCurve::Clip {
+            optional.push_str(&format!(" tone_curve={}", t.curve));
+        }
+    }
+    if data.dither {
+        optional.push_str(" dither=true");
+    }
+
+    format!(
+        "{} kind={} n={} c={},{} iters={} center={},{} extents={},{} colors={} midpts={} \
+         offset={} scale={} strict={} export={}x{}{}",
+        secs,
+        data.kind,
+        data.n,
+        data.c.x,
+        data.c.y,
+        data.iters,
+        data.center.x,
+        data.center.y,
+        data.extents.x,
+        data.extents.y,
+        colors,
+        midpts,
+        data.color_offset,
+        data.color_scale,
+        data.strict,
+        checkpoint.export_dimensions.width,
+        checkpoint.export_dimensions.height,
+        optional,
+    )
+}
+
+pub(crate) fn decode(line: &str) -> Option<Checkpoint> {
+    fn floats(s: &str) -> Option<Vec<f32>> {
+        s.split(',').map(|v| v.parse().ok()).collect()
+    }
+
+    fn vec2(s: &str) -> Option<Vec2> {
+        match floats(s)?.as_slice() {
+            [x, y] => Some(vec2!(*x, *y)),
+            _ => None,
+        }
+    }
+
+    let mut fields = line.split_whitespace();
+    let secs: u64 = fields.next()?.parse().ok()?;
+
+    let mut kind = None;
+    let mut n = None;
+    let mut c = None;
+    let mut iters = None;
+    let mut center = None;
+    let mut extents = None;
+    let mut colors = None;
+    let mut positions = None;
+    let mut color_offset = 0.0;
+    let mut color_scale = 1.0;
+    let mut poly = None;
+    let mut denom = None;
+    let mut z0 = Vec2::zeros();
+    let mut shading = None;
+    let mut relief = Relief::default();
+    let mut nebulabrot = None;
+    let mut plane = None;
+    let mut hybrid = None;
+    let mut strict = false;
+    let mut periodicity = true;
+    let mut interior_checks = true;
+    let mut rotation = 0.0;
+    let mut coloring = ColoringMode::Iterations;
+    let mut stripe_density = DEFAULT_STRIPE_DENSITY;
+    let mut channels = Channels::default();
+    let mut equalize = false;
+    let mut color_period = None;
+    let mut color_phase = 0.0;
+    let mut interpolation = Interpolation::Rgb;
+    let mut interior_color = None;
+    let mut tone = Tone::default();
+    let mut dither = false;
+    let mut export_dimensions = None;
+
+    for field in fields {
+        let mut kv = field.splitn(2, '=');
+        let (key, value) = (kv.next()?, kv.next()?);
+
+        match key {
+            "kind" => kind = Some(value.parse().ok()?),
+            "n" => n = value.parse().ok(),
+            "c" => c = vec2(value),
+            "iters" => iters = value.parse().ok(),
+            "center" => center = vec2(value),
+            "extents" => extents = vec2(value),
+            "colors" => {
+                let cs = value
+                    .split(';')
+                    .map(|c| match floats(c)?.as_slice() {
+                        [r, g, b, a] => Some(vec4!(*r, *g, *b, *a)),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                colors = Some(cs);
+            }
+            "midpts" => positions = floats(value),
+            "offset" => color_offset = value.parse().ok()?,
+            "scale" => color_scale = value.parse().ok()?,
+            "poly" => poly = Some(value.parse().ok()?),
+            "z0" => z0 = vec2(value)?,
+            "hybrid" => hybrid = Some(value.parse().ok()?),
+            "denom" => denom = Some(value.parse().ok()?),
+            "light" => {
+                let l = vec2(value)?;
+                shading = Some(Light {
+                    azimuth: l.x,
+                    elevation: l.y,
+                    relief: Relief::default(),
+                });
+            }
+            "relief" => relief = value.parse().ok()?,
+            "nebulabrot" => {
+                let iters: Vec<u32> = value
+                    .split(',')
+                    .map(|v| v.parse().ok())
+                    .collect::<Option<_>>()?;
+                nebulabrot = match iters.as_slice() {
+                    [r, g, b] => Some([*r, *g, *b]),
+                    _ => return None,
+                };
+            }
+            "plane" => plane = Some(value.parse().ok()?),
+            "strict" => strict = value.parse().ok()?,
+            "periodicity" => periodicity = value.parse().ok()?,
+            "interior_checks" => interior_checks = value.parse().ok()?,
+            "rotation" => rotation = value.parse().ok()?,
+            "coloring" => coloring = value.parse().ok()?,
+            "stripe_density" => stripe_density = value.parse().ok()?,
+            "channels" => {
+                channels = match floats(value)?.as_slice() {
+                    [sr, sg, sb, or, og, ob] => Channels {
+                        scale: vec3!(*sr, *sg, *sb),
+                        offset: vec3!(*or, *og, *ob),
+                    },
+                    _ => return None,
+                }
+            }
+            "equalize" => equalize = value.parse().ok()?,
+            "color_period" => color_period = Some(value.parse().ok()?),
+            "color_phase" => color_phase = value.parse().ok()?,
+            "interpolation" => interpolation = value.parse().ok()?,
+            "dither" => dither = value.parse().ok()?,
+            "tone_curve" => tone.curve = value.parse().ok()?,
+            "tone" => {
+                tone = match floats(value)?.as_slice() {
+                    [exposure, gamma, contrast] => Tone {
+                        exposure: *exposure,
+                        gamma: *gamma,
+                        contrast: *contrast,
+                        curve: tone.curve,
+                    },
+                    _ => return None,
+                }
+            }
+            "interior_color" => {
+                interior_color = match floats(value)?.as_slice() {
+                    [r, g, b, a] => Some(vec4!(*r, *g, *b, *a)),
+                    _ => return None,
+                }
+            }
+            "export" => {
+                let mut wh = value.splitn(2, 'x');
+                export_dimensions = Some(ImgDimensions {
+                    width: wh.next()?.parse().ok()?,
+                    height: wh.next()?.parse().ok()?,
+                });
+            }
+            // Keys from newer versions are ignored
+            _ => (),
+        }
+    }
+
+    let colors = colors?;
+    let positions = positions?;
+    if colors.len() != positions.len() {
+        return None;
+    }
+    let stops: Vec<_> = colors
+        .into_iter()
+        .zip(positions)
+        .map(|(color, position)| GradientStop { color, position })
+        .collect();
+
+    // The light may come before or after its relief
+    if let Some(l) = &mut shading {
+        l.relief = relief;
+    }
+
+    Some(Checkpoint {
+        time: UNIX_EPOCH + Duration::from_secs(secs),
+        data: JuliaData {
+            gradient: Gradient::new(&stops)?,
+            color_offset,
+            color_scale,
+            kind: kind?,
+            n: n?,
+            c: c?,
+            z0,
+            iters: iters?,
+            center: center?,
+            extents: extents?,
+            poly,
+            denom,
+            hybrid,
+            shading,
+            nebulabrot,
+            plane,
+            strict,
+            periodicity,
+            interior_checks,
+            rotation,
+            coloring,
+            stripe_density,
+            channels,
+            equalize,
+            color_period,
+            color_phase,
+            interpolation,
+            interior_color,
+            tone,
+            dither,
+        },
+        export_dimensions: export_dimensions?,
+    })
+}
+
+/// Formats a time as `YYYY-MM-DD hh:mm:ss UTC`.
+fn fmt_utc(time: SystemTime) -> String {
+    let [year, month, day, hour, minute, second] = utc(time);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year, month, day, hour, minute, second,
+    )
+}
+
+/// The year, month, day, hour, minute and second of a time in UTC.
+pub(crate) fn utc(time: SystemTime) -> [i64; 6] {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, rem) = (secs / 86400, secs % 86400);
+
+    // Civil date from days since 1970-01-01, after Howard Hinnant's `civil_from_days`
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let rem = rem as i64;
+    [year, month, day, rem / 3600, rem % 3600 / 60, rem % 60]
+}
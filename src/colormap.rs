@@ -0,0 +1,123 @@
+//! Named gradients: perceptually uniform colormaps from scientific plotting, whose lightness rises
+//! steadily from one end to the other so that no band of iterations stands out more than another.
+//! Each is sampled at `MAX_STOPS` evenly spaced points and becomes an ordinary gradient.
+//!
+//! The last few are chosen to stay readable with deuteranopia and protanopia, the common forms of
+//! red-green color blindness: they vary along the blue-yellow axis and in lightness, which those
+//! viewers still tell apart, rather than between red and green.
+
+use crate::gradient::{Gradient, MAX_STOPS};
+
+/// A named gradient, as sRGB colors `0xrrggbb` spread evenly from 0 to 1.
+struct Colormap {
+    name: &'static str,
+    colors: [u32; MAX_STOPS],
+}
+
+const COLORMAPS: &[Colormap] = &[
+    Colormap {
+        name: "viridis",
+        colors: [
+            0x440154, 0x481a6c, 0x472f7d, 0x414487, 0x39568c, 0x31688e, 0x2a788e, 0x23888e,
+            0x1f988b, 0x22a884, 0x35b779, 0x54c568, 0x7ad151, 0xa5db36, 0xd2e21b, 0xfde725,
+        ],
+    },
+    Colormap {
+        name: "magma",
+        colors: [
+            0x000004, 0x0b0828, 0x210d4e, 0x3b126b, 0x56177e, 0x721d86, 0x8e2585, 0xaa2f7d,
+            0xc53c71, 0xdc4d66, 0xef6260, 0xfc7d61, 0xff9d6c, 0xfec081, 0xfbe29d, 0xfcfdbf,
+        ],
+    },
+    Colormap {
+        name: "inferno",
+        colors: [
+            0x000004, 0x0c072f, 0x250a4d, 0x410d60, 0x5d1269, 0x79196c, 0x932367, 0xad2f5a,
+            0xc63d48, 0xdb5032, 0xed661d, 0xf78210, 0xfba311, 0xf7c627, 0xf3e858, 0xfcffa4,
+        ],
+    },
+    Colormap {
+        name: "plasma",
+        colors: [
+            0x0d0887, 0x310499, 0x5000a5, 0x6b00ab, 0x8508a9, 0x9c16a0, 0xb12893, 0xc43d84,
+            0xd55174, 0xe36664, 0xef7a55, 0xf78f46, 0xfda638, 0xffc12b, 0xfbde22, 0xf0f921,
+        ],
+    },
+    Colormap {
+        name: "turbo",
+        colors: [
+            0x30123b, 0x4a41b5, 0x4270f2, 0x2f9ef5, 0x25c6d7, 0x2fe5ad, 0x4ef983, 0x7cfd5e,
+            0xb0f444, 0xdfdc32, 0xffb827, 0xff8c1f, 0xf45c17, 0xce310d, 0xa31302, 0x7a0403,
+        ],
+    },
+    // Color-blind-safe gradients
+    Colormap {
+        name: "cividis",
+        colors: [
+            0x00224e, 0x0c2e64, 0x1f3a6f, 0x35466d, 0x48526c, 0x575d6d, 0x676a71, 0x767674,
+            0x858277, 0x968f76, 0xa59c74, 0xb8aa6e, 0xc9b865, 0xdbc759, 0xedd84b, 0xfee838,
+        ],
+    },
+    // Paul Tol's iridescent scheme, from dark to light like the others
+    Colormap {
+        name: "iridescent",
+        colors: [
+            0x46353a, 0x745064, 0x8f6287, 0x9b75a6, 0x9b88c2, 0x8f9cd6, 0x80afe3, 0x7dbee7,
+            0x8ac9e5, 0x9ed3e0, 0xb1dbd9, 0xc4e4d1, 0xd8eac3, 0xebf0b6, 0xf9f5cc, 0xfefbe9,
+        ],
+    },
+    // Black through the blue, sky blue and yellow of the Okabe-Ito palette to white
+    Colormap {
+        name: "okabe-ito",
+        colors: [
+            0x000000, 0x003c61, 0x005486, 0x0067a1, 0x1278b6, 0x318dc7, 0x439ed5, 0x50ade3,
+            0x7bbbdc, 0xaac9bd, 0xcdd697, 0xeae15b, 0xf3ea87, 0xf7f1bb, 0xfbf8e0, 0xffffff,
+        ],
+    },
+];
+
+impl Colormap {
+    fn gradient(&self) -> Gradient {
+        let colors: Vec<_> = self
+            .colors
+            .iter()
+            .map(|&c| {
+                let channel = |shift: u32| ((c >> shift) & 0xff) as f32 / 255.0;
+                vec4!(channel(16), channel(8), channel(0), 1.0)
+            })
+            .collect();
+
+        Gradient::even(&colors).unwrap()
+    }
+}
+
+/// The names of the built-in gradients, in the order they are cycled through.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    COLORMAPS.iter().map(|c| c.name)
+}
+
+/// The built-in gradient with the given name, ignoring case.
+pub fn named(name: &str) -> Option<Gradient> {
+    COLORMAPS
+        .iter()
+        .find(|c| c.name.eq_ignore_ascii_case(name))
+        .map(Colormap::gradient)
+}
+
+/// The name of the built-in gradient equal to `gradient`, if it is one.
+pub fn name_of(gradient: &Gradient) -> Option<&'static str> {
+    COLORMAPS
+        .iter()
+        .find(|c| c.gradient() == *gradient)
+        .map(|c| c.name)
+}
+
+/// The built-in gradient after `gradient`, or the first one if `gradient` isn't built in.
+pub fn next(gradient: &Gradient) -> Gradient {
+    let idx = COLORMAPS
+        .iter()
+        .position(|c| c.gradient() == *gradient)
+        .map_or(0, |i| (i + 1) % COLORMAPS.len());
+
+    COLORMAPS[idx].gradient()
+}
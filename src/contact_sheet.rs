@@ -0,0 +1,283 @@
+//! Contact sheets: small previews of many views composed into a grid in one PNG, each labeled
+//! underneath, for surveying candidates before committing to full renders. The views come either
+//! from a list, such as a batch file, or from a sweep of one parameter across a range, as in
+//! `cr=-0.8..-0.7`.
+
+use image::imageops;
+use image::{Rgba, RgbaImage};
+
+use crate::atomic::write_atomic;
+use crate::hud;
+use crate::{ImgDimensions, JuliaContext, JuliaData, JuliaExportError, PixelRect, Progress};
+
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::str::FromStr;
+
+/// The space around and between previews, in pixels.
+const GAP: u32 = 8;
+
+const BACKGROUND: Rgba<u8> = Rgba([32, 32, 32, 255]);
+const LABEL_COLOR: Rgba<u8> = Rgba([224, 224, 224, 255]);
+
+/// A parameter of the view that a sweep varies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SweepParam {
+    /// The real part of `c`.
+    Cr,
+    /// The imaginary part of `c`.
+    Ci,
+    /// The exponent.
+    N,
+    /// The iteration limit, rounded to whole iterations.
+    Iters,
+    /// The rotation of the view, in degrees.
+    Rotation,
+    /// The offset of the gradient.
+    ColorOffset,
+}
+
+impl SweepParam {
+    fn set(self, data: &mut JuliaData, value: f32) {
+        match self {
+            SweepParam::Cr => data.c.x = value,
+            SweepParam::Ci => data.c.y = value,
+            SweepParam::N => data.n = value,
+            SweepParam::Iters => data.iters = value.round().max(1.0) as u32,
+            SweepParam::Rotation => data.rotation = value,
+            SweepParam::ColorOffset => data.color_offset = value,
+        }
+    }
+
+    fn format(self, value: f32) -> String {
+        match self {
+            SweepParam::Iters => format!("{}", value.round().max(1.0)),
+            SweepParam::Rotation => format!("{:.2}", value),
+            _ => format!("{:.5}", value),
+        }
+    }
+}
+
+/// Formats the parameter as a sweep names it: `cr`, `ci`, `n`, `iters`, `rotation` or
+/// `color-offset`.
+impl Display for SweepParam {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            SweepParam::Cr => "cr",
+            SweepParam::Ci => "ci",
+            SweepParam::N => "n",
+            SweepParam::Iters => "iters",
+            SweepParam::Rotation => "rotation",
+            SweepParam::ColorOffset => "color-offset",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for SweepParam {
+    type Err = ParseSweepError;
+
+    fn from_str(s: &str) -> Result<SweepParam, ParseSweepError> {
+        match s {
+            "cr" => Ok(SweepParam::Cr),
+            "ci" => Ok(SweepParam::Ci),
+            "n" => Ok(SweepParam::N),
+            "iters" => Ok(SweepParam::Iters),
+            "rotation" => Ok(SweepParam::Rotation),
+            "color-offset" => Ok(SweepParam::ColorOffset),
+            _ => Err(ParseSweepError::UnknownParam(s.to_string())),
+        }
+    }
+}
+
+/// One parameter stepped evenly from `start` to `end`, given as `PARAM=START..END`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sweep {
+    pub param: SweepParam,
+    pub start: f32,
+    pub end: f32,
+}
+
+impl Sweep {
+    /// `count` copies of `base` with the parameter stepped from the start to the end, both
+    /// included, each labeled with its value.
+    pub fn views(&self, base: &JuliaData, count: u32) -> Vec<(JuliaData, String)> {
+        (0..count)
+            .map(|i| {
+                let t = if count > 1 {
+                    i as f32 / (count - 1) as f32
+                } else {
+                    0.0
+                };
+                let value = self.start + (self.end - self.start) * t;
+
+                let mut data = *base;
+                self.param.set(&mut data, value);
+                (
+                    data,
+                    format!("{} = {}", self.param, self.param.format(value)),
+                )
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Sweep {
+    type Err = ParseSweepError;
+
+    fn from_str(s: &str) -> Result<Sweep, ParseSweepError> {
+        let eq = s.find('=').ok_or(ParseSweepError::Malformed)?;
+        let param = s[..eq].trim().parse()?;
+
+        let range = &s[eq + 1..];
+        let dots = range.find("..").ok_or(ParseSweepError::Malformed)?;
+        let bound = |s: &str| s.trim().parse().map_err(|_| ParseSweepError::Malformed);
+
+        Ok(Sweep {
+            param,
+            start: bound(&range[..dots])?,
+            end: bound(&range[dots + 2..])?,
+        })
+    }
+}
+
+/// A malformed sweep.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ParseSweepError {
+    UnknownParam(String),
+    Malformed,
+}
+
+impl Display for ParseSweepError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ParseSweepError::UnknownParam(name) => write!(
+                f,
+                "unknown parameter {}, expected cr, ci, n, iters, rotation or color-offset",
+                name
+            ),
+            ParseSweepError::Malformed => write!(f, "expected PARAM=START..END"),
+        }
+    }
+}
+
+impl std::error::Error for ParseSweepError {}
+
+/// The layout of a contact sheet: square previews of one size, in rows of a given length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContactSheet {
+    cell: u32,
+    columns: Option<u32>,
+}
+
+impl ContactSheet {
+    /// The default preview size, in pixels along each side.
+    pub const DEFAULT_CELL: u32 = 256;
+
+    /// A sheet of previews `cell` pixels square, rounded down to a multiple of 8 as the renderer
+    /// requires, in rows of `columns`. Without a number of columns, the sheet is made as close to
+    /// square as it can be.
+    pub fn new(cell: u32, columns: Option<u32>) -> ContactSheet {
+        ContactSheet {
+            cell: u32::max(8, cell / 8 * 8),
+            columns: columns.map(|c| c.max(1)),
+        }
+    }
+
+    /// Renders a preview of each view and composes them into a sheet, in rows from the top left,
+    /// with each label under its preview. Labels too long for a preview are cut short. `progress`
+    /// is called as each preview is rendered.
+    pub fn render<F>(
+        &self,
+        views: &[(JuliaData, String)],
+        context: &JuliaContext,
+        mut progress: F,
+    ) -> Result<RgbaImage, JuliaExportError>
+    where
+        F: FnMut(Progress),
+    {
+        let count = views.len().max(1) as u32;
+        let columns = self
+            .columns
+            .unwrap_or_else(|| (count as f32).sqrt().ceil() as u32)
+            .min(count);
+        let rows = (count + columns - 1) / columns;
+
+        let scale = u32::max(1, self.cell / 192);
+        let label_height = hud::line_height(scale) + GAP;
+        let step_x = self.cell + GAP;
+        let step_y = self.cell + label_height + GAP;
+        let mut sheet =
+            RgbaImage::from_pixel(columns * step_x + GAP, rows * step_y + GAP / 2, BACKGROUND);
+
+        let dims = ImgDimensions {
+            width: self.cell,
+            height: self.cell,
+        };
+        let rect = PixelRect {
+            x: 0,
+            y: 0,
+            width: self.cell,
+            height: self.cell,
+        };
+
+        for (i, (data, label)) in views.iter().enumerate() {
+            let x = GAP + i as u32 % columns * step_x;
+            let y = GAP + i as u32 / columns * step_y;
+
+            let pixels = context.render_region(dims, rect, &data.fit_to(dims))?;
+            let preview = RgbaImage::from_raw(dims.width, dims.height, pixels)
+                .expect("preview has the wrong size");
+            imageops::overlay(&mut sheet, &preview, x, y);
+
+            let label = fit_label(label, self.cell, scale);
+            hud::draw_text(
+                &mut sheet,
+                x,
+                y + self.cell + GAP / 2,
+                &label,
+                scale,
+                LABEL_COLOR,
+            );
+
+            progress(Progress {
+                done: i as u32 + 1,
+                total: views.len() as u32,
+            });
+        }
+
+        Ok(sheet)
+    }
+}
+
+/// `label`, cut short and ended with `...` if it is wider than `width` at `scale`.
+fn fit_label(label: &str, width: u32, scale: u32) -> String {
+    if hud::text_width(label, scale) <= width {
+        return label.to_string();
+    }
+
+    let fits = (width / hud::text_width(" ", scale)) as usize;
+    let mut label: String = label.chars().take(fits.saturating_sub(3)).collect();
+    label.push_str("...");
+    label
+}
+
+/// Writes `sheet` to `filename` as an 8-bit PNG. The file is replaced atomically.
+pub fn write(sheet: &RgbaImage, filename: &Path) -> io::Result<()> {
+    write_atomic(filename, |path| {
+        let out = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(out, sheet.width(), sheet.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(png_err)?;
+        writer.write_image_data(sheet).map_err(png_err)
+    })
+}
+
+fn png_err(err: png::EncodingError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
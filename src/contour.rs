@@ -0,0 +1,197 @@
+//! Vector contours of the escape time, for laser cutters and pen plotters. Marching squares runs
+//! over the smooth escape time of every pixel, and traces the lines at each threshold between the
+//! pixels that escape sooner and those that take longer or never escape. A threshold at the
+//! iteration limit traces the boundary of the set itself, since points that never escape take the
+//! limit.
+//!
+//! The contours are written as an SVG the size of the image in pixels, with one `<path>` per
+//! threshold, so that each can be cut or plotted as a layer of its own.
+
+use crate::atomic::write_atomic;
+use crate::ImgDimensions;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// An edge between the centers of two neighboring pixels: the pixel at `(x, y)`, and whether the
+/// edge runs to the pixel on its right rather than the one below.
+type Edge = (u32, u32, bool);
+
+/// A traced contour, as points in pixels from the top left corner of the image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contour {
+    pub points: Vec<(f32, f32)>,
+    /// Whether the last point joins back to the first.
+    pub closed: bool,
+}
+
+/// Traces the contours at `threshold` through `values`, one per pixel in rows from the top.
+/// Pixels whose value is at least the threshold are inside the contours. Saddle points, where the
+/// corners of a square alternate, are decided by the average of the four.
+pub fn trace(values: &[f32], dims: ImgDimensions, threshold: f32) -> Vec<Contour> {
+    let ImgDimensions { width, height } = dims;
+    let value = |x: u32, y: u32| values[(y * width + x) as usize];
+    let inside = |x: u32, y: u32| value(x, y) >= threshold;
+
+    let mut segments: Vec<[Edge; 2]> = Vec::new();
+    for y in 0..height.saturating_sub(1) {
+        for x in 0..width.saturating_sub(1) {
+            // Corners clockwise from the top left, each followed by the edge to the next
+            let corners = [
+                inside(x, y),
+                inside(x + 1, y),
+                inside(x + 1, y + 1),
+                inside(x, y + 1),
+            ];
+            let edges = [
+                (x, y, true),
+                (x + 1, y, false),
+                (x, y + 1, true),
+                (x, y, false),
+            ];
+            let crossed: Vec<_> = (0..4)
+                .filter(|&i| corners[i] != corners[(i + 1) % 4])
+                .map(|i| edges[i])
+                .collect();
+
+            match crossed.len() {
+                2 => segments.push([crossed[0], crossed[1]]),
+                4 => {
+                    // Cut off the corners that the center doesn't side with, each by a segment
+                    // between the two edges that meet at it
+                    let sum = value(x, y) + value(x + 1, y) + value(x + 1, y + 1) + value(x, y + 1);
+                    let center_inside = sum / 4.0 >= threshold;
+                    for (i, &corner) in corners.iter().enumerate() {
+                        if corner != center_inside {
+                            segments.push([edges[(i + 3) % 4], edges[i]]);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let point = |(x, y, right): Edge| {
+        let (x2, y2) = if right { (x + 1, y) } else { (x, y + 1) };
+        let (a, b) = (value(x, y), value(x2, y2));
+        let t = ((threshold - a) / (b - a)).max(0.0).min(1.0);
+        let (dx, dy) = if right { (t, 0.0) } else { (0.0, t) };
+        (x as f32 + dx + 0.5, y as f32 + dy + 0.5)
+    };
+
+    join(&segments)
+        .into_iter()
+        .map(|(edges, closed)| Contour {
+            points: edges.into_iter().map(point).collect(),
+            closed,
+        })
+        .collect()
+}
+
+/// Joins segments that share edges into chains of edges, each with whether it closes into a loop.
+/// Every edge is shared by at most the two squares on either side of it, so the segments form
+/// simple chains and loops.
+fn join(segments: &[[Edge; 2]]) -> Vec<(Vec<Edge>, bool)> {
+    let mut at_edge: HashMap<Edge, Vec<usize>> = HashMap::new();
+    for (i, segment) in segments.iter().enumerate() {
+        for &edge in segment {
+            at_edge.entry(edge).or_default().push(i);
+        }
+    }
+
+    let mut used = vec![false; segments.len()];
+    // The far edge of a segment at `edge` that isn't used yet, marking the segment used
+    let next = |edge: Edge, used: &mut [bool]| {
+        let i = *at_edge.get(&edge)?.iter().find(|&&i| !used[i])?;
+        used[i] = true;
+        let [a, b] = segments[i];
+        Some(if a == edge { b } else { a })
+    };
+
+    let mut chains = Vec::new();
+    for start in 0..segments.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+
+        let [first, second] = segments[start];
+        let mut chain = vec![first, second];
+        while let Some(edge) = next(*chain.last().unwrap(), &mut used) {
+            chain.push(edge);
+        }
+
+        let closed = chain.len() > 2 && chain.first() == chain.last();
+        if closed {
+            chain.pop();
+        } else {
+            let mut back = Vec::new();
+            while let Some(edge) = next(*back.last().unwrap_or(&first), &mut used) {
+                back.push(edge);
+            }
+            back.reverse();
+            back.extend(chain);
+            chain = back;
+        }
+
+        chains.push((chain, closed));
+    }
+
+    chains
+}
+
+/// Writes the contours of `values` at each of `thresholds` as an SVG of the image's size, with a
+/// path per threshold. The file is replaced atomically.
+pub(crate) fn write_svg(
+    filename: &Path,
+    dims: ImgDimensions,
+    values: &[f32],
+    thresholds: &[f32],
+) -> io::Result<()> {
+    write_atomic(filename, |path| {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\">",
+            w = dims.width,
+            h = dims.height
+        )?;
+        writeln!(
+            out,
+            "  <g fill=\"none\" stroke=\"black\" stroke-width=\"1\" stroke-linejoin=\"round\">"
+        )?;
+
+        for &threshold in thresholds {
+            write!(out, "    <path id=\"contour-{}\" d=\"", threshold)?;
+            for (i, contour) in trace(values, dims, threshold).iter().enumerate() {
+                if i > 0 {
+                    write!(out, " ")?;
+                }
+                write_path(&mut out, contour)?;
+            }
+            writeln!(out, "\"/>")?;
+        }
+
+        writeln!(out, "  </g>")?;
+        writeln!(out, "</svg>")?;
+        out.flush()
+    })
+}
+
+/// Writes the path data of one contour: a move to its first point and lines through the rest.
+fn write_path<W: Write>(out: &mut W, contour: &Contour) -> io::Result<()> {
+    for (i, (x, y)) in contour.points.iter().enumerate() {
+        let command = if i == 0 { 'M' } else { 'L' };
+        write!(out, "{}{:.2} {:.2}", command, x, y)?;
+    }
+    if contour.closed {
+        write!(out, "Z")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,197 @@
+//! Deep Zoom Image exports: a pyramid of tiles at every resolution from the full image down to a
+//! single pixel, in the layout that OpenSeadragon and other Deep Zoom viewers read, so that a huge
+//! render can be explored in a browser. The export is a descriptor, `<name>.dzi`, and a directory
+//! `<name>_files` holding a directory of PNG tiles per level, named `<column>_<row>.png`:
+//!
+//! ```xml
+//! <?xml version="1.0" encoding="UTF-8"?>
+//! <Image xmlns="http://schemas.microsoft.com/deepzoom/2008"
+//!   Format="png" Overlap="0" TileSize="256">
+//!   <Size Width="16000" Height="9000"/>
+//! </Image>
+//! ```
+//!
+//! Level 0 is the image shrunk to one pixel, and each level after it is twice the size of the one
+//! before, up to the full image. The tiles of the full image are rendered one at a time as regions
+//! of it, as in a tiled export, and each tile of a smaller level is shrunk from the four tiles it
+//! covers in the level above. No more than four tiles are ever held in memory.
+
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+use crate::atomic::write_atomic;
+use crate::tiled::{read_tile, write_tile};
+use crate::{ImgDimensions, JuliaContext, JuliaData, PixelRect, Progress};
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// An image to be exported as a Deep Zoom pyramid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeepZoom {
+    dims: ImgDimensions,
+    data: JuliaData,
+    tile_size: u32,
+    filename: PathBuf,
+}
+
+impl DeepZoom {
+    /// The default tile size, in pixels along each side.
+    pub const DEFAULT_TILE_SIZE: u32 = 256;
+
+    /// An export of `data` at `dims` to the descriptor `filename`, in square tiles of
+    /// `tile_size` pixels, rounded down to a multiple of 8 as the renderer requires. The
+    /// dimensions must be multiples of 8 as well.
+    pub fn new(
+        dims: ImgDimensions,
+        data: JuliaData,
+        tile_size: u32,
+        filename: PathBuf,
+    ) -> DeepZoom {
+        DeepZoom {
+            dims,
+            data,
+            tile_size: u32::max(8, tile_size / 8 * 8),
+            filename,
+        }
+    }
+
+    /// The directory the levels are written to: the descriptor's name with `_files` in place of
+    /// its extension.
+    pub fn tile_dir(&self) -> PathBuf {
+        let stem = self
+            .filename
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.filename.with_file_name(format!("{}_files", stem))
+    }
+
+    /// The number of the level holding the full image, the last of them.
+    pub fn max_level(&self) -> u32 {
+        let longest = self.dims.width.max(self.dims.height);
+        let mut level = 0;
+        while 1 << level < longest {
+            level += 1;
+        }
+        level
+    }
+
+    /// The dimensions of the image at `level`, halved from the full image and rounded up once for
+    /// every level below the last.
+    fn level_dims(&self, level: u32) -> ImgDimensions {
+        let shift = self.max_level() - level;
+        let shrink = |n: u32| ((n - 1) >> shift) + 1;
+        ImgDimensions {
+            width: shrink(self.dims.width),
+            height: shrink(self.dims.height),
+        }
+    }
+
+    /// The tiles of `level`, by column and row, with the part of the level's image each covers.
+    fn tiles(&self, level: u32) -> Vec<(u32, u32, PixelRect)> {
+        let ImgDimensions { width, height } = self.level_dims(level);
+        let size = self.tile_size;
+
+        let mut tiles = Vec::new();
+        for (row, y) in (0..height).step_by(size as usize).enumerate() {
+            for (col, x) in (0..width).step_by(size as usize).enumerate() {
+                let rect = PixelRect {
+                    x,
+                    y,
+                    width: size.min(width - x),
+                    height: size.min(height - y),
+                };
+                tiles.push((col as u32, row as u32, rect));
+            }
+        }
+        tiles
+    }
+
+    fn tile_path(&self, level: u32, col: u32, row: u32) -> PathBuf {
+        self.tile_dir()
+            .join(level.to_string())
+            .join(format!("{}_{}.png", col, row))
+    }
+
+    /// Renders the tiles of the full image, calling `progress` as each is written, then shrinks
+    /// them into the smaller levels and writes the descriptor.
+    pub fn run<F>(&self, context: &JuliaContext, mut progress: F) -> io::Result<()>
+    where
+        F: FnMut(Progress),
+    {
+        let max_level = self.max_level();
+        fs::create_dir_all(self.tile_dir().join(max_level.to_string()))?;
+
+        let tiles = self.tiles(max_level);
+        let total = tiles.len() as u32;
+        for (i, &(col, row, rect)) in tiles.iter().enumerate() {
+            let pixels = context
+                .render_region(self.dims, rect, &self.data)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            let path = self.tile_path(max_level, col, row);
+            write_tile(&path, rect, &pixels, png::Compression::Default)?;
+
+            progress(Progress {
+                done: i as u32 + 1,
+                total,
+            });
+        }
+
+        for level in (0..max_level).rev() {
+            fs::create_dir_all(self.tile_dir().join(level.to_string()))?;
+            for (col, row, rect) in self.tiles(level) {
+                let pixels = self.shrink_tile(level, col, row, rect)?;
+                let path = self.tile_path(level, col, row);
+                write_tile(&path, rect, &pixels, png::Compression::Default)?;
+            }
+        }
+
+        self.write_descriptor()
+    }
+
+    /// The pixels of a tile of `level`, shrunk from the tiles it covers in the level above.
+    fn shrink_tile(&self, level: u32, col: u32, row: u32, rect: PixelRect) -> io::Result<Vec<u8>> {
+        let above = self.level_dims(level + 1);
+        let size = self.tile_size;
+        let left = rect.x * 2;
+        let top = rect.y * 2;
+        let width = u32::min(2 * size, above.width - left);
+        let height = u32::min(2 * size, above.height - top);
+
+        let mut composite = RgbaImage::new(width, height);
+        for (dy, y) in (top..top + height).step_by(size as usize).enumerate() {
+            for (dx, x) in (left..left + width).step_by(size as usize).enumerate() {
+                let source = PixelRect {
+                    x,
+                    y,
+                    width: size.min(above.width - x),
+                    height: size.min(above.height - y),
+                };
+                let path = self.tile_path(level + 1, 2 * col + dx as u32, 2 * row + dy as u32);
+                let pixels = read_tile(&path, source)?;
+                let tile = RgbaImage::from_raw(source.width, source.height, pixels)
+                    .expect("tile has the wrong size");
+                imageops::replace(&mut composite, &tile, x - left, y - top);
+            }
+        }
+
+        Ok(imageops::resize(&composite, rect.width, rect.height, FilterType::Triangle).into_raw())
+    }
+
+    fn write_descriptor(&self) -> io::Result<()> {
+        let contents = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" Format=\"png\" \
+             Overlap=\"0\" TileSize=\"{}\">\n  <Size Width=\"{}\" Height=\"{}\"/>\n</Image>\n",
+            self.tile_size, self.dims.width, self.dims.height
+        );
+        write_atomic(&self.filename, |path| fs::write(path, &contents))
+    }
+
+    /// The descriptor the export is written to.
+    pub fn filename(&self) -> &Path {
+        &self.filename
+    }
+}
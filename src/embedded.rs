@@ -0,0 +1,54 @@
+//! Views embedded in exported PNGs. Every color export written as a PNG carries the view it was
+//! rendered from in a `tEXt` chunk under the `julia` keyword, as a line of the checkpoint log, so
+//! that a shared image can be reopened or rendered again with exactly the settings that made it.
+//! The time of the line is the time of the export.
+
+use crate::checkpoint::{self, Checkpoint};
+use crate::{ImgDimensions, JuliaData};
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// The keyword of the text chunk holding the view.
+const KEYWORD: &str = "julia";
+
+/// The contents of a PNG `tEXt` chunk holding the view of an image of `dims` rendered from `data`.
+/// Checkpoint lines are plain ASCII, so they need no conversion to Latin-1.
+pub(crate) fn text_chunk(dims: ImgDimensions, data: &JuliaData) -> Vec<u8> {
+    let view = Checkpoint {
+        time: SystemTime::now(),
+        data: *data,
+        export_dimensions: dims,
+    };
+
+    let mut chunk = KEYWORD.as_bytes().to_vec();
+    chunk.push(0);
+    chunk.extend_from_slice(checkpoint::encode(&view).as_bytes());
+    chunk
+}
+
+/// The view embedded in the PNG at `path`, or `None` if it has none, as in images that weren't
+/// exported by julia. The dimensions of the checkpoint are those of the image.
+pub fn read_view(path: &Path) -> io::Result<Option<Checkpoint>> {
+    let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    let reader = decoder.read_info().map_err(invalid_data)?;
+
+    let text = reader
+        .info()
+        .uncompressed_latin1_text
+        .iter()
+        .find(|chunk| chunk.keyword == KEYWORD);
+
+    match text {
+        Some(chunk) => checkpoint::decode(&chunk.text).map(Some).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "the embedded view is malformed")
+        }),
+        None => Ok(None),
+    }
+}
+
+fn invalid_data(err: png::DecodingError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
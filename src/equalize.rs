@@ -0,0 +1,147 @@
+//! Histogram equalization of the iteration count. Deep zooms spend most of their pixels in a
+//! narrow band of escape times, so a linear mapping into the gradient paints them all one color.
+//! Equalizing maps each escape time to the fraction of the view that escapes sooner, which spreads
+//! the gradient evenly over the pixels instead.
+//!
+//! The histogram comes from a small render of the continuous potential, whose logarithm is the
+//! smooth iteration count turned around and shifted: `ln G = ln ln R - (nu - 1) ln d`. Its
+//! quantiles are passed to the color shader, which interpolates between them.
+
+use crate::export::{JuliaExport, JuliaExportError};
+use crate::shaders::julia_comp;
+use crate::{ColoringMode, FractalKind, ImgDimensions, JuliaContext, JuliaData};
+
+/// The longer side of the render the histogram is taken from, in pixels.
+const SAMPLE_SIZE: u32 = 256;
+
+/// The number of histogram bins between the lowest and highest potential of the view.
+const BINS: usize = 4096;
+
+/// The number of quantiles passed to the shader, from the quickest escape to the slowest.
+const STOPS: usize = 16;
+
+/// The quantiles of the log potential of a view's escaping points, from highest to lowest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Equalization {
+    stops: [f32; STOPS],
+}
+
+impl Equalization {
+    /// The equalization of potentials sampled over a view, or `None` if none of them escape.
+    fn from_potentials(potentials: &[f32]) -> Option<Equalization> {
+        let logs: Vec<f32> = potentials
+            .iter()
+            .filter(|&&p| p > 0.0 && p.is_finite())
+            .map(|p| p.ln())
+            .collect();
+        if logs.is_empty() {
+            return None;
+        }
+
+        let max = logs.iter().cloned().fold(f32::MIN, f32::max);
+        let min = logs.iter().cloned().fold(f32::MAX, f32::min);
+        if max <= min {
+            return Some(Equalization {
+                stops: [max; STOPS],
+            });
+        }
+
+        // Bins run from the highest potential down, so that they're in order of escape time
+        let width = (max - min) / BINS as f32;
+        let mut histogram = vec![0u32; BINS];
+        for &l in &logs {
+            let bin = ((max - l) / width) as usize;
+            histogram[bin.min(BINS - 1)] += 1;
+        }
+
+        let total = logs.len() as f32;
+        let mut stops = [min; STOPS];
+        let mut below = 0.0;
+        let mut k = 0;
+
+        for (bin, &count) in histogram.iter().enumerate() {
+            let next = below + count as f32;
+            while k < STOPS {
+                let target = total * k as f32 / (STOPS - 1) as f32;
+                if target > next {
+                    break;
+                }
+
+                let fraction = if count > 0 {
+                    (target - below) / count as f32
+                } else {
+                    0.0
+                };
+                stops[k] = max - (bin as f32 + fraction) * width;
+                k += 1;
+            }
+            below = next;
+        }
+
+        Some(Equalization { stops })
+    }
+
+    /// The equalization of `data`, from a render of its potential at a small size, or `None` if
+    /// it isn't equalized or nothing in the view escapes.
+    fn of(
+        data: &JuliaData,
+        context: &JuliaContext,
+    ) -> Result<Option<Equalization>, JuliaExportError> {
+        let equalized = match data.coloring {
+            ColoringMode::Iterations | ColoringMode::BinaryDecomposition => data.equalize,
+            _ => false,
+        };
+        if !equalized || data.color_period.is_some() || data.kind == FractalKind::Newton {
+            return Ok(None);
+        }
+
+        let aspect = data.extents.x / data.extents.y;
+        let short = |long: f32| u32::max(8, (long / 8.0).round() as u32 * 8);
+        let dims = if aspect >= 1.0 {
+            ImgDimensions {
+                width: SAMPLE_SIZE,
+                height: short(SAMPLE_SIZE as f32 / aspect),
+            }
+        } else {
+            ImgDimensions {
+                width: short(SAMPLE_SIZE as f32 * aspect),
+                height: SAMPLE_SIZE,
+            }
+        };
+
+        let potentials = JuliaExport::render_potential(dims, data, context)?;
+        Ok(Equalization::from_potentials(&potentials))
+    }
+
+    /// Sets the quantiles in uniform data for the color pipelines.
+    pub(crate) fn apply(&self, data: &mut julia_comp::ty::Data) {
+        data.equalize = 1;
+        for (quad, stops) in data.equalization.iter_mut().zip(self.stops.chunks(4)) {
+            quad.copy_from_slice(stops);
+        }
+    }
+}
+
+/// The equalization of the last view asked for, since the interactive view asks for the same one
+/// on every frame.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EqualizationCache {
+    last: Option<(JuliaData, Option<Equalization>)>,
+}
+
+impl EqualizationCache {
+    pub(crate) fn get(
+        &mut self,
+        data: &JuliaData,
+        context: &JuliaContext,
+    ) -> Result<Option<Equalization>, JuliaExportError> {
+        match self.last {
+            Some((last, equalization)) if last == *data => Ok(equalization),
+            _ => {
+                let equalization = Equalization::of(data, context)?;
+                self.last = Some((*data, equalization));
+                Ok(equalization)
+            }
+        }
+    }
+}
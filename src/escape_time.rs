@@ -0,0 +1,118 @@
+//! Raw escape-time data: the number of iterations each pixel takes to escape, written as numbers
+//! rather than colors for post-processing in other tools. Values are averaged over the four
+//! samples of a pixel, and points that never escape take the iteration limit.
+//!
+//! Files ending in `.csv` hold one line of comma-separated values per row of the image, from the
+//! top. Anything else is written as a NumPy `.npy` array of little-endian 32-bit floats with shape
+//! `(height, width)`, which `numpy.load` reads directly.
+
+use crate::atomic::write_atomic;
+use crate::ImgDimensions;
+
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Which escape time is written for each pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EscapeValue {
+    /// The whole number of iterations before the orbit escapes.
+    Count,
+    /// The smooth iteration count, which varies continuously across the bands of whole counts.
+    Smooth,
+}
+
+impl Default for EscapeValue {
+    fn default() -> EscapeValue {
+        EscapeValue::Smooth
+    }
+}
+
+/// Formats the value as its command-line name, `count` or `smooth`.
+impl Display for EscapeValue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            EscapeValue::Count => "count",
+            EscapeValue::Smooth => "smooth",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for EscapeValue {
+    type Err = ParseEscapeValueError;
+
+    fn from_str(s: &str) -> Result<EscapeValue, ParseEscapeValueError> {
+        match s {
+            "count" => Ok(EscapeValue::Count),
+            "smooth" => Ok(EscapeValue::Smooth),
+            _ => Err(ParseEscapeValueError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseEscapeValueError;
+
+impl Display for ParseEscapeValueError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "unknown escape time, expected count or smooth")
+    }
+}
+
+impl std::error::Error for ParseEscapeValueError {}
+
+/// Writes `values`, in rows from the top, as CSV if `filename` ends in `.csv` and as a NumPy array
+/// otherwise. The file is replaced atomically.
+pub(crate) fn write(filename: &Path, dims: ImgDimensions, values: &[f32]) -> io::Result<()> {
+    let csv = filename
+        .extension()
+        .map_or(false, |e| e.eq_ignore_ascii_case("csv"));
+
+    write_atomic(filename, |path| {
+        let mut out = BufWriter::new(File::create(path)?);
+        if csv {
+            write_csv(&mut out, dims, values)?;
+        } else {
+            write_npy(&mut out, dims, values)?;
+        }
+        out.flush()
+    })
+}
+
+/// Writes a version 1.0 `.npy` array of shape `(height, width)`. The header is padded with spaces
+/// so that the data starts at a multiple of 64 bytes, as NumPy expects.
+pub fn write_npy<W: Write>(out: &mut W, dims: ImgDimensions, values: &[f32]) -> io::Result<()> {
+    const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        dims.height, dims.width
+    );
+    // The magic string and the header length take 10 bytes, and the header ends in a newline
+    let unpadded = MAGIC.len() + 2 + header.len() + 1;
+    header.extend(std::iter::repeat(' ').take((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    out.write_all(MAGIC)?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+    for v in values {
+        out.write_all(&v.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Writes one line of comma-separated values per row.
+pub fn write_csv<W: Write>(out: &mut W, dims: ImgDimensions, values: &[f32]) -> io::Result<()> {
+    for row in values.chunks_exact(dims.width as usize) {
+        let row: Vec<_> = row.iter().map(f32::to_string).collect();
+        writeln!(out, "{}", row.join(","))?;
+    }
+
+    Ok(())
+}
@@ -1,18 +1,59 @@
-use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, ImmutableBuffer};
-use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder, CommandBuffer};
-use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::buffer::cpu_access::ReadLockError;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, DeviceLocalBuffer, ImmutableBuffer};
+use vulkano::command_buffer::{
+    AutoCommandBuffer, AutoCommandBufferBuilder, BuildError, CommandBuffer, CommandBufferExecError,
+    CommandBufferExecFuture, CopyBufferImageError, DispatchError,
+};
+use vulkano::descriptor::descriptor_set::{
+    DescriptorSet, PersistentDescriptorSet, PersistentDescriptorSetBuildError,
+    PersistentDescriptorSetError,
+};
+use vulkano::device::Device;
 use vulkano::format::Format;
-use vulkano::image::{Dimensions, StorageImage};
-use vulkano::sync::GpuFuture;
+use vulkano::image::{Dimensions, ImageCreationError, StorageImage};
+use vulkano::instance::QueueFamily;
+use vulkano::memory::DeviceMemoryAllocError;
+use vulkano::pipeline::ComputePipelineAbstract;
+use vulkano::sync::{FenceSignalFuture, FlushError, GpuFuture};
+use vulkano::OomError;
 
 use palette::{LinSrgba, Srgba};
 
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use image::imageops;
+use image::{Rgba, RgbaImage};
+
+use crate::atomic::write_atomic;
+use crate::buddhabrot::{self, BuddhabrotOptions};
+use crate::contour;
+use crate::embedded;
+use crate::escape_time::{self, EscapeValue};
+use crate::heatmap::{self, CostReport};
+use crate::hud;
+use crate::icc::IccProfile;
+use crate::iim;
+use crate::mesh::{self, MeshOptions};
+use crate::name_template::NameTemplate;
+use crate::print::PrintSettings;
+use crate::sidecar;
 use crate::{JuliaContext, JuliaData};
 
-use std::cell::Cell;
-use std::fmt::{self, Debug, Formatter};
-use std::path::Path;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::iter;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
 use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::SystemTime;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ImgDimensions {
@@ -20,176 +61,2805 @@ pub struct ImgDimensions {
     pub height: u32,
 }
 
-pub struct JuliaExport {
-    cached_data: Cell<Option<JuliaExportCache>>,
+/// Encoder settings for AVIF exports.
+#[cfg(feature = "avif")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AvifOptions {
+    /// Quality from 1 to 100; higher is better and larger.
+    pub quality: f32,
+    /// Encoder speed from 1 to 10; lower is slower but compresses better.
+    pub speed: u8,
 }
 
-struct JuliaExportCache {
-    dims: ImgDimensions,
-    data: JuliaData,
-    command_buffer: Arc<AutoCommandBuffer>,
-    output_buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+#[cfg(feature = "avif")]
+impl Default for AvifOptions {
+    fn default() -> AvifOptions {
+        AvifOptions {
+            quality: 80.0,
+            speed: 4,
+        }
+    }
 }
 
-impl JuliaExport {
-    pub fn new() -> JuliaExport {
-        JuliaExport {
-            cached_data: Cell::new(None),
+/// Encoder settings for WebP exports.
+#[cfg(feature = "webp")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WebpOptions {
+    /// Quality from 0 to 100; higher is better and larger.
+    pub quality: f32,
+}
+
+#[cfg(feature = "webp")]
+impl Default for WebpOptions {
+    fn default() -> WebpOptions {
+        WebpOptions { quality: 80.0 }
+    }
+}
+
+/// The file format of exported images. Unless one is set with `JuliaExport::set_format`, it is
+/// chosen by the extension of the file name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputFormat {
+    Png,
+    /// JPEG, at the quality set with `JuliaExport::set_jpeg_quality`. JPEGs have no alpha, so
+    /// transparent pixels are written with their colors as if they were opaque.
+    Jpeg,
+    /// Uncompressed TIFF, with alpha and with 16-bit channels at `BitDepth::Sixteen`.
+    Tiff,
+    /// Uncompressed BMP, without alpha like JPEG.
+    Bmp,
+    /// WebP, which needs julia to be built with the `webp` feature.
+    Webp,
+    /// AVIF, which needs julia to be built with the `avif` feature.
+    Avif,
+    /// Binary PPM, without alpha like JPEG, with 16-bit channels at `BitDepth::Sixteen`.
+    Ppm,
+    /// PAM, the Netpbm format with alpha, with 16-bit channels at `BitDepth::Sixteen`.
+    Pam,
+}
+
+impl OutputFormat {
+    /// The format for the extension of `path`, if it names one.
+    pub fn from_path(path: &Path) -> Option<OutputFormat> {
+        path.extension()?
+            .to_str()?
+            .to_ascii_lowercase()
+            .parse()
+            .ok()
+    }
+
+    /// The extension given to generated file names in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Tiff => "tiff",
+            OutputFormat::Bmp => "bmp",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Avif => "avif",
+            OutputFormat::Ppm => "ppm",
+            OutputFormat::Pam => "pam",
+        }
+    }
+
+    /// Whether images in this format can be written to a stream, as `JuliaExport::export_to`
+    /// does, rather than only to a file.
+    pub fn streams(self) -> bool {
+        match self {
+            OutputFormat::Ppm | OutputFormat::Pam => true,
+            _ => false,
         }
     }
+}
 
-    fn regen_cache(&self, dims: ImgDimensions, data: &JuliaData, context: &JuliaContext) {
-        let shader_data = data.into_shader_data();
-        let (input_buffer, future) =
-            ImmutableBuffer::from_data(shader_data, BufferUsage::all(), context.queue().clone())
-                .unwrap();
+impl Default for OutputFormat {
+    fn default() -> OutputFormat {
+        OutputFormat::Png
+    }
+}
 
-        let image = StorageImage::new(
-            context.device().clone(),
-            Dimensions::Dim2d {
-                width: dims.width,
-                height: dims.height,
-            },
-            Format::R8G8B8A8Unorm,
-            Some(context.queue().family()),
+/// Formats the format as its command-line name: `png`, `jpeg`, `tiff`, `bmp`, `webp`, `avif`,
+/// `ppm` or `pam`.
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpeg",
+            OutputFormat::Tiff => "tiff",
+            OutputFormat::Bmp => "bmp",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Avif => "avif",
+            OutputFormat::Ppm => "ppm",
+            OutputFormat::Pam => "pam",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = ParseOutputFormatError;
+
+    fn from_str(s: &str) -> Result<OutputFormat, ParseOutputFormatError> {
+        match s {
+            "png" => Ok(OutputFormat::Png),
+            "jpeg" | "jpg" => Ok(OutputFormat::Jpeg),
+            "tiff" | "tif" => Ok(OutputFormat::Tiff),
+            "bmp" => Ok(OutputFormat::Bmp),
+            "webp" => Ok(OutputFormat::Webp),
+            "avif" => Ok(OutputFormat::Avif),
+            "ppm" => Ok(OutputFormat::Ppm),
+            "pam" => Ok(OutputFormat::Pam),
+            _ => Err(ParseOutputFormatError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseOutputFormatError;
+
+impl Display for ParseOutputFormatError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown image format, expected png, jpeg, tiff, bmp, webp, avif, ppm or pam"
         )
-        .unwrap();
+    }
+}
 
-        let output_buffer = CpuAccessibleBuffer::from_iter(
-            context.device().clone(),
-            BufferUsage::all(),
-            (0..dims.width * dims.height * 4).map(|_| 0u8),
+impl std::error::Error for ParseOutputFormatError {}
+
+/// What an export does when its file already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverwritePolicy {
+    /// Replace the existing file.
+    Replace,
+    /// Leave the existing file alone and number the export, as `julia_001.png`.
+    Number,
+}
+
+impl Default for OverwritePolicy {
+    fn default() -> OverwritePolicy {
+        OverwritePolicy::Replace
+    }
+}
+
+/// The number of bits per channel of exported color images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BitDepth {
+    Eight,
+    /// 16 bits per channel, which keeps smooth gradients free of banding at deep zooms. PNGs,
+    /// TIFFs and Netpbm images are written with 16-bit channels; other formats are reduced to 8
+    /// bits after rendering. Images aren't dithered at this depth.
+    Sixteen,
+}
+
+impl Default for BitDepth {
+    fn default() -> BitDepth {
+        BitDepth::Eight
+    }
+}
+
+/// The color space of exported color images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    Srgb,
+    /// Display P3: the wider gamut of the DCI-P3 primaries, with the D65 white point and the sRGB
+    /// transfer function. Gradients are blended and clamped in this gamut rather than in sRGB's,
+    /// so saturated blends keep colors that sRGB can't show. PNGs are tagged with a Display P3
+    /// profile and `cICP` chunk; other formats are written untagged.
+    DisplayP3,
+}
+
+impl ColorSpace {
+    pub(crate) fn shader_id(self) -> u32 {
+        match self {
+            ColorSpace::Srgb => 0,
+            ColorSpace::DisplayP3 => 1,
+        }
+    }
+
+    /// The coding-independent code points of values in the space encoded with `transfer`, for a
+    /// PNG `cICP` chunk: its primaries and transfer function as numbered in ITU-T H.273, an RGB
+    /// matrix, and full-range values.
+    fn cicp(self, transfer: Transfer) -> [u8; 4] {
+        let primaries = match self {
+            ColorSpace::Srgb => 1,
+            ColorSpace::DisplayP3 => 12,
+        };
+        let transfer = match transfer {
+            Transfer::Linear => 8,
+            Transfer::Srgb => 13,
+        };
+
+        [primaries, transfer, 0, 1]
+    }
+
+    /// The ICC profile of values in the space encoded with `transfer`.
+    fn profile(self, transfer: Transfer) -> IccProfile {
+        match (self, transfer) {
+            (ColorSpace::Srgb, Transfer::Srgb) => IccProfile::srgb(),
+            (ColorSpace::Srgb, Transfer::Linear) => IccProfile::linear_srgb(),
+            (ColorSpace::DisplayP3, Transfer::Srgb) => IccProfile::display_p3(),
+            (ColorSpace::DisplayP3, Transfer::Linear) => IccProfile::linear_display_p3(),
+        }
+    }
+}
+
+impl Default for ColorSpace {
+    fn default() -> ColorSpace {
+        ColorSpace::Srgb
+    }
+}
+
+/// Formats the color space as its command-line name, `srgb` or `p3`.
+impl Display for ColorSpace {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            ColorSpace::Srgb => "srgb",
+            ColorSpace::DisplayP3 => "p3",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ColorSpace {
+    type Err = ParseColorSpaceError;
+
+    fn from_str(s: &str) -> Result<ColorSpace, ParseColorSpaceError> {
+        match s {
+            "srgb" => Ok(ColorSpace::Srgb),
+            "p3" | "display-p3" => Ok(ColorSpace::DisplayP3),
+            _ => Err(ParseColorSpaceError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseColorSpaceError;
+
+impl Display for ParseColorSpaceError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "unknown color space, expected srgb or p3")
+    }
+}
+
+impl std::error::Error for ParseColorSpaceError {}
+
+/// A transfer function, relating stored color values to linear light.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transfer {
+    /// Values proportional to light, as the color pipelines compute them.
+    Linear,
+    /// The sRGB transfer function, which Display P3 shares, and which spends more of the values on
+    /// dark colors than linear values do.
+    Srgb,
+}
+
+impl Transfer {
+    pub(crate) fn shader_id(self) -> u32 {
+        match self {
+            Transfer::Linear => 0,
+            Transfer::Srgb => 1,
+        }
+    }
+}
+
+impl Default for Transfer {
+    fn default() -> Transfer {
+        Transfer::Srgb
+    }
+}
+
+/// Formats the transfer function as its command-line name, `linear` or `srgb`.
+impl Display for Transfer {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Transfer::Linear => "linear",
+            Transfer::Srgb => "srgb",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Transfer {
+    type Err = ParseTransferError;
+
+    fn from_str(s: &str) -> Result<Transfer, ParseTransferError> {
+        match s {
+            "linear" => Ok(Transfer::Linear),
+            "srgb" => Ok(Transfer::Srgb),
+            _ => Err(ParseTransferError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseTransferError;
+
+impl Display for ParseTransferError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "unknown transfer function, expected linear or srgb")
+    }
+}
+
+impl std::error::Error for ParseTransferError {}
+
+/// The transfer functions exported colors are encoded with at each stage after the color
+/// pipelines, which always compute linear colors from the linearized gradient. Values are converted
+/// between stages on the CPU only where their transfer functions differ.
+///
+/// The interactive view encodes with the sRGB transfer function on the GPU, so the default of
+/// encoding in the tonemapping pass and writing the result as is gives files with the same
+/// values as the view. Encoding in the tonemapping pass also quantizes after encoding, which keeps
+/// dark colors from banding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TransferStages {
+    /// The encoding of the image the tonemapping pass renders.
+    pub render: Transfer,
+    /// The encoding of the written file. Linear PNGs are tagged as such; other formats are
+    /// written untagged.
+    pub file: Transfer,
+}
+
+/// How far the rendering of an export has come, counted in the strips it is rendered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Progress {
+    /// The strips rendered and read back.
+    pub done: u32,
+    pub total: u32,
+}
+
+impl Progress {
+    /// The part of the export rendered, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.done as f32 / self.total as f32
+        }
+    }
+}
+
+/// Formats the progress as a whole percentage.
+impl Display for Progress {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}%", self.done * 100 / self.total.max(1))
+    }
+}
+
+/// The most strips an export is rendered in. Each is submitted separately, so that the
+/// progress of the export can be followed as they finish.
+const MAX_STRIPS: u32 = 16;
+
+/// A rectangle of pixels within an image, measured from its top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The corner, or the center, of the image that an overlay is placed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverlayPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl Default for OverlayPosition {
+    fn default() -> OverlayPosition {
+        OverlayPosition::BottomRight
+    }
+}
+
+/// Formats the position as its command-line name, such as `bottom-right`.
+impl Display for OverlayPosition {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            OverlayPosition::TopLeft => "top-left",
+            OverlayPosition::TopRight => "top-right",
+            OverlayPosition::BottomLeft => "bottom-left",
+            OverlayPosition::BottomRight => "bottom-right",
+            OverlayPosition::Center => "center",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for OverlayPosition {
+    type Err = ParseOverlayPositionError;
+
+    fn from_str(s: &str) -> Result<OverlayPosition, ParseOverlayPositionError> {
+        match s {
+            "top-left" => Ok(OverlayPosition::TopLeft),
+            "top-right" => Ok(OverlayPosition::TopRight),
+            "bottom-left" => Ok(OverlayPosition::BottomLeft),
+            "bottom-right" => Ok(OverlayPosition::BottomRight),
+            "center" => Ok(OverlayPosition::Center),
+            _ => Err(ParseOverlayPositionError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseOverlayPositionError;
+
+impl Display for ParseOverlayPositionError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown position, expected top-left, top-right, bottom-left, bottom-right or center"
         )
-        .unwrap();
-
-        let descriptor_set = Arc::new(
-            PersistentDescriptorSet::start(context.pipeline().clone(), 0)
-                .add_image(image.clone())
-                .unwrap()
-                .add_buffer(input_buffer.clone())
-                .unwrap()
-                .build()
-                .unwrap(),
-        );
+    }
+}
 
-        let command_buffer = Arc::new(
-            AutoCommandBufferBuilder::primary(
-                context.device().clone(),
-                context.queue().family(),
-            )
-            .unwrap()
-            .dispatch(
-                [dims.width / 8, dims.height / 8, 1],
-                context.pipeline().clone(),
-                descriptor_set.clone(),
-                (),
-            )
-            .unwrap()
-            .copy_image_to_buffer(image.clone(), output_buffer.clone())
-            .unwrap()
-            .build()
-            .unwrap(),
-        );
+impl std::error::Error for ParseOverlayPositionError {}
 
-        future
-            .then_signal_fence_and_flush()
-            .unwrap()
-            .wait(None)
-            .unwrap();
+/// A caption, a watermark image, or both, stamped onto color exports. The watermark is placed
+/// above the caption, and both are blended over the image after it is encoded for the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlay {
+    /// The caption, filled in with the parameters of each image.
+    pub caption: Option<NameTemplate>,
+    /// The watermark, at its own size.
+    pub watermark: Option<Arc<RgbaImage>>,
+    pub position: OverlayPosition,
+    /// How opaque the overlay is, from 0 to 1.
+    pub opacity: f32,
+    /// How many times the caption's 5x7 pixel glyphs are magnified, or `None` to scale them with
+    /// the image, one step for every 400 pixels of its height.
+    pub text_scale: Option<u32>,
+}
 
-        self.cached_data.set(Some(JuliaExportCache {
-            dims,
-            data: *data,
-            command_buffer,
-            output_buffer,
-        }));
+impl Overlay {
+    /// Reads a watermark image in any format the `image` crate reads.
+    pub fn open_watermark<P: AsRef<Path>>(path: P) -> io::Result<Arc<RgbaImage>> {
+        let image = image::open(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Arc::new(image.to_rgba()))
     }
 
-    pub fn export(
-        &self,
-        dims: ImgDimensions,
-        data: &JuliaData,
-        filename: &Path,
-        context: &JuliaContext,
-    ) {
-        let cache_opt = self.cached_data.take();
+    /// The overlay as it is stamped onto an image of `data` at `dims`, with its straight sRGBA
+    /// pixels, and the position of its top left corner in the image.
+    fn layer(&self, dims: ImgDimensions, data: &JuliaData) -> (RgbaImage, u32, u32) {
+        let scale = self
+            .text_scale
+            .unwrap_or(dims.height / 400)
+            .max(1)
+            .min(hud::MAX_SCALE);
+        let caption = self
+            .caption
+            .as_ref()
+            .map(|template| template.render(data, dims, SystemTime::now()));
+        // The caption is drawn on a translucent box, padded by a glyph pixel on every side
+        let pad = scale;
+        let (text_width, text_height) = match &caption {
+            Some(text) => (
+                hud::text_width(text, scale) + 2 * pad,
+                hud::line_height(scale) + 2 * pad,
+            ),
+            None => (0, 0),
+        };
+        let (mark_width, mark_height) = self
+            .watermark
+            .as_ref()
+            .map_or((0, 0), |mark| mark.dimensions());
 
-        match cache_opt {
-            None => self.regen_cache(dims, data, context),
-            Some(c) => {
-                if c.data != *data || c.dims != dims {
-                    self.regen_cache(dims, data, context);
-                } else {
-                    self.cached_data.set(Some(c));
+        let width = mark_width.max(text_width);
+        let height = mark_height + text_height;
+        let right = match self.position {
+            OverlayPosition::TopRight | OverlayPosition::BottomRight => true,
+            _ => false,
+        };
+        let align = |w: u32| if right { width - w } else { 0 };
+
+        let mut layer = RgbaImage::new(width, height);
+        if let Some(mark) = &self.watermark {
+            imageops::replace(&mut layer, mark.as_ref(), align(mark_width), 0);
+        }
+        if let Some(text) = &caption {
+            let x = align(text_width);
+            for dy in 0..text_height {
+                for dx in 0..text_width {
+                    layer.put_pixel(x + dx, mark_height + dy, Rgba([0, 0, 0, 128]));
                 }
             }
+            let white = Rgba([255, 255, 255, 255]);
+            hud::draw_text(&mut layer, x + pad, mark_height + pad, text, scale, white);
         }
 
-        self.export_core(filename, context);
+        // A margin of a fiftieth of the smaller side keeps the overlay off the edges
+        let margin = dims.width.min(dims.height) / 50;
+        let place = |size: u32, extent: u32, start: bool, center: bool| {
+            if center {
+                extent.saturating_sub(size) / 2
+            } else if start {
+                margin
+            } else {
+                extent.saturating_sub(size + margin)
+            }
+        };
+        let center = self.position == OverlayPosition::Center;
+        let top = match self.position {
+            OverlayPosition::TopLeft | OverlayPosition::TopRight => true,
+            _ => false,
+        };
+        let x = place(width, dims.width, !right, center);
+        let y = place(height, dims.height, top, center);
+
+        (layer, x, y)
     }
 
-    fn export_core(&self, filename: &Path, context: &JuliaContext) {
-        let cache = self.cached_data.take().unwrap();
-        //self.command_buffer(context)
-        cache
-            .command_buffer
-            .clone()
-            .execute(context.queue().clone())
-            .unwrap()
-            .then_signal_fence_and_flush()
-            .unwrap()
-            .wait(None)
-            .unwrap();
-
-        let img_contents = cache.output_buffer.read().unwrap();
-        let mut img_vec = Vec::new();
-        img_vec.extend_from_slice(&img_contents[..]);
-        drop(img_contents);
+    /// Blends the overlay over 8-bit RGBA pixels of an image of `data` at `dims`.
+    fn stamp8(&self, dims: ImgDimensions, data: &JuliaData, img_vec: &mut [u8]) {
+        self.stamp(dims, data, |i, [r, g, b, _], alpha| {
+            let over = [r, g, b, 255];
+            for (under, &over) in img_vec[i * 4..i * 4 + 4].iter_mut().zip(&over) {
+                let (u, o) = (*under as f32, over as f32);
+                *under = (u + (o - u) * alpha).round() as u8;
+            }
+        });
+    }
+
+    /// Blends the overlay over 16-bit RGBA pixels of an image of `data` at `dims`.
+    fn stamp16(&self, dims: ImgDimensions, data: &JuliaData, rgba: &mut [u16]) {
+        self.stamp(dims, data, |i, [r, g, b, _], alpha| {
+            let over = [r as u16 * 257, g as u16 * 257, b as u16 * 257, 65535];
+            for (under, &over) in rgba[i * 4..i * 4 + 4].iter_mut().zip(&over) {
+                let (u, o) = (*under as f32, over as f32);
+                *under = (u + (o - u) * alpha).round() as u16;
+            }
+        });
+    }
 
-        for c in img_vec.chunks_exact_mut(4) {
-            let lin = LinSrgba::<f32>::from_format(LinSrgba::new(c[0], c[1], c[2], c[3]));
-            let nonlin = Srgba::<u8>::from_format(Srgba::from_linear(lin));
-            let (r, g, b, a) = nonlin.into_components();
-            c.copy_from_slice(&[r, g, b, a]);
+    /// Calls `blend` with the index of every pixel the overlay covers, its color there, and how
+    /// much of it to blend in.
+    fn stamp<F>(&self, dims: ImgDimensions, data: &JuliaData, mut blend: F)
+    where
+        F: FnMut(usize, [u8; 4], f32),
+    {
+        let (layer, x, y) = self.layer(dims, data);
+        let opacity = self.opacity.max(0.0).min(1.0);
+
+        for (dx, dy, pixel) in layer.enumerate_pixels() {
+            let (px, py) = (x + dx, y + dy);
+            let alpha = pixel[3] as f32 / 255.0 * opacity;
+            if px < dims.width && py < dims.height && alpha > 0.0 {
+                blend((py * dims.width + px) as usize, pixel.0, alpha);
+            }
         }
+    }
+}
+
+/// One image of a batch export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportJob {
+    pub dims: ImgDimensions,
+    pub data: JuliaData,
+    pub filename: PathBuf,
+    /// Export the continuous potential instead of a colored image, as `export_potential` does.
+    pub potential: bool,
+}
 
-        /*
-        for subpx in &img_contents[..] {
-            img_vec.extend_from_slice(&subpx.to_ne_bytes());
+/// Renders and writes exported images, keeping the buffers of the last few renders of each kind
+/// for the next. Every file is written atomically with `write_atomic`. Exports can also be written
+/// in the background, by a worker thread started with the first of them.
+pub struct JuliaExport {
+    cached_data: RefCell<CacheLru>,
+    cached_potential: RefCell<CacheLru>,
+    cached_cost: RefCell<CacheLru>,
+    cached_escape: RefCell<CacheLru>,
+    #[cfg(feature = "avif")]
+    avif_options: Cell<AvifOptions>,
+    #[cfg(feature = "webp")]
+    webp_options: Cell<WebpOptions>,
+    format: Cell<Option<OutputFormat>>,
+    jpeg_quality: Cell<u8>,
+    sidecar: Cell<bool>,
+    overwrite: Cell<OverwritePolicy>,
+    icc_profile: RefCell<Option<IccProfile>>,
+    print_settings: Cell<Option<PrintSettings>>,
+    bit_depth: Cell<BitDepth>,
+    color_space: Cell<ColorSpace>,
+    transfer: Cell<TransferStages>,
+    overlay: RefCell<Option<Overlay>>,
+    queue: RefCell<Option<ExportQueue>>,
+}
+
+/// The settings files are written with, taken from a `JuliaExport` when an export is submitted so
+/// that it can be written on another thread.
+#[derive(Clone)]
+struct WriteSettings {
+    #[cfg(feature = "avif")]
+    avif_options: AvifOptions,
+    #[cfg(feature = "webp")]
+    webp_options: WebpOptions,
+    format: Option<OutputFormat>,
+    jpeg_quality: u8,
+    sidecar: bool,
+    icc_profile: Option<IccProfile>,
+    print_settings: Option<PrintSettings>,
+    bit_depth: BitDepth,
+    color_space: ColorSpace,
+    transfer: TransferStages,
+    overlay: Option<Overlay>,
+}
+
+/// The upload of an export's uniform data, which its command buffer is submitted after.
+type Upload = Box<dyn GpuFuture + Send + Sync>;
+
+struct JuliaExportCache {
+    dims: ImgDimensions,
+    data: JuliaData,
+    command_buffer: Arc<AutoCommandBuffer>,
+    output_buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+    /// The iteration data of a color render, which stays valid after the command buffer runs.
+    samples: Option<Arc<DeviceLocalBuffer<[u32]>>>,
+}
+
+/// How many renders of each kind `JuliaExport` keeps built. Each holds on to its buffers on the
+/// GPU and its readback buffer, so that exporting at any of a few sizes in turn never rebuilds
+/// them.
+const EXPORT_CACHE_SIZE: usize = 4;
+
+/// Recently exported renders, least recently used first.
+#[derive(Default)]
+struct CacheLru {
+    entries: VecDeque<JuliaExportCache>,
+}
+
+impl CacheLru {
+    /// Takes the render of `data` at `dims` out of the cache, if there is one.
+    fn take(&mut self, dims: ImgDimensions, data: &JuliaData) -> Option<JuliaExportCache> {
+        let i = self
+            .entries
+            .iter()
+            .position(|c| c.dims == dims && c.data == *data)?;
+        self.entries.remove(i)
+    }
+
+    /// The iteration data of a cached render at `dims` with the same iterations as `data`, if
+    /// there is one. It stays in the cache, since coloring it again leaves it as it is.
+    fn samples(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+    ) -> Option<Arc<DeviceLocalBuffer<[u32]>>> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|c| c.dims == dims && data.same_iterations(&c.data))
+            .find_map(|c| c.samples.clone())
+    }
+
+    /// Puts `cache` in as the most recently used, dropping the least recently used if the cache
+    /// is full.
+    fn put(&mut self, cache: JuliaExportCache) {
+        self.entries.push_back(cache);
+        if self.entries.len() > EXPORT_CACHE_SIZE {
+            self.entries.pop_front();
         }
-        */
+    }
 
-        /*
-        let image = ImageBuffer::<Rgba<u16>, _>::from_raw(
-            cache.dims.width,
-            cache.dims.height,
-            &img_contents[..],
-        )
-        .unwrap();
-        image.save(filename).unwrap();
-        */
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// The passes around a color pipeline, and the iteration data that it colors.
+struct ColorPasses {
+    /// The pass that finds the iteration data first, or `None` if the data is reused or the color
+    /// pipeline has no separate iteration pass.
+    iterate: Option<Arc<dyn ComputePipelineAbstract + Send + Sync>>,
+    tonemap: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+    samples: Arc<DeviceLocalBuffer<[u32]>>,
+    color_space: ColorSpace,
+    /// The transfer function the tonemapping pass encodes with.
+    transfer: Transfer,
+}
+
+impl ColorPasses {
+    /// The passes for rendering `data` at the given dimensions and tonemapping it with `tonemap`
+    /// into `color_space`, encoded with `transfer`. If `samples` is given, it holds iteration data
+    /// found for the same iterations, which is colored again instead of iterating.
+    fn new(
+        dims: ImgDimensions,
+        data: &JuliaData,
+        tonemap: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+        samples: Option<Arc<DeviceLocalBuffer<[u32]>>>,
+        color_space: ColorSpace,
+        transfer: Transfer,
+        context: &JuliaContext,
+    ) -> Result<ColorPasses, JuliaExportError> {
+        ImageTooLargeError::check(dims, context)?;
+        let new_samples =
+            |dims| samples_buffer(dims, context.device(), context.export_queue().family());
+        let (iterate, samples) = match (samples, context.iteration_pipeline(data)) {
+            (Some(samples), _) => (None, samples),
+            (None, Some(iterate)) => (Some(iterate), new_samples(dims)?),
+            // The buffer is bound but unused
+            (None, None) => (
+                None,
+                new_samples(ImgDimensions {
+                    width: 1,
+                    height: 1,
+                })?,
+            ),
+        };
+
+        Ok(ColorPasses {
+            iterate,
+            tonemap,
+            samples,
+            color_space,
+            transfer,
+        })
+    }
+}
+
+/// A job whose rendering has been submitted, in strips with their own buffers.
+struct InFlightJob {
+    job: ExportJob,
+    /// The strips of the image, from the top.
+    strips: Vec<InFlightStrip>,
+}
+
+struct InFlightStrip {
+    cache: JuliaExportCache,
+    fence: FenceSignalFuture<CommandBufferExecFuture<Upload, Arc<AutoCommandBuffer>>>,
+}
 
-        image::save_buffer(filename, img_vec.as_ref(), cache.dims.width, cache.dims.height,
-            image::ColorType::RGBA(8)).unwrap();
+impl InFlightJob {
+    /// Waits for the job to render, calling `progress` as each strip is read back, then writes it
+    /// to `filename` with `settings`.
+    fn finish<F>(
+        self,
+        filename: &Path,
+        settings: &WriteSettings,
+        mut progress: F,
+    ) -> Result<(), JuliaExportError>
+    where
+        F: FnMut(Progress),
+    {
+        let InFlightJob { job, strips } = self;
+        let total = strips.len() as u32;
+        let mut img_vec = Vec::new();
+        for (i, strip) in strips.into_iter().enumerate() {
+            strip.fence.wait(None)?;
+            img_vec.extend(JuliaExport::read_output(&strip.cache)?);
+            progress(Progress {
+                done: i as u32 + 1,
+                total,
+            });
+        }
 
-        self.cached_data.set(Some(cache));
+        if job.potential {
+            write_potential(filename, job.dims, &img_vec)?;
+        } else {
+            let from = settings.transfer.render;
+            settings.write_color(filename, job.dims, &job.data, img_vec, from)?;
+        }
+        Ok(settings.write_sidecar(filename, job.dims, &job.data)?)
     }
 }
 
-impl Debug for JuliaExport {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let cache = self.cached_data.take();
-        let res = f
-            .debug_struct("JuliaExport")
-            .field(
-                "cached_data",
-                &match cache {
-                    None => String::from("None"),
-                    Some(ref c) => format!("{:?}", c.data),
-                },
-            )
-            .finish();
-        self.cached_data.set(cache);
-        res
+/// What the export thread reports back.
+enum QueueEvent {
+    /// The progress of the export being written.
+    Progress(Progress),
+    Written(PathBuf),
+    /// An export that couldn't be written, such as for a full disk. The worker goes on with the
+    /// next one.
+    Failed(PathBuf, JuliaExportError),
+}
+
+/// A background export once it's done: its file, and whether it was written.
+pub type FinishedExport = (PathBuf, Result<(), JuliaExportError>);
+
+/// A worker thread that waits for submitted exports and writes them, in the order they were
+/// submitted, so that the thread submitting them never waits on the GPU or the disk.
+struct ExportQueue {
+    jobs: Option<Sender<(InFlightJob, PathBuf, WriteSettings)>>,
+    events: Receiver<QueueEvent>,
+    /// The files of the exports not yet written, in the order they were submitted.
+    pending: VecDeque<PathBuf>,
+    /// The progress of the first of them, once it has been reported.
+    progress: Option<Progress>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ExportQueue {
+    fn new() -> io::Result<ExportQueue> {
+        let (jobs, queued) = mpsc::channel::<(InFlightJob, PathBuf, WriteSettings)>();
+        let (report, events) = mpsc::channel();
+        let worker = thread::Builder::new()
+            .name(String::from("julia-export"))
+            .spawn(move || {
+                // Nobody is left to tell once the queue is dropped, so failed reports are ignored
+                for (job, filename, settings) in queued {
+                    let written = job.finish(&filename, &settings, |progress| {
+                        let _ = report.send(QueueEvent::Progress(progress));
+                    });
+                    let event = match written {
+                        Ok(()) => QueueEvent::Written(filename),
+                        Err(err) => QueueEvent::Failed(filename, err),
+                    };
+                    let _ = report.send(event);
+                }
+            })?;
+
+        Ok(ExportQueue {
+            jobs: Some(jobs),
+            events,
+            pending: VecDeque::new(),
+            progress: None,
+            worker: Some(worker),
+        })
+    }
+
+    fn submit(
+        &mut self,
+        job: InFlightJob,
+        filename: PathBuf,
+        settings: WriteSettings,
+    ) -> Result<(), JuliaExportError> {
+        let jobs = self.jobs.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::BrokenPipe, "the export queue is shut down")
+        })?;
+
+        self.pending.push_back(filename.clone());
+        if jobs.send((job, filename, settings)).is_err() {
+            self.resume_panic();
+        }
+        Ok(())
+    }
+
+    /// The exports finished since the last poll, without waiting for any more.
+    fn poll(&mut self) -> Vec<FinishedExport> {
+        let mut written = Vec::new();
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => self.receive(event, &mut written),
+                Err(TryRecvError::Empty) => return written,
+                Err(TryRecvError::Disconnected) => self.resume_panic(),
+            }
+        }
+    }
+
+    /// The exports finished since the last poll, once every pending export is finished.
+    fn wait(&mut self) -> Vec<FinishedExport> {
+        let mut written = Vec::new();
+        while !self.pending.is_empty() {
+            match self.events.recv() {
+                Ok(event) => self.receive(event, &mut written),
+                Err(_) => self.resume_panic(),
+            }
+        }
+        written
+    }
+
+    fn receive(&mut self, event: QueueEvent, finished: &mut Vec<FinishedExport>) {
+        let (filename, result) = match event {
+            QueueEvent::Progress(progress) => {
+                self.progress = Some(progress);
+                return;
+            }
+            QueueEvent::Written(filename) => (filename, Ok(())),
+            QueueEvent::Failed(filename, err) => (filename, Err(err)),
+        };
+
+        self.pending.pop_front();
+        self.progress = None;
+        finished.push((filename, result));
+    }
+
+    /// Passes on the panic that stopped the worker thread, which only stops early by panicking.
+    fn resume_panic(&mut self) -> ! {
+        match self.worker.take().map(JoinHandle::join) {
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            _ => panic!("the export thread stopped"),
+        }
+    }
+}
+
+impl Drop for ExportQueue {
+    /// Waits for every submitted export to be written.
+    fn drop(&mut self) {
+        self.jobs.take();
+        if let Some(worker) = self.worker.take() {
+            // A panic on the worker has already been reported by it
+            let _ = worker.join();
+        }
+    }
+}
+
+impl JuliaExport {
+    pub fn new() -> JuliaExport {
+        JuliaExport {
+            cached_data: RefCell::new(CacheLru::default()),
+            cached_potential: RefCell::new(CacheLru::default()),
+            cached_cost: RefCell::new(CacheLru::default()),
+            cached_escape: RefCell::new(CacheLru::default()),
+            #[cfg(feature = "avif")]
+            avif_options: Cell::new(AvifOptions::default()),
+            #[cfg(feature = "webp")]
+            webp_options: Cell::new(WebpOptions::default()),
+            format: Cell::new(None),
+            jpeg_quality: Cell::new(90),
+            sidecar: Cell::new(false),
+            overwrite: Cell::new(OverwritePolicy::default()),
+            icc_profile: RefCell::new(Some(IccProfile::srgb())),
+            print_settings: Cell::new(None),
+            bit_depth: Cell::new(BitDepth::default()),
+            color_space: Cell::new(ColorSpace::default()),
+            transfer: Cell::new(TransferStages::default()),
+            overlay: RefCell::new(None),
+            queue: RefCell::new(None),
+        }
+    }
+
+    /// Sets the ICC profile embedded in exported PNGs, or disables embedding. Defaults to sRGB.
+    pub fn set_icc_profile(&self, profile: Option<IccProfile>) {
+        self.icc_profile.replace(profile);
+    }
+
+    pub fn set_overlay(&self, overlay: Option<Overlay>) {
+        self.overlay.replace(overlay);
+    }
+
+    /// Sets the print settings recorded in exported PNGs, as their physical pixel size and a
+    /// description of the paper and margins. None are recorded by default.
+    pub fn set_print_settings(&self, settings: Option<PrintSettings>) {
+        self.print_settings.set(settings);
+    }
+
+    #[cfg(feature = "avif")]
+    pub fn set_avif_options(&self, options: AvifOptions) {
+        self.avif_options.set(options);
+    }
+
+    #[cfg(feature = "webp")]
+    pub fn set_webp_options(&self, options: WebpOptions) {
+        self.webp_options.set(options);
+    }
+
+    /// Sets the format every export is written in, whatever the extension of its file name, or
+    /// chooses it by the extension again. Chosen by the extension by default.
+    pub fn set_format(&self, format: Option<OutputFormat>) {
+        self.format.set(format);
+    }
+
+    /// The extension to give generated file names: that of the format set, or `png`.
+    pub fn extension(&self) -> &'static str {
+        self.format.get().unwrap_or_default().extension()
+    }
+
+    /// Sets the quality of JPEG exports, from 1 to 100. Defaults to 90.
+    pub fn set_jpeg_quality(&self, quality: u8) {
+        self.jpeg_quality.set(quality.max(1).min(100));
+    }
+
+    /// Sets whether every export is followed by a sidecar file, its name with `.json` appended,
+    /// holding its dimensions and parameters. Off by default.
+    pub fn set_sidecar(&self, sidecar: bool) {
+        self.sidecar.set(sidecar);
+    }
+
+    /// Sets what happens when an export's file already exists. Defaults to replacing it.
+    pub fn set_overwrite_policy(&self, policy: OverwritePolicy) {
+        self.overwrite.set(policy);
+    }
+
+    /// The file an export to `filename` is written to: `filename` itself, unless it exists and
+    /// the overwrite policy numbers exports, in which case the first free name with `_001`,
+    /// `_002` and so on after its stem. Files that background exports are yet to write count as
+    /// existing.
+    pub fn target(&self, filename: &Path) -> PathBuf {
+        let taken = |path: &Path| path.exists() || self.is_pending(path);
+        if self.overwrite.get() == OverwritePolicy::Replace || !taken(filename) {
+            return filename.to_path_buf();
+        }
+
+        let stem = filename
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let extension = filename
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+
+        let mut i = 1;
+        loop {
+            let path = filename.with_file_name(format!("{}_{:03}{}", stem, i, extension));
+            if !taken(&path) {
+                return path;
+            }
+            i += 1;
+        }
+    }
+
+    /// Whether a background export is yet to write `path`.
+    fn is_pending(&self, path: &Path) -> bool {
+        self.queue
+            .borrow()
+            .as_ref()
+            .map_or(false, |queue| queue.pending.iter().any(|p| p == path))
+    }
+
+    /// Sets the bit depth of exported color images. Defaults to 8 bits.
+    pub fn set_bit_depth(&self, depth: BitDepth) {
+        if self.bit_depth.replace(depth) != depth {
+            self.cached_data.borrow_mut().clear();
+        }
+    }
+
+    /// Sets the color space of exported color images. Defaults to sRGB. In Display P3, the default
+    /// sRGB profile is replaced by a Display P3 one in exported PNGs; a profile given with
+    /// `set_icc_profile` is embedded as is.
+    pub fn set_color_space(&self, space: ColorSpace) {
+        if self.color_space.replace(space) != space {
+            self.cached_data.borrow_mut().clear();
+        }
+    }
+
+    /// Sets the transfer functions exported colors are encoded with. By default they are encoded
+    /// with the sRGB transfer function on the GPU, as in the interactive view.
+    pub fn set_transfer(&self, stages: TransferStages) {
+        if self.transfer.replace(stages).render != stages.render {
+            self.cached_data.borrow_mut().clear();
+        }
+    }
+
+    /// Drops the cached command buffers, which hold on to the context's orbit trap.
+    pub fn clear_caches(&self) {
+        self.cached_data.borrow_mut().clear();
+        self.cached_potential.borrow_mut().clear();
+        self.cached_cost.borrow_mut().clear();
+    }
+
+    /// Builds the command buffer and readback buffer for rendering `data` with `pipeline` into a
+    /// `format` image of the given dimensions. The format must have 4 channels, or be a single
+    /// 32-bit channel. `color` is given for the color pipelines, which bind the context's orbit
+    /// trap and the iteration data, take its histogram equalization, and render into a
+    /// floating-point image that the tonemapping pipeline then maps into the `format` image.
+    fn build_cache<P>(
+        dims: ImgDimensions,
+        data: &JuliaData,
+        format: Format,
+        pipeline: P,
+        color: Option<ColorPasses>,
+        context: &JuliaContext,
+    ) -> Result<JuliaExportCache, JuliaExportError>
+    where
+        P: ComputePipelineAbstract + Clone + Send + Sync + 'static,
+    {
+        let (cache, upload) = Self::build_job(dims, data, None, format, pipeline, color, context)?;
+        upload.then_signal_fence_and_flush()?.wait(None)?;
+        Ok(cache)
+    }
+
+    /// Like `build_cache`, but returns the upload of the uniform data instead of waiting for it,
+    /// so that the command buffer can be submitted after it without blocking on the queue. If
+    /// `region` is given, the image is that rectangle of a larger one with the given dimensions.
+    fn build_job<P>(
+        dims: ImgDimensions,
+        data: &JuliaData,
+        region: Option<(ImgDimensions, PixelRect)>,
+        format: Format,
+        pipeline: P,
+        color: Option<ColorPasses>,
+        context: &JuliaContext,
+    ) -> Result<(JuliaExportCache, Upload), JuliaExportError>
+    where
+        P: ComputePipelineAbstract + Clone + Send + Sync + 'static,
+    {
+        ImageTooLargeError::check(dims, context)?;
+        let trap_texture = context.trap_texture();
+        let mut shader_data = data.into_shader_data();
+        if let Some((full_dims, rect)) = region {
+            shader_data.origin = [rect.x, rect.y];
+            shader_data.full_size = [full_dims.width, full_dims.height];
+        }
+        if let Some(color) = &color {
+            shader_data.color_space = color.color_space.shader_id();
+            shader_data.transfer = color.transfer.shader_id();
+            trap_texture.apply(&mut shader_data);
+            if let Some(equalization) = context.equalization(data)? {
+                equalization.apply(&mut shader_data);
+            }
+        }
+
+        let (input_buffer, future) = ImmutableBuffer::from_data(
+            shader_data,
+            BufferUsage::all(),
+            context.export_queue().clone(),
+        )?;
+
+        let new_image = |format| {
+            StorageImage::new(
+                context.device().clone(),
+                Dimensions::Dim2d {
+                    width: dims.width,
+                    height: dims.height,
+                },
+                format,
+                Some(context.export_queue().family()),
+            )
+        };
+        let image = new_image(format)?;
+        let hdr = if color.is_some() {
+            Some(new_image(Format::R32G32B32A32Sfloat)?)
+        } else {
+            None
+        };
+        let samples = color.as_ref().map(|c| c.samples.clone());
+
+        let output_buffer = CpuAccessibleBuffer::from_iter(
+            context.device().clone(),
+            BufferUsage::all(),
+            (0..dims.width * dims.height * bytes_per_pixel(format)).map(|_| 0u8),
+        )?;
+
+        let descriptor_set: Arc<dyn DescriptorSet + Send + Sync> = match (&hdr, &samples) {
+            (Some(hdr), Some(samples)) => Arc::new(
+                PersistentDescriptorSet::start(pipeline.clone(), 0)
+                    .add_image(hdr.clone())?
+                    .add_buffer(input_buffer.clone())?
+                    .add_sampled_image(
+                        trap_texture.image().clone(),
+                        trap_texture.sampler().clone(),
+                    )?
+                    .add_buffer(samples.clone())?
+                    .build()?,
+            ),
+            _ => Arc::new(
+                PersistentDescriptorSet::start(pipeline.clone(), 0)
+                    .add_image(image.clone())?
+                    .add_buffer(input_buffer.clone())?
+                    .build()?,
+            ),
+        };
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            context.device().clone(),
+            context.export_queue().family(),
+        )?;
+
+        // The iteration pass has the same layout as the color pipeline that follows it
+        if let Some(iterate) = color.as_ref().and_then(|c| c.iterate.clone()) {
+            builder = builder.dispatch(
+                [dims.width / 8, dims.height / 8, 1],
+                iterate,
+                descriptor_set.clone(),
+                (),
+            )?;
+        }
+
+        builder = builder.dispatch(
+            [dims.width / 8, dims.height / 8, 1],
+            pipeline.clone(),
+            descriptor_set.clone(),
+            (),
+        )?;
+
+        if let (Some(ColorPasses { tonemap, .. }), Some(hdr)) = (color, hdr) {
+            let tonemap_set = Arc::new(
+                PersistentDescriptorSet::start(tonemap.clone(), 0)
+                    .add_image(image.clone())?
+                    .add_buffer(input_buffer.clone())?
+                    .add_image(hdr)?
+                    .build()?,
+            );
+            builder = builder.dispatch(
+                [dims.width / 8, dims.height / 8, 1],
+                tonemap,
+                tonemap_set,
+                (),
+            )?;
+        }
+
+        let command_buffer = Arc::new(
+            builder
+                .copy_image_to_buffer(image.clone(), output_buffer.clone())?
+                .build()?,
+        );
+
+        let cache = JuliaExportCache {
+            dims,
+            data: *data,
+            command_buffer,
+            output_buffer,
+            samples,
+        };
+
+        Ok((cache, Box::new(future)))
+    }
+
+    /// Takes the cache of the requested parameters out of `lru`, or builds it with `regen` if
+    /// there is none.
+    fn take_cache<F>(
+        lru: &RefCell<CacheLru>,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        regen: F,
+    ) -> Result<JuliaExportCache, JuliaExportError>
+    where
+        F: FnOnce() -> Result<JuliaExportCache, JuliaExportError>,
+    {
+        let cached = lru.borrow_mut().take(dims, data);
+        match cached {
+            Some(c) => Ok(c),
+            None => regen(),
+        }
+    }
+
+    /// Renders `data` at `dims` and writes it to `filename`, in the format its extension names or
+    /// the one set. If the render or the write fails, nothing is cached and the error is returned;
+    /// a file that was being replaced is left as it was.
+    pub fn export(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        filename: &Path,
+        context: &JuliaContext,
+    ) -> Result<(), JuliaExportError> {
+        let filename = &self.target(filename);
+        let data = &self.color_data(data);
+        let cache = self.color_cache(dims, data, context)?;
+
+        self.export_core(&cache, filename, context)?;
+        self.write_settings().write_sidecar(filename, dims, data)?;
+        self.cached_data.borrow_mut().put(cache);
+        Ok(())
+    }
+
+    /// Renders `data` like `export`, but writes it to `out` rather than to a file, for piping into
+    /// other programs. Only formats that `OutputFormat::streams` can be written this way; others
+    /// give an I/O error of kind `InvalidInput`.
+    pub fn export_to<W: Write>(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        format: OutputFormat,
+        out: &mut W,
+        context: &JuliaContext,
+    ) -> Result<(), JuliaExportError> {
+        if !format.streams() {
+            return Err(IoErr(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} images can't be written to a stream", format),
+            )));
+        }
+
+        let data = &self.color_data(data);
+        let cache = self.color_cache(dims, data, context)?;
+
+        let img_vec = Self::execute(&cache, context)?;
+        self.write_settings()
+            .write_netpbm_color(out, cache.dims, data, img_vec, format)?;
+        self.cached_data.borrow_mut().put(cache);
+        Ok(())
+    }
+
+    /// Renders `data` like `export`, but returns its pixels as rows of 8-bit RGBA values, with the
+    /// overlay stamped on, rather than writing them. 16-bit renders are rounded to 8 bits. For
+    /// encoders that take raw frames, such as those of videos.
+    pub fn render_rgba(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        context: &JuliaContext,
+    ) -> Result<Vec<u8>, JuliaExportError> {
+        let data = &self.color_data(data);
+        let cache = self.color_cache(dims, data, context)?;
+
+        let img_vec = Self::execute(&cache, context)?;
+        let rgba = self.write_settings().rgba8(cache.dims, data, img_vec);
+        self.cached_data.borrow_mut().put(cache);
+        Ok(rgba)
+    }
+
+    /// The cached color render of `data` at `dims`, or a new one. A palette change colors the
+    /// iteration data of a cached render of the same view again without iterating.
+    fn color_cache(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        context: &JuliaContext,
+    ) -> Result<JuliaExportCache, JuliaExportError> {
+        let mut lru = self.cached_data.borrow_mut();
+        match lru.take(dims, data) {
+            Some(c) => Ok(c),
+            None => {
+                let samples = lru.samples(dims, data);
+                drop(lru);
+                let (format, tonemap) = self.color_target(context)?;
+                let (space, transfer) = self.color_output();
+                let color =
+                    ColorPasses::new(dims, data, tonemap, samples, space, transfer, context)?;
+                let pipeline = context.color_pipeline(data);
+                Self::build_cache(dims, data, format, pipeline, Some(color), context)
+            }
+        }
+    }
+
+    /// Exports the continuous potential of the set as a grayscale image. A `.pfm` file name
+    /// produces raw 32-bit float values; anything else produces a 16-bit PNG normalized to the
+    /// largest potential in the image.
+    pub fn export_potential(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        filename: &Path,
+        context: &JuliaContext,
+    ) -> Result<(), JuliaExportError> {
+        let filename = &self.target(filename);
+        let cache = Self::take_cache(&self.cached_potential, dims, data, || {
+            Self::build_cache(
+                dims,
+                data,
+                Format::R32Sfloat,
+                context.potential_pipeline().clone(),
+                None,
+                context,
+            )
+        })?;
+
+        let img_vec = Self::execute(&cache, context)?;
+        write_potential(filename, cache.dims, &img_vec)?;
+        self.write_settings().write_sidecar(filename, dims, data)?;
+
+        self.cached_potential.borrow_mut().put(cache);
+        Ok(())
+    }
+
+    /// Exports a heatmap of the iteration steps spent on each pixel, colored with viridis from
+    /// none to the most that four samples can take, and summarizes the cost over tiles.
+    pub fn export_heatmap(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        filename: &Path,
+        context: &JuliaContext,
+    ) -> Result<CostReport, JuliaExportError> {
+        let filename = &self.target(filename);
+        let cache = Self::take_cache(&self.cached_cost, dims, data, || {
+            Self::build_cache(
+                dims,
+                data,
+                Format::R32Sfloat,
+                context.cost_pipeline().clone(),
+                None,
+                context,
+            )
+        })?;
+
+        let img_vec = Self::execute(&cache, context)?;
+        let costs: Vec<f32> = img_vec
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+
+        let rgba = heatmap::colorize(&costs, 4.0 * data.iters as f32);
+        self.write_srgb(filename, cache.dims, &rgba)?;
+        self.write_settings().write_sidecar(filename, dims, data)?;
+
+        let report = CostReport::analyze(&costs, cache.dims, heatmap::TILE_SIZE);
+        self.cached_cost.borrow_mut().put(cache);
+        Ok(report)
+    }
+
+    /// Exports the escape time of every pixel as raw numbers, as a NumPy array, or as CSV if the
+    /// file name ends in `.csv`. Not for Newton fractals.
+    pub fn export_escape_time(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        value: EscapeValue,
+        filename: &Path,
+        context: &JuliaContext,
+    ) -> Result<(), JuliaExportError> {
+        let filename = &self.target(filename);
+        let values = self.escape_times(dims, data, value, context)?;
+        escape_time::write(filename, dims, &values)?;
+        Ok(self.write_settings().write_sidecar(filename, dims, data)?)
+    }
+
+    /// Exports contours of the smooth escape time of `data` at each of `thresholds` as an SVG.
+    /// Not for Newton fractals.
+    pub fn export_contours(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        thresholds: &[f32],
+        filename: &Path,
+        context: &JuliaContext,
+    ) -> Result<(), JuliaExportError> {
+        let filename = &self.target(filename);
+        let values = self.escape_times(dims, data, EscapeValue::Smooth, context)?;
+        contour::write_svg(filename, dims, &values, thresholds)?;
+        Ok(self.write_settings().write_sidecar(filename, dims, data)?)
+    }
+
+    /// Exports the smooth escape time of `data` as a heightmap mesh, as STL or OBJ depending on the
+    /// file name. Not for Newton fractals.
+    pub fn export_mesh(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        options: &MeshOptions,
+        filename: &Path,
+        context: &JuliaContext,
+    ) -> Result<(), JuliaExportError> {
+        let filename = &self.target(filename);
+        let values = self.escape_times(dims, data, EscapeValue::Smooth, context)?;
+        mesh::write(filename, dims, &values, data.iters, options)?;
+        Ok(self.write_settings().write_sidecar(filename, dims, data)?)
+    }
+
+    /// Renders the escape time of every pixel of `data`, in rows from the top.
+    fn escape_times(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        value: EscapeValue,
+        context: &JuliaContext,
+    ) -> Result<Vec<f32>, JuliaExportError> {
+        let cache = Self::take_cache(&self.cached_escape, dims, data, || {
+            Self::build_cache(
+                dims,
+                data,
+                Format::R32G32B32A32Sfloat,
+                context.escape_pipeline().clone(),
+                None,
+                context,
+            )
+        })?;
+
+        // The count is in the red channel and the smooth count in the green
+        let i = match value {
+            EscapeValue::Count => 0,
+            EscapeValue::Smooth => 4,
+        };
+        let img_vec = Self::execute(&cache, context)?;
+        let values: Vec<f32> = img_vec
+            .chunks_exact(16)
+            .map(|b| f32::from_ne_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]]))
+            .collect();
+
+        self.cached_escape.borrow_mut().put(cache);
+        Ok(values)
+    }
+
+    /// Renders the continuous potential of `data`, one value per pixel in rows from the top, with
+    /// buffers of its own.
+    pub(crate) fn render_potential(
+        dims: ImgDimensions,
+        data: &JuliaData,
+        context: &JuliaContext,
+    ) -> Result<Vec<f32>, JuliaExportError> {
+        let cache = Self::build_cache(
+            dims,
+            data,
+            Format::R32Sfloat,
+            context.potential_pipeline().clone(),
+            None,
+            context,
+        )?;
+
+        Ok(Self::execute(&cache, context)?
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+
+    /// Renders a Buddhabrot with its own buffers, which aren't cached since every render samples
+    /// afresh.
+    pub fn export_buddhabrot(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        options: &BuddhabrotOptions,
+        filename: &Path,
+        context: &JuliaContext,
+    ) -> Result<(), JuliaExportError> {
+        let filename = &self.target(filename);
+        ImageTooLargeError::check(dims, context)?;
+        let img_vec = buddhabrot::render(dims, data, options, context);
+        let settings = self.write_settings();
+        settings.write_color(filename, dims, data, img_vec, Transfer::Linear)?;
+        Ok(settings.write_sidecar(filename, dims, data)?)
+    }
+
+    /// Renders a Julia set by inverse iteration, which like Buddhabrots isn't cached.
+    pub fn export_iim(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        options: &BuddhabrotOptions,
+        filename: &Path,
+        context: &JuliaContext,
+    ) -> Result<(), JuliaExportError> {
+        let filename = &self.target(filename);
+        ImageTooLargeError::check(dims, context)?;
+        let img_vec = iim::render(dims, data, options, context);
+        let settings = self.write_settings();
+        settings.write_color(filename, dims, data, img_vec, Transfer::Linear)?;
+        Ok(settings.write_sidecar(filename, dims, data)?)
+    }
+
+    /// Renders and writes a sequence of images, keeping up to `max_in_flight` of them submitted to
+    /// the GPU at once so that it renders the next ones while earlier ones are read back and
+    /// encoded. Every job gets its own buffers, so the caches used by `export` are left alone.
+    /// Stops at the first job that fails, once the jobs submitted before it are written.
+    pub fn export_batch<I>(
+        &self,
+        jobs: I,
+        max_in_flight: usize,
+        context: &JuliaContext,
+    ) -> Result<(), JuliaExportError>
+    where
+        I: IntoIterator<Item = ExportJob>,
+    {
+        let max_in_flight = max_in_flight.max(1);
+        let mut in_flight = VecDeque::with_capacity(max_in_flight);
+
+        for job in jobs {
+            if in_flight.len() >= max_in_flight {
+                self.finish_job(in_flight.pop_front().unwrap())?;
+            }
+
+            match self.submit_job(job, context) {
+                Ok(job) => in_flight.push_back(job),
+                Err(err) => {
+                    for job in in_flight {
+                        self.finish_job(job)?;
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        for job in in_flight {
+            self.finish_job(job)?;
+        }
+        Ok(())
+    }
+
+    /// Renders and writes `job` like `export_batch`, but returns as soon as its rendering is
+    /// submitted to the GPU. A worker thread waits for it and writes it, in the order background
+    /// exports are submitted, with the settings they were submitted with. Returns the file the
+    /// export is written to, or the error if it couldn't be submitted. Errors in writing it are
+    /// passed on as panics by `finished_exports` and `wait_for_exports`.
+    pub fn export_in_background(
+        &self,
+        mut job: ExportJob,
+        context: &JuliaContext,
+    ) -> Result<PathBuf, JuliaExportError> {
+        let filename = self.target(&job.filename);
+        job.filename = filename.clone();
+        let in_flight = self.submit_job(job, context)?;
+
+        let mut queue = self.queue.borrow_mut();
+        if queue.is_none() {
+            *queue = Some(ExportQueue::new()?);
+        }
+        if let Some(queue) = queue.as_mut() {
+            queue.submit(in_flight, filename.clone(), self.write_settings())?;
+        }
+        Ok(filename)
+    }
+
+    /// The background exports finished since the last call, in the order they were submitted,
+    /// each with the error that kept it from being written, if any. Never waits for exports still
+    /// being written.
+    pub fn finished_exports(&self) -> Vec<FinishedExport> {
+        match self.queue.borrow_mut().as_mut() {
+            Some(queue) => queue.poll(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Waits for every background export to finish, and returns those finished since
+    /// `finished_exports` was last called.
+    pub fn wait_for_exports(&self) -> Vec<FinishedExport> {
+        match self.queue.borrow_mut().as_mut() {
+            Some(queue) => queue.wait(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The number of background exports not yet written.
+    pub fn pending_exports(&self) -> usize {
+        self.queue
+            .borrow()
+            .as_ref()
+            .map_or(0, |queue| queue.pending.len())
+    }
+
+    /// The progress of the first background export not yet written, as of the last call to
+    /// `finished_exports`, or `None` if none is pending or its progress is yet to be reported.
+    pub fn export_progress(&self) -> Option<Progress> {
+        self.queue
+            .borrow()
+            .as_ref()
+            .and_then(|queue| queue.progress)
+    }
+
+    /// Renders and writes `job` like `export_batch`, calling `progress` each time another strip of
+    /// the image has been rendered, so that long exports can show how far they have come. Returns
+    /// the file the export is written to.
+    pub fn export_with_progress<F>(
+        &self,
+        job: ExportJob,
+        context: &JuliaContext,
+        progress: F,
+    ) -> Result<PathBuf, JuliaExportError>
+    where
+        F: FnMut(Progress),
+    {
+        let filename = self.target(&job.filename);
+        self.submit_job(job, context)?
+            .finish(&filename, &self.write_settings(), progress)?;
+        Ok(filename)
+    }
+
+    /// See `JuliaContext::render_region`.
+    pub fn render_region(
+        full_dims: ImgDimensions,
+        rect: PixelRect,
+        data: &JuliaData,
+        context: &JuliaContext,
+    ) -> Result<Vec<u8>, JuliaExportError> {
+        RegionError::check(full_dims, rect)?;
+        let dims = ImgDimensions {
+            width: rect.width,
+            height: rect.height,
+        };
+        let color = ColorPasses::new(
+            dims,
+            data,
+            context.tonemap_pipeline().clone(),
+            None,
+            ColorSpace::Srgb,
+            Transfer::Srgb,
+            context,
+        )?;
+        let (cache, upload) = Self::build_job(
+            dims,
+            data,
+            Some((full_dims, rect)),
+            Format::R8G8B8A8Unorm,
+            context.color_pipeline(data),
+            Some(color),
+            context,
+        )?;
+
+        upload
+            .then_execute(context.export_queue().clone(), cache.command_buffer.clone())?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
+
+        Self::read_output(&cache)
+    }
+
+    fn submit_job(
+        &self,
+        mut job: ExportJob,
+        context: &JuliaContext,
+    ) -> Result<InFlightJob, JuliaExportError> {
+        if !job.potential {
+            job.data = self.color_data(&job.data);
+        }
+
+        let strips = strips(job.dims)
+            .into_iter()
+            .map(|rect| self.submit_strip(&job, rect, context))
+            .collect::<Result<_, _>>()?;
+        Ok(InFlightJob { job, strips })
+    }
+
+    /// Submits the rendering of the `rect` strip of `job`'s image, with its own buffers.
+    fn submit_strip(
+        &self,
+        job: &ExportJob,
+        rect: PixelRect,
+        context: &JuliaContext,
+    ) -> Result<InFlightStrip, JuliaExportError> {
+        let dims = ImgDimensions {
+            width: rect.width,
+            height: rect.height,
+        };
+        let (format, pipeline, color) = if job.potential {
+            let pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync> =
+                context.potential_pipeline().clone();
+            (Format::R32Sfloat, pipeline, None)
+        } else {
+            let (format, tonemap) = self.color_target(context)?;
+            let (space, transfer) = self.color_output();
+            let color = ColorPasses::new(dims, &job.data, tonemap, None, space, transfer, context)?;
+            (format, context.color_pipeline(&job.data), Some(color))
+        };
+
+        let region = Some((job.dims, rect));
+        let (cache, upload) =
+            Self::build_job(dims, &job.data, region, format, pipeline, color, context)?;
+        let fence = upload
+            .then_execute(context.export_queue().clone(), cache.command_buffer.clone())?
+            .then_signal_fence_and_flush()?;
+
+        Ok(InFlightStrip { cache, fence })
+    }
+
+    fn finish_job(&self, in_flight: InFlightJob) -> Result<(), JuliaExportError> {
+        let filename = self.target(&in_flight.job.filename);
+        in_flight.finish(&filename, &self.write_settings(), |_| ())
+    }
+
+    /// The current settings files are written with.
+    fn write_settings(&self) -> WriteSettings {
+        WriteSettings {
+            #[cfg(feature = "avif")]
+            avif_options: self.avif_options.get(),
+            #[cfg(feature = "webp")]
+            webp_options: self.webp_options.get(),
+            format: self.format.get(),
+            jpeg_quality: self.jpeg_quality.get(),
+            sidecar: self.sidecar.get(),
+            icc_profile: self.icc_profile.borrow().clone(),
+            print_settings: self.print_settings.get(),
+            bit_depth: self.bit_depth.get(),
+            color_space: self.color_space.get(),
+            transfer: self.transfer.get(),
+            overlay: self.overlay.borrow().clone(),
+        }
+    }
+
+    /// `data` as it is rendered at the export bit depth. Dithering only hides the steps between 8-bit
+    /// values, so it is turned off at 16 bits.
+    fn color_data(&self, data: &JuliaData) -> JuliaData {
+        match self.bit_depth.get() {
+            BitDepth::Eight => *data,
+            BitDepth::Sixteen => JuliaData {
+                dither: false,
+                ..*data
+            },
+        }
+    }
+
+    /// The color space and transfer function the tonemapping pass renders exports into.
+    fn color_output(&self) -> (ColorSpace, Transfer) {
+        (self.color_space.get(), self.transfer.get().render)
+    }
+
+    /// The image format and tonemapping pipeline for the export bit depth.
+    fn color_target(
+        &self,
+        context: &JuliaContext,
+    ) -> Result<(Format, Arc<dyn ComputePipelineAbstract + Send + Sync>), UnsupportedError> {
+        match self.bit_depth.get() {
+            BitDepth::Eight => Ok((Format::R8G8B8A8Unorm, context.tonemap_pipeline().clone())),
+            BitDepth::Sixteen => match context.tonemap_wide_pipeline() {
+                Some(pipeline) => Ok((Format::R16G16B16A16Unorm, pipeline.clone())),
+                None => Err(UnsupportedError("16-bit exports")),
+            },
+        }
+    }
+
+    fn execute(
+        cache: &JuliaExportCache,
+        context: &JuliaContext,
+    ) -> Result<Vec<u8>, JuliaExportError> {
+        cache
+            .command_buffer
+            .clone()
+            .execute(context.export_queue().clone())?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
+
+        Self::read_output(cache)
+    }
+
+    fn read_output(cache: &JuliaExportCache) -> Result<Vec<u8>, JuliaExportError> {
+        let img_contents = cache.output_buffer.read()?;
+        let mut img_vec = Vec::new();
+        img_vec.extend_from_slice(&img_contents[..]);
+        Ok(img_vec)
+    }
+
+    fn export_core(
+        &self,
+        cache: &JuliaExportCache,
+        filename: &Path,
+        context: &JuliaContext,
+    ) -> Result<(), JuliaExportError> {
+        let img_vec = Self::execute(cache, context)?;
+        let settings = self.write_settings();
+        let from = settings.transfer.render;
+        Ok(settings.write_color(filename, cache.dims, &cache.data, img_vec, from)?)
+    }
+
+    /// Writes sRGBA pixels in the export format.
+    pub fn write_srgb(
+        &self,
+        filename: &Path,
+        dims: ImgDimensions,
+        img_vec: &[u8],
+    ) -> io::Result<()> {
+        let settings = self.write_settings();
+        settings.write_encoded(
+            filename,
+            dims,
+            img_vec,
+            ColorSpace::Srgb,
+            Transfer::Srgb,
+            None,
+        )
+    }
+}
+
+impl WriteSettings {
+    /// Converts RGBA pixels read back from the GPU, at the export bit depth and encoded with
+    /// `from`, to the file's transfer function, stamps the overlay of `data` onto them, and writes
+    /// them in the export format. Display P3 shares the sRGB transfer function, so both spaces are
+    /// encoded alike.
+    fn write_color(
+        &self,
+        filename: &Path,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        mut img_vec: Vec<u8>,
+        from: Transfer,
+    ) -> io::Result<()> {
+        let space = self.color_space;
+        let to = self.transfer.file;
+        if self.bit_depth == BitDepth::Eight {
+            convert_transfer8(&mut img_vec, from, to);
+            if let Some(overlay) = &self.overlay {
+                overlay.stamp8(dims, data, &mut img_vec);
+            }
+            return self.write_encoded(filename, dims, &img_vec, space, to, Some(data));
+        }
+
+        let mut rgba = convert_transfer16(&img_vec, from, to);
+        if let Some(overlay) = &self.overlay {
+            overlay.stamp16(dims, data, &mut rgba);
+        }
+        match self.format_for(filename) {
+            Some(OutputFormat::Png) => {
+                let bytes: Vec<u8> = rgba.iter().flat_map(|c| c.to_be_bytes().to_vec()).collect();
+                let print = self.print_settings;
+                write_rgba_png(
+                    filename,
+                    dims,
+                    &bytes,
+                    png::BitDepth::Sixteen,
+                    self.png_profile(space, to).as_ref(),
+                    print.as_ref(),
+                    space,
+                    to,
+                    Some(data),
+                )
+            }
+            Some(OutputFormat::Tiff) => {
+                let bytes: Vec<u8> = rgba.iter().flat_map(|c| c.to_le_bytes().to_vec()).collect();
+                write_rgba_tiff(filename, dims, &bytes, 16)
+            }
+            Some(format @ OutputFormat::Ppm) | Some(format @ OutputFormat::Pam) => {
+                let bytes: Vec<u8> = rgba.iter().flat_map(|c| c.to_be_bytes().to_vec()).collect();
+                write_netpbm_file(filename, dims, &bytes, true, format)
+            }
+            _ => {
+                let bytes: Vec<u8> = rgba
+                    .iter()
+                    .map(|&c| ((c as u32 * 255 + 32767) / 65535) as u8)
+                    .collect();
+                self.write_encoded(filename, dims, &bytes, space, to, Some(data))
+            }
+        }
+    }
+
+    /// Converts RGBA pixels read back from the GPU like `write_color`, rounding 16-bit channels to
+    /// 8 bits.
+    fn rgba8(&self, dims: ImgDimensions, data: &JuliaData, mut img_vec: Vec<u8>) -> Vec<u8> {
+        let TransferStages { render, file } = self.transfer;
+        if self.bit_depth == BitDepth::Eight {
+            convert_transfer8(&mut img_vec, render, file);
+            if let Some(overlay) = &self.overlay {
+                overlay.stamp8(dims, data, &mut img_vec);
+            }
+            return img_vec;
+        }
+
+        let mut rgba = convert_transfer16(&img_vec, render, file);
+        if let Some(overlay) = &self.overlay {
+            overlay.stamp16(dims, data, &mut rgba);
+        }
+        rgba.iter()
+            .map(|&c| ((c as u32 * 255 + 32767) / 65535) as u8)
+            .collect()
+    }
+
+    /// Converts RGBA pixels read back from the GPU like `write_color`, and writes them to `out` as
+    /// PPM or PAM.
+    fn write_netpbm_color<W: Write>(
+        &self,
+        out: &mut W,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        mut img_vec: Vec<u8>,
+        format: OutputFormat,
+    ) -> io::Result<()> {
+        let TransferStages { render, file } = self.transfer;
+        if self.bit_depth == BitDepth::Eight {
+            convert_transfer8(&mut img_vec, render, file);
+            if let Some(overlay) = &self.overlay {
+                overlay.stamp8(dims, data, &mut img_vec);
+            }
+            return write_netpbm(out, dims, &img_vec, false, format);
+        }
+
+        let mut rgba = convert_transfer16(&img_vec, render, file);
+        if let Some(overlay) = &self.overlay {
+            overlay.stamp16(dims, data, &mut rgba);
+        }
+        let bytes: Vec<u8> = rgba.iter().flat_map(|c| c.to_be_bytes().to_vec()).collect();
+        write_netpbm(out, dims, &bytes, true, format)
+    }
+
+    /// The format to write `filename` in: the one set, or the one its extension names.
+    fn format_for(&self, filename: &Path) -> Option<OutputFormat> {
+        self.format.or_else(|| OutputFormat::from_path(filename))
+    }
+
+    /// Writes 8-bit RGBA pixels in `space`, encoded with `transfer`, in the export format. PNGs
+    /// carry `view`, the view the pixels were rendered from, if given. File names that name no
+    /// format are left to the `image` crate to make out.
+    fn write_encoded(
+        &self,
+        filename: &Path,
+        dims: ImgDimensions,
+        img_vec: &[u8],
+        space: ColorSpace,
+        transfer: Transfer,
+        view: Option<&JuliaData>,
+    ) -> io::Result<()> {
+        match self.format_for(filename) {
+            Some(OutputFormat::Avif) => {
+                #[cfg(feature = "avif")]
+                return write_avif(filename, dims, img_vec, self.avif_options);
+                #[cfg(not(feature = "avif"))]
+                return Err(missing_feature("AVIF", "avif"));
+            }
+            Some(OutputFormat::Webp) => {
+                #[cfg(feature = "webp")]
+                return write_webp(filename, dims, img_vec, self.webp_options);
+                #[cfg(not(feature = "webp"))]
+                return Err(missing_feature("WebP", "webp"));
+            }
+            Some(OutputFormat::Png) => {
+                let print = self.print_settings;
+                write_rgba_png(
+                    filename,
+                    dims,
+                    img_vec,
+                    png::BitDepth::Eight,
+                    self.png_profile(space, transfer).as_ref(),
+                    print.as_ref(),
+                    space,
+                    transfer,
+                    view,
+                )
+            }
+            Some(OutputFormat::Tiff) => write_rgba_tiff(filename, dims, img_vec, 8),
+            Some(format @ OutputFormat::Ppm) | Some(format @ OutputFormat::Pam) => {
+                write_netpbm_file(filename, dims, img_vec, false, format)
+            }
+            Some(format @ OutputFormat::Jpeg) | Some(format @ OutputFormat::Bmp) => {
+                write_opaque(filename, dims, img_vec, format, self.jpeg_quality)
+            }
+            None => write_atomic(filename, |path| {
+                image::save_buffer(
+                    path,
+                    img_vec,
+                    dims.width,
+                    dims.height,
+                    image::ColorType::RGBA(8),
+                )
+            }),
+        }
+    }
+
+    /// The ICC profile embedded in PNGs in `space` encoded with `transfer`: the one set, with the
+    /// default sRGB profile swapped for the profile of the space and transfer function.
+    fn png_profile(&self, space: ColorSpace, transfer: Transfer) -> Option<IccProfile> {
+        match &self.icc_profile {
+            Some(p) if *p == IccProfile::srgb() => Some(space.profile(transfer)),
+            profile => profile.clone(),
+        }
+    }
+
+    /// Writes the sidecar file of the image at `filename`, if sidecars are turned on.
+    fn write_sidecar(
+        &self,
+        filename: &Path,
+        dims: ImgDimensions,
+        data: &JuliaData,
+    ) -> io::Result<()> {
+        if self.sidecar {
+            sidecar::write(filename, dims, data)?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts 8-bit RGBA pixels encoded with `from` to `to` in place. Alpha is always linear.
+fn convert_transfer8(img_vec: &mut [u8], from: Transfer, to: Transfer) {
+    for c in img_vec.chunks_exact_mut(4) {
+        let (r, g, b, a) = match (from, to) {
+            (Transfer::Linear, Transfer::Srgb) => {
+                let lin = LinSrgba::<f32>::from_format(LinSrgba::new(c[0], c[1], c[2], c[3]));
+                Srgba::<u8>::from_format(Srgba::from_linear(lin)).into_components()
+            }
+            (Transfer::Srgb, Transfer::Linear) => {
+                let nonlin = Srgba::<f32>::from_format(Srgba::new(c[0], c[1], c[2], c[3]));
+                LinSrgba::<u8>::from_format(nonlin.into_linear()).into_components()
+            }
+            _ => return,
+        };
+        c.copy_from_slice(&[r, g, b, a]);
+    }
+}
+
+/// Converts 16-bit RGBA pixels, as native-endian bytes, encoded with `from` to `to`.
+fn convert_transfer16(img_vec: &[u8], from: Transfer, to: Transfer) -> Vec<u16> {
+    let mut channels: Vec<u16> = img_vec
+        .chunks_exact(2)
+        .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+        .collect();
+
+    for c in channels.chunks_exact_mut(4) {
+        let (r, g, b, a) = match (from, to) {
+            (Transfer::Linear, Transfer::Srgb) => {
+                let lin = LinSrgba::<f32>::from_format(LinSrgba::new(c[0], c[1], c[2], c[3]));
+                Srgba::<u16>::from_format(Srgba::from_linear(lin)).into_components()
+            }
+            (Transfer::Srgb, Transfer::Linear) => {
+                let nonlin = Srgba::<f32>::from_format(Srgba::new(c[0], c[1], c[2], c[3]));
+                LinSrgba::<u16>::from_format(nonlin.into_linear()).into_components()
+            }
+            _ => break,
+        };
+        c.copy_from_slice(&[r, g, b, a]);
+    }
+    channels
+}
+
+/// The size of a pixel of the formats exports render to.
+fn bytes_per_pixel(format: Format) -> u32 {
+    match format {
+        Format::R16G16B16A16Unorm => 8,
+        Format::R32G32B32A32Sfloat => 16,
+        _ => 4,
+    }
+}
+
+/// The number of 32-bit words of iteration data that the color shaders keep for each pixel.
+const SAMPLE_WORDS_PER_PIXEL: usize = 16;
+
+/// Creates a buffer for the iteration data of an image with the given dimensions, which the
+/// iteration pass fills in and the color pipelines color.
+pub(crate) fn samples_buffer(
+    dims: ImgDimensions,
+    device: &Arc<Device>,
+    family: QueueFamily,
+) -> Result<Arc<DeviceLocalBuffer<[u32]>>, DeviceMemoryAllocError> {
+    DeviceLocalBuffer::array(
+        device.clone(),
+        dims.width as usize * dims.height as usize * SAMPLE_WORDS_PER_PIXEL,
+        BufferUsage {
+            storage_buffer: true,
+            ..BufferUsage::none()
+        },
+        iter::once(family),
+    )
+}
+
+/// The strips of rows, from the top, that an image of `dims` is rendered in: at most
+/// `MAX_STRIPS` of them, all but the last a multiple of 8 rows high.
+fn strips(dims: ImgDimensions) -> Vec<PixelRect> {
+    let rows = ((dims.height + MAX_STRIPS * 8 - 1) / (MAX_STRIPS * 8) * 8).max(8);
+    (0..dims.height)
+        .step_by(rows as usize)
+        .map(|y| PixelRect {
+            x: 0,
+            y,
+            width: dims.width,
+            height: rows.min(dims.height - y),
+        })
+        .collect()
+}
+
+fn has_extension(path: &Path, ext: &str) -> bool {
+    path.extension()
+        .map(|e| e.eq_ignore_ascii_case(ext))
+        .unwrap_or(false)
+}
+
+/// The error for a format julia was built without the feature for.
+#[cfg(not(all(feature = "avif", feature = "webp")))]
+fn missing_feature(format: &str, feature: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "{} export requires julia to be built with the `{}` feature",
+            format, feature
+        ),
+    )
+}
+
+/// Writes 8-bit sRGBA pixels as an AVIF file.
+#[cfg(feature = "avif")]
+fn write_avif(
+    filename: &Path,
+    dims: ImgDimensions,
+    rgba: &[u8],
+    options: AvifOptions,
+) -> io::Result<()> {
+    let pixels: Vec<_> = rgba
+        .chunks_exact(4)
+        .map(|c| ravif::RGBA8::new(c[0], c[1], c[2], c[3]))
+        .collect();
+    let img = ravif::Img::new(&pixels[..], dims.width as usize, dims.height as usize);
+
+    let encoded = ravif::Encoder::new()
+        .with_quality(options.quality)
+        .with_alpha_quality(options.quality)
+        .with_speed(options.speed)
+        .encode_rgba(img)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+    write_atomic(filename, |path| std::fs::write(path, &encoded.avif_file))
+}
+
+/// Writes 8-bit sRGBA pixels as a WebP file.
+#[cfg(feature = "webp")]
+fn write_webp(
+    filename: &Path,
+    dims: ImgDimensions,
+    rgba: &[u8],
+    options: WebpOptions,
+) -> io::Result<()> {
+    let encoded =
+        libwebp::Encoder::from_rgba(rgba, dims.width, dims.height).encode(options.quality);
+
+    write_atomic(filename, |path| std::fs::write(path, &*encoded))
+}
+
+/// Writes 8-bit RGBA pixels without their alpha, as a JPEG of the given quality or as a BMP.
+fn write_opaque(
+    filename: &Path,
+    dims: ImgDimensions,
+    rgba: &[u8],
+    format: OutputFormat,
+    quality: u8,
+) -> io::Result<()> {
+    let rgb: Vec<u8> = rgba.chunks_exact(4).flat_map(|c| c[..3].to_vec()).collect();
+    let color = image::ColorType::RGB(8);
+
+    write_atomic(filename, |path| {
+        let mut out = BufWriter::new(File::create(path)?);
+        match format {
+            OutputFormat::Jpeg => {
+                let mut encoder = image::jpeg::JPEGEncoder::new_with_quality(&mut out, quality);
+                encoder.encode(&rgb, dims.width, dims.height, color)?;
+            }
+            _ => {
+                let mut encoder = image::bmp::BMPEncoder::new(&mut out);
+                encoder.encode(&rgb, dims.width, dims.height, color)?;
+            }
+        }
+        out.flush()
+    })
+}
+
+/// Writes RGBA pixels to a file as PPM or PAM, replacing it atomically.
+fn write_netpbm_file(
+    filename: &Path,
+    dims: ImgDimensions,
+    rgba: &[u8],
+    sixteen: bool,
+    format: OutputFormat,
+) -> io::Result<()> {
+    write_atomic(filename, |path| {
+        let mut out = BufWriter::new(File::create(path)?);
+        write_netpbm(&mut out, dims, rgba, sixteen, format)?;
+        out.flush()
+    })
+}
+
+/// Writes RGBA pixels as a binary Netpbm image: PAM with alpha, or PPM without it. Channels are a
+/// byte each, or two big-endian bytes each if `sixteen` is set, as the pixels are given.
+fn write_netpbm<W: Write>(
+    out: &mut W,
+    dims: ImgDimensions,
+    rgba: &[u8],
+    sixteen: bool,
+    format: OutputFormat,
+) -> io::Result<()> {
+    let (maxval, bytes) = if sixteen { (65535, 2) } else { (255, 1) };
+
+    match format {
+        OutputFormat::Pam => {
+            write!(
+                out,
+                "P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL {}\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+                dims.width, dims.height, maxval
+            )?;
+            out.write_all(rgba)
+        }
+        _ => {
+            write!(out, "P6\n{} {}\n{}\n", dims.width, dims.height, maxval)?;
+            for pixel in rgba.chunks_exact(4 * bytes) {
+                out.write_all(&pixel[..3 * bytes])?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Writes RGBA pixels as an uncompressed baseline TIFF in a single strip, with `bits` of 8 or 16
+/// per channel. 16-bit channels are little-endian, as the file's byte order says.
+fn write_rgba_tiff(filename: &Path, dims: ImgDimensions, rgba: &[u8], bits: u16) -> io::Result<()> {
+    const SHORT: u16 = 3;
+    const LONG: u16 = 4;
+    const ENTRIES: u32 = 11;
+
+    // The header, then the directory, then the bits per sample, then the pixels
+    let bits_offset = 8 + 2 + ENTRIES * 12 + 4;
+    let data_offset = bits_offset + 8;
+
+    // Each entry is a tag, the type of its values, their count, and the values themselves if they
+    // fit in 4 bytes or their offset if they don't
+    let entries: [(u16, u16, u32, u32); ENTRIES as usize] = [
+        (256, LONG, 1, dims.width),
+        (257, LONG, 1, dims.height),
+        (258, SHORT, 4, bits_offset),
+        // No compression, RGB
+        (259, SHORT, 1, 1),
+        (262, SHORT, 1, 2),
+        (273, LONG, 1, data_offset),
+        (277, SHORT, 1, 4),
+        (278, LONG, 1, dims.height),
+        (279, LONG, 1, rgba.len() as u32),
+        // Channels interleaved, with the fourth unassociated alpha
+        (284, SHORT, 1, 1),
+        (338, SHORT, 1, 2),
+    ];
+
+    write_atomic(filename, |path| {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(b"II")?;
+        out.write_all(&42u16.to_le_bytes())?;
+        out.write_all(&8u32.to_le_bytes())?;
+
+        out.write_all(&(ENTRIES as u16).to_le_bytes())?;
+        for &(tag, kind, count, value) in entries.iter() {
+            out.write_all(&tag.to_le_bytes())?;
+            out.write_all(&kind.to_le_bytes())?;
+            out.write_all(&count.to_le_bytes())?;
+            if kind == SHORT && count == 1 {
+                out.write_all(&(value as u16).to_le_bytes())?;
+                out.write_all(&[0; 2])?;
+            } else {
+                out.write_all(&value.to_le_bytes())?;
+            }
+        }
+        out.write_all(&0u32.to_le_bytes())?;
+
+        for _ in 0..4 {
+            out.write_all(&bits.to_le_bytes())?;
+        }
+        out.write_all(rgba)?;
+
+        out.flush()
+    })
+}
+
+/// Writes potential values read back from the GPU, as a PFM for a `.pfm` file name and a 16-bit
+/// PNG otherwise.
+fn write_potential(filename: &Path, dims: ImgDimensions, img_vec: &[u8]) -> io::Result<()> {
+    let potential: Vec<f32> = img_vec
+        .chunks_exact(4)
+        .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+
+    if has_extension(filename, "pfm") {
+        write_pfm(filename, dims, &potential)
+    } else {
+        write_gray16(filename, dims, &potential)
+    }
+}
+
+/// Writes a grayscale Portable FloatMap. PFM stores rows bottom-to-top.
+fn write_pfm(filename: &Path, dims: ImgDimensions, values: &[f32]) -> io::Result<()> {
+    write_atomic(filename, |path| {
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "Pf\n{} {}\n-1.0\n", dims.width, dims.height)?;
+
+        for row in values.chunks_exact(dims.width as usize).rev() {
+            for v in row {
+                out.write_all(&v.to_le_bytes())?;
+            }
+        }
+
+        out.flush()
+    })
+}
+
+/// Writes a 16-bit grayscale PNG, scaling the values so the largest maps to white.
+fn write_gray16(filename: &Path, dims: ImgDimensions, values: &[f32]) -> io::Result<()> {
+    let max = values.iter().cloned().fold(0.0, f32::max);
+    let scale = if max > 0.0 { 65535.0 / max } else { 0.0 };
+
+    let mut bytes = Vec::with_capacity(values.len() * 2);
+    for v in values {
+        let v = (v * scale).round().max(0.0).min(65535.0) as u16;
+        bytes.extend_from_slice(&v.to_be_bytes());
+    }
+
+    write_atomic(filename, |path| {
+        let out = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(out, dims.width, dims.height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Sixteen);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&bytes))
+            .map_err(png_err)
+    })
+}
+
+/// Writes sRGBA pixels as a PNG, embedding `icc` if given. 16-bit channels are big-endian. Print
+/// settings are recorded as the physical size of the pixels and a description in a `Comment` text
+/// chunk, and the view the pixels were rendered from, if given, in a `julia` text chunk.
+fn write_rgba_png(
+    filename: &Path,
+    dims: ImgDimensions,
+    rgba: &[u8],
+    depth: png::BitDepth,
+    icc: Option<&IccProfile>,
+    print: Option<&PrintSettings>,
+    space: ColorSpace,
+    transfer: Transfer,
+    view: Option<&JuliaData>,
+) -> io::Result<()> {
+    write_atomic(filename, |path| {
+        let out = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(out, dims.width, dims.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(depth);
+
+        let mut writer = encoder.write_header().map_err(png_err)?;
+        if let Some(icc) = icc {
+            writer
+                .write_chunk(png::chunk::ChunkType(*b"iCCP"), &iccp_chunk(icc)?)
+                .map_err(png_err)?;
+        }
+        // sRGB is what readers assume anyway, so only other encodings are tagged
+        if (space, transfer) != (ColorSpace::Srgb, Transfer::Srgb) {
+            writer
+                .write_chunk(png::chunk::ChunkType(*b"cICP"), &space.cicp(transfer))
+                .map_err(png_err)?;
+        }
+        if let Some(print) = print {
+            writer
+                .write_chunk(png::chunk::ChunkType(*b"pHYs"), &phys_chunk(print))
+                .map_err(png_err)?;
+            writer
+                .write_chunk(png::chunk::ChunkType(*b"tEXt"), &print_text_chunk(print))
+                .map_err(png_err)?;
+        }
+        if let Some(data) = view {
+            writer
+                .write_chunk(
+                    png::chunk::ChunkType(*b"tEXt"),
+                    &embedded::text_chunk(dims, data),
+                )
+                .map_err(png_err)?;
+        }
+
+        writer.write_image_data(rgba).map_err(png_err)
+    })
+}
+
+/// The contents of a PNG `iCCP` chunk: a Latin-1 profile name, a null separator, the compression
+/// method (always zlib), and the compressed profile.
+fn iccp_chunk(icc: &IccProfile) -> io::Result<Vec<u8>> {
+    let mut name: Vec<u8> = icc
+        .name()
+        .bytes()
+        .filter(|b| (b' '..=b'~').contains(b))
+        .take(79)
+        .collect();
+    if name.is_empty() {
+        name.extend_from_slice(b"ICC profile");
+    }
+
+    let mut chunk = name;
+    chunk.extend_from_slice(&[0, 0]);
+
+    let mut encoder = ZlibEncoder::new(chunk, Compression::default());
+    encoder.write_all(icc.data())?;
+    encoder.finish()
+}
+
+/// The contents of a PNG `pHYs` chunk giving the print resolution: pixels per meter along each
+/// axis, and the unit specifier 1 for meters.
+fn phys_chunk(print: &PrintSettings) -> Vec<u8> {
+    let ppm = print.pixels_per_meter().to_be_bytes();
+
+    let mut chunk = Vec::with_capacity(9);
+    chunk.extend_from_slice(&ppm);
+    chunk.extend_from_slice(&ppm);
+    chunk.push(1);
+    chunk
+}
+
+/// The contents of a PNG `tEXt` chunk describing the print settings under the `Comment` keyword.
+/// The text is Latin-1, which the description never leaves.
+fn print_text_chunk(print: &PrintSettings) -> Vec<u8> {
+    let mut chunk = b"Comment\0Print: ".to_vec();
+    chunk.extend(
+        print
+            .to_string()
+            .bytes()
+            .filter(|b| (b' '..=b'~').contains(b)),
+    );
+    chunk
+}
+
+fn png_err(err: png::EncodingError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
+
+// A failed export: a render the GPU couldn't allocate, build or run, or a file that couldn't be
+// written
+impl_error! {
+    pub enum JuliaExportError {
+        VkAllocErr(DeviceMemoryAllocError),
+        VkImageErr(ImageCreationError),
+        VkDescSetErr(PersistentDescriptorSetBuildError),
+        VkDescSetAddErr(PersistentDescriptorSetError),
+        VkOomErr(OomError),
+        VkDispatchErr(DispatchError),
+        VkCopyErr(CopyBufferImageError),
+        VkCmdBufBuildErr(BuildError),
+        VkExecErr(CommandBufferExecError),
+        VkFlushErr(FlushError),
+        VkReadErr(ReadLockError),
+        TooLargeErr(ImageTooLargeError),
+        RegionErr(RegionError),
+        UnsupportedErr(UnsupportedError),
+        IoErr(io::Error),
+    }
+}
+
+/// An image wider or taller than the device can render at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageTooLargeError {
+    pub dims: ImgDimensions,
+    /// The largest width or height the device can render, from
+    /// `JuliaContext::max_image_dimension`.
+    pub max: u32,
+}
+
+impl ImageTooLargeError {
+    /// An error if `dims` is too large for the device of `context`.
+    fn check(dims: ImgDimensions, context: &JuliaContext) -> Result<(), ImageTooLargeError> {
+        let max = context.max_image_dimension();
+        if dims.width > max || dims.height > max {
+            Err(ImageTooLargeError { dims, max })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Display for ImageTooLargeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a {}x{} image is larger than the GPU can render, at most {} pixels along each side",
+            self.dims.width, self.dims.height, self.max
+        )
+    }
+}
+
+impl Error for ImageTooLargeError {}
+
+/// A region that can't be rendered: one that is empty, reaches outside of its image, or whose
+/// sides aren't multiples of 8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionError {
+    pub full_dims: ImgDimensions,
+    pub rect: PixelRect,
+}
+
+impl RegionError {
+    /// An error if `rect` can't be rendered as a region of an image of `full_dims`.
+    fn check(full_dims: ImgDimensions, rect: PixelRect) -> Result<(), RegionError> {
+        let empty = rect.width == 0 || rect.height == 0;
+        if empty || !Self::inside(full_dims, rect) || rect.width % 8 != 0 || rect.height % 8 != 0 {
+            Err(RegionError { full_dims, rect })
+        } else {
+            Ok(())
+        }
+    }
+
+    fn inside(full_dims: ImgDimensions, rect: PixelRect) -> bool {
+        let within = |start: u32, len: u32, full: u32| {
+            start.checked_add(len).map_or(false, |end| end <= full)
+        };
+        within(rect.x, rect.width, full_dims.width) && within(rect.y, rect.height, full_dims.height)
+    }
+}
+
+impl Display for RegionError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let PixelRect {
+            x,
+            y,
+            width,
+            height,
+        } = self.rect;
+        if width == 0 || height == 0 {
+            write!(f, "a {}x{} region is empty", width, height)
+        } else if Self::inside(self.full_dims, self.rect) {
+            write!(
+                f,
+                "the sides of a {}x{} region aren't multiples of 8",
+                width, height
+            )
+        } else {
+            write!(
+                f,
+                "a {}x{} region at ({}, {}) lies outside of a {}x{} image",
+                width, height, x, y, self.full_dims.width, self.full_dims.height
+            )
+        }
+    }
+}
+
+impl Error for RegionError {}
+
+/// A render the device can't do, named in the plural, such as 16-bit exports on a device
+/// without 16-bit storage images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedError(pub &'static str);
+
+impl Display for UnsupportedError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} aren't supported by this device", self.0)
+    }
+}
+
+impl Error for UnsupportedError {}
+
+impl Debug for JuliaExport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let cached: Vec<_> = self
+            .cached_data
+            .borrow()
+            .entries
+            .iter()
+            .map(|c| (c.dims, c.data))
+            .collect();
+        f.debug_struct("JuliaExport")
+            .field("cached_data", &cached)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `linear_to_srgb` of `julia_common.glsl`, which the tonemapping pass encodes the view and
+    /// exports with.
+    fn shader_linear_to_srgb(c: f32) -> f32 {
+        if c > 0.0031308 {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        } else {
+            c * 12.92
+        }
+    }
+
+    /// A linear value as the tonemapping pass stores it in a UNORM image encoded with `transfer`.
+    fn shader_store(c: f32, transfer: Transfer, max: f32) -> u32 {
+        let c = match transfer {
+            Transfer::Linear => c,
+            Transfer::Srgb => shader_linear_to_srgb(c.max(0.0)),
+        };
+        (c.max(0.0).min(1.0) * max).round() as u32
+    }
+
+    /// Linear values from black to white, dense in the dark end where the encodings differ most.
+    fn linear_values() -> Vec<f32> {
+        (0..=1000).map(|i| (i as f32 / 1000.0).powi(3)).collect()
+    }
+
+    fn pixels8(values: &[u8]) -> Vec<u8> {
+        values.iter().flat_map(|&c| vec![c, c, c, 255]).collect()
+    }
+
+    fn pixels16(values: &[u16]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|&c| [c, c, c, 65535].to_vec())
+            .flat_map(|c| c.to_ne_bytes().to_vec())
+            .collect()
+    }
+
+    /// Whether `a` and `b` are at most one step apart, the most the GPU's rounding of stores to
+    /// UNORM images may differ from rounding to nearest.
+    fn within_one(a: u32, b: u32) -> bool {
+        (a as i64 - b as i64).abs() <= 1
+    }
+
+    #[test]
+    fn default_stages_keep_the_view_encoding() {
+        let stages = TransferStages::default();
+        assert_eq!(stages.render, Transfer::Srgb);
+        assert_eq!(stages.file, Transfer::Srgb);
+
+        let values: Vec<u8> = (0..=255).collect();
+        let mut img_vec = pixels8(&values);
+        convert_transfer8(&mut img_vec, stages.render, stages.file);
+        assert_eq!(img_vec, pixels8(&values));
+
+        let values: Vec<u16> = (0..=65535).step_by(257).collect();
+        let channels = convert_transfer16(&pixels16(&values), stages.render, stages.file);
+        let expected: Vec<u16> = values.iter().flat_map(|&c| vec![c, c, c, 65535]).collect();
+        assert_eq!(channels, expected);
+    }
+
+    #[test]
+    fn linear_8_bit_renders_encode_like_the_view() {
+        for c in 0..=255u8 {
+            let linear = c as f32 / 255.0;
+            let mut img_vec = pixels8(&[c]);
+            convert_transfer8(&mut img_vec, Transfer::Linear, Transfer::Srgb);
+
+            let view = shader_store(linear, Transfer::Srgb, 255.0);
+            assert!(
+                within_one(img_vec[0] as u32, view),
+                "linear {} encoded as {}, the view shows {}",
+                c,
+                img_vec[0],
+                view
+            );
+            assert_eq!(img_vec[3], 255, "alpha changed");
+        }
+    }
+
+    #[test]
+    fn linear_16_bit_renders_encode_like_the_view() {
+        for linear in linear_values() {
+            let stored = shader_store(linear, Transfer::Linear, 65535.0) as u16;
+            let channels =
+                convert_transfer16(&pixels16(&[stored]), Transfer::Linear, Transfer::Srgb);
+
+            let view16 = shader_store(linear, Transfer::Srgb, 65535.0);
+            assert!(
+                within_one(channels[0] as u32, view16),
+                "linear {} encoded as {}, the view's encoding is {}",
+                linear,
+                channels[0],
+                view16
+            );
+            assert_eq!(channels[3], 65535, "alpha changed");
+
+            // Rounded to 8 bits as 16-bit renders are written to 8-bit files
+            let written = (channels[0] as u32 * 255 + 32767) / 65535;
+            let view8 = shader_store(linear, Transfer::Srgb, 255.0);
+            assert!(
+                within_one(written, view8),
+                "linear {} written as {}, the view shows {}",
+                linear,
+                written,
+                view8
+            );
+        }
+    }
+
+    #[test]
+    fn view_values_survive_16_bit_linear() {
+        let values: Vec<u16> = (0..=255).map(|c| c * 257).collect();
+        let linear = convert_transfer16(&pixels16(&values), Transfer::Srgb, Transfer::Linear);
+        let bytes: Vec<u8> = linear
+            .iter()
+            .flat_map(|c| c.to_ne_bytes().to_vec())
+            .collect();
+        let srgb = convert_transfer16(&bytes, Transfer::Linear, Transfer::Srgb);
+
+        for (c, pixel) in srgb.chunks_exact(4).enumerate() {
+            let written: Vec<u32> = pixel
+                .iter()
+                .map(|&c| (c as u32 * 255 + 32767) / 65535)
+                .collect();
+            assert_eq!(written, [c as u32, c as u32, c as u32, 255]);
+        }
+    }
+
+    const FULL: ImgDimensions = ImgDimensions {
+        width: 64,
+        height: 48,
+    };
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> PixelRect {
+        PixelRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn regions_within_the_image_are_accepted() {
+        assert_eq!(RegionError::check(FULL, rect(0, 0, 64, 48)), Ok(()));
+        assert_eq!(RegionError::check(FULL, rect(16, 8, 32, 24)), Ok(()));
+        assert_eq!(RegionError::check(FULL, rect(56, 40, 8, 8)), Ok(()));
+    }
+
+    #[test]
+    fn regions_outside_the_image_are_rejected() {
+        for &r in &[
+            rect(8, 0, 64, 48),
+            rect(0, 8, 64, 48),
+            rect(64, 0, 8, 8),
+            rect(0, 48, 8, 8),
+            rect(u32::MAX - 3, 0, 8, 8),
+        ] {
+            let err = RegionError {
+                full_dims: FULL,
+                rect: r,
+            };
+            assert_eq!(RegionError::check(FULL, r), Err(err));
+            assert!(err.to_string().contains("outside"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn empty_regions_are_rejected() {
+        for &r in &[rect(0, 0, 0, 8), rect(0, 0, 8, 0), rect(8, 8, 0, 0)] {
+            let err = RegionError {
+                full_dims: FULL,
+                rect: r,
+            };
+            assert_eq!(RegionError::check(FULL, r), Err(err));
+            assert!(err.to_string().contains("empty"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn region_sides_must_be_multiples_of_8() {
+        for &r in &[rect(0, 0, 12, 8), rect(0, 0, 8, 20)] {
+            let err = RegionError {
+                full_dims: FULL,
+                rect: r,
+            };
+            assert_eq!(RegionError::check(FULL, r), Err(err));
+            assert!(err.to_string().contains("multiples of 8"), "{}", err);
+        }
     }
 }
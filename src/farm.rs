@@ -0,0 +1,410 @@
+//! Rendering spread over several machines. A coordinator splits images into pieces, either whole
+//! frames or tiles of large exports, hands them out to workers that connect to it over TCP, and
+//! writes each image once all of its pieces are back. Workers only render; every image is written
+//! on the coordinator.
+//!
+//! The protocol is plain text lines, with the pixels of a piece following as raw bytes:
+//!
+//! - A worker opens with `julia-farm 2` and `token <token>`, the coordinator's token or nothing if
+//!   it has none. The coordinator answers `welcome`, or `denied` and hangs up if the token is
+//!   wrong. The worker then asks for work with `ready`.
+//! - The coordinator answers with `piece <id> <x> <y> <width> <height>` followed by the view as a
+//!   checkpoint line, whose export dimensions are those of the whole image; with `wait` if every
+//!   piece has been handed out but some are still being rendered; or with `done`.
+//! - The worker sends `pixels <id> <length>` followed by the piece as rows of 8-bit sRGBA values,
+//!   then asks again.
+//!
+//! Pieces held by a worker that disconnects go back in the queue for the others.
+//!
+//! Workers render with nothing but the views they are sent, so pieces come back in sRGB, without
+//! an orbit trap image or an overlay. Coordinators only listen on loopback addresses unless they
+//! are given a token.
+
+use crate::checkpoint::{self, Checkpoint};
+use crate::locale::{tr, Message};
+use crate::{ExportJob, ImgDimensions, JuliaContext, PixelRect};
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+const GREETING: &str = "julia-farm 2";
+
+/// How long a worker waits before asking again when told to.
+const WAIT: Duration = Duration::from_secs(1);
+
+/// A rectangle of one of the images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Piece {
+    image: usize,
+    rect: PixelRect,
+}
+
+/// What the threads serving workers share.
+#[derive(Debug)]
+struct Shared {
+    images: Vec<ExportJob>,
+    pieces: Vec<Piece>,
+    /// Pieces not handed out yet, or handed back by workers that left.
+    pending: Mutex<VecDeque<usize>>,
+    /// Pieces whose pixels haven't arrived yet.
+    outstanding: AtomicUsize,
+    /// The token workers must present, if any.
+    token: Option<String>,
+}
+
+/// Hands out the pieces of a set of images to workers and writes the images as they complete.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coordinator {
+    images: Vec<ExportJob>,
+    pieces: Vec<Piece>,
+}
+
+impl Coordinator {
+    /// Splits every image into square tiles of `tile_size` pixels, rounded down to a multiple of
+    /// 8 as the renderer requires, or into one piece per image if `None`. Images are rendered in
+    /// color; the `potential` flag of the jobs is ignored.
+    pub fn new(images: Vec<ExportJob>, tile_size: Option<u32>) -> Coordinator {
+        let tile_size = tile_size.map(|t| u32::max(8, t / 8 * 8));
+        let mut pieces = Vec::new();
+
+        for (image, job) in images.iter().enumerate() {
+            let ImgDimensions { width, height } = job.dims;
+            let (tile_width, tile_height) = match tile_size {
+                Some(t) => (t, t),
+                None => (width, height),
+            };
+
+            for y in (0..height).step_by(tile_height as usize) {
+                for x in (0..width).step_by(tile_width as usize) {
+                    let rect = PixelRect {
+                        x,
+                        y,
+                        width: tile_width.min(width - x),
+                        height: tile_height.min(height - y),
+                    };
+                    pieces.push(Piece { image, rect });
+                }
+            }
+        }
+
+        Coordinator { images, pieces }
+    }
+
+    /// The number of pieces the images are split into.
+    pub fn piece_count(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// Listens for workers on `addr` and serves them until every image has been written and each
+    /// of them has been told there is nothing left. Workers must present `token` if one is given;
+    /// without one, only loopback addresses can be listened on.
+    pub fn run<A: ToSocketAddrs>(
+        self,
+        addr: A,
+        token: Option<&str>,
+        context: &JuliaContext,
+    ) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        if token.is_none() && !listener.local_addr()?.ip().is_loopback() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "a token is required to listen beyond this machine",
+            ));
+        }
+
+        let total = self.pieces.len();
+        println!(
+            "{}",
+            tr(Message::FarmListening, &[&listener.local_addr()?, &total])
+        );
+
+        let mut remaining = vec![0usize; self.images.len()];
+        for piece in &self.pieces {
+            remaining[piece.image] += 1;
+        }
+
+        let shared = Arc::new(Shared {
+            images: self.images,
+            pieces: self.pieces,
+            pending: Mutex::new((0..total).collect()),
+            outstanding: AtomicUsize::new(total),
+            token: token.map(str::to_string),
+        });
+
+        let (sender, receiver) = mpsc::channel();
+        let serving: Arc<Mutex<Vec<JoinHandle<()>>>> = Arc::default();
+        {
+            let shared = shared.clone();
+            let serving = serving.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming().filter_map(Result::ok) {
+                    let shared = shared.clone();
+                    let sender = sender.clone();
+                    let handle = thread::spawn(move || serve(stream, &shared, &sender));
+                    serving.lock().unwrap().push(handle);
+                }
+            });
+        }
+
+        let mut buffers: Vec<Option<Vec<u8>>> = vec![None; shared.images.len()];
+        for received in 1..=total {
+            // The listening thread keeps a sender alive, so this only fails if it panicked
+            let (id, pixels): (usize, Vec<u8>) = receiver.recv().map_err(|_| {
+                io::Error::new(io::ErrorKind::Other, "stopped listening for workers")
+            })?;
+            let piece = shared.pieces[id];
+            let job = &shared.images[piece.image];
+
+            let dims = job.dims;
+            let buffer = buffers[piece.image]
+                .get_or_insert_with(|| vec![0; (dims.width * dims.height * 4) as usize]);
+            blit(buffer, dims, piece.rect, &pixels);
+
+            remaining[piece.image] -= 1;
+            if remaining[piece.image] == 0 {
+                if let Some(buffer) = buffers[piece.image].take() {
+                    context.write_image(&job.filename, dims, &buffer)?;
+                    println!(
+                        "{}",
+                        tr(Message::FarmImageWritten, &[&job.filename.display()])
+                    );
+                }
+            }
+
+            println!("{}", tr(Message::FarmProgress, &[&received, &total]));
+        }
+
+        // Every worker still connected is told there is nothing left the next time it asks
+        let serving: Vec<_> = serving.lock().unwrap().drain(..).collect();
+        for handle in serving {
+            let _ = handle.join();
+        }
+
+        Ok(())
+    }
+}
+
+/// Copies the rows of a piece into an image.
+fn blit(image: &mut [u8], dims: ImgDimensions, rect: PixelRect, pixels: &[u8]) {
+    let row_len = (rect.width * 4) as usize;
+
+    for (row, src) in pixels.chunks_exact(row_len).enumerate() {
+        let start = (((rect.y + row as u32) * dims.width + rect.x) * 4) as usize;
+        image[start..start + row_len].copy_from_slice(src);
+    }
+}
+
+/// Serves one worker until it leaves or there is nothing left to render, putting the piece it
+/// holds back in the queue if it leaves halfway.
+fn serve(stream: TcpStream, shared: &Shared, results: &Sender<(usize, Vec<u8>)>) {
+    let peer = stream
+        .peer_addr()
+        .map(|a| a.to_string())
+        .unwrap_or_default();
+    let mut held = None;
+
+    let result = serve_worker(stream, &peer, shared, results, &mut held);
+    if let Some(id) = held {
+        shared.pending.lock().unwrap().push_front(id);
+    }
+    if let Err(e) = result {
+        eprintln!("{}", tr(Message::FarmWorkerLost, &[&peer, &e]));
+    }
+}
+
+fn serve_worker(
+    stream: TcpStream,
+    peer: &str,
+    shared: &Shared,
+    results: &Sender<(usize, Vec<u8>)>,
+    held: &mut Option<usize>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut line = String::new();
+
+    read_line(&mut reader, &mut line)?;
+    if line != GREETING {
+        return Err(invalid_data("not a julia-farm worker"));
+    }
+
+    read_line(&mut reader, &mut line)?;
+    let mut words = line.splitn(2, ' ');
+    let token = match (words.next(), words.next()) {
+        (Some("token"), token) => token.unwrap_or(""),
+        _ => return Err(invalid_data(format!("unexpected message: {}", line))),
+    };
+    if let Some(expected) = &shared.token {
+        if !same_token(token, expected) {
+            writeln!(writer, "denied")?;
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "wrong token",
+            ));
+        }
+    }
+    writeln!(writer, "welcome")?;
+    println!("{}", tr(Message::FarmWorkerJoined, &[&peer]));
+
+    loop {
+        read_line(&mut reader, &mut line)?;
+        if line != "ready" {
+            return Err(invalid_data(format!("unexpected message: {}", line)));
+        }
+
+        let next = shared.pending.lock().unwrap().pop_front();
+        let id = match next {
+            Some(id) => id,
+            None if shared.outstanding.load(Ordering::SeqCst) > 0 => {
+                writeln!(writer, "wait")?;
+                continue;
+            }
+            None => {
+                writeln!(writer, "done")?;
+                return Ok(());
+            }
+        };
+
+        *held = Some(id);
+        let Piece { image, rect } = shared.pieces[id];
+        let job = &shared.images[image];
+        let view = Checkpoint {
+            time: SystemTime::now(),
+            data: job.data,
+            export_dimensions: job.dims,
+        };
+        write!(
+            writer,
+            "piece {} {} {} {} {}\n{}\n",
+            id,
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height,
+            checkpoint::encode(&view)
+        )?;
+
+        read_line(&mut reader, &mut line)?;
+        let expected_len = (rect.width * rect.height * 4) as usize;
+        let words: Vec<_> = line.split_whitespace().collect();
+        let valid = match words.as_slice() {
+            ["pixels", i, len] => parse::<usize>(i)? == id && parse::<usize>(len)? == expected_len,
+            _ => false,
+        };
+        if !valid {
+            return Err(invalid_data(format!("unexpected message: {}", line)));
+        }
+
+        let mut pixels = vec![0; expected_len];
+        reader.read_exact(&mut pixels)?;
+
+        *held = None;
+        shared.outstanding.fetch_sub(1, Ordering::SeqCst);
+        // The coordinator only stops listening once every piece is in
+        let _ = results.send((id, pixels));
+    }
+}
+
+/// Connects to the coordinator at `addr`, presenting `token` if given, and renders pieces for it
+/// until it has none left. Returns the number of pieces rendered.
+pub fn work<A: ToSocketAddrs>(
+    addr: A,
+    token: Option<&str>,
+    context: &JuliaContext,
+) -> io::Result<usize> {
+    let stream = TcpStream::connect(addr)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut line = String::new();
+    let mut view = String::new();
+    let mut rendered = 0;
+
+    writeln!(writer, "{}\ntoken {}", GREETING, token.unwrap_or(""))?;
+    read_line(&mut reader, &mut line)?;
+    match line.as_str() {
+        "welcome" => {}
+        "denied" => {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the coordinator refused the token",
+            ))
+        }
+        _ => return Err(invalid_data(format!("unexpected message: {}", line))),
+    }
+
+    loop {
+        writeln!(writer, "ready")?;
+        read_line(&mut reader, &mut line)?;
+
+        let words: Vec<_> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["piece", id, x, y, width, height] => {
+                let id: usize = parse(id)?;
+                let rect = PixelRect {
+                    x: parse(x)?,
+                    y: parse(y)?,
+                    width: parse(width)?,
+                    height: parse(height)?,
+                };
+
+                read_line(&mut reader, &mut view)?;
+                let view = checkpoint::decode(&view)
+                    .ok_or_else(|| invalid_data("unreadable view from the coordinator"))?;
+
+                let pixels = context
+                    .render_region(view.export_dimensions, rect, &view.data)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                write!(writer, "pixels {} {}\n", id, pixels.len())?;
+                writer.write_all(&pixels)?;
+                writer.flush()?;
+                rendered += 1;
+            }
+            ["wait"] => thread::sleep(WAIT),
+            ["done"] => return Ok(rendered),
+            _ => return Err(invalid_data(format!("unexpected message: {}", line))),
+        }
+    }
+}
+
+/// Compares tokens in time independent of where they first differ.
+fn same_token(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+/// Reads a line into `line` without its line ending, failing at the end of the stream.
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> io::Result<()> {
+    line.clear();
+    if reader.read_line(line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "connection closed",
+        ));
+    }
+
+    let len = line.trim_end_matches(&['\r', '\n'][..]).len();
+    line.truncate(len);
+    Ok(())
+}
+
+fn parse<T: FromStr>(s: &str) -> io::Result<T> {
+    s.parse()
+        .map_err(|_| invalid_data(format!("expected a number, got {}", s)))
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
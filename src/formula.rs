@@ -0,0 +1,188 @@
+//! CPU mirror of the iteration formulas in `shaders/julia_common.glsl`. Anything that changes the
+//! shader's iteration must be reflected here so analysis agrees with what is rendered.
+
+use gramit::Vec2;
+
+use crate::hybrid::Fold;
+use crate::{FractalKind, JuliaData, Plane};
+
+pub(crate) fn complex_mul(a: Vec2, b: Vec2) -> Vec2 {
+    vec2!(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x)
+}
+
+pub(crate) fn complex_exp(z: Vec2, n: u32) -> Vec2 {
+    let mut zn = vec2!(1.0, 0.0);
+
+    for _ in 0..n {
+        zn = complex_mul(zn, z);
+    }
+
+    zn
+}
+
+/// `z^n` for real `n`, as in the shader: whole powers are multiplied out, others use the
+/// principal branch.
+pub(crate) fn complex_pow(z: Vec2, n: f32) -> Vec2 {
+    if n >= 0.0 && n == n.floor() {
+        return complex_exp(z, n as u32);
+    } else if z.x == 0.0 && z.y == 0.0 {
+        return vec2!(0.0, 0.0);
+    }
+
+    let r = complex_abs(z).powf(n);
+    let theta = n * z.y.atan2(z.x);
+    vec2!(r * theta.cos(), r * theta.sin())
+}
+
+pub(crate) fn complex_div(a: Vec2, b: Vec2) -> Vec2 {
+    let d = b.x * b.x + b.y * b.y;
+    vec2!((a.x * b.x + a.y * b.y) / d, (a.y * b.x - a.x * b.y) / d)
+}
+
+pub(crate) fn complex_abs(z: Vec2) -> f32 {
+    (z.x * z.x + z.y * z.y).sqrt()
+}
+
+/// Applies the fold of the `i`-th step: the hybrid's if there is one, otherwise the kind's.
+fn fold(data: &JuliaData, z: Vec2, i: usize) -> Vec2 {
+    let fold = match &data.hybrid {
+        Some(h) => h.fold(i),
+        None => Fold::from(data.kind),
+    };
+
+    fold.apply(z)
+}
+
+/// The numerator `P(z)` of the iterated map: the polynomial if there is one, otherwise `z^n`.
+fn numerator(data: &JuliaData, z: Vec2) -> Vec2 {
+    match &data.poly {
+        Some(p) => p.eval(z),
+        None => complex_pow(z, data.n),
+    }
+}
+
+fn numerator_derivative(data: &JuliaData, z: Vec2) -> Vec2 {
+    match &data.poly {
+        Some(p) => p.derivative().eval(z),
+        None if data.n == 0.0 => vec2!(0.0, 0.0),
+        None => data.n * complex_pow(z, data.n - 1.0),
+    }
+}
+
+/// The `i`-th step of the iteration, counting from 0. For Newton fractals this is a step of
+/// Newton's method for `z^n - 1`, as in `shaders/julia_newton.glsl`, and `c` is unused.
+pub(crate) fn mandelbrot_iteration(data: &JuliaData, c: Vec2, z: Vec2, i: usize) -> Vec2 {
+    match (data.kind, &data.poly) {
+        (FractalKind::Newton, Some(p)) => z - complex_div(p.eval(z), p.derivative().eval(z)),
+        (FractalKind::Newton, None) => {
+            let n = data.n.max(1.0);
+            let zn1 = complex_pow(z, n - 1.0);
+            let f = complex_mul(zn1, z) - vec2!(1.0, 0.0);
+            z - complex_div(f, n * zn1)
+        }
+        _ => {
+            let z = fold(data, z, i);
+            match &data.denom {
+                Some(q) => complex_div(numerator(data, z), q.eval(z)) + c,
+                None => numerator(data, z) + c,
+            }
+        }
+    }
+}
+
+/// The initial iterate and the constant for the orbit of the point `p`, as `(z, c)`.
+pub(crate) fn orbit_start(data: &JuliaData, p: Vec2) -> (Vec2, Vec2) {
+    match data.view_plane() {
+        Plane::Dynamical => (p, data.c),
+        Plane::Parameter => (data.z0, p),
+    }
+}
+
+/// The derivative of the `i`-th iteration step with respect to `z`. The Burning Ship's folding and
+/// the Tricorn's conjugation aren't complex differentiable, so for them this is the derivative of
+/// the power at the folded point.
+pub(crate) fn iteration_derivative(data: &JuliaData, z: Vec2, i: usize) -> Vec2 {
+    if data.kind == FractalKind::Newton {
+        // N'(z) = f(z) f''(z) / f'(z)^2, which is (n - 1)(z^n - 1) / (n z^n) for z^n - 1
+        return match &data.poly {
+            Some(p) => {
+                let dp = p.derivative();
+                let d = dp.eval(z);
+                complex_div(
+                    complex_mul(p.eval(z), dp.derivative().eval(z)),
+                    complex_mul(d, d),
+                )
+            }
+            None => {
+                let n = data.n.max(1.0);
+                let zn = complex_pow(z, n);
+                complex_div((n - 1.0) * (zn - vec2!(1.0, 0.0)), n * zn)
+            }
+        };
+    }
+
+    let z = fold(data, z, i);
+    match &data.denom {
+        // (P/Q)' = (P'Q - PQ') / Q^2
+        Some(q) => {
+            let qz = q.eval(z);
+            complex_div(
+                complex_mul(numerator_derivative(data, z), qz)
+                    - complex_mul(numerator(data, z), q.derivative().eval(z)),
+                complex_mul(qz, qz),
+            )
+        }
+        None => numerator_derivative(data, z),
+    }
+}
+
+/// The radius beyond which orbits count as escaped. Newton orbits converge rather than escape, so
+/// they never do.
+pub(crate) fn escape_radius(data: &JuliaData) -> f32 {
+    match data.kind {
+        FractalKind::Newton => f32::INFINITY,
+        _ => 250.0 * degree(data),
+    }
+}
+
+/// The degree of the iterated polynomial. For a rational map this is the difference of the
+/// degrees, but at least 1.
+pub(crate) fn degree(data: &JuliaData) -> f32 {
+    let p = match &data.poly {
+        Some(p) => p.degree() as f32,
+        None => data.n,
+    };
+
+    match &data.denom {
+        Some(q) => f32::max(p - q.degree() as f32, 1.0),
+        None => p,
+    }
+}
+
+/// A repelling fixed point of `x^n + c`, which lies on the Julia set, or `None` if none is found.
+/// Newton's method for `x^n + c - x` is started from points spread around a circle that encloses
+/// every fixed point.
+pub(crate) fn repelling_fixed_point(data: &JuliaData) -> Option<Vec2> {
+    let n = data.n;
+    let radius = 1.0 + f32::max(1.0, complex_abs(data.c));
+    let starts = 4 * n.ceil() as usize;
+
+    (0..starts).find_map(|k| {
+        let theta = 2.0 * std::f32::consts::PI * k as f32 / starts as f32;
+        let mut z = vec2!(radius * theta.cos(), radius * theta.sin());
+
+        for _ in 0..64 {
+            let g = complex_pow(z, n) + data.c - z;
+            let dg = n * complex_pow(z, n - 1.0) - vec2!(1.0, 0.0);
+            z = z - complex_div(g, dg);
+        }
+
+        let residual = complex_abs(complex_pow(z, n) + data.c - z);
+        let multiplier = complex_abs(n * complex_pow(z, n - 1.0));
+        if residual < 1e-3 && multiplier > 1.0 {
+            Some(z)
+        } else {
+            None
+        }
+    })
+}
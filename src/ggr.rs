@@ -0,0 +1,302 @@
+//! GIMP gradient files (`.ggr`), so that gradients made in GIMP or collected from its libraries
+//! can color fractals.
+//!
+//! A `.ggr` gradient is a list of segments, each blending between a left and a right color with
+//! one of several curves, in RGB or around the HSV hue circle. The shaders only blend linearly
+//! between at most `MAX_STOPS` stops, so segments are turned into stops at their ends, and at their
+//! midpoints where they don't blend linearly; gradients with more segments than fit are sampled
+//! evenly instead.
+
+use gramit::Vec4;
+use palette::{Hsv, RgbHue, Srgb};
+
+use crate::gradient::{Gradient, GradientStop, MAX_STOPS};
+
+use std::error::Error;
+use std::f32::consts::PI;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How a segment's colors are weighted along it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Blending {
+    Linear,
+    Curved,
+    Sine,
+    SphereIncreasing,
+    SphereDecreasing,
+    Step,
+}
+
+/// The space a segment's colors are blended in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Coloring {
+    Rgb,
+    HsvCounterclockwise,
+    HsvClockwise,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+    left: f32,
+    middle: f32,
+    right: f32,
+    left_color: Vec4,
+    right_color: Vec4,
+    blending: Blending,
+    coloring: Coloring,
+}
+
+impl Segment {
+    /// Whether the segment is a plain linear RGB blend that two stops reproduce exactly.
+    fn is_linear(&self) -> bool {
+        let centered = (self.middle - 0.5 * (self.left + self.right)).abs() < 1e-4;
+        self.blending == Blending::Linear && self.coloring == Coloring::Rgb && centered
+    }
+
+    /// The weight of the right color at `x`, following GIMP's blending functions.
+    fn factor(&self, x: f32) -> f32 {
+        let len = self.right - self.left;
+        let (middle, pos) = if len < 1e-6 {
+            (0.5, 0.5)
+        } else {
+            ((self.middle - self.left) / len, (x - self.left) / len)
+        };
+
+        let linear = || {
+            if pos <= middle {
+                if middle < 1e-6 {
+                    0.0
+                } else {
+                    0.5 * pos / middle
+                }
+            } else if 1.0 - middle < 1e-6 {
+                1.0
+            } else {
+                0.5 + 0.5 * (pos - middle) / (1.0 - middle)
+            }
+        };
+
+        match self.blending {
+            Blending::Linear => linear(),
+            Blending::Curved => pos.powf(0.5f32.ln() / middle.max(1e-6).ln()),
+            Blending::Sine => 0.5 * ((PI * linear() - 0.5 * PI).sin() + 1.0),
+            Blending::SphereIncreasing => {
+                let f = linear() - 1.0;
+                (1.0 - f * f).sqrt()
+            }
+            Blending::SphereDecreasing => {
+                let f = linear();
+                1.0 - (1.0 - f * f).sqrt()
+            }
+            Blending::Step => {
+                if pos >= middle {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// The segment's color at `x`.
+    fn color(&self, x: f32) -> Vec4 {
+        let f = self.factor(x).max(0.0).min(1.0);
+        let (a, b) = (self.left_color, self.right_color);
+        let alpha = a.w + f * (b.w - a.w);
+
+        if self.coloring == Coloring::Rgb {
+            let mut c = a + f * (b - a);
+            c.w = alpha;
+            return c;
+        }
+
+        let left = Hsv::from(Srgb::new(a.x, a.y, a.z));
+        let right = Hsv::from(Srgb::new(b.x, b.y, b.z));
+        let (h0, h1) = (
+            left.hue.to_positive_degrees(),
+            right.hue.to_positive_degrees(),
+        );
+        let turn = match self.coloring {
+            Coloring::HsvCounterclockwise if h1 < h0 => h1 + 360.0 - h0,
+            Coloring::HsvClockwise if h1 > h0 => h1 - 360.0 - h0,
+            _ => h1 - h0,
+        };
+
+        let hsv = Hsv::new(
+            RgbHue::from_degrees(h0 + f * turn),
+            left.saturation + f * (right.saturation - left.saturation),
+            left.value + f * (right.value - left.value),
+        );
+        let (r, g, b) = Srgb::from(hsv).into_components();
+        vec4!(r, g, b, alpha)
+    }
+}
+
+impl Gradient {
+    /// Reads a gradient from a GIMP `.ggr` file. Segments that don't blend linearly in RGB are
+    /// approximated.
+    pub fn from_ggr<P: AsRef<Path>>(path: P) -> io::Result<Gradient> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+
+        parse(&contents).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+}
+
+/// Parses the contents of a GIMP `.ggr` file into a gradient.
+pub fn parse(contents: &str) -> Result<Gradient, ParseGgrError> {
+    let mut lines = contents.lines().enumerate().map(|(i, l)| (i + 1, l.trim()));
+    let error = |line, reason| ParseGgrError { line, reason };
+
+    match lines.next() {
+        Some((_, "GIMP Gradient")) => (),
+        _ => return Err(error(1, "missing `GIMP Gradient` header")),
+    }
+
+    // The name line is optional in old files
+    let (mut line, mut text) = lines
+        .next()
+        .ok_or_else(|| error(2, "missing segment count"))?;
+    if text.starts_with("Name:") {
+        let next = lines
+            .next()
+            .ok_or_else(|| error(3, "missing segment count"))?;
+        line = next.0;
+        text = next.1;
+    }
+
+    let count: usize = text
+        .parse()
+        .map_err(|_| error(line, "invalid segment count"))?;
+    if count == 0 {
+        return Err(error(line, "the gradient has no segments"));
+    }
+
+    let mut segments = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (next, text) = lines
+            .next()
+            .ok_or_else(|| error(line + 1, "fewer segments than counted"))?;
+        line = next;
+
+        let segment = parse_segment(text).ok_or_else(|| error(line, "invalid segment"))?;
+        if segments
+            .last()
+            .map_or(false, |s: &Segment| segment.left < s.right - 1e-4)
+        {
+            return Err(error(line, "segment overlaps the one before it"));
+        }
+        segments.push(segment);
+    }
+
+    Gradient::new(&stops(&segments)).ok_or_else(|| error(line, "invalid segment positions"))
+}
+
+/// A segment line: the left, middle and right positions, the left and right colors as RGBA, the
+/// blending and coloring types, and optionally the kinds of the end colors, which are ignored.
+fn parse_segment(text: &str) -> Option<Segment> {
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    if fields.len() < 13 {
+        return None;
+    }
+
+    let floats = fields[..11]
+        .iter()
+        .map(|f| f.parse::<f32>().ok().filter(|v| v.is_finite()))
+        .collect::<Option<Vec<_>>>()?;
+    let color = |c: &[f32]| vec4!(c[0], c[1], c[2], c[3]);
+
+    let blending = match fields[11] {
+        "0" => Blending::Linear,
+        "1" => Blending::Curved,
+        "2" => Blending::Sine,
+        "3" => Blending::SphereIncreasing,
+        "4" => Blending::SphereDecreasing,
+        "5" => Blending::Step,
+        _ => return None,
+    };
+    let coloring = match fields[12] {
+        "0" => Coloring::Rgb,
+        "1" => Coloring::HsvCounterclockwise,
+        "2" => Coloring::HsvClockwise,
+        _ => return None,
+    };
+
+    let (left, middle, right) = (floats[0], floats[1], floats[2]);
+    if left > middle || middle > right {
+        return None;
+    }
+
+    Some(Segment {
+        left,
+        middle,
+        right,
+        left_color: color(&floats[3..7]),
+        right_color: color(&floats[7..11]),
+        blending,
+        coloring,
+    })
+}
+
+/// Gradient stops approximating the segments: their ends, and their midpoints where they don't
+/// blend linearly, with the ends of neighboring segments that meet in one color merged. If those
+/// are too many, the segments are sampled at evenly spaced positions instead.
+fn stops(segments: &[Segment]) -> Vec<GradientStop> {
+    let mut stops: Vec<GradientStop> = Vec::new();
+    let mut push = |color: Vec4, position: f32| {
+        let stop = GradientStop { color, position };
+        if stops.last() != Some(&stop) {
+            stops.push(stop);
+        }
+    };
+
+    for s in segments {
+        push(s.left_color, s.left);
+        if !s.is_linear() {
+            push(s.color(s.middle), s.middle);
+        }
+        push(s.right_color, s.right);
+    }
+
+    if stops.len() <= MAX_STOPS {
+        return stops;
+    }
+
+    let (start, end) = (segments[0].left, segments[segments.len() - 1].right);
+    (0..MAX_STOPS)
+        .map(|i| {
+            let x = start + (end - start) * i as f32 / (MAX_STOPS - 1) as f32;
+            let segment = segments
+                .iter()
+                .find(|s| x <= s.right)
+                .unwrap_or(&segments[segments.len() - 1]);
+            GradientStop {
+                color: segment.color(x),
+                position: x,
+            }
+        })
+        .collect()
+}
+
+/// A malformed `.ggr` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseGgrError {
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+impl Display for ParseGgrError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+impl Error for ParseGgrError {}
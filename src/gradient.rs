@@ -0,0 +1,365 @@
+//! Color gradients: lists of colors at positions between which values are mapped.
+
+use gramit::Vec4;
+use palette::{named, Srgb, Srgba};
+
+use crate::{colormap, palette_expr};
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The most stops a gradient can have, which is as many as the shaders accept.
+pub const MAX_STOPS: usize = 16;
+
+/// A color of a gradient and where it lies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientStop {
+    /// The color as non-linear sRGB with alpha, each channel between 0 and 1.
+    pub color: Vec4,
+    /// The value the color is reached at. Values below the first stop take its color, and values
+    /// above the last stop take that one's.
+    pub position: f32,
+}
+
+/// A gradient of between 2 and `MAX_STOPS` stops, in order of position. Colors are interpolated
+/// linearly between neighboring stops.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gradient {
+    /// Stops in order of position. Entries past `len` are copies of the last stop.
+    stops: [GradientStop; MAX_STOPS],
+    len: usize,
+}
+
+impl Gradient {
+    /// Creates a gradient from its stops. Returns `None` if there are fewer than two or more than
+    /// `MAX_STOPS`, or if their positions aren't in order.
+    pub fn new(stops: &[GradientStop]) -> Option<Gradient> {
+        if stops.len() < 2 || stops.len() > MAX_STOPS {
+            return None;
+        }
+        if stops.iter().any(|s| s.position.is_nan())
+            || stops.windows(2).any(|w| w[1].position < w[0].position)
+        {
+            return None;
+        }
+
+        let last = stops[stops.len() - 1];
+        let mut gradient = Gradient {
+            stops: [last; MAX_STOPS],
+            len: stops.len(),
+        };
+        gradient.stops[..stops.len()].copy_from_slice(stops);
+
+        Some(gradient)
+    }
+
+    /// A gradient of the given colors spread evenly from 0 to 1.
+    pub fn even(colors: &[Vec4]) -> Option<Gradient> {
+        let last = colors.len().saturating_sub(1).max(1) as f32;
+        let stops: Vec<_> = colors
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| GradientStop {
+                color,
+                position: i as f32 / last,
+            })
+            .collect();
+
+        Gradient::new(&stops)
+    }
+
+    pub fn stops(&self) -> &[GradientStop] {
+        &self.stops[..self.len]
+    }
+
+    pub fn set_color(&mut self, i: usize, color: Vec4) {
+        self.stops[..self.len][i].color = color;
+    }
+
+    /// Moves stop `i` to `position`, moving any stops it passes along with it so that they stay
+    /// in order.
+    pub fn set_position(&mut self, i: usize, position: f32) {
+        for (j, stop) in self.stops[..self.len].iter_mut().enumerate() {
+            if (j < i && stop.position > position) || (j > i && stop.position < position) {
+                stop.position = position;
+            }
+        }
+        self.stops[i].position = position;
+    }
+
+    /// Adds a stop halfway between stop `i` and the next one, or past the last stop if `i` is the
+    /// last, with the color of the gradient there. Returns the index of the new stop, or `None` if
+    /// the gradient is full.
+    pub fn insert_after(&mut self, i: usize) -> Option<usize> {
+        if self.len >= MAX_STOPS {
+            return None;
+        }
+
+        let stop = match self.stops().get(i + 1) {
+            Some(next) => {
+                let position = 0.5 * (self.stops[i].position + next.position);
+                GradientStop {
+                    color: 0.5 * (self.stops[i].color + next.color),
+                    position,
+                }
+            }
+            None => self.stops[i],
+        };
+
+        self.stops.copy_within(i + 1..self.len, i + 2);
+        self.stops[i + 1] = stop;
+        self.len += 1;
+
+        Some(i + 1)
+    }
+
+    /// Removes stop `i`, unless only two are left. Returns whether it was removed.
+    pub fn remove(&mut self, i: usize) -> bool {
+        if self.len <= 2 || i >= self.len {
+            return false;
+        }
+
+        self.stops.copy_within(i + 1..self.len, i);
+        self.len -= 1;
+        self.stops[self.len] = self.stops[self.len - 1];
+
+        true
+    }
+
+    /// The colors in linear RGBA and their positions packed four to a `vec4`, for the shader's
+    /// uniform block.
+    pub(crate) fn shader_stops(&self) -> ([[f32; 4]; MAX_STOPS], [[f32; 4]; MAX_STOPS / 4]) {
+        let mut colors = [[0f32; 4]; MAX_STOPS];
+        let mut positions = [[0f32; 4]; MAX_STOPS / 4];
+
+        for (i, stop) in self.stops.iter().enumerate() {
+            colors[i] = to_linear(stop.color);
+            positions[i / 4][i % 4] = stop.position;
+        }
+
+        (colors, positions)
+    }
+}
+
+/// A non-linear sRGBA color in linear RGBA, as the shaders take colors.
+pub(crate) fn to_linear(c: Vec4) -> [f32; 4] {
+    let lin = Srgba::new(c.x, c.y, c.z, c.w).into_linear();
+    let (r, g, b, a) = lin.into_components();
+    [r, g, b, a]
+}
+
+/// The space colors are blended in between neighboring stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interpolation {
+    /// Linear RGB, which is physically even but darkens and grays the blend between saturated
+    /// hues that lie apart on the color wheel.
+    Rgb,
+    /// Oklab, a perceptual space: lightness changes evenly between stops and blends of
+    /// complementary colors stay clean, though they pass through gray.
+    Oklab,
+    /// The polar form of Oklab, lightness, chroma and hue, with the hue turning the short way
+    /// around. Blends between saturated colors stay saturated and pass through the hues between
+    /// them.
+    Lch,
+    /// Hue, saturation and value of the sRGB colors, as the interface edits them, with the hue
+    /// turning the short way around.
+    Hsv,
+    /// Like `Hsv`, but with the hue turning the long way around, through every hue that `Hsv`
+    /// skips.
+    HsvLong,
+}
+
+impl Interpolation {
+    pub(crate) fn shader_id(self) -> u32 {
+        match self {
+            Interpolation::Rgb => 0,
+            Interpolation::Oklab => 1,
+            Interpolation::Lch => 2,
+            Interpolation::Hsv => 3,
+            Interpolation::HsvLong => 4,
+        }
+    }
+}
+
+impl Default for Interpolation {
+    fn default() -> Interpolation {
+        Interpolation::Rgb
+    }
+}
+
+/// Formats the space as its command-line name, `rgb`, `oklab`, `lch`, `hsv` or `hsv-long`.
+impl Display for Interpolation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Interpolation::Rgb => "rgb",
+            Interpolation::Oklab => "oklab",
+            Interpolation::Lch => "lch",
+            Interpolation::Hsv => "hsv",
+            Interpolation::HsvLong => "hsv-long",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Interpolation {
+    type Err = ParseInterpolationError;
+
+    fn from_str(s: &str) -> Result<Interpolation, ParseInterpolationError> {
+        match s {
+            "rgb" | "linear" => Ok(Interpolation::Rgb),
+            "oklab" | "lab" => Ok(Interpolation::Oklab),
+            "lch" | "oklch" => Ok(Interpolation::Lch),
+            "hsv" => Ok(Interpolation::Hsv),
+            "hsv-long" => Ok(Interpolation::HsvLong),
+            _ => Err(ParseInterpolationError),
+        }
+    }
+}
+
+/// The color as a hex code without the `#`, with an alpha byte only if it isn't opaque.
+pub fn to_hex(c: Vec4) -> String {
+    let alpha = c[3];
+    let c = Srgb::new(c[0], c[1], c[2]);
+    let c = Srgb::<u8>::from_format(c);
+    let rgb = format!("{:02x}{:02x}{:02x}", c.red, c.green, c.blue);
+    if alpha < 1.0 {
+        format!("{}{:02x}", rgb, (alpha * 255.0).round() as u8)
+    } else {
+        rgb
+    }
+}
+
+/// Formats the stops as `#rrggbb@position`, separated by commas, in the same form that is parsed.
+impl Display for Gradient {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (i, stop) in self.stops().iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "#{}@{}", to_hex(stop.color), stop.position)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Gradient {
+    type Err = ParseGradientError;
+
+    /// Parses the name of a built-in gradient, or comma-separated stops, each a color optionally
+    /// followed by `@` and its position. Colors are names from the CSS3 specification or hex codes
+    /// `#rrggbb`, or `#rrggbbaa` to give an alpha value. Stops without a position are spread evenly
+    /// between their neighbors, with the first defaulting to 0 and the last to 1. A gradient may
+    /// also be given by expressions in `t` after `expr:`, as described in `palette_expr`.
+    fn from_str(s: &str) -> Result<Gradient, ParseGradientError> {
+        if let Some(gradient) = colormap::named(s.trim()) {
+            return Ok(gradient);
+        }
+
+        if s.trim().starts_with("expr:") {
+            return palette_expr::gradient(&s.trim()["expr:".len()..]).ok_or(ParseGradientError);
+        }
+
+        let mut colors = Vec::new();
+        let mut positions = Vec::new();
+
+        for stop in s.split(',').map(str::trim) {
+            let mut parts = stop.splitn(2, '@');
+            colors.push(parse_color(parts.next().unwrap_or("").trim())?);
+            positions.push(match parts.next() {
+                Some(p) => Some(p.trim().parse().map_err(|_| ParseGradientError)?),
+                None => None,
+            });
+        }
+
+        let last = positions.len() - 1;
+        positions[0] = positions[0].or(Some(0.0));
+        positions[last] = positions[last].or(Some(1.0));
+
+        // Fill in each run of missing positions between the given ones around it
+        let mut known = 0;
+        for i in 1..positions.len() {
+            if let Some(end) = positions[i] {
+                let start = positions[known].unwrap();
+                let run = (i - known) as f32;
+                for (k, p) in positions[known + 1..i].iter_mut().enumerate() {
+                    *p = Some(start + (k + 1) as f32 / run * (end - start));
+                }
+                known = i;
+            }
+        }
+
+        let stops: Vec<_> = colors
+            .into_iter()
+            .zip(positions)
+            .map(|(color, position)| GradientStop {
+                color,
+                position: position.unwrap(),
+            })
+            .collect();
+
+        Gradient::new(&stops).ok_or(ParseGradientError)
+    }
+}
+
+/// Parses a CSS3 color name, `transparent`, or a hex code `#rrggbb` or `#rrggbbaa`.
+pub fn parse_color(s: &str) -> Result<Vec4, ParseGradientError> {
+    if s.eq_ignore_ascii_case("transparent") {
+        return Ok(vec4!(0.0, 0.0, 0.0, 0.0));
+    }
+
+    let c = named::from_str(s)
+        .map(|c| {
+            let (r, g, b) = c.into_components();
+            Srgba::new(r, g, b, 255)
+        })
+        .or_else(|| parse_hexcode(s))
+        .ok_or(ParseGradientError)?;
+
+    let (r, g, b, a) = Srgba::<f32>::from_format(c).into_components();
+    Ok(vec4!(r, g, b, a))
+}
+
+fn parse_hexcode(s: &str) -> Option<Srgba<u8>> {
+    if !s.starts_with('#') || (s.len() != 7 && s.len() != 9) {
+        return None;
+    }
+
+    let channels = (1..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    match channels[..] {
+        [r, g, b] => Some(Srgba::new(r, g, b, 255)),
+        [r, g, b, a] => Some(Srgba::new(r, g, b, a)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseInterpolationError;
+
+impl Display for ParseInterpolationError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown interpolation, expected rgb, oklab, lch, hsv or hsv-long"
+        )
+    }
+}
+
+impl Error for ParseInterpolationError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseGradientError;
+
+impl Display for ParseGradientError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "failed to parse color gradient")
+    }
+}
+
+impl Error for ParseGradientError {}
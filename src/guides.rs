@@ -0,0 +1,123 @@
+//! Composition guides drawn over the interactive view: the frame of the export, print margins,
+//! rule-of-thirds lines and a center cross.
+
+use vulkano::command_buffer::{
+    AutoCommandBuffer, AutoCommandBufferBuilder, BuildError, CommandBufferExecError,
+    CommandBufferExecFuture, DispatchError,
+};
+use vulkano::descriptor::descriptor_set::{
+    FixedSizeDescriptorSetsPool, PersistentDescriptorSetBuildError,
+};
+use vulkano::format::Format;
+use vulkano::image::{ImageAccess, StorageImage};
+use vulkano::pipeline::ComputePipeline;
+use vulkano::sync::GpuFuture;
+use vulkano::OomError;
+
+use gramit::Vec2;
+
+use crate::shaders::julia_guides_comp;
+use crate::{GuidesDesc, JuliaContext};
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::sync::Arc;
+
+/// Flags of the guides to draw, as in `shaders/julia_guides.comp`.
+pub const GUIDE_FRAME: u32 = 1;
+pub const GUIDE_MARGINS: u32 = 2;
+pub const GUIDE_THIRDS: u32 = 4;
+pub const GUIDE_CENTER: u32 = 8;
+
+/// Where the guides go within the view, in pixels of the image they are drawn over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GuideLayout {
+    /// The top left corner of the export's frame.
+    pub origin: Vec2,
+    /// The size of the export's frame.
+    pub size: Vec2,
+    /// The inset of the trim line from the frame along each axis.
+    pub bleed: Vec2,
+    /// The inset of the safe line from the trim line along each axis.
+    pub safe: Vec2,
+    /// The `GUIDE_*` flags of the guides to draw.
+    pub flags: u32,
+}
+
+pub struct JuliaGuides {
+    desc_set_pool: RefCell<FixedSizeDescriptorSetsPool<Arc<ComputePipeline<GuidesDesc>>>>,
+}
+
+impl JuliaGuides {
+    pub fn new(context: &JuliaContext) -> JuliaGuides {
+        JuliaGuides {
+            desc_set_pool: RefCell::new(FixedSizeDescriptorSetsPool::new(
+                context.guides_pipeline().clone(),
+                0,
+            )),
+        }
+    }
+
+    /// Draws the guides of `layout` over `image`, inverting the pixels under them.
+    pub fn draw_after<F: GpuFuture>(
+        &self,
+        future: F,
+        image: Arc<StorageImage<Format>>,
+        layout: &GuideLayout,
+        context: &JuliaContext,
+    ) -> Result<CommandBufferExecFuture<F, AutoCommandBuffer>, JuliaGuidesError> {
+        let [width, height] = image.dimensions().width_height();
+
+        let desc_set = self
+            .desc_set_pool
+            .borrow_mut()
+            .next()
+            .add_image(image)
+            .unwrap()
+            .build()?;
+
+        let end = layout.origin + layout.size;
+        let guides = julia_guides_comp::ty::Guides {
+            frame: [
+                layout.origin.x.round() as i32,
+                layout.origin.y.round() as i32,
+                end.x.round() as i32,
+                end.y.round() as i32,
+            ],
+            bleed: [layout.bleed.x, layout.bleed.y],
+            safe: [layout.safe.x, layout.safe.y],
+            flags: layout.flags,
+        };
+
+        let cmd_buf = AutoCommandBufferBuilder::primary_one_time_submit(
+            context.device().clone(),
+            context.queue().family(),
+        )?
+        .dispatch(
+            [(width + 7) / 8, (height + 7) / 8, 1],
+            context.guides_pipeline().clone(),
+            desc_set,
+            guides,
+        )?
+        .build()?;
+
+        Ok(future.then_execute(context.queue().clone(), cmd_buf)?)
+    }
+}
+
+impl Debug for JuliaGuides {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("JuliaGuides").finish()
+    }
+}
+
+impl_error! {
+    pub enum JuliaGuidesError {
+        VkDescSetErr(PersistentDescriptorSetBuildError),
+        VkOomErr(OomError),
+        VkDispatchErr(DispatchError),
+        VkCmdBufBuildErr(BuildError),
+        VkExecErr(CommandBufferExecError),
+    }
+}
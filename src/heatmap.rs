@@ -0,0 +1,113 @@
+//! A debug view of where rendering time goes: every pixel colored by the number of iteration
+//! steps spent on it, and a summary of the most expensive tile, to show why a view is slow and
+//! where finer or coarser sampling would pay off.
+
+use crate::{ImgDimensions, PixelRect};
+
+/// The side of the square tiles that costs are summed over, in pixels.
+pub const TILE_SIZE: u32 = 64;
+
+/// Stops of the viridis color map, in sRGB, evenly spaced from 0 to 1.
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 44, 122],
+    [59, 81, 139],
+    [44, 113, 142],
+    [33, 144, 141],
+    [39, 173, 129],
+    [92, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+
+/// The viridis color of a value between 0 and 1, which runs from dark purple through blue and
+/// green to yellow and reads the same in grayscale and to most color-blind viewers.
+pub fn viridis(t: f32) -> [u8; 3] {
+    let t = t.max(0.0).min(1.0) * (VIRIDIS.len() - 1) as f32;
+    let i = (t.floor() as usize).min(VIRIDIS.len() - 2);
+    let f = t - i as f32;
+
+    let mut rgb = [0; 3];
+    for (k, c) in rgb.iter_mut().enumerate() {
+        let (a, b) = (f32::from(VIRIDIS[i][k]), f32::from(VIRIDIS[i + 1][k]));
+        *c = (a + f * (b - a)).round() as u8;
+    }
+    rgb
+}
+
+/// sRGBA pixels coloring each cost by its fraction of `max_cost`.
+pub fn colorize(costs: &[f32], max_cost: f32) -> Vec<u8> {
+    let scale = if max_cost > 0.0 { 1.0 / max_cost } else { 0.0 };
+
+    let mut rgba = Vec::with_capacity(costs.len() * 4);
+    for &cost in costs {
+        let [r, g, b] = viridis(cost * scale);
+        rgba.extend_from_slice(&[r, g, b, 255]);
+    }
+    rgba
+}
+
+/// A summary of the iteration steps spent on an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostReport {
+    pub dims: ImgDimensions,
+    /// Steps spent on the whole image.
+    pub total: f64,
+    /// The tile with the most steps per pixel. Tiles on the right and bottom edges may be smaller
+    /// than the rest.
+    pub worst_tile: PixelRect,
+    /// Steps spent on `worst_tile`.
+    pub worst_tile_total: f64,
+}
+
+impl CostReport {
+    /// Summarizes per-pixel costs, given in rows from the top, over tiles of the given size.
+    pub fn analyze(costs: &[f32], dims: ImgDimensions, tile_size: u32) -> CostReport {
+        let tile_size = tile_size.max(1);
+        let tiles_x = (dims.width + tile_size - 1) / tile_size;
+        let tiles_y = (dims.height + tile_size - 1) / tile_size;
+        let mut tiles = vec![0.0f64; (tiles_x * tiles_y) as usize];
+
+        for (i, &cost) in costs.iter().enumerate() {
+            let (x, y) = (i as u32 % dims.width, i as u32 / dims.width);
+            tiles[(y / tile_size * tiles_x + x / tile_size) as usize] += f64::from(cost);
+        }
+
+        let tile_rect = |i: usize| {
+            let (tx, ty) = (i as u32 % tiles_x, i as u32 / tiles_x);
+            let (x, y) = (tx * tile_size, ty * tile_size);
+            PixelRect {
+                x,
+                y,
+                width: tile_size.min(dims.width - x),
+                height: tile_size.min(dims.height - y),
+            }
+        };
+        let mean = |i: usize| {
+            let rect = tile_rect(i);
+            tiles[i] / f64::from(rect.width * rect.height)
+        };
+
+        let worst = (0..tiles.len())
+            .max_by(|&a, &b| mean(a).partial_cmp(&mean(b)).unwrap())
+            .unwrap_or(0);
+
+        CostReport {
+            dims,
+            total: tiles.iter().sum(),
+            worst_tile: tile_rect(worst),
+            worst_tile_total: tiles.get(worst).cloned().unwrap_or(0.0),
+        }
+    }
+
+    /// Mean steps per pixel over the whole image.
+    pub fn mean(&self) -> f64 {
+        self.total / f64::from((self.dims.width * self.dims.height).max(1))
+    }
+
+    /// Mean steps per pixel over the worst tile.
+    pub fn worst_tile_mean(&self) -> f64 {
+        let rect = self.worst_tile;
+        self.worst_tile_total / f64::from((rect.width * rect.height).max(1))
+    }
+}
@@ -0,0 +1,133 @@
+//! A rolling history of the parameters of the last few seconds of the interactive view, so that a
+//! moment found while exploring can be saved after the fact without having started a recording
+//! beforehand.
+//!
+//! No pixels are kept, only the `JuliaData` of each recorded frame, so a long history costs little
+//! memory. The frames are rendered again, at a reduced size, when the history is saved. Each entry
+//! is the state as it was presented, including the color phase that cycling had reached on that
+//! frame, so the saved frames match what was on screen apart from the heads-up display and guides.
+
+use crate::{ExportJob, ImgDimensions, JuliaData};
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How much of the view is kept and how it is saved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HistoryOptions {
+    /// How many seconds of frames to keep.
+    pub seconds: f32,
+    /// How many frames per second to keep. Frames are recorded no more often than this, and at
+    /// most once per frame shown.
+    pub fps: u32,
+    /// Saved frames are this many times smaller than the window in each dimension.
+    pub scale: u32,
+}
+
+impl Default for HistoryOptions {
+    fn default() -> HistoryOptions {
+        HistoryOptions {
+            seconds: 10.0,
+            fps: 15,
+            scale: 2,
+        }
+    }
+}
+
+/// A ring buffer of the parameters of the frames shown in the interactive view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewHistory {
+    options: HistoryOptions,
+    frames: VecDeque<JuliaData>,
+    last_recorded: Option<Instant>,
+}
+
+impl ViewHistory {
+    pub fn new(options: HistoryOptions) -> ViewHistory {
+        let options = HistoryOptions {
+            fps: options.fps.max(1),
+            scale: options.scale.max(1),
+            ..options
+        };
+
+        ViewHistory {
+            options,
+            frames: VecDeque::new(),
+            last_recorded: None,
+        }
+    }
+
+    pub fn options(&self) -> HistoryOptions {
+        self.options
+    }
+
+    /// The most frames kept at once.
+    pub fn capacity(&self) -> usize {
+        let frames = (self.options.seconds.max(0.0) * self.options.fps as f32).ceil();
+        (frames as usize).max(1)
+    }
+
+    /// The number of frames currently kept.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The parameters of the kept frames, oldest first.
+    pub fn frames(&self) -> impl Iterator<Item = &JuliaData> {
+        self.frames.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.last_recorded = None;
+    }
+
+    /// Records the state presented at `now`, unless a frame was recorded less than one frame
+    /// interval before. Drops the oldest frame once the buffer is full.
+    pub fn record(&mut self, data: &JuliaData, now: Instant) {
+        let interval = Duration::from_secs_f64(1.0 / f64::from(self.options.fps));
+        if let Some(last) = self.last_recorded {
+            if now.duration_since(last) < interval {
+                return;
+            }
+        }
+
+        if self.frames.len() >= self.capacity() {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(*data);
+        self.last_recorded = Some(now);
+    }
+
+    /// The size of saved frames for a view of the given size: reduced by the capture scale and
+    /// rounded down to multiples of 8, as the renderer requires.
+    pub fn frame_dimensions(&self, view: ImgDimensions) -> ImgDimensions {
+        let reduce = |d: u32| u32::max(8, d / self.options.scale / 8 * 8);
+        ImgDimensions {
+            width: reduce(view.width),
+            height: reduce(view.height),
+        }
+    }
+
+    /// Export jobs rendering every kept frame again as `frame_00000.png`, `frame_00001.png`, ...
+    /// in `dir`, fitted to the frame size for a view of the given size.
+    pub fn jobs(&self, view: ImgDimensions, dir: &Path) -> Vec<ExportJob> {
+        let dims = self.frame_dimensions(view);
+
+        self.frames
+            .iter()
+            .enumerate()
+            .map(|(i, data)| ExportJob {
+                dims,
+                data: data.fit_to(dims),
+                filename: dir.join(format!("frame_{:05}.png", i)),
+                potential: false,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,334 @@
+//! The heads-up display: lines of text drawn in white on black over the corner of the interactive
+//! view, at an adjustable size.
+
+use vulkano::buffer::{BufferUsage, CpuBufferPool, ImmutableBuffer};
+use vulkano::command_buffer::{
+    AutoCommandBuffer, AutoCommandBufferBuilder, BuildError, CommandBufferExecError,
+    CommandBufferExecFuture, DispatchError,
+};
+use vulkano::descriptor::descriptor_set::{
+    FixedSizeDescriptorSetsPool, PersistentDescriptorSetBuildError,
+};
+use vulkano::format::Format;
+use vulkano::image::StorageImage;
+use vulkano::memory::DeviceMemoryAllocError;
+use vulkano::pipeline::ComputePipeline;
+use vulkano::sync::{FlushError, GpuFuture};
+use vulkano::OomError;
+
+use image::{Rgba, RgbaImage};
+
+use crate::shaders::julia_hud_comp;
+use crate::{HudDesc, JuliaContext};
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::iter;
+use std::sync::Arc;
+
+/// The largest magnification of the text.
+pub const MAX_SCALE: u32 = 8;
+
+/// Cell size and border in glyph pixels, as in `shaders/julia_hud.comp`.
+const CELL: [u32; 2] = [6, 9];
+const PAD: u32 = 2;
+
+pub struct JuliaHud {
+    font: Arc<ImmutableBuffer<[u32]>>,
+    text_pool: CpuBufferPool<u32>,
+    desc_set_pool: RefCell<FixedSizeDescriptorSetsPool<Arc<ComputePipeline<HudDesc>>>>,
+}
+
+impl JuliaHud {
+    pub fn new(context: &JuliaContext) -> Result<JuliaHud, JuliaHudError> {
+        let (font, upload) = ImmutableBuffer::from_iter(
+            FONT.iter().cloned(),
+            BufferUsage {
+                storage_buffer: true,
+                ..BufferUsage::none()
+            },
+            context.queue().clone(),
+        )?;
+        upload.then_signal_fence_and_flush()?.wait(None)?;
+
+        let text_pool = CpuBufferPool::new(
+            context.device().clone(),
+            BufferUsage {
+                storage_buffer: true,
+                ..BufferUsage::none()
+            },
+        );
+        let desc_set_pool = RefCell::new(FixedSizeDescriptorSetsPool::new(
+            context.hud_pipeline().clone(),
+            0,
+        ));
+
+        Ok(JuliaHud {
+            font,
+            text_pool,
+            desc_set_pool,
+        })
+    }
+
+    /// Draws `lines` over the top left corner of `image`, each glyph magnified `scale` times.
+    /// Characters the font lacks are drawn as `?`, apart from accented letters, which lose their
+    /// accents.
+    pub fn draw_after<F: GpuFuture>(
+        &self,
+        future: F,
+        image: Arc<StorageImage<Format>>,
+        lines: &[String],
+        scale: u32,
+        context: &JuliaContext,
+    ) -> Result<CommandBufferExecFuture<F, AutoCommandBuffer>, JuliaHudError> {
+        // Every line is followed by a space, so that its background extends past the last glyph
+        let columns = lines
+            .iter()
+            .map(|l| l.chars().count() + 1)
+            .max()
+            .unwrap_or(1);
+        let rows = usize::max(lines.len(), 1);
+
+        let mut cells = vec![0; columns * rows];
+        for (row, line) in lines.iter().enumerate() {
+            for (col, c) in line.chars().chain(iter::once(' ')).enumerate() {
+                cells[row * columns + col] = cell_code(c);
+            }
+        }
+
+        let text = self.text_pool.chunk(cells)?;
+        let desc_set = self
+            .desc_set_pool
+            .borrow_mut()
+            .next()
+            .add_image(image)
+            .unwrap()
+            .add_buffer(self.font.clone())
+            .unwrap()
+            .add_buffer(text)
+            .unwrap()
+            .build()?;
+
+        let scale = scale.max(1).min(MAX_SCALE);
+        let width = (columns as u32 * CELL[0] + 2 * PAD) * scale;
+        let height = (rows as u32 * CELL[1] + 2 * PAD) * scale;
+        let hud = julia_hud_comp::ty::Hud {
+            columns: columns as u32,
+            rows: rows as u32,
+            scale,
+        };
+
+        let cmd_buf = AutoCommandBufferBuilder::primary_one_time_submit(
+            context.device().clone(),
+            context.queue().family(),
+        )?
+        .dispatch(
+            [(width + 7) / 8, (height + 7) / 8, 1],
+            context.hud_pipeline().clone(),
+            desc_set,
+            hud,
+        )?
+        .build()?;
+
+        Ok(future.then_execute(context.queue().clone(), cmd_buf)?)
+    }
+}
+
+impl Debug for JuliaHud {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("JuliaHud")
+            .field("font", &self.font)
+            .finish()
+    }
+}
+
+/// The width in pixels of `text` drawn by `draw_text` at `scale`.
+pub(crate) fn text_width(text: &str, scale: u32) -> u32 {
+    text.chars().count() as u32 * CELL[0] * scale
+}
+
+/// The height in pixels of a line drawn by `draw_text` at `scale`.
+pub(crate) fn line_height(scale: u32) -> u32 {
+    CELL[1] * scale
+}
+
+/// Draws `text` into `image` on the CPU with the display's font, in `color` on a transparent
+/// background, with the top left corner of its first cell at `(x, y)`. Glyphs are magnified
+/// `scale` times and clipped to the image.
+pub(crate) fn draw_text(
+    image: &mut RgbaImage,
+    x: u32,
+    y: u32,
+    text: &str,
+    scale: u32,
+    color: Rgba<u8>,
+) {
+    let scale = scale.max(1);
+    for (i, c) in text.chars().enumerate() {
+        let code = cell_code(c);
+        let left = x + i as u32 * CELL[0] * scale;
+
+        for row in 0..7 {
+            let word = FONT[(code - 32) as usize * 2 + row / 4];
+            let bits = (word >> (8 * (row % 4))) & 0xff;
+            for col in 0..5 {
+                if (bits >> (4 - col)) & 1 == 0 {
+                    continue;
+                }
+
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let px = left + col * scale + dx;
+                        let py = y + row as u32 * scale + dy;
+                        if px < image.width() && py < image.height() {
+                            image.put_pixel(px, py, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The code of the glyph drawn for `c`, after folding it into the printable ASCII the font covers.
+fn cell_code(c: char) -> u32 {
+    let c = match c {
+        'á' | 'à' | 'â' | 'ä' | 'ã' => 'a',
+        'Á' | 'À' | 'Â' | 'Ä' | 'Ã' => 'A',
+        'é' | 'è' | 'ê' | 'ë' => 'e',
+        'É' | 'È' | 'Ê' | 'Ë' => 'E',
+        'í' | 'ì' | 'î' | 'ï' => 'i',
+        'Í' | 'Ì' | 'Î' | 'Ï' => 'I',
+        'ó' | 'ò' | 'ô' | 'ö' | 'õ' => 'o',
+        'Ó' | 'Ò' | 'Ô' | 'Ö' | 'Õ' => 'O',
+        'ú' | 'ù' | 'û' | 'ü' => 'u',
+        'Ú' | 'Ù' | 'Û' | 'Ü' => 'U',
+        'ñ' => 'n',
+        'Ñ' => 'N',
+        'ç' => 'c',
+        'Ç' => 'C',
+        '¡' => '!',
+        '¿' => '?',
+        c => c,
+    };
+
+    if (' '..='~').contains(&c) {
+        c as u32
+    } else {
+        '?' as u32
+    }
+}
+
+impl_error! {
+    pub enum JuliaHudError {
+        VkAllocErr(DeviceMemoryAllocError),
+        VkFlushErr(FlushError),
+        VkDescSetErr(PersistentDescriptorSetBuildError),
+        VkOomErr(OomError),
+        VkDispatchErr(DispatchError),
+        VkCmdBufBuildErr(BuildError),
+        VkExecErr(CommandBufferExecError),
+    }
+}
+
+/// A 5x7 font covering `' '` to `'~'`, with lowercase letters drawn as capitals. Two words per
+/// glyph, holding one byte per row from the top, with the leftmost column in bit 4.
+#[rustfmt::skip]
+const FONT: [u32; 190] = [
+    0x00000000, 0x00000000, // space
+    0x04040404, 0x00040004, // !
+    0x00000a0a, 0x00000000, // "
+    0x0a1f0a0a, 0x000a0a1f, // #
+    0x0e140f04, 0x00041e05, // $
+    0x04021918, 0x00031308, // %
+    0x0814120c, 0x000d1215, // &
+    0x00000404, 0x00000000, // '
+    0x08080402, 0x00020408, // (
+    0x02020408, 0x00080402, // )
+    0x0e150400, 0x00000415, // *
+    0x1f040400, 0x00000404, // +
+    0x00000000, 0x0008040c, // ,
+    0x1f000000, 0x00000000, // -
+    0x00000000, 0x000c0c00, // .
+    0x04020100, 0x00001008, // /
+    0x1513110e, 0x000e1119, // 0
+    0x04040c04, 0x000e0404, // 1
+    0x0201110e, 0x001f0804, // 2
+    0x0204021f, 0x000e1101, // 3
+    0x120a0602, 0x0002021f, // 4
+    0x011e101f, 0x000e1101, // 5
+    0x1e100806, 0x000e1111, // 6
+    0x0402011f, 0x00080808, // 7
+    0x0e11110e, 0x000e1111, // 8
+    0x0f11110e, 0x000c0201, // 9
+    0x000c0c00, 0x00000c0c, // :
+    0x000c0c00, 0x0008040c, // ;
+    0x10080402, 0x00020408, // <
+    0x001f0000, 0x0000001f, // =
+    0x01020408, 0x00080402, // >
+    0x0201110e, 0x00040004, // ?
+    0x0d01110e, 0x000e1515, // @
+    0x1f11110e, 0x00111111, // A
+    0x1e11111e, 0x001e1111, // B
+    0x1010110e, 0x000e1110, // C
+    0x1111121c, 0x001c1211, // D
+    0x1e10101f, 0x001f1010, // E
+    0x1e10101f, 0x00101010, // F
+    0x1710110e, 0x000f1111, // G
+    0x1f111111, 0x00111111, // H
+    0x0404040e, 0x000e0404, // I
+    0x02020207, 0x000c1202, // J
+    0x18141211, 0x00111214, // K
+    0x10101010, 0x001f1010, // L
+    0x15151b11, 0x00111111, // M
+    0x15191111, 0x00111113, // N
+    0x1111110e, 0x000e1111, // O
+    0x1e11111e, 0x00101010, // P
+    0x1111110e, 0x000d1215, // Q
+    0x1e11111e, 0x00111214, // R
+    0x0e10100f, 0x001e0101, // S
+    0x0404041f, 0x00040404, // T
+    0x11111111, 0x000e1111, // U
+    0x11111111, 0x00040a11, // V
+    0x15111111, 0x000a1515, // W
+    0x040a1111, 0x0011110a, // X
+    0x040a1111, 0x00040404, // Y
+    0x0402011f, 0x001f1008, // Z
+    0x0808080e, 0x000e0808, // [
+    0x04081000, 0x00000102, // \
+    0x0202020e, 0x000e0202, // ]
+    0x00110a04, 0x00000000, // ^
+    0x00000000, 0x001f0000, // _
+    0x00000408, 0x00000000, // `
+    0x1f11110e, 0x00111111, // a
+    0x1e11111e, 0x001e1111, // b
+    0x1010110e, 0x000e1110, // c
+    0x1111121c, 0x001c1211, // d
+    0x1e10101f, 0x001f1010, // e
+    0x1e10101f, 0x00101010, // f
+    0x1710110e, 0x000f1111, // g
+    0x1f111111, 0x00111111, // h
+    0x0404040e, 0x000e0404, // i
+    0x02020207, 0x000c1202, // j
+    0x18141211, 0x00111214, // k
+    0x10101010, 0x001f1010, // l
+    0x15151b11, 0x00111111, // m
+    0x15191111, 0x00111113, // n
+    0x1111110e, 0x000e1111, // o
+    0x1e11111e, 0x00101010, // p
+    0x1111110e, 0x000d1215, // q
+    0x1e11111e, 0x00111214, // r
+    0x0e10100f, 0x001e0101, // s
+    0x0404041f, 0x00040404, // t
+    0x11111111, 0x000e1111, // u
+    0x11111111, 0x00040a11, // v
+    0x15111111, 0x000a1515, // w
+    0x040a1111, 0x0011110a, // x
+    0x040a1111, 0x00040404, // y
+    0x0402011f, 0x001f1008, // z
+    0x08040402, 0x00020404, // {
+    0x04040404, 0x00040404, // |
+    0x02040408, 0x00080404, // }
+    0x15080000, 0x00000002, // ~
+];
@@ -0,0 +1,181 @@
+//! Hybrid formulas, which cycle through the folds of several fractal kinds on a fixed schedule.
+
+use gramit::Vec2;
+
+use crate::FractalKind;
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The most steps a hybrid may have. Each step takes two bits of a `uint` in the shader.
+pub const MAX_STEPS: usize = 16;
+
+/// What is done to `z` before it is raised to the power in one iteration step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fold {
+    /// Nothing, as for Julia and Mandelbrot sets.
+    None,
+    /// `|Re z| + i|Im z|`, as for the Burning Ship.
+    Abs,
+    /// The complex conjugate, as for the Tricorn.
+    Conj,
+}
+
+impl Fold {
+    pub fn apply(self, z: Vec2) -> Vec2 {
+        match self {
+            Fold::None => z,
+            Fold::Abs => vec2!(z.x.abs(), z.y.abs()),
+            Fold::Conj => vec2!(z.x, -z.y),
+        }
+    }
+
+    fn code(self) -> u32 {
+        match self {
+            Fold::None => 0,
+            Fold::Abs => 1,
+            Fold::Conj => 2,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Fold::None => "mandelbrot",
+            Fold::Abs => "burning-ship",
+            Fold::Conj => "tricorn",
+        }
+    }
+}
+
+impl From<FractalKind> for Fold {
+    fn from(kind: FractalKind) -> Fold {
+        match kind {
+            FractalKind::BurningShip => Fold::Abs,
+            FractalKind::Tricorn => Fold::Conj,
+            _ => Fold::None,
+        }
+    }
+}
+
+/// A repeating sequence of folds, one per iteration step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hybrid {
+    steps: [Fold; MAX_STEPS],
+    len: usize,
+}
+
+impl Hybrid {
+    /// Creates a hybrid from its steps. Returns `None` if there are none, or more than
+    /// `MAX_STEPS`.
+    pub fn new(steps: &[Fold]) -> Option<Hybrid> {
+        if steps.is_empty() || steps.len() > MAX_STEPS {
+            return None;
+        }
+
+        let mut hybrid = Hybrid {
+            steps: [Fold::None; MAX_STEPS],
+            len: steps.len(),
+        };
+        hybrid.steps[..steps.len()].copy_from_slice(steps);
+
+        Some(hybrid)
+    }
+
+    pub fn steps(&self) -> &[Fold] {
+        &self.steps[..self.len]
+    }
+
+    /// The fold of the `i`-th iteration step, counting from 0.
+    pub fn fold(&self, i: usize) -> Fold {
+        self.steps[i % self.len]
+    }
+
+    /// The steps packed two bits each, first step lowest, for the shader's uniform block.
+    pub(crate) fn shader_program(&self) -> u32 {
+        self.steps()
+            .iter()
+            .enumerate()
+            .fold(0, |prog, (i, f)| prog | (f.code() << (2 * i)))
+    }
+}
+
+/// Formats the steps as comma-separated kind names, with runs of the same kind written once with
+/// a repeat count, e.g. `mandelbrot*2,burning-ship`.
+impl Display for Hybrid {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut steps = self.steps().iter().peekable();
+        let mut first = true;
+
+        while let Some(&fold) = steps.next() {
+            let mut count = 1;
+            while steps.peek() == Some(&&fold) {
+                steps.next();
+                count += 1;
+            }
+
+            if !first {
+                write!(f, ",")?;
+            }
+            first = false;
+
+            write!(f, "{}", fold.name())?;
+            if count > 1 {
+                write!(f, "*{}", count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Hybrid {
+    type Err = ParseHybridError;
+
+    /// Parses comma-separated kind names, each optionally followed by `*` and a repeat count.
+    /// `mandelbrot` (or `julia`) steps are plain, and `burning-ship` and `tricorn` steps apply
+    /// their fold.
+    fn from_str(s: &str) -> Result<Hybrid, ParseHybridError> {
+        let mut steps = Vec::new();
+
+        for part in s.split(',') {
+            let mut split = part.trim().splitn(2, '*');
+            let name = split.next().unwrap_or("").trim();
+            let count = match split.next() {
+                Some(n) => n.trim().parse().map_err(|_| ParseHybridError)?,
+                None => 1,
+            };
+
+            let fold = match name {
+                "mandelbrot" | "julia" => Fold::None,
+                kind => match kind.parse::<FractalKind>() {
+                    Ok(FractalKind::Newton) | Err(_) => return Err(ParseHybridError),
+                    Ok(kind) => Fold::from(kind),
+                },
+            };
+
+            if steps.len() + count > MAX_STEPS {
+                return Err(ParseHybridError);
+            }
+            steps.extend(std::iter::repeat(fold).take(count));
+        }
+
+        Hybrid::new(&steps).ok_or(ParseHybridError)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseHybridError;
+
+impl Display for ParseHybridError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "failed to parse hybrid, expected up to {} comma-separated steps of mandelbrot, \
+             burning-ship or tricorn, each optionally followed by *count",
+            MAX_STEPS
+        )
+    }
+}
+
+impl Error for ParseHybridError {}
@@ -0,0 +1,308 @@
+//! Construction of ICC color profiles for embedding in exported images.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+type Mat3 = [[f64; 3]; 3];
+
+const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+const D65: [f64; 2] = [0.3127, 0.3290];
+
+const SRGB_PRIMARIES: [[f64; 2]; 3] = [[0.64, 0.33], [0.30, 0.60], [0.15, 0.06]];
+const P3_PRIMARIES: [[f64; 2]; 3] = [[0.680, 0.320], [0.265, 0.690], [0.150, 0.060]];
+
+/// An ICC profile, either generated for one of the supported color spaces or read from disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IccProfile {
+    name: String,
+    data: Vec<u8>,
+}
+
+impl IccProfile {
+    /// A profile for the sRGB color space (IEC 61966-2-1).
+    pub fn srgb() -> IccProfile {
+        IccProfile::from_primaries("sRGB", SRGB_PRIMARIES, D65, false)
+    }
+
+    /// A profile for the Display P3 color space: the DCI-P3 primaries with the D65 white point and
+    /// the sRGB transfer function.
+    pub fn display_p3() -> IccProfile {
+        IccProfile::from_primaries("Display P3", P3_PRIMARIES, D65, false)
+    }
+
+    /// A profile for linear values with the sRGB primaries.
+    pub fn linear_srgb() -> IccProfile {
+        IccProfile::from_primaries("Linear sRGB", SRGB_PRIMARIES, D65, true)
+    }
+
+    /// A profile for linear values with the Display P3 primaries.
+    pub fn linear_display_p3() -> IccProfile {
+        IccProfile::from_primaries("Linear Display P3", P3_PRIMARIES, D65, true)
+    }
+
+    /// Reads an existing profile from an `.icc`/`.icm` file. The profile's name is taken from the
+    /// file name.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<IccProfile> {
+        let path = path.as_ref();
+        let data = fs::read(path)?;
+        if data.len() < 132 || &data[36..40] != b"acsp" {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not an ICC profile", path.display()),
+            ));
+        }
+
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("ICC profile"));
+
+        Ok(IccProfile { name, data })
+    }
+
+    /// Builds a display profile for an RGB space with the given primary and white point
+    /// chromaticities, using the sRGB transfer function, or none if `linear` is set.
+    pub(crate) fn from_primaries(
+        name: &str,
+        primaries: [[f64; 2]; 3],
+        white: [f64; 2],
+        linear: bool,
+    ) -> IccProfile {
+        let to_xyz = rgb_to_xyz(primaries, white);
+        let to_d50 = bradford(xy_to_xyz(white), D50);
+        let colorants = mat_mul(&to_d50, &to_xyz);
+
+        let mut tags = TagTable::new();
+        tags.add(b"desc", text_description(name));
+        tags.add(b"cprt", text("No copyright, use freely"));
+        tags.add(b"wtpt", xyz(xy_to_xyz(white)));
+        for (i, sig) in [b"rXYZ", b"gXYZ", b"bXYZ"].iter().enumerate() {
+            tags.add(
+                sig,
+                xyz([colorants[0][i], colorants[1][i], colorants[2][i]]),
+            );
+        }
+
+        let trc = if linear { linear_curve() } else { srgb_curve() };
+        tags.add(b"rTRC", trc.clone());
+        tags.add(b"gTRC", trc.clone());
+        tags.add(b"bTRC", trc);
+
+        IccProfile {
+            name: String::from(name),
+            data: tags.into_profile(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+struct TagTable {
+    tags: Vec<([u8; 4], Vec<u8>)>,
+}
+
+impl TagTable {
+    fn new() -> TagTable {
+        TagTable { tags: Vec::new() }
+    }
+
+    fn add(&mut self, sig: &[u8; 4], data: Vec<u8>) {
+        self.tags.push((*sig, data));
+    }
+
+    fn into_profile(self) -> Vec<u8> {
+        let table_len = 4 + 12 * self.tags.len();
+        let mut offset = 128 + table_len;
+
+        let mut table = Vec::with_capacity(table_len);
+        let mut body = Vec::new();
+        push_u32(&mut table, self.tags.len() as u32);
+        for (sig, data) in &self.tags {
+            table.extend_from_slice(sig);
+            push_u32(&mut table, offset as u32);
+            push_u32(&mut table, data.len() as u32);
+
+            body.extend_from_slice(data);
+            while body.len() % 4 != 0 {
+                body.push(0);
+            }
+            offset = 128 + table_len + body.len();
+        }
+
+        let size = 128 + table.len() + body.len();
+        let mut profile = Vec::with_capacity(size);
+        push_u32(&mut profile, size as u32);
+        profile.extend_from_slice(&[0; 4]); // preferred CMM
+        push_u32(&mut profile, 0x0210_0000); // version 2.1
+        profile.extend_from_slice(b"mntr");
+        profile.extend_from_slice(b"RGB ");
+        profile.extend_from_slice(b"XYZ ");
+        for v in &[2020u16, 1, 1, 0, 0, 0] {
+            profile.extend_from_slice(&v.to_be_bytes());
+        }
+        profile.extend_from_slice(b"acsp");
+        profile.extend_from_slice(&[0; 24]); // platform, flags, manufacturer, model, attributes
+        push_u32(&mut profile, 0); // perceptual rendering intent
+        for v in &D50 {
+            push_s15f16(&mut profile, *v);
+        }
+        profile.resize(128, 0); // creator, profile ID and reserved bytes
+
+        profile.extend_from_slice(&table);
+        profile.extend_from_slice(&body);
+        profile
+    }
+}
+
+fn push_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+fn push_s15f16(buf: &mut Vec<u8>, v: f64) {
+    buf.extend_from_slice(&((v * 65536.0).round() as i32).to_be_bytes());
+}
+
+fn text(s: &str) -> Vec<u8> {
+    let mut tag = b"text\0\0\0\0".to_vec();
+    tag.extend_from_slice(s.as_bytes());
+    tag.push(0);
+    tag
+}
+
+fn text_description(s: &str) -> Vec<u8> {
+    let mut tag = b"desc\0\0\0\0".to_vec();
+    push_u32(&mut tag, s.len() as u32 + 1);
+    tag.extend_from_slice(s.as_bytes());
+    tag.push(0);
+    // Empty Unicode and ScriptCode descriptions
+    tag.extend_from_slice(&[0; 8]);
+    tag.extend_from_slice(&[0; 3]);
+    tag.extend_from_slice(&[0; 67]);
+    tag
+}
+
+fn xyz(v: [f64; 3]) -> Vec<u8> {
+    let mut tag = b"XYZ \0\0\0\0".to_vec();
+    for c in &v {
+        push_s15f16(&mut tag, *c);
+    }
+    tag
+}
+
+/// A curve with no entries, which ICC readers take as the identity.
+fn linear_curve() -> Vec<u8> {
+    let mut tag = b"curv\0\0\0\0".to_vec();
+    push_u32(&mut tag, 0);
+    tag
+}
+
+fn srgb_curve() -> Vec<u8> {
+    const ENTRIES: u32 = 1024;
+
+    let mut tag = b"curv\0\0\0\0".to_vec();
+    push_u32(&mut tag, ENTRIES);
+    for i in 0..ENTRIES {
+        let v = i as f64 / (ENTRIES - 1) as f64;
+        let lin = if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        };
+        tag.extend_from_slice(&((lin * 65535.0).round() as u16).to_be_bytes());
+    }
+    tag
+}
+
+fn xy_to_xyz(xy: [f64; 2]) -> [f64; 3] {
+    let [x, y] = xy;
+    [x / y, 1.0, (1.0 - x - y) / y]
+}
+
+fn rgb_to_xyz(primaries: [[f64; 2]; 3], white: [f64; 2]) -> Mat3 {
+    let [r, g, b] = [
+        xy_to_xyz(primaries[0]),
+        xy_to_xyz(primaries[1]),
+        xy_to_xyz(primaries[2]),
+    ];
+    let m = [[r[0], g[0], b[0]], [r[1], g[1], b[1]], [r[2], g[2], b[2]]];
+    let s = mat_vec(&mat_inv(&m), xy_to_xyz(white));
+
+    let mut out = m;
+    for row in out.iter_mut() {
+        for (v, s) in row.iter_mut().zip(s.iter()) {
+            *v *= s;
+        }
+    }
+    out
+}
+
+/// The Bradford chromatic adaptation from white point `src` to `dst`, both in XYZ.
+fn bradford(src: [f64; 3], dst: [f64; 3]) -> Mat3 {
+    const B: Mat3 = [
+        [0.8951, 0.2664, -0.1614],
+        [-0.7502, 1.7135, 0.0367],
+        [0.0389, -0.0685, 1.0296],
+    ];
+
+    let src = mat_vec(&B, src);
+    let dst = mat_vec(&B, dst);
+    let scale = [
+        [dst[0] / src[0], 0.0, 0.0],
+        [0.0, dst[1] / src[1], 0.0],
+        [0.0, 0.0, dst[2] / src[2]],
+    ];
+
+    mat_mul(&mat_inv(&B), &mat_mul(&scale, &B))
+}
+
+fn mat_vec(m: &Mat3, v: [f64; 3]) -> [f64; 3] {
+    let mut out = [0.0; 3];
+    for (o, row) in out.iter_mut().zip(m.iter()) {
+        *o = row[0] * v[0] + row[1] * v[1] + row[2] * v[2];
+    }
+    out
+}
+
+fn mat_mul(a: &Mat3, b: &Mat3) -> Mat3 {
+    let mut out = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            out[i][j] = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    out
+}
+
+fn mat_inv(m: &Mat3) -> Mat3 {
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+
+    let mut out = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            // Cofactor of m[j][i], giving the transposed (adjugate) matrix
+            let (r0, r1) = match j {
+                0 => (1, 2),
+                1 => (0, 2),
+                _ => (0, 1),
+            };
+            let (c0, c1) = match i {
+                0 => (1, 2),
+                1 => (0, 2),
+                _ => (0, 1),
+            };
+            let minor = m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+            let sign = if (i + j) % 2 == 0 { 1.0 } else { -1.0 };
+            out[i][j] = sign * minor / det;
+        }
+    }
+    out
+}
@@ -0,0 +1,101 @@
+//! Julia set renders by the inverse iteration method, which walks backwards along random branches
+//! of the inverse of `x^n + c` and plots every point reached. This draws the boundary crisply even
+//! where escape times vary too little to show it, as for dust-like sets.
+
+use vulkano::buffer::{BufferUsage, ImmutableBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::sync::GpuFuture;
+
+use crate::buddhabrot::{
+    self, BuddhabrotOptions, INVOCATIONS_PER_DISPATCH, SAMPLES_PER_INVOCATION,
+};
+use crate::formula;
+use crate::shaders::julia_iim_comp;
+use crate::{FractalKind, ImgDimensions, JuliaContext, JuliaData, Plane};
+
+use std::sync::Arc;
+
+/// Whether `data` can be rendered by inverse iteration: the Julia set of `x^n + c` for a whole
+/// exponent of at least 2, without a polynomial, denominator or hybrid.
+pub fn supports(data: &JuliaData) -> bool {
+    data.kind == FractalKind::Julia
+        && data.view_plane() == Plane::Dynamical
+        && data.n >= 2.0
+        && data.n == data.n.floor()
+        && data.poly.is_none()
+        && data.denom.is_none()
+        && data.hybrid.is_none()
+}
+
+/// Renders the Julia set of `data` by inverse iteration at the given dimensions, returning linear
+/// RGBA pixels. Points are counted per pixel and mapped into the gradient as for Buddhabrots, with
+/// `options.samples` points plotted per pixel on average. `data` must be supported.
+pub(crate) fn render(
+    dims: ImgDimensions,
+    data: &JuliaData,
+    options: &BuddhabrotOptions,
+    context: &JuliaContext,
+) -> Vec<u8> {
+    assert!(
+        supports(data),
+        "inverse iteration needs the Julia set of x^n + c"
+    );
+
+    let device = context.device();
+    let queue = context.export_queue();
+
+    // Any point works as a start, but one on the Julia set needs no steps to reach it
+    let start = formula::repelling_fixed_point(data).unwrap_or(vec2!(1.0, 0.0));
+
+    let (uniform, upload) =
+        ImmutableBuffer::from_data(data.into_shader_data(), BufferUsage::all(), queue.clone())
+            .unwrap();
+    let hits = buddhabrot::hits_buffer(dims, context);
+
+    upload
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    let pipeline = context.iim_pipeline().clone();
+    let desc_set = Arc::new(
+        PersistentDescriptorSet::start(pipeline.clone(), 0)
+            .add_buffer(hits.clone())
+            .unwrap()
+            .add_buffer(uniform)
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+
+    for i in 0..buddhabrot::dispatch_count(dims, options) {
+        let sampling = julia_iim_comp::ty::Sampling {
+            size: [dims.width, dims.height],
+            seed: options.seed.wrapping_add(i as u32),
+            samples: SAMPLES_PER_INVOCATION,
+            start: [start.x, start.y],
+        };
+
+        AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+            .unwrap()
+            .dispatch(
+                [INVOCATIONS_PER_DISPATCH / 64, 1, 1],
+                pipeline.clone(),
+                desc_set.clone(),
+                sampling,
+            )
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+    }
+
+    buddhabrot::tonemap(dims, data, hits, options, context)
+}
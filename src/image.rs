@@ -1,31 +1,44 @@
-use vulkano::buffer::{BufferUsage, CpuBufferPool};
+use vulkano::buffer::{BufferUsage, CpuBufferPool, DeviceLocalBuffer};
 use vulkano::command_buffer::{
-    AutoCommandBuffer, AutoCommandBufferBuilder, BuildError, CommandBufferExecError,
-    CommandBufferExecFuture, DispatchError,
+    AutoCommandBuffer, AutoCommandBufferBuilder, BlitImageError, BuildError,
+    CommandBufferExecError, CommandBufferExecFuture, DispatchError,
 };
 use vulkano::descriptor::descriptor_set::{
     FixedSizeDescriptorSetsPool, PersistentDescriptorSetBuildError,
 };
 use vulkano::format::Format;
-use vulkano::image::{ImageUsage, Dimensions, ImageCreationError, StorageImage};
+use vulkano::image::{ImageAccess, ImageUsage, Dimensions, ImageCreationError, StorageImage};
+use vulkano::instance::PhysicalDevice;
 use vulkano::memory::DeviceMemoryAllocError;
 use vulkano::pipeline::ComputePipeline;
+use vulkano::sampler::Filter;
 use vulkano::sync::{self, GpuFuture, NowFuture};
 use vulkano::OomError;
 
+use crate::export::{self, ImgDimensions, JuliaExportError, Transfer};
 use crate::shaders::julia_comp;
-use crate::{CompDesc, JuliaContext, JuliaData};
+use crate::{CompDesc, JuliaContext, JuliaData, TonemapDesc};
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::error::Error;
 use std::fmt::{self, Debug, Display, Formatter};
 use std::iter;
 use std::sync::Arc;
 
+/// The image the interactive view is drawn into. The fractal is rendered in floating point into
+/// `hdr`, and then tonemapped into `image`, which the overlays are drawn onto and which is
+/// presented. The iteration data behind `hdr` is kept in `samples`, so that views which only
+/// color it differently skip the iteration pass.
 pub struct JuliaImage {
     image: Arc<StorageImage<Format>>,
+    hdr: Arc<StorageImage<Format>>,
+    samples: Arc<DeviceLocalBuffer<[u32]>>,
+    /// The view whose iteration data is in `samples`, if any.
+    iterated: Cell<Option<JuliaData>>,
     buffer_pool: CpuBufferPool<julia_comp::ty::Data>,
     desc_set_pool: RefCell<FixedSizeDescriptorSetsPool<Arc<ComputePipeline<CompDesc>>>>,
+    tonemap_set_pool: RefCell<FixedSizeDescriptorSetsPool<Arc<ComputePipeline<TonemapDesc>>>>,
+    transfer: Cell<Transfer>,
 }
 
 impl JuliaImage {
@@ -34,6 +47,13 @@ impl JuliaImage {
         dimensions: [u32; 2],
     ) -> Result<JuliaImage, JuliaImageError> {
         let image = create_image(context, dimensions)?;
+        let hdr = create_hdr_image(context, dimensions)?;
+        let [width, height] = dimensions;
+        let samples = export::samples_buffer(
+            ImgDimensions { width, height },
+            context.device(),
+            context.queue().family(),
+        )?;
         let buffer_pool = CpuBufferPool::<julia_comp::ty::Data>::new(
             context.device().clone(),
             BufferUsage {
@@ -45,11 +65,20 @@ impl JuliaImage {
             context.pipeline().clone(),
             0,
         ));
+        let tonemap_set_pool = RefCell::new(FixedSizeDescriptorSetsPool::new(
+            context.tonemap_pipeline().clone(),
+            0,
+        ));
 
         Ok(JuliaImage {
             image,
+            hdr,
+            samples,
+            iterated: Cell::new(None),
             buffer_pool,
             desc_set_pool,
+            tonemap_set_pool,
+            transfer: Cell::new(Transfer::Srgb),
         })
     }
 
@@ -67,27 +96,107 @@ impl JuliaImage {
         data: JuliaData,
         context: &JuliaContext,
     ) -> Result<CommandBufferExecFuture<F, AutoCommandBuffer>, JuliaImageError> {
-        let buffer = self.buffer_pool.next(data.into_shader_data())?;
+        let trap = context.trap_texture();
+        let mut shader_data = data.into_shader_data();
+        shader_data.transfer = self.transfer.get().shader_id();
+        trap.apply(&mut shader_data);
+        if let Some(equalization) = context.equalization(&data)? {
+            equalization.apply(&mut shader_data);
+        }
+
+        let buffer = self.buffer_pool.next(shader_data)?;
         let desc_set = self
             .desc_set_pool
             .borrow_mut()
             .next()
+            .add_image(self.hdr.clone())
+            .unwrap()
+            .add_buffer(buffer.clone())
+            .unwrap()
+            .add_sampled_image(trap.image().clone(), trap.sampler().clone())
+            .unwrap()
+            .add_buffer(self.samples.clone())
+            .unwrap()
+            .build()?;
+        let tonemap_set = self
+            .tonemap_set_pool
+            .borrow_mut()
+            .next()
             .add_image(self.image.clone())
             .unwrap()
             .add_buffer(buffer)
             .unwrap()
+            .add_image(self.hdr.clone())
+            .unwrap()
             .build()?;
 
         let [width, height] = self.dimensions();
+        let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(
+            context.device().clone(),
+            context.queue().family(),
+        )?;
+
+        let iterated = self.iterated.get();
+        match context.iteration_pipeline(&data) {
+            Some(_) if iterated.map_or(false, |d| d.same_iterations(&data)) => (),
+            Some(iterate) => {
+                builder =
+                    builder.dispatch([width / 8, height / 8, 1], iterate, desc_set.clone(), ())?;
+                self.iterated.set(Some(data));
+            }
+            None => self.iterated.set(None),
+        }
+
+        let cmd_buf = builder
+            .dispatch(
+                [width / 8, height / 8, 1],
+                context.color_pipeline(&data),
+                desc_set,
+                (),
+            )?
+            .dispatch(
+                [width / 8, height / 8, 1],
+                context.tonemap_pipeline().clone(),
+                tonemap_set,
+                (),
+            )?
+            .build()?;
+
+        Ok(future.then_execute(context.queue().clone(), cmd_buf)?)
+    }
+
+    /// Copies the image into `target` with a blit, converting to the target's format. This lets
+    /// it be presented without a render pass when the target supports transfers.
+    pub fn blit_after<F, I>(
+        &self,
+        future: F,
+        target: I,
+        context: &JuliaContext,
+    ) -> Result<CommandBufferExecFuture<F, AutoCommandBuffer>, JuliaImageError>
+    where
+        F: GpuFuture,
+        I: ImageAccess + Send + Sync + 'static,
+    {
+        let [width, height] = self.dimensions();
+        let corner = [width as i32, height as i32, 1];
+
         let cmd_buf = AutoCommandBufferBuilder::primary_one_time_submit(
             context.device().clone(),
             context.queue().family(),
         )?
-        .dispatch(
-            [width / 8, height / 8, 1],
-            context.pipeline().clone(),
-            desc_set,
-            (),
+        .blit_image(
+            self.image.clone(),
+            [0, 0, 0],
+            corner,
+            0,
+            0,
+            target,
+            [0, 0, 0],
+            corner,
+            0,
+            0,
+            1,
+            Filter::Nearest,
         )?
         .build()?;
 
@@ -106,6 +215,41 @@ impl JuliaImage {
     pub fn image(&self) -> &Arc<StorageImage<Format>> {
         &self.image
     }
+
+    /// The transfer function that colors are encoded with in `image`.
+    pub fn transfer(&self) -> Transfer {
+        self.transfer.get()
+    }
+
+    /// Sets the transfer function that colors are encoded with in `image`. Defaults to sRGB, which
+    /// suits presenting to a target that shows values as they are; see `view_transfer`.
+    pub fn set_transfer(&self, transfer: Transfer) {
+        self.transfer.set(transfer);
+    }
+}
+
+/// The transfer function the view must be encoded with to be presented to a target of `format`.
+/// Copies and draws into sRGB formats encode linear values as they are written, so those take
+/// linear values; other formats show values as they are, so those take encoded ones. Either way
+/// the view shows the same colors as exports.
+pub fn view_transfer(format: Format) -> Transfer {
+    match format {
+        Format::B8G8R8A8Srgb | Format::R8G8B8A8Srgb | Format::A8B8G8R8SrgbPack32 => {
+            Transfer::Linear
+        }
+        _ => Transfer::Srgb,
+    }
+}
+
+/// The format of the image the view is tonemapped into and presented from.
+const VIEW_FORMAT: Format = Format::R8G8B8A8Unorm;
+
+/// Whether the device can blit the view into swapchain images of `format`. Both images are
+/// optimally tiled.
+pub(crate) fn can_blit_to(format: Format, physical: PhysicalDevice) -> bool {
+    let from = VIEW_FORMAT.properties(physical).optimal_tiling_features;
+    let to = format.properties(physical).optimal_tiling_features;
+    from.blit_src && to.blit_dst
 }
 
 fn create_image(
@@ -118,7 +262,7 @@ fn create_image(
     StorageImage::with_usage(
         context.device().clone(),
         dimensions,
-        Format::R8G8B8A8Unorm,
+        VIEW_FORMAT,
         ImageUsage {
             transfer_source: true,
             sampled: true,
@@ -129,6 +273,27 @@ fn create_image(
     )
 }
 
+/// The floating-point image the color pipelines render into, which only the tonemapping pass
+/// reads.
+fn create_hdr_image(
+    context: &JuliaContext,
+    dimensions: [u32; 2],
+) -> Result<Arc<StorageImage<Format>>, ImageCreationError> {
+    let [width, height] = dimensions;
+    let dimensions = Dimensions::Dim2d { width, height };
+
+    StorageImage::with_usage(
+        context.device().clone(),
+        dimensions,
+        Format::R32G32B32A32Sfloat,
+        ImageUsage {
+            storage: true,
+            ..ImageUsage::none()
+        },
+        iter::once(context.queue().family()),
+    )
+}
+
 #[derive(Debug, Clone)]
 pub enum JuliaImageError {
     VkImageErr(ImageCreationError),
@@ -136,8 +301,12 @@ pub enum JuliaImageError {
     VkDescSetErr(PersistentDescriptorSetBuildError),
     VkOomErr(OomError),
     VkDispatchErr(DispatchError),
+    VkBlitErr(BlitImageError),
     VkCmdBufBuildErr(BuildError),
     VkExecErr(CommandBufferExecError),
+    /// The potential render behind histogram equalization failed. Shared, since export errors
+    /// can't be cloned.
+    EqualizationErr(Arc<JuliaExportError>),
 }
 
 use JuliaImageError::*;
@@ -150,8 +319,10 @@ impl Display for JuliaImageError {
             VkDescSetErr(e) => write!(f, "{}", e),
             VkOomErr(e) => write!(f, "{}", e),
             VkDispatchErr(e) => write!(f, "{}", e),
+            VkBlitErr(e) => write!(f, "{}", e),
             VkCmdBufBuildErr(e) => write!(f, "{}", e),
             VkExecErr(e) => write!(f, "{}", e),
+            EqualizationErr(e) => write!(f, "{}", e),
         }
     }
 }
@@ -188,6 +359,12 @@ impl From<DispatchError> for JuliaImageError {
     }
 }
 
+impl From<BlitImageError> for JuliaImageError {
+    fn from(err: BlitImageError) -> JuliaImageError {
+        VkBlitErr(err)
+    }
+}
+
 impl From<BuildError> for JuliaImageError {
     fn from(err: BuildError) -> JuliaImageError {
         VkCmdBufBuildErr(err)
@@ -200,6 +377,12 @@ impl From<CommandBufferExecError> for JuliaImageError {
     }
 }
 
+impl From<JuliaExportError> for JuliaImageError {
+    fn from(err: JuliaExportError) -> JuliaImageError {
+        EqualizationErr(Arc::new(err))
+    }
+}
+
 impl Debug for JuliaImage {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.debug_struct("JuliaImage")
@@ -207,3 +390,21 @@ impl Debug for JuliaImage {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn views_are_encoded_once() {
+        // sRGB swapchains encode as the view is copied in, so the view stays linear
+        for &format in &[Format::B8G8R8A8Srgb, Format::R8G8B8A8Srgb] {
+            assert_eq!(view_transfer(format), Transfer::Linear);
+        }
+        // Other targets show the values the tonemapping pass encodes, as exports are written
+        for &format in &[Format::B8G8R8A8Unorm, Format::R8G8B8A8Unorm] {
+            assert_eq!(view_transfer(format), Transfer::Srgb);
+        }
+        assert_eq!(view_transfer(Format::B8G8R8A8Unorm), Transfer::default());
+    }
+}
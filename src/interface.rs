@@ -13,21 +13,49 @@ use winit::{
     VirtualKeyCode, Window, WindowBuilder, WindowEvent,
 };
 
-use gramit::{Angle, Vec2, Vec4, Vector};
+use gramit::{Angle, Vec2, Vec3, Vec4, Vector};
 
 use palette::{Hsv, RgbHue, Srgb};
 
-use crate::export::{ImgDimensions, JuliaExport};
-use crate::image::{JuliaImage, JuliaImageError};
+use crate::checkpoint::CheckpointLog;
+use crate::colormap;
+#[cfg(feature = "webp")]
+use crate::export::WebpOptions;
+use crate::export::{
+    BitDepth, ColorSpace, ExportJob, ImgDimensions, JuliaExport, OutputFormat, OverwritePolicy,
+    Progress, TransferStages,
+};
+use crate::gradient::{self, Gradient, GradientStop, MAX_STOPS};
+use crate::guides::{self, GuideLayout, JuliaGuides, JuliaGuidesError};
+use crate::history::ViewHistory;
+use crate::hud::{self, JuliaHud, JuliaHudError};
+use crate::image::{can_blit_to, view_transfer, JuliaImage, JuliaImageError};
+use crate::locale::{tr, Message};
+use crate::name_template::NameTemplate;
+use crate::palette_file::Palette;
+use crate::path::PathRecorder;
+use crate::print::PrintSettings;
+use crate::random_palette;
 use crate::render::{JuliaRender, JuliaRenderError};
-use crate::{JuliaContext, JuliaData};
+use crate::strip::{JuliaStrip, JuliaStripError};
+use crate::{
+    Channels, ColoringMode, FractalKind, Interpolation, JuliaContext, JuliaData, Light, Plane,
+    Relief, Tone, ToneCurve, DEFAULT_COLOR_PERIOD, DEFAULT_STRIPE_DENSITY, MIN_EXPONENT,
+};
 
 use std::error::Error;
 use std::fmt::{self, Debug, Display, Formatter};
+use std::fs;
 use std::io::{self, Write};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// The speed color cycling starts at, and the slowest and fastest it can be made, in spans of the
+/// gradient per second.
+const DEFAULT_CYCLE_SPEED: f32 = 0.1;
+const MIN_CYCLE_SPEED: f32 = 0.005;
+const MAX_CYCLE_SPEED: f32 = 4.0;
 
 pub struct JuliaInterface {
     events_loop: EventsLoop,
@@ -36,26 +64,93 @@ pub struct JuliaInterface {
     swapchain: Mutex<Arc<Swapchain<Window>>>,
     swapchain_images: Vec<Arc<SwapchainImage<Window>>>,
     image: JuliaImage,
-    render: JuliaRender,
+    /// The render pass used to present the image, or `None` if it is blitted to the swapchain.
+    render: Option<JuliaRender>,
     export: JuliaExport,
+    checkpoint_log: Option<CheckpointLog>,
+    history: Option<ViewHistory>,
+    /// Records the path of `c` between presses of F2.
+    path_recorder: Option<PathRecorder>,
+    hud: JuliaHud,
+    guides_drawer: JuliaGuides,
+    strip: JuliaStrip,
+    /// The print settings whose margins the guides show, if any.
+    print: Option<PrintSettings>,
+    /// The palette file that Ctrl+S saves the gradient to and Ctrl+O loads it from.
+    palette_path: PathBuf,
+    /// The template exported images are named by, if not the generated name.
+    name_template: Option<NameTemplate>,
+    /// The size of the presented image, which the view is fitted to.
+    window_dimensions: ImgDimensions,
+}
+
+/// Which composition guides are drawn over the view while they are shown. The outline of the
+/// export is always drawn when its shape differs from the window's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Guides {
+    /// Lines dividing the export into thirds along each axis.
+    pub thirds: bool,
+    /// A cross at the center of the export.
+    pub center: bool,
+    /// The trim and safe lines of the print settings, if there are any.
+    pub margins: bool,
+}
+
+/// Where the interactive window opens and how it behaves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WindowOptions {
+    /// The index of the monitor to open on, in the order the windowing system lists them. The
+    /// primary monitor if `None`.
+    pub monitor: Option<usize>,
+    /// The initial size of the window in logical pixels. 80% of the smaller dimension of the
+    /// monitor, as a square, if `None`. Ignored when fullscreen.
+    pub size: Option<(u32, u32)>,
+    /// Cover the whole monitor with a borderless window.
+    pub fullscreen: bool,
+    /// Keep the window above all others.
+    pub always_on_top: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 struct JuliaState {
     data: JuliaData,
     mouse_state: MouseState,
-    hsv_colors: [Hsv; 3],
-    active_color: u8,
-    active_midpt: u8,
+    /// The colors of the gradient stops, kept apart so that hues aren't lost while colors are
+    /// gray.
+    hsv_colors: [Hsv; MAX_STOPS],
+    active_stop: u8,
     close_requested: bool,
     export_dimensions: ImgDimensions,
     export_requested: bool,
+    potential_export_requested: bool,
+    /// The number of exports still being written in the background.
+    pending_exports: usize,
+    /// How far the first of them has been rendered.
+    export_progress: Progress,
+    capture_save_requested: bool,
+    path_recording_toggled: bool,
+    palette_save_requested: bool,
+    palette_load_requested: bool,
+    hud_visible: bool,
+    /// Show the key reference in the HUD instead of the state.
+    hud_help: bool,
+    hud_scale: u32,
+    help_requested: bool,
+    guides: Guides,
+    guides_visible: bool,
+    /// Show the gradient strip along the bottom of the view.
+    strip_visible: bool,
+    /// Turn the gradient continuously, by `cycle_speed` of its span per second.
+    cycling: bool,
+    cycle_speed: f32,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 struct MouseState {
     pos: LogicalPosition,
     dragging: bool,
+    /// Dragging with the right button, which moves `c` (or `z0`) instead of the view.
+    dragging_param: bool,
 }
 
 impl JuliaState {
@@ -67,8 +162,9 @@ impl JuliaState {
         self.data.extents = extents;
     }
 
+    /// Moves the view by `offset` along its own axes.
     pub fn pan(&mut self, offset: Vec2) {
-        self.data.center += offset;
+        self.data.center += self.data.view_to_plane(offset);
     }
 
     pub fn set_center(&mut self, center: Vec2) {
@@ -79,7 +175,20 @@ impl JuliaState {
         self.data.c = c;
     }
 
-    pub fn set_n(&mut self, n: u32) {
+    pub fn set_z0(&mut self, z0: Vec2) {
+        self.data.z0 = z0;
+    }
+
+    /// Moves `c`, or `z0` in the parameter plane, by `offset` along the axes of the view.
+    pub fn drag_param(&mut self, offset: Vec2) {
+        let offset = self.data.view_to_plane(offset);
+        match self.data.view_plane() {
+            Plane::Dynamical => self.data.c += offset,
+            Plane::Parameter => self.data.z0 += offset,
+        }
+    }
+
+    pub fn set_n(&mut self, n: f32) {
         self.data.n = n;
     }
 
@@ -87,6 +196,26 @@ impl JuliaState {
         self.data.iters = iters;
     }
 
+    /// Switches to the next kind, in its own plane.
+    pub fn next_kind(&mut self) {
+        self.data.plane = None;
+        self.data.kind = match self.data.kind {
+            FractalKind::Julia => FractalKind::Mandelbrot,
+            FractalKind::Mandelbrot => FractalKind::BurningShip,
+            FractalKind::BurningShip => FractalKind::Tricorn,
+            FractalKind::Tricorn => FractalKind::Newton,
+            FractalKind::Newton => FractalKind::Julia,
+        };
+    }
+
+    /// Switches between the dynamical and parameter planes of the current kind.
+    pub fn toggle_plane(&mut self) {
+        self.data.plane = match self.data.view_plane() {
+            Plane::Dynamical => Some(Plane::Parameter),
+            Plane::Parameter => Some(Plane::Dynamical),
+        };
+    }
+
     pub fn close(&mut self) {
         self.close_requested = true;
     }
@@ -103,7 +232,11 @@ impl JuliaState {
         self.data.c
     }
 
-    pub fn n(&self) -> u32 {
+    pub fn z0(&self) -> Vec2 {
+        self.data.z0
+    }
+
+    pub fn n(&self) -> f32 {
         self.data.n
     }
 
@@ -112,126 +245,318 @@ impl JuliaState {
     }
 
     pub fn active_color(&self) -> Vec4 {
-        let hsv = self.hsv_colors[self.active_color_idx()];
+        let hsv = self.hsv_colors[self.active_stop_idx()];
         let rgb = Srgb::from(hsv);
         let (r, g, b) = rgb.into_components();
-        vec4!(r, g, b, 1.0)
+        vec4!(r, g, b, self.active_stop().color.w)
     }
 
-    pub fn active_color_idx(&self) -> usize {
-        self.active_color as usize
+    pub fn active_stop(&self) -> GradientStop {
+        self.data.gradient.stops()[self.active_stop_idx()]
     }
 
-    pub fn set_active_color(&mut self, idx: usize) -> Result<(), &'static str> {
-        if idx < 3 {
-            self.active_color = idx as u8;
-            Ok(())
-        } else {
-            Err("index out of range")
+    pub fn active_stop_idx(&self) -> usize {
+        self.active_stop as usize
+    }
+
+    /// Makes stop `idx` the active one, if the gradient has that many.
+    pub fn set_active_stop(&mut self, idx: usize) {
+        if idx < self.data.gradient.stops().len() {
+            self.active_stop = idx as u8;
         }
     }
 
-    pub fn active_midpt(&self) -> f32 {
-        self.data.color_midpoint[self.active_midpt_idx()]
+    pub fn adjust_active_position(&mut self, amount: f32) {
+        let idx = self.active_stop_idx();
+        let position = self.active_stop().position + amount;
+        self.data.gradient.set_position(idx, position);
     }
 
-    pub fn active_midpt_idx(&self) -> usize {
-        self.active_midpt as usize
+    /// Replaces the gradient with the next built-in one, making its first stop active.
+    pub fn next_colormap(&mut self) {
+        self.set_gradient(colormap::next(&self.data.gradient));
     }
 
-    pub fn set_active_midpt(&mut self, idx: usize) -> Result<(), &'static str> {
-        if idx < 3 {
-            self.active_midpt = idx as u8;
-            Ok(())
-        } else {
-            Err("index out of range")
+    /// Replaces the gradient with a random one, and prints the seed it was made from so that
+    /// `--random-colors` can make it again.
+    pub fn random_colors(&mut self) {
+        let seed = random_palette::time_seed();
+        self.set_gradient(random_palette::random_gradient(seed));
+        println!("{}", tr(Message::RandomColors, &[&seed]));
+    }
+
+    fn set_gradient(&mut self, gradient: Gradient) {
+        self.data.gradient = gradient;
+        for (hsv, stop) in self.hsv_colors.iter_mut().zip(self.data.gradient.stops()) {
+            *hsv = to_hsv(stop.color);
         }
+        self.active_stop = 0;
     }
 
-    pub fn adjust_active_midpt(&mut self, amount: f32) {
-        let idx = self.active_midpt_idx();
+    /// Adds a stop after the active one, halfway to the next, and makes it active.
+    pub fn insert_stop(&mut self) {
+        let idx = self.active_stop_idx();
+        let len = self.data.gradient.stops().len();
 
-        let midpt = self.data.color_midpoint[idx] + amount;
-        for (i, m) in self.data.color_midpoint.iter_mut().enumerate() {
-            if i < idx && *m > midpt {
-                *m = midpt;
-            } else if i > idx && *m < midpt {
-                *m = midpt;
-            }
+        if let Some(new) = self.data.gradient.insert_after(idx) {
+            self.hsv_colors.copy_within(new..len, new + 1);
+            self.hsv_colors[new] = to_hsv(self.data.gradient.stops()[new].color);
+            self.active_stop = new as u8;
         }
+    }
+
+    /// Removes the active stop, unless only two are left, and makes the one before it active.
+    pub fn remove_stop(&mut self) {
+        let idx = self.active_stop_idx();
+        let len = self.data.gradient.stops().len();
 
-        self.data.color_midpoint[idx] = midpt;
+        if self.data.gradient.remove(idx) {
+            self.hsv_colors.copy_within(idx + 1..len, idx);
+            self.active_stop = idx.saturating_sub(1) as u8;
+        }
     }
 
     pub fn adjust_hue(&mut self, amount: f32) {
-        let mut hsv = self.hsv_colors[self.active_color_idx()];
+        let mut hsv = self.hsv_colors[self.active_stop_idx()];
         let mut hue = Angle::from_radians(hsv.hue.to_radians());
         hue += Angle::from_degrees(amount);
         hsv.hue = RgbHue::from_radians(hue.radians());
-        self.hsv_colors[self.active_color_idx()] = hsv;
-        self.data.color[self.active_color_idx()] = self.active_color();
+        self.set_active_hsv(hsv);
     }
 
     pub fn adjust_saturation(&mut self, amount: f32) {
-        let mut hsv = self.hsv_colors[self.active_color_idx()];
+        let mut hsv = self.hsv_colors[self.active_stop_idx()];
         hsv.saturation += amount / 360.0;
         if hsv.saturation > 1.0 {
             hsv.saturation = 1.0;
         } else if hsv.saturation < 0.0 {
             hsv.saturation = 0.0;
         }
-        self.hsv_colors[self.active_color_idx()] = hsv;
-        self.data.color[self.active_color_idx()] = self.active_color();
+        self.set_active_hsv(hsv);
     }
 
     pub fn adjust_value(&mut self, amount: f32) {
-        let mut hsv = self.hsv_colors[self.active_color_idx()];
+        let mut hsv = self.hsv_colors[self.active_stop_idx()];
         hsv.value += amount / 360.0;
         if hsv.value > 1.0 {
             hsv.value = 1.0;
         } else if hsv.value < 0.0 {
             hsv.value = 0.0;
         }
-        self.hsv_colors[self.active_color_idx()] = hsv;
-        self.data.color[self.active_color_idx()] = self.active_color();
+        self.set_active_hsv(hsv);
+    }
+
+    pub fn adjust_alpha(&mut self, amount: f32) {
+        let mut color = self.active_stop().color;
+        color.w = f32::min(f32::max(color.w + amount, 0.0), 1.0);
+        self.data.gradient.set_color(self.active_stop_idx(), color);
+    }
+
+    fn set_active_hsv(&mut self, hsv: Hsv) {
+        self.hsv_colors[self.active_stop_idx()] = hsv;
+        let color = self.active_color();
+        self.data.gradient.set_color(self.active_stop_idx(), color);
+    }
+
+    pub fn adjust_color_offset(&mut self, amount: f32) {
+        self.data.color_offset += amount;
+    }
+
+    /// Multiplies the color scale by the given factor.
+    pub fn scale_colors(&mut self, factor: f32) {
+        self.data.color_scale *= factor;
+    }
+
+    /// Turns slope shading on with the default light, or off.
+    pub fn toggle_shading(&mut self) {
+        self.data.shading = match self.data.shading {
+            Some(_) => None,
+            None => Some(Light::default()),
+        };
+    }
+
+    /// Turns the light by the given angle in degrees, turning slope shading on if it was off.
+    pub fn rotate_light(&mut self, degrees: f32) {
+        let light = self.data.shading.get_or_insert_with(Light::default);
+        light.azimuth = (light.azimuth + degrees).rem_euclid(360.0);
+    }
+
+    /// Raises the light by the given angle in degrees, keeping it between the image plane and
+    /// straight overhead, and turning slope shading on if it was off.
+    pub fn raise_light(&mut self, degrees: f32) {
+        let light = self.data.shading.get_or_insert_with(Light::default);
+        light.elevation = (light.elevation + degrees).max(0.0).min(90.0);
+    }
+
+    /// Switches slope shading between lighting the iteration count and the distance estimate,
+    /// turning it on if it was off.
+    pub fn toggle_relief(&mut self) {
+        let light = self.data.shading.get_or_insert_with(Light::default);
+        light.relief = match light.relief {
+            Relief::Iterations => Relief::Distance,
+            Relief::Distance => Relief::Iterations,
+        };
+    }
+
+    /// Switches to the next coloring mode, from iterations to distance, stripes, binary
+    /// decomposition, per-channel coloring, curvature and back.
+    pub fn next_coloring(&mut self) {
+        self.data.coloring = match self.data.coloring {
+            ColoringMode::Iterations => ColoringMode::DistanceEstimate,
+            ColoringMode::DistanceEstimate => ColoringMode::Stripes,
+            ColoringMode::Stripes => ColoringMode::BinaryDecomposition,
+            ColoringMode::BinaryDecomposition => ColoringMode::Channels,
+            ColoringMode::Channels => ColoringMode::Curvature,
+            ColoringMode::Curvature => ColoringMode::Iterations,
+        };
+    }
+
+    /// Switches to the next space for blending gradient colors, from RGB to Oklab to LCh to HSV,
+    /// the short and then the long way around, and back.
+    pub fn next_interpolation(&mut self) {
+        self.data.interpolation = match self.data.interpolation {
+            Interpolation::Rgb => Interpolation::Oklab,
+            Interpolation::Oklab => Interpolation::Lch,
+            Interpolation::Lch => Interpolation::Hsv,
+            Interpolation::Hsv => Interpolation::HsvLong,
+            Interpolation::HsvLong => Interpolation::Rgb,
+        };
+    }
+
+    /// Adds to the exposure, in stops.
+    pub fn adjust_exposure(&mut self, stops: f32) {
+        self.data.tone.exposure += stops;
+    }
+
+    /// Multiplies the gamma by the given factor.
+    pub fn scale_gamma(&mut self, factor: f32) {
+        self.data.tone.gamma *= factor;
+    }
+
+    /// Multiplies the contrast by the given factor.
+    pub fn scale_contrast(&mut self, factor: f32) {
+        self.data.tone.contrast *= factor;
+    }
+
+    /// Switches to the next tone curve: clip, Reinhard, then ACES.
+    pub fn cycle_tone_curve(&mut self) {
+        self.data.tone.curve = match self.data.tone.curve {
+            ToneCurve::Clip => ToneCurve::Reinhard,
+            ToneCurve::Reinhard => ToneCurve::Aces,
+            ToneCurve::Aces => ToneCurve::Clip,
+        };
+    }
+
+    /// Makes the interior of the set transparent, or gives it back its color from the gradient.
+    pub fn toggle_transparent_interior(&mut self) {
+        self.data.interior_color = match self.data.interior_color {
+            Some(_) => None,
+            None => Some(vec4!(0.0, 0.0, 0.0, 0.0)),
+        };
+    }
+
+    /// Turns histogram equalization of the iteration count on or off.
+    pub fn toggle_equalization(&mut self) {
+        self.data.equalize = !self.data.equalize;
+    }
+
+    /// Turns the repeating gradient on with the default period, or off.
+    pub fn toggle_color_period(&mut self) {
+        self.data.color_period = match self.data.color_period {
+            Some(_) => None,
+            None => Some(DEFAULT_COLOR_PERIOD),
+        };
+    }
+
+    /// Multiplies the gradient's period by the given factor, turning the repeating gradient on if
+    /// it was off.
+    pub fn scale_color_period(&mut self, factor: f32) {
+        let period = self.data.color_period.get_or_insert(DEFAULT_COLOR_PERIOD);
+        *period = f32::max(1.0, *period * factor);
+    }
+
+    /// Adds to the stripe density, keeping it at least 1.
+    pub fn adjust_stripe_density(&mut self, amount: f32) {
+        self.data.stripe_density = f32::max(1.0, self.data.stripe_density + amount);
     }
 
     pub fn close_requested(&self) -> bool {
         self.close_requested
     }
-}
 
-impl MouseState {
-    fn start_drag(&mut self) {
-        self.dragging = true;
+    pub fn toggle_hud(&mut self) {
+        self.hud_visible = !self.hud_visible;
+    }
+
+    /// Switches the HUD between the key reference and the state, showing it if it's hidden. The
+    /// key reference is also printed when it's switched to.
+    pub fn toggle_help(&mut self) {
+        if self.hud_visible && self.hud_help {
+            self.hud_help = false;
+        } else {
+            self.hud_visible = true;
+            self.hud_help = true;
+            self.help_requested = true;
+        }
+    }
+
+    pub fn toggle_guides(&mut self) {
+        self.guides_visible = !self.guides_visible;
+    }
+
+    pub fn toggle_strip(&mut self) {
+        self.strip_visible = !self.strip_visible;
+    }
+
+    /// Starts or stops turning the gradient over time. The gradient stays where it was stopped.
+    pub fn toggle_cycling(&mut self) {
+        self.cycling = !self.cycling;
+    }
+
+    /// Multiplies the speed of color cycling by the given factor.
+    pub fn scale_cycle_speed(&mut self, factor: f32) {
+        self.cycle_speed = (self.cycle_speed * factor)
+            .max(MIN_CYCLE_SPEED)
+            .min(MAX_CYCLE_SPEED);
+    }
+
+    /// Turns the gradient by as much as color cycling does in `seconds`, if it's on.
+    fn advance_cycle(&mut self, seconds: f32) {
+        if self.cycling {
+            let phase = self.data.color_phase + self.cycle_speed * seconds;
+            self.data.color_phase = phase - phase.floor();
+        }
     }
 
-    fn stop_drag(&mut self) {
-        self.dragging = false;
+    pub fn scale_hud(&mut self, larger: bool) {
+        self.hud_scale = if larger {
+            u32::min(self.hud_scale + 1, hud::MAX_SCALE)
+        } else {
+            u32::max(self.hud_scale - 1, 1)
+        };
     }
+}
 
-    fn update_and_get_offset(
+impl MouseState {
+    /// Returns the cursor's move to `new_pos` in the units of the complex plane, with the
+    /// imaginary axis pointing up. `state` should be fitted to the window, so that the move is
+    /// scaled alike along both axes.
+    fn update_and_get_motion(
         &mut self,
         new_pos: LogicalPosition,
         win_size: LogicalSize,
         state: &JuliaData,
     ) -> Vec2 {
-        let offset = if self.dragging {
-            let diff = vec2!(new_pos.x as f32, new_pos.y as f32)
-                - vec2!(self.pos.x as f32, self.pos.y as f32);
-            let dims = {
-                let (width, height): (f64, f64) = win_size.into();
-                vec2!(width as f32, height as f32)
-            };
-            let ratio = diff / dims;
-
-            ratio * state.extents
-        } else {
-            vec2!(0.0, 0.0)
+        let diff =
+            vec2!(new_pos.x as f32, new_pos.y as f32) - vec2!(self.pos.x as f32, self.pos.y as f32);
+        let dims = {
+            let (width, height): (f64, f64) = win_size.into();
+            vec2!(width as f32, height as f32)
         };
+        let motion = diff / dims * state.extents;
 
         self.pos = new_pos;
-        vec2!(-offset.x, offset.y)
+        vec2!(motion.x, -motion.y)
     }
 }
 
@@ -244,19 +569,62 @@ impl Debug for JuliaInterface {
             .field("events_loop", &self.events_loop)
             .field("image", &self.image)
             .field("render", &self.render)
+            .field("checkpoint_log", &self.checkpoint_log)
+            .field("hud", &self.hud)
+            .field("guides_drawer", &self.guides_drawer)
+            .field("strip", &self.strip)
+            .field("print", &self.print)
             .finish()
     }
 }
 
-fn default_state() -> JuliaData {
+/// The HSV form of a gradient color, ignoring its alpha.
+fn to_hsv(c: Vec4) -> Hsv {
+    Hsv::from(Srgb::new(c.x, c.y, c.z))
+}
+
+pub(crate) fn default_state() -> JuliaData {
     JuliaData {
-        color: [Vec4::zeros(), Vec4::ones(), Vec4::ones()],
-        color_midpoint: [0.0, 0.25, 1.0],
-        n: 2,
+        gradient: Gradient::new(&[
+            GradientStop {
+                color: vec4!(0.0, 0.0, 0.0, 1.0),
+                position: 0.0,
+            },
+            GradientStop {
+                color: Vec4::ones(),
+                position: 0.25,
+            },
+        ])
+        .unwrap(),
+        color_offset: 0.0,
+        color_scale: 1.0,
+        kind: FractalKind::Julia,
+        n: 2.0,
         c: vec2!(0.2, 0.0),
+        z0: Vec2::zeros(),
         iters: 100,
         center: Vec2::zeros(),
         extents: vec2!(3.6, 3.6),
+        poly: None,
+        denom: None,
+        hybrid: None,
+        shading: None,
+        nebulabrot: None,
+        plane: None,
+        strict: false,
+        periodicity: true,
+        interior_checks: true,
+        rotation: 0.0,
+        coloring: ColoringMode::Iterations,
+        stripe_density: DEFAULT_STRIPE_DENSITY,
+        channels: Channels::default(),
+        equalize: false,
+        color_period: None,
+        color_phase: 0.0,
+        interpolation: Interpolation::Rgb,
+        interior_color: None,
+        tone: Tone::default(),
+        dither: false,
     }
 }
 
@@ -267,21 +635,36 @@ fn event_callback<'ifc>(
     move |e| {
         if let Event::WindowEvent { event, .. } = e {
             match event {
-                WindowEvent::CursorMoved { position, .. } => {
-                    let offset = julia_state.mouse_state.update_and_get_offset(
+                WindowEvent::CursorMoved {
+                    position,
+                    modifiers,
+                    ..
+                } => {
+                    let fitted = julia_state.data.fit_to(ImgDimensions {
+                        width: window_dims.width.round() as u32,
+                        height: window_dims.height.round() as u32,
+                    });
+                    let motion = julia_state.mouse_state.update_and_get_motion(
                         position,
                         window_dims,
-                        &julia_state.data,
+                        &fitted,
                     );
-                    julia_state.pan(offset);
+
+                    if julia_state.mouse_state.dragging {
+                        julia_state.pan(vec2!(-motion.x, -motion.y));
+                    }
+                    if julia_state.mouse_state.dragging_param {
+                        let scale = if modifiers.shift { 0.01 } else { 0.1 };
+                        julia_state.drag_param(scale * motion);
+                    }
                 }
 
                 WindowEvent::MouseInput { state, button, .. } => {
-                    if let MouseButton::Left = button {
-                        match state {
-                            ElementState::Pressed => julia_state.mouse_state.start_drag(),
-                            ElementState::Released => julia_state.mouse_state.stop_drag(),
-                        }
+                    let pressed = state == ElementState::Pressed;
+                    match button {
+                        MouseButton::Left => julia_state.mouse_state.dragging = pressed,
+                        MouseButton::Right => julia_state.mouse_state.dragging_param = pressed,
+                        _ => (),
                     }
                 }
 
@@ -312,27 +695,64 @@ fn event_callback<'ifc>(
                 } => {
                     if let ElementState::Pressed = state {
                         if let Some(code) = virtual_keycode {
+                            // Panning steps, smaller with Shift held
+                            let pan_step = if modifiers.shift {
+                                julia_state.extents().y / 300.0
+                            } else {
+                                julia_state.extents().y / 30.0
+                            };
+
                             match code {
                                 VirtualKeyCode::Q | VirtualKeyCode::Escape => julia_state.close(),
+                                VirtualKeyCode::Add | VirtualKeyCode::Equals if modifiers.ctrl => {
+                                    julia_state.scale_hud(true)
+                                }
+                                VirtualKeyCode::Subtract | VirtualKeyCode::Minus
+                                    if modifiers.ctrl =>
+                                {
+                                    julia_state.scale_hud(false)
+                                }
                                 VirtualKeyCode::Add | VirtualKeyCode::Equals => {
                                     julia_state.zoom(1.0 / 1.1)
                                 }
-                                VirtualKeyCode::Subtract => julia_state.zoom(1.1),
+                                VirtualKeyCode::Subtract | VirtualKeyCode::Minus => {
+                                    julia_state.zoom(1.1)
+                                }
+                                VirtualKeyCode::Tab => julia_state.toggle_hud(),
+                                VirtualKeyCode::F1 => julia_state.toggle_help(),
+                                VirtualKeyCode::F2 => julia_state.path_recording_toggled = true,
+                                VirtualKeyCode::F3 => julia_state.toggle_guides(),
+                                VirtualKeyCode::F4 => julia_state.toggle_strip(),
+                                VirtualKeyCode::F5 => julia_state.toggle_cycling(),
                                 VirtualKeyCode::Up
                                 | VirtualKeyCode::Down
                                 | VirtualKeyCode::Left
-                                | VirtualKeyCode::Right => move_c(julia_state, code, modifiers),
-                                VirtualKeyCode::PageUp => julia_state.set_n(julia_state.n() + 1),
-                                VirtualKeyCode::PageDown => {
-                                    let n = julia_state.n();
-                                    if n > 2 {
-                                        julia_state.set_n(julia_state.n() - 1);
-                                    }
+                                | VirtualKeyCode::Right => move_param(julia_state, code, modifiers),
+                                VirtualKeyCode::PageUp | VirtualKeyCode::PageDown => {
+                                    step_n(julia_state, code, modifiers)
                                 }
 
+                                VirtualKeyCode::M if modifiers.shift => julia_state.toggle_plane(),
+                                VirtualKeyCode::M => julia_state.next_kind(),
+
+                                VirtualKeyCode::C if modifiers.shift => julia_state.next_colormap(),
+                                VirtualKeyCode::C if modifiers.alt => julia_state.random_colors(),
                                 VirtualKeyCode::C => julia_state.set_center(vec2!(0.0, 0.0)),
                                 VirtualKeyCode::Z => julia_state.set_extents(vec2!(3.6, 3.6)),
 
+                                VirtualKeyCode::RBracket if modifiers.ctrl => {
+                                    julia_state.scale_gamma(1.05)
+                                }
+                                VirtualKeyCode::LBracket if modifiers.ctrl => {
+                                    julia_state.scale_gamma(1.0 / 1.05)
+                                }
+                                VirtualKeyCode::RBracket if modifiers.shift => {
+                                    julia_state.rotate_light(15.0)
+                                }
+                                VirtualKeyCode::LBracket if modifiers.shift => {
+                                    julia_state.rotate_light(-15.0)
+                                }
+
                                 VirtualKeyCode::RBracket => {
                                     julia_state.set_iters(julia_state.iters() + 10)
                                 }
@@ -346,28 +766,92 @@ fn event_callback<'ifc>(
                                     }
                                 }
 
-                                VirtualKeyCode::Key1 => julia_state.set_active_color(0).unwrap(),
-                                VirtualKeyCode::Key2 => julia_state.set_active_color(1).unwrap(),
-                                VirtualKeyCode::Key3 => julia_state.set_active_color(2).unwrap(),
-
-                                VirtualKeyCode::Key4 => julia_state.set_active_midpt(0).unwrap(),
-                                VirtualKeyCode::Key5 => julia_state.set_active_midpt(1).unwrap(),
-                                VirtualKeyCode::Key6 => julia_state.set_active_midpt(2).unwrap(),
+                                VirtualKeyCode::Key1 => julia_state.set_active_stop(0),
+                                VirtualKeyCode::Key2 => julia_state.set_active_stop(1),
+                                VirtualKeyCode::Key3 => julia_state.set_active_stop(2),
+                                VirtualKeyCode::Key4 => julia_state.set_active_stop(3),
+                                VirtualKeyCode::Key5 => julia_state.set_active_stop(4),
+                                VirtualKeyCode::Key6 => julia_state.set_active_stop(5),
+                                VirtualKeyCode::Key7 => julia_state.set_active_stop(6),
+                                VirtualKeyCode::Key8 => julia_state.set_active_stop(7),
+                                VirtualKeyCode::Key9 => julia_state.set_active_stop(8),
+                                VirtualKeyCode::Insert => julia_state.insert_stop(),
+                                VirtualKeyCode::Delete => julia_state.remove_stop(),
 
                                 VirtualKeyCode::R => julia_state.adjust_hue(5.0),
                                 VirtualKeyCode::F => julia_state.adjust_hue(-5.0),
+                                VirtualKeyCode::T if modifiers.ctrl => {
+                                    julia_state.toggle_transparent_interior()
+                                }
+                                VirtualKeyCode::T if modifiers.alt => {
+                                    julia_state.cycle_tone_curve()
+                                }
                                 VirtualKeyCode::T => julia_state.adjust_saturation(5.0),
                                 VirtualKeyCode::G => julia_state.adjust_saturation(-5.0),
                                 VirtualKeyCode::Y => julia_state.adjust_value(2.5),
+                                VirtualKeyCode::H if modifiers.ctrl => {
+                                    julia_state.toggle_equalization()
+                                }
                                 VirtualKeyCode::H => julia_state.adjust_value(-2.5),
-                                VirtualKeyCode::U => julia_state.adjust_active_midpt(0.01),
-                                VirtualKeyCode::J => julia_state.adjust_active_midpt(-0.01),
+                                VirtualKeyCode::N => julia_state.adjust_alpha(0.05),
+                                VirtualKeyCode::B => julia_state.adjust_alpha(-0.05),
+                                VirtualKeyCode::U => julia_state.adjust_active_position(0.01),
+                                VirtualKeyCode::J => julia_state.adjust_active_position(-0.01),
+                                VirtualKeyCode::Period if modifiers.ctrl => {
+                                    julia_state.scale_color_period(1.1)
+                                }
+                                VirtualKeyCode::Comma if modifiers.ctrl => {
+                                    julia_state.scale_color_period(1.0 / 1.1)
+                                }
+                                VirtualKeyCode::Period if modifiers.alt => {
+                                    julia_state.scale_cycle_speed(1.25)
+                                }
+                                VirtualKeyCode::Comma if modifiers.alt => {
+                                    julia_state.scale_cycle_speed(1.0 / 1.25)
+                                }
+                                VirtualKeyCode::Period if modifiers.shift => {
+                                    julia_state.adjust_stripe_density(1.0)
+                                }
+                                VirtualKeyCode::Comma if modifiers.shift => {
+                                    julia_state.adjust_stripe_density(-1.0)
+                                }
+                                VirtualKeyCode::Period => julia_state.adjust_color_offset(0.01),
+                                VirtualKeyCode::Comma => julia_state.adjust_color_offset(-0.01),
+                                VirtualKeyCode::Apostrophe if modifiers.ctrl => {
+                                    julia_state.adjust_exposure(0.1)
+                                }
+                                VirtualKeyCode::Semicolon if modifiers.ctrl => {
+                                    julia_state.adjust_exposure(-0.1)
+                                }
+                                VirtualKeyCode::Apostrophe if modifiers.alt => {
+                                    julia_state.scale_contrast(1.05)
+                                }
+                                VirtualKeyCode::Semicolon if modifiers.alt => {
+                                    julia_state.scale_contrast(1.0 / 1.05)
+                                }
+                                VirtualKeyCode::Apostrophe if modifiers.shift => {
+                                    julia_state.raise_light(5.0)
+                                }
+                                VirtualKeyCode::Semicolon if modifiers.shift => {
+                                    julia_state.raise_light(-5.0)
+                                }
+                                VirtualKeyCode::Apostrophe => julia_state.scale_colors(1.05),
+                                VirtualKeyCode::Semicolon => julia_state.scale_colors(1.0 / 1.05),
+                                VirtualKeyCode::V if modifiers.ctrl => julia_state.toggle_relief(),
+                                VirtualKeyCode::V if modifiers.shift => julia_state.next_coloring(),
+                                VirtualKeyCode::V => julia_state.toggle_shading(),
+                                VirtualKeyCode::I if modifiers.ctrl => {
+                                    julia_state.next_interpolation()
+                                }
                                 VirtualKeyCode::I => julia_state.export_dimensions.width += 40,
                                 VirtualKeyCode::K => {
                                     if julia_state.export_dimensions.width > 40 {
                                         julia_state.export_dimensions.width -= 40;
                                     }
                                 }
+                                VirtualKeyCode::O if modifiers.ctrl => {
+                                    julia_state.palette_load_requested = true
+                                }
                                 VirtualKeyCode::O => julia_state.export_dimensions.height += 40,
                                 VirtualKeyCode::L => {
                                     if julia_state.export_dimensions.height > 40 {
@@ -375,20 +859,20 @@ fn event_callback<'ifc>(
                                     }
                                 }
 
-                                VirtualKeyCode::W => {
-                                    julia_state.pan(vec2!(0.0, julia_state.extents().y / 30.0))
-                                }
-                                VirtualKeyCode::A => {
-                                    julia_state.pan(vec2!(-julia_state.extents().y / 30.0, 0.0))
-                                }
-                                VirtualKeyCode::S => {
-                                    julia_state.pan(vec2!(0.0, -julia_state.extents().y / 30.0))
-                                }
-                                VirtualKeyCode::D => {
-                                    julia_state.pan(vec2!(julia_state.extents().y / 30.0, 0.0))
+                                VirtualKeyCode::W => julia_state.pan(vec2!(0.0, pan_step)),
+                                VirtualKeyCode::A => julia_state.pan(vec2!(-pan_step, 0.0)),
+                                VirtualKeyCode::S if modifiers.ctrl => {
+                                    julia_state.palette_save_requested = true
                                 }
+                                VirtualKeyCode::S => julia_state.pan(vec2!(0.0, -pan_step)),
+                                VirtualKeyCode::D => julia_state.pan(vec2!(pan_step, 0.0)),
 
                                 VirtualKeyCode::E => julia_state.export_requested = true,
+                                VirtualKeyCode::P if modifiers.ctrl => {
+                                    julia_state.toggle_color_period()
+                                }
+                                VirtualKeyCode::P => julia_state.potential_export_requested = true,
+                                VirtualKeyCode::X => julia_state.capture_save_requested = true,
 
                                 _ => (),
                             }
@@ -404,7 +888,8 @@ fn event_callback<'ifc>(
     }
 }
 
-fn move_c(julia_state: &mut JuliaState, key: VirtualKeyCode, mods: ModifiersState) {
+/// Moves `c`, or `z0` in the parameter plane, where `c` is taken from the pixel.
+fn move_param(julia_state: &mut JuliaState, key: VirtualKeyCode, mods: ModifiersState) {
     let dist = 0.001 * {
         if mods.shift {
             0.1
@@ -417,139 +902,357 @@ fn move_c(julia_state: &mut JuliaState, key: VirtualKeyCode, mods: ModifiersStat
         }
     };
 
-    let mut c = julia_state.c();
+    let z0 = julia_state.data.view_plane() == Plane::Parameter;
+    let mut param = if z0 {
+        julia_state.z0()
+    } else {
+        julia_state.c()
+    };
 
     match key {
-        VirtualKeyCode::Up => c += vec2!(0.0, dist),
-        VirtualKeyCode::Down => c -= vec2!(0.0, dist),
-        VirtualKeyCode::Left => c -= vec2!(dist, 0.0),
-        VirtualKeyCode::Right => c += vec2!(dist, 0.0),
+        VirtualKeyCode::Up => param += vec2!(0.0, dist),
+        VirtualKeyCode::Down => param -= vec2!(0.0, dist),
+        VirtualKeyCode::Left => param -= vec2!(dist, 0.0),
+        VirtualKeyCode::Right => param += vec2!(dist, 0.0),
         _ => (),
     }
 
-    julia_state.set_c(c);
+    if z0 {
+        julia_state.set_z0(param);
+    } else {
+        julia_state.set_c(param);
+    }
 }
 
-fn print_state<W: Write>(state: &JuliaState, writer: &mut W) -> io::Result<()> {
+/// Steps the exponent by 1, or by 0.1 with Shift held, keeping it above 1.
+fn step_n(julia_state: &mut JuliaState, key: VirtualKeyCode, mods: ModifiersState) {
+    let step = if mods.shift { 0.1 } else { 1.0 };
+    let step = if key == VirtualKeyCode::PageDown {
+        -step
+    } else {
+        step
+    };
+
+    // Rounded to a tenth so repeated steps don't accumulate error
+    let n = ((julia_state.n() + step) * 10.0).round() / 10.0;
+    if n > MIN_EXPONENT {
+        julia_state.set_n(n);
+    }
+}
+
+/// The lines of the state printout, below its banner.
+fn state_lines(state: &JuliaState) -> Vec<String> {
     fn fmt_complex(z: Vec2) -> String {
         let op = if z.y < 0.0 { '-' } else { '+' };
 
         format!("{} {} {}i", z.x, op, z.y.abs())
     }
 
-    fn to_hex(c: Vec4) -> String {
-        let c = Srgb::new(c[0], c[1], c[2]);
-        let c = Srgb::<u8>::from_format(c);
-        format!("{:02x}{:02x}{:02x}", c.red, c.green, c.blue)
-    }
-
-    fn wrap_active(s: &str, active: usize, i: usize) -> String {
-        let brackets = if i == active { ("[", "]") } else { ("", "") };
-
-        format!("{}{}{}", brackets.0, s, brackets.1)
-    }
+    fn fmt_gradient(gradient: &Gradient, active: usize) -> String {
+        if let Some(name) = colormap::name_of(gradient) {
+            return String::from(name);
+        }
 
-    fn fmt_color(c: Vec4, active: usize, i: usize) -> String {
-        wrap_active(&format!("#{}", to_hex(c)), active, i)
+        gradient
+            .stops()
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                let stop = format!("#{}@{}", gradient::to_hex(s.color), s.position);
+                if i == active {
+                    format!("[{}]", stop)
+                } else {
+                    stop
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 
-    fn fmt_gradient(
-        colors: &[Vec4; 3],
-        midpts: &[f32; 3],
-        active_color: usize,
-        active_midpt: usize,
-    ) -> String {
+    fn fmt_hsv(c: Hsv) -> String {
         format!(
-            "{}, {}, {} ({}, {}, {})",
-            fmt_color(colors[0], active_color, 0),
-            fmt_color(colors[1], active_color, 1),
-            fmt_color(colors[2], active_color, 2),
-            wrap_active(&format!("{}", midpts[0]), active_midpt, 0),
-            wrap_active(&format!("{}", midpts[1]), active_midpt, 1),
-            wrap_active(&format!("{}", midpts[2]), active_midpt, 2),
+            "H{:5.1} S{:.2} V{:.2}",
+            c.hue.to_positive_degrees(),
+            c.saturation,
+            c.value,
         )
     }
 
-    fn fmt_single_hsv(c: Hsv, active: usize, i: usize) -> String {
-        wrap_active(
-            &format!(
-                "H{:5.1} S{:.2} V{:.2}",
-                c.hue.to_positive_degrees(),
-                c.saturation,
-                c.value,
+    fn fmt_formula(data: &JuliaData) -> String {
+        // The iterated polynomial applied to the given argument
+        let power = |arg: &str| {
+            let num = match &data.poly {
+                Some(_) => format!("P({})", arg),
+                None if arg.len() == 1 => format!("{}^{}", arg, data.n),
+                None => format!("({})^{}", arg, data.n),
+            };
+
+            match &data.denom {
+                Some(_) if data.kind != FractalKind::Newton => format!("{} / Q({})", num, arg),
+                _ => num,
+            }
+        };
+
+        let formula = match data.kind {
+            FractalKind::Julia => tr(Message::JuliaFormula, &[&power("x"), &fmt_complex(data.c)]),
+            FractalKind::Mandelbrot => tr(Message::MandelbrotFormula, &[&power("x")]),
+            FractalKind::BurningShip => {
+                tr(Message::BurningShipFormula, &[&power("|Re x| + i|Im x|")])
+            }
+            FractalKind::Tricorn => tr(Message::TricornFormula, &[&power("conj(x)")]),
+            FractalKind::Newton => match &data.poly {
+                Some(_) => tr(Message::NewtonPolynomial, &[]),
+                None => tr(Message::NewtonFormula, &[&data.n]),
+            },
+        };
+
+        let formula = match data.view_plane() {
+            Plane::Parameter if data.kind == FractalKind::Julia => tr(
+                Message::ParameterPlane,
+                &[&power("x"), &fmt_complex(data.z0)],
             ),
-            active,
-            i,
-        )
-    }
+            Plane::Parameter => tr(Message::FromZ0, &[&formula, &fmt_complex(data.z0)]),
+            Plane::Dynamical if data.kind.is_parameter_plane() => {
+                tr(Message::DynamicalPlane, &[&formula, &fmt_complex(data.c)])
+            }
+            Plane::Dynamical => formula,
+        };
 
-    fn fmt_hsv(colors: &[Hsv; 3], active: usize) -> String {
-        format!(
-            "{}, {}, {}",
-            fmt_single_hsv(colors[0], active, 0),
-            fmt_single_hsv(colors[1], active, 1),
-            fmt_single_hsv(colors[2], active, 2),
-        )
+        let formula = match &data.hybrid {
+            Some(h) if data.kind != FractalKind::Newton => {
+                format!("{}\n{}", formula, tr(Message::HybridSteps, &[h]))
+            }
+            _ => formula,
+        };
+
+        let formula = match &data.poly {
+            Some(p) => format!("{}\n{}", formula, tr(Message::NumeratorCoefficients, &[p])),
+            None => formula,
+        };
+
+        match &data.denom {
+            Some(q) if data.kind != FractalKind::Newton => {
+                let coefficients = tr(Message::DenominatorCoefficients, &[q]);
+                format!("{}\n{}", formula, coefficients)
+            }
+            _ => formula,
+        }
     }
 
     let range1 = state.center() - 0.5 * state.extents();
     let range2 = state.center() + 0.5 * state.extents();
 
+    let mut lines: Vec<_> = fmt_formula(&state.data).lines().map(String::from).collect();
+    lines.push(tr(Message::Iterations, &[&state.iters()]));
+    lines.push(tr(
+        Message::Range,
+        &[&fmt_complex(range1), &fmt_complex(range2)],
+    ));
+    lines.push(tr(
+        Message::ColorGradient,
+        &[&fmt_gradient(&state.data.gradient, state.active_stop_idx())],
+    ));
+    lines.push(format!(
+        "    {}",
+        fmt_hsv(state.hsv_colors[state.active_stop_idx()])
+    ));
+    lines.push(tr(
+        Message::Interior,
+        &[&match state.data.interior_color {
+            Some(c) if c.w == 0.0 => tr(Message::InteriorTransparent, &[]),
+            Some(c) => format!("#{}", gradient::to_hex(c)),
+            None => tr(Message::InteriorGradient, &[]),
+        }],
+    ));
+    lines.push(tr(
+        Message::ColorBlending,
+        &[&tr(
+            match state.data.interpolation {
+                Interpolation::Rgb => Message::BlendingRgb,
+                Interpolation::Oklab => Message::BlendingOklab,
+                Interpolation::Lch => Message::BlendingLch,
+                Interpolation::Hsv => Message::BlendingHsv,
+                Interpolation::HsvLong => Message::BlendingHsvLong,
+            },
+            &[],
+        )],
+    ));
+    lines.push(match state.data.color_period {
+        Some(p) => tr(
+            Message::ColorMappingRepeating,
+            &[&state.data.color_scale, &state.data.color_offset, &p],
+        ),
+        None => tr(
+            if state.data.equalize {
+                Message::ColorMappingEqualized
+            } else {
+                Message::ColorMapping
+            },
+            &[&state.data.color_scale, &state.data.color_offset],
+        ),
+    });
+    lines.push(tr(
+        Message::ColorCycling,
+        &[&if state.cycling {
+            tr(
+                Message::CyclingSpeed,
+                &[
+                    &format!("{:.3}", state.cycle_speed),
+                    &format!("{:.2}", state.data.color_phase),
+                ],
+            )
+        } else {
+            tr(Message::Off, &[])
+        }],
+    ));
+    lines.push(tr(
+        Message::ToneAdjustment,
+        &[
+            &format!("{:+.1}", state.data.tone.exposure),
+            &format!("{:.2}", state.data.tone.gamma),
+            &format!("{:.2}", state.data.tone.contrast),
+            &state.data.tone.curve,
+        ],
+    ));
+    lines.push(tr(
+        Message::Shading,
+        &[&match state.data.shading {
+            Some(l) => tr(
+                match l.relief {
+                    Relief::Iterations => Message::ShadingLight,
+                    Relief::Distance => Message::ShadingLightDistance,
+                },
+                &[&l.azimuth, &l.elevation],
+            ),
+            None => tr(Message::Off, &[]),
+        }],
+    ));
+    lines.push(tr(
+        Message::Coloring,
+        &[&match state.data.coloring {
+            ColoringMode::Iterations => tr(Message::ColoringIterations, &[]),
+            ColoringMode::DistanceEstimate => tr(Message::ColoringDistance, &[]),
+            ColoringMode::Stripes => tr(Message::ColoringStripes, &[&state.data.stripe_density]),
+            ColoringMode::BinaryDecomposition => tr(Message::ColoringBinary, &[]),
+            ColoringMode::Curvature => tr(Message::ColoringCurvature, &[]),
+            ColoringMode::Channels => {
+                let Channels { scale, offset } = state.data.channels;
+                let fmt = |v: Vec3| format!("{:.2}, {:.2}, {:.2}", v.x, v.y, v.z);
+                tr(Message::ColoringChannels, &[&fmt(scale), &fmt(offset)])
+            }
+        }],
+    ));
+    lines.push(tr(
+        Message::ExportDimensions,
+        &[
+            &state.export_dimensions.width,
+            &state.export_dimensions.height,
+        ],
+    ));
+    if state.pending_exports > 0 {
+        lines.push(tr(
+            Message::PendingExports,
+            &[&state.pending_exports, &state.export_progress],
+        ));
+    }
+
+    lines
+}
+
+fn print_state<W: Write>(state: &JuliaState, writer: &mut W) -> io::Result<()> {
+    let title = tr(Message::CurrentState, &[]);
+    let rule = "=".repeat(title.chars().count() + 16);
+
     writeln!(
         writer,
-        r#"
-=============================
-======= Current state =======
-=============================
-f(x) = x^{} + ({})
-{} Iterations
-Range: ({}) -- ({})
-Color gradient: {}
-    {}
-Export dimensions: {}x{}"#,
-        state.n(),
-        fmt_complex(state.c()),
-        state.iters(),
-        fmt_complex(range1),
-        fmt_complex(range2),
-        fmt_gradient(
-            &state.data.color,
-            &state.data.color_midpoint,
-            state.active_color_idx(),
-            state.active_midpt_idx(),
-        ),
-        fmt_hsv(&state.hsv_colors, state.active_color_idx()),
-        state.export_dimensions.width,
-        state.export_dimensions.height,
+        "\n{}\n======= {} =======\n{}\n{}",
+        rule,
+        title,
+        rule,
+        state_lines(state).join("\n")
     )
 }
 
+/// The key reference, one key or group of keys per line.
+fn help_lines() -> Vec<String> {
+    tr(Message::KeyHelp, &[])
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
 impl JuliaInterface {
     pub fn new(
         context: &JuliaContext,
         init_state: Option<JuliaData>,
         init_export_dimensions: Option<ImgDimensions>,
+    ) -> Result<JuliaInterface, JuliaInterfaceError> {
+        JuliaInterface::with_window_options(
+            context,
+            init_state,
+            init_export_dimensions,
+            &WindowOptions::default(),
+        )
+    }
+
+    pub fn with_window_options(
+        context: &JuliaContext,
+        init_state: Option<JuliaData>,
+        init_export_dimensions: Option<ImgDimensions>,
+        window_options: &WindowOptions,
     ) -> Result<JuliaInterface, JuliaInterfaceError> {
         let events_loop = EventsLoop::new();
 
-        let monitor = events_loop.get_primary_monitor();
+        let monitor = match window_options.monitor {
+            Some(i) => events_loop
+                .get_available_monitors()
+                .nth(i)
+                .ok_or(NoSuchMonitor(i))?,
+            None => events_loop.get_primary_monitor(),
+        };
 
+        let hidpi_factor = monitor.get_hidpi_factor();
         let phys_size = monitor.get_dimensions();
-        let log_size = phys_size.to_logical(monitor.get_hidpi_factor());
-
-        let min_dim = f64::min(log_size.width, log_size.height);
-        let win_dim = min_dim * 0.8;
-
-        let phys_min_dim = f64::min(phys_size.width, phys_size.height);
-        let phys_win_dim = phys_min_dim * 0.8;
-
-        let win_size = LogicalSize::new(win_dim, win_dim);
+        let log_size = phys_size.to_logical(hidpi_factor);
+
+        let win_size = match window_options.size {
+            _ if window_options.fullscreen => log_size,
+            Some((width, height)) => LogicalSize::new(width.into(), height.into()),
+            None => {
+                let win_dim = f64::min(log_size.width, log_size.height) * 0.8;
+                LogicalSize::new(win_dim, win_dim)
+            }
+        };
+        let phys_win_size = win_size.to_physical(hidpi_factor);
 
         let surface = WindowBuilder::new()
             .with_dimensions(win_size)
             .with_resizable(false)
+            .with_always_on_top(window_options.always_on_top)
+            .with_fullscreen(if window_options.fullscreen {
+                Some(monitor.clone())
+            } else {
+                None
+            })
             .build_vk_surface(&events_loop, context.instance().clone())?;
 
+        if !window_options.fullscreen {
+            // Centered on the monitor
+            let monitor_pos = monitor.get_position().to_logical(hidpi_factor);
+            surface.window().set_position(LogicalPosition::new(
+                monitor_pos.x + (log_size.width - win_size.width) / 2.0,
+                monitor_pos.y + (log_size.height - win_size.height) / 2.0,
+            ));
+        }
+
+        if !surface
+            .is_supported(context.queue().family())
+            .unwrap_or(false)
+        {
+            let family = context.queue().family().id();
+            eprintln!("{}", tr(Message::PresentUnsupported, &[&family]));
+        }
+
         let caps = surface
             .capabilities(context.device().physical_device())
             .unwrap();
@@ -560,13 +1263,20 @@ impl JuliaInterface {
         let (format, _) = caps.supported_formats[0];
         eprintln!("Supported formats: {:?}", caps.supported_formats);
         eprintln!("Format: {:?}", format);
-        let dimensions = caps.current_extent.unwrap_or({
-            let w = phys_win_dim.round() as u32;
-            [w, w]
-        });
+        let dimensions = caps.current_extent.unwrap_or([
+            phys_win_size.width.round() as u32,
+            phys_win_size.height.round() as u32,
+        ]);
         let layers = 1;
+
+        // Blitting the compute output straight into the swapchain image avoids a render pass and
+        // sampler per frame. It takes a surface that allows transfers into its images and a device
+        // that blits between the two formats; otherwise the render pass draws the view.
+        let direct_present = caps.supported_usage_flags.transfer_destination
+            && can_blit_to(format, context.device().physical_device());
         let usage = ImageUsage {
             color_attachment: true,
+            transfer_destination: direct_present,
             ..ImageUsage::none()
         };
         let sharing = SharingMode::Exclusive(context.queue().family().id());
@@ -599,23 +1309,27 @@ impl JuliaInterface {
         let swapchain = Mutex::new(swapchain);
 
         let image = JuliaImage::new(context, dimensions.clone())?;
-        let render = JuliaRender::new(context, format, 1)?;
+        image.set_transfer(view_transfer(format));
+        let render = if direct_present {
+            None
+        } else {
+            Some(JuliaRender::new(context, format, 1)?)
+        };
 
         let data = init_state.unwrap_or_else(default_state);
-        let mut hsv_colors = [Hsv::new(0.0, 0.0, 0.0); 3];
-        hsv_colors
-            .iter_mut()
-            .zip(data.color.iter())
-            .for_each(|(hsv, vec)| {
-                let rgb = Srgb::new(vec.x, vec.y, vec.z);
-                *hsv = Hsv::from(rgb);
-            });
+        let mut hsv_colors = [Hsv::new(0.0, 0.0, 0.0); MAX_STOPS];
+        for (hsv, stop) in hsv_colors.iter_mut().zip(data.gradient.stops()) {
+            *hsv = to_hsv(stop.color);
+        }
 
         let export_dimensions = init_export_dimensions.unwrap_or_else(|| {
             let [width, height] = dimensions;
             ImgDimensions { width, height }
         });
         let export = JuliaExport::new();
+        let hud = JuliaHud::new(context)?;
+        let guides_drawer = JuliaGuides::new(context);
+        let strip = JuliaStrip::new(context);
 
         Ok(JuliaInterface {
             events_loop,
@@ -624,13 +1338,29 @@ impl JuliaInterface {
                 mouse_state: MouseState {
                     pos: LogicalPosition { x: 0.0, y: 0.0 },
                     dragging: false,
+                    dragging_param: false,
                 },
-                active_color: 0,
-                active_midpt: 1,
+                active_stop: 0,
                 hsv_colors,
                 close_requested: false,
                 export_dimensions,
                 export_requested: false,
+                potential_export_requested: false,
+                pending_exports: 0,
+                export_progress: Progress::default(),
+                capture_save_requested: false,
+                path_recording_toggled: false,
+                palette_save_requested: false,
+                palette_load_requested: false,
+                hud_visible: false,
+                hud_help: false,
+                hud_scale: 2,
+                help_requested: false,
+                guides: Guides::default(),
+                guides_visible: false,
+                strip_visible: false,
+                cycling: false,
+                cycle_speed: DEFAULT_CYCLE_SPEED,
             },
             surface,
             swapchain,
@@ -638,29 +1368,250 @@ impl JuliaInterface {
             image,
             render,
             export,
+            checkpoint_log: None,
+            history: None,
+            path_recorder: None,
+            hud,
+            guides_drawer,
+            strip,
+            print: None,
+            palette_path: PathBuf::from("palette.toml"),
+            name_template: None,
+            window_dimensions: ImgDimensions {
+                width: dimensions[0],
+                height: dimensions[1],
+            },
         })
     }
 
+    /// Shows or hides the heads-up display, which shows the state over the view. Hidden by
+    /// default; Tab toggles it while running.
+    pub fn set_hud_visible(&mut self, visible: bool) {
+        self.state.hud_visible = visible;
+    }
+
+    /// Sets the magnification of the heads-up display's text, from 1 to `hud::MAX_SCALE`. 2 by
+    /// default.
+    pub fn set_hud_scale(&mut self, scale: u32) {
+        self.state.hud_scale = scale.max(1).min(hud::MAX_SCALE);
+    }
+
+    /// Sets which composition guides are drawn and whether they are shown. Hidden by default; F3
+    /// toggles them while running.
+    pub fn set_guides(&mut self, guides: Guides, visible: bool) {
+        self.state.guides = guides;
+        self.state.guides_visible = visible;
+    }
+
+    /// Sets the print settings that exported PNGs record and whose margins the guides show.
+    pub fn set_print_settings(&mut self, print: Option<PrintSettings>) {
+        self.print = print;
+        self.export.set_print_settings(print);
+    }
+
+    /// Sets the bit depth of exported color images. Defaults to 8 bits.
+    pub fn set_bit_depth(&mut self, depth: BitDepth) {
+        self.export.set_bit_depth(depth);
+    }
+
+    /// Sets the color space of exported color images. Defaults to sRGB; the view itself is always
+    /// shown in sRGB.
+    pub fn set_color_space(&mut self, space: ColorSpace) {
+        self.export.set_color_space(space);
+    }
+
+    /// Sets the transfer functions exported colors are encoded with. The view is always encoded
+    /// to suit the window.
+    pub fn set_transfer(&mut self, stages: TransferStages) {
+        self.export.set_transfer(stages);
+    }
+
+    /// Sets the format of exported color images, which are given its extension. PNG by default.
+    /// Potential exports are always PNGs.
+    pub fn set_format(&mut self, format: OutputFormat) {
+        self.export.set_format(Some(format));
+    }
+
+    /// Sets the quality of JPEG exports, from 1 to 100. Defaults to 90.
+    pub fn set_jpeg_quality(&mut self, quality: u8) {
+        self.export.set_jpeg_quality(quality);
+    }
+
+    /// Sets whether every export is followed by a sidecar file holding its parameters.
+    pub fn set_sidecar(&mut self, sidecar: bool) {
+        self.export.set_sidecar(sidecar);
+    }
+
+    /// Sets what happens when an export's file already exists. Defaults to replacing it.
+    pub fn set_overwrite_policy(&mut self, policy: OverwritePolicy) {
+        self.export.set_overwrite_policy(policy);
+    }
+
+    #[cfg(feature = "webp")]
+    pub fn set_webp_options(&mut self, options: WebpOptions) {
+        self.export.set_webp_options(options);
+    }
+
+    /// Sets the history file that the state is periodically saved to while running, or disables
+    /// checkpoints. They are disabled by default.
+    pub fn set_checkpoint_log(&mut self, log: Option<CheckpointLog>) {
+        self.checkpoint_log = log;
+    }
+
+    /// Sets the palette file that Ctrl+S saves the gradient to and Ctrl+O loads it from, as JSON if
+    /// its name ends in `.json` and as TOML otherwise. Defaults to `palette.toml` in the working
+    /// directory.
+    pub fn set_palette_path(&mut self, path: PathBuf) {
+        self.palette_path = path;
+    }
+
+    /// Sets the template that exported images are named by, or names them from all of their
+    /// parameters, as by default.
+    pub fn set_name_template(&mut self, template: Option<NameTemplate>) {
+        self.name_template = template;
+    }
+
+    /// Sets the history that the parameters of recent frames are recorded into while running, so
+    /// that X can render and save them, or disables it. It is disabled by default.
+    pub fn set_view_history(&mut self, history: Option<ViewHistory>) {
+        self.history = history;
+    }
+
+    /// Appends the current state to the checkpoint log, if there is one. Failures are reported but
+    /// don't interrupt the interface.
+    fn checkpoint(&self) {
+        if let Some(log) = &self.checkpoint_log {
+            if let Err(e) = log.append(&self.state.data, self.state.export_dimensions) {
+                eprintln!(
+                    "{}",
+                    tr(Message::CheckpointWriteFailed, &[&log.path().display(), &e])
+                );
+            }
+        }
+    }
+
     fn new_frame(&self, context: &JuliaContext) -> Result<impl GpuFuture, JuliaInterfaceError> {
-        let compute_future = self.image.draw(self.state.data, context)?;
+        let data = self.state.data.fit_to(self.window_dimensions);
+        let compute_future = self.image.draw(data, context)?;
+
+        let compute_future: Box<dyn GpuFuture> = if self.state.hud_visible {
+            let lines = if self.state.hud_help {
+                help_lines()
+            } else {
+                state_lines(&self.state)
+            };
+
+            Box::new(self.hud.draw_after(
+                compute_future,
+                self.image.image().clone(),
+                &lines,
+                self.state.hud_scale,
+                context,
+            )?)
+        } else {
+            Box::new(compute_future)
+        };
+
+        let compute_future: Box<dyn GpuFuture> = match self.guide_layout() {
+            Some(layout) => Box::new(self.guides_drawer.draw_after(
+                compute_future,
+                self.image.image().clone(),
+                &layout,
+                context,
+            )?),
+            None => compute_future,
+        };
+
+        let compute_future: Box<dyn GpuFuture> = if self.state.strip_visible {
+            Box::new(self.strip.draw_after(
+                compute_future,
+                &self.image,
+                &data,
+                self.state.active_stop_idx(),
+                self.state.hud_scale,
+                context,
+            )?)
+        } else {
+            compute_future
+        };
 
         let (idx, acquire_future) =
             swapchain::acquire_next_image(self.swapchain.lock().unwrap().clone(), None)?;
         let swapchain_image = self.swapchain_images[idx].clone();
 
-        Ok(self
-            .render
-            .draw_after(
+        let draw_future: Box<dyn GpuFuture> = match &self.render {
+            Some(render) => Box::new(render.draw_after(
                 compute_future.join(acquire_future),
                 self.image.image().clone(),
                 swapchain_image,
                 context,
-            )?
-            .then_swapchain_present(
-                context.queue().clone(),
-                self.swapchain.lock().unwrap().clone(),
-                idx,
-            ))
+            )?),
+            None => Box::new(self.image.blit_after(
+                compute_future.join(acquire_future),
+                swapchain_image,
+                context,
+            )?),
+        };
+
+        Ok(draw_future.then_swapchain_present(
+            context.queue().clone(),
+            self.swapchain.lock().unwrap().clone(),
+            idx,
+        ))
+    }
+
+    /// Where the guides go in the window, or `None` if there are none to draw. The export covers
+    /// the same part of the plane as the window along its longer axis, so its frame is centered in
+    /// the window and scaled by the ratio of their extents.
+    fn guide_layout(&self) -> Option<GuideLayout> {
+        let guides = self.state.guides;
+        if !self.state.guides_visible {
+            return None;
+        }
+
+        let window = self.window_dimensions;
+        let export = self.state.export_dimensions;
+        let window_size = vec2!(window.width as f32, window.height as f32);
+        let export_size = vec2!(export.width as f32, export.height as f32);
+
+        let window_extents = self.state.data.fit_to(window).extents;
+        let export_extents = self.state.data.fit_to(export).extents;
+        let size = export_extents / window_extents * window_size;
+        let origin = 0.5 * (window_size - size);
+
+        let mut flags = 0;
+        if export.width * window.height != window.width * export.height {
+            flags |= guides::GUIDE_FRAME;
+        }
+        if guides.thirds {
+            flags |= guides::GUIDE_THIRDS;
+        }
+        if guides.center {
+            flags |= guides::GUIDE_CENTER;
+        }
+
+        let px_scale = size.x / export_size.x;
+        let (bleed, safe) = match &self.print {
+            Some(print) if guides.margins => {
+                flags |= guides::GUIDE_MARGINS;
+                let bleed = px_scale * print.mm_to_pixels(print.bleed_mm);
+                let safe = px_scale * print.mm_to_pixels(print.safe_mm);
+                (vec2!(bleed, bleed), vec2!(safe, safe))
+            }
+            _ => (Vec2::zeros(), Vec2::zeros()),
+        };
+
+        if flags == 0 {
+            return None;
+        }
+
+        Some(GuideLayout {
+            origin,
+            size,
+            bleed,
+            safe,
+            flags,
+        })
     }
 
     fn update(&mut self, context: &JuliaContext) -> Result<(), JuliaInterfaceError> {
@@ -670,6 +1621,14 @@ impl JuliaInterface {
             .poll_events(event_callback(&mut new_state, window_dims));
         self.state = new_state;
 
+        // Recorded after cycling has advanced, so the history has this frame's color phase
+        if let Some(history) = &mut self.history {
+            history.record(&self.state.data, Instant::now());
+        }
+        if let Some(recorder) = &mut self.path_recorder {
+            recorder.record(self.state.data.c, Instant::now());
+        }
+
         let mut finished = self
             .new_frame(context)?
             .then_signal_fence_and_flush()
@@ -681,53 +1640,217 @@ impl JuliaInterface {
         Ok(())
     }
 
+    /// The name of an export of the current view, with `suffix` after the name the template gives,
+    /// or after the generated name and the dimensions if there is no template, and numbered if
+    /// the overwrite policy asks for it.
+    fn export_name(&self, suffix: &str, extension: &str) -> PathBuf {
+        let dims = self.state.export_dimensions;
+        let name = match &self.name_template {
+            Some(template) => {
+                let data = self.state.data.fit_to(dims);
+                template.render(&data, dims, SystemTime::now())
+            }
+            None => format!("{}_{}x{}", self.state.data.name(), dims.width, dims.height),
+        };
+
+        self.export
+            .target(&PathBuf::from(format!("{}{}.{}", name, suffix, extension)))
+    }
+
+    /// Starts exporting the current view in the background. `finish_exports` reports it once it
+    /// is written. Failures to start are reported but don't interrupt the interface.
     pub fn export(&mut self, context: &JuliaContext) {
-        let ImgDimensions { width, height } = self.state.export_dimensions;
+        let job = ExportJob {
+            dims: self.state.export_dimensions,
+            data: self.state.data.fit_to(self.state.export_dimensions),
+            filename: self.export_name("", self.export.extension()),
+            potential: false,
+        };
 
-        let filename = PathBuf::from(format!(
-            "{}_{}x{}.png",
-            self.state.data.name(),
-            width,
-            height,
-        ));
+        let filename = job.filename.clone();
+        match self.export.export_in_background(job, context) {
+            Ok(filename) => println!("{}", tr(Message::Exporting, &[&filename.display()])),
+            Err(e) => println!(
+                "{}",
+                tr(Message::PathWriteFailed, &[&filename.display(), &e])
+            ),
+        }
+    }
 
-        let mut export_data = self.state.data;
-        if width != height {
-            if width < height {
-                let ratio = height as f32 / width as f32;
-                export_data.extents.y *= ratio;
-            } else {
-                let ratio = width as f32 / height as f32;
-                export_data.extents.x *= ratio;
+    pub fn export_potential(&mut self, context: &JuliaContext) {
+        if self.state.data.kind == FractalKind::Newton {
+            println!("{}", tr(Message::NewtonNoPotential, &[]));
+            return;
+        }
+
+        let job = ExportJob {
+            dims: self.state.export_dimensions,
+            data: self.state.data.fit_to(self.state.export_dimensions),
+            filename: self.export_name("_potential", "png"),
+            potential: true,
+        };
+
+        let filename = job.filename.clone();
+        match self.export.export_in_background(job, context) {
+            Ok(filename) => println!(
+                "{}",
+                tr(Message::ExportingPotential, &[&filename.display()])
+            ),
+            Err(e) => println!(
+                "{}",
+                tr(Message::PathWriteFailed, &[&filename.display(), &e])
+            ),
+        }
+    }
+
+    /// Reports the background exports written since the last call, or waits for all of them to
+    /// be written if `wait` is set.
+    fn finish_exports(&mut self, wait: bool) {
+        let finished = if wait {
+            self.export.wait_for_exports()
+        } else {
+            self.export.finished_exports()
+        };
+
+        for (filename, result) in finished {
+            match result {
+                Ok(()) => println!("{}", tr(Message::Exported, &[&filename.display()])),
+                Err(e) => println!(
+                    "{}",
+                    tr(Message::PathWriteFailed, &[&filename.display(), &e])
+                ),
             }
         }
+        self.state.pending_exports = self.export.pending_exports();
+        self.state.export_progress = self.export.export_progress().unwrap_or_default();
+    }
 
-        print!("Exporting to {} ...", filename.to_str().unwrap());
-        io::stdout().flush().unwrap();
-        self.export.export(
-            self.state.export_dimensions,
-            &export_data,
-            &filename,
-            context,
+    /// Renders the frames in the view history as a numbered sequence of PNGs in a new directory
+    /// named after the current state.
+    pub fn save_capture(&mut self, context: &JuliaContext) {
+        let history = match &self.history {
+            Some(history) if !history.is_empty() => history,
+            _ => {
+                println!("{}", tr(Message::NoCapturedFrames, &[]));
+                return;
+            }
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let dir = PathBuf::from(format!("{}_capture_{}", self.state.data.name(), timestamp));
+        if let Err(e) = fs::create_dir_all(&dir) {
+            println!("{}", tr(Message::CaptureDirFailed, &[&dir.display(), &e]));
+            return;
+        }
+
+        print!(
+            "{}",
+            tr(Message::SavingCapture, &[&history.len(), &dir.display()])
         );
-        println!(" Done!");
+        io::stdout().flush().unwrap();
+        let jobs = history.jobs(self.window_dimensions, &dir);
+        match self.export.export_batch(jobs, 2, context) {
+            Ok(()) => println!("{}", tr(Message::Done, &[])),
+            Err(e) => println!("\n{}", tr(Message::ExportFailed, &[&e])),
+        }
 
         // Ignore any window events that came in during export
         self.events_loop.poll_events(|_| ());
     }
 
+    /// Saves the gradient and its interpolation to the palette file. Failures are reported but
+    /// don't interrupt the interface.
+    fn save_palette(&self) {
+        let path = self.palette_path.display();
+        let palette = Palette {
+            gradient: self.state.data.gradient,
+            interpolation: Some(self.state.data.interpolation),
+        };
+        match palette.save(&self.palette_path) {
+            Ok(()) => println!("{}", tr(Message::PaletteSaved, &[&path])),
+            Err(e) => println!("{}", tr(Message::PathWriteFailed, &[&path, &e])),
+        }
+    }
+
+    /// Replaces the gradient with the one in the palette file, if it can be read, along with its
+    /// interpolation if it gives one.
+    fn load_palette(&mut self) {
+        let path = self.palette_path.display();
+        match Palette::load(&self.palette_path) {
+            Ok(palette) => {
+                self.state.set_gradient(palette.gradient);
+                if let Some(interpolation) = palette.interpolation {
+                    self.state.data.interpolation = interpolation;
+                }
+                println!("{}", tr(Message::PaletteLoaded, &[&path]));
+            }
+            Err(e) => println!("{}", tr(Message::ReadFailed, &[&path, &e])),
+        }
+    }
+
+    /// Starts recording the path of `c`, or stops and saves the recorded path to a file named
+    /// after the current state, for rendering with `julia path`.
+    pub fn toggle_path_recording(&mut self) {
+        let recorder = match self.path_recorder.take() {
+            Some(recorder) => recorder,
+            None => {
+                self.path_recorder = Some(PathRecorder::new(Instant::now()));
+                println!("{}", tr(Message::RecordingPath, &[]));
+                return;
+            }
+        };
+
+        let path = recorder.finish();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let filename = PathBuf::from(format!("{}_path_{}.txt", self.state.data.name(), timestamp));
+
+        match path.write(&filename) {
+            Ok(()) => println!(
+                "{}",
+                tr(
+                    Message::PathSaved,
+                    &[&path.points().len(), &filename.display()]
+                )
+            ),
+            Err(e) => println!(
+                "{}",
+                tr(Message::PathWriteFailed, &[&filename.display(), &e])
+            ),
+        }
+    }
+
     pub fn run(&mut self, context: &JuliaContext) -> Result<(), JuliaInterfaceError> {
         let mut presented_state = self.state;
         let mut presented_time = Instant::now();
         print_state(&presented_state, &mut io::stdout()).unwrap();
 
+        let mut checkpoint_state = self.state;
+        let mut checkpoint_time = Instant::now();
+
+        let mut frame_time = Instant::now();
+
         while !self.state.close_requested() {
+            self.state.advance_cycle(frame_time.elapsed().as_secs_f32());
+            frame_time = Instant::now();
+
             self.update(context)?;
+            self.finish_exports(false);
+
+            // Color cycling alone doesn't reprint the state, which it would every time otherwise
+            let uncycled = JuliaData {
+                color_phase: presented_state.data.color_phase,
+                ..self.state.data
+            };
 
             if presented_time.elapsed().as_secs_f64() > 0.25
-                && (self.state.data != presented_state.data
-                    || self.state.active_color != presented_state.active_color
-                    || self.state.active_midpt != presented_state.active_midpt
+                && (uncycled != presented_state.data
+                    || self.state.active_stop != presented_state.active_stop
                     || self.state.hsv_colors != presented_state.hsv_colors
                     || self.state.export_dimensions != presented_state.export_dimensions)
             {
@@ -740,8 +1863,67 @@ impl JuliaInterface {
                 self.export(context);
                 self.state.export_requested = false;
             }
+
+            if self.state.potential_export_requested {
+                self.export_potential(context);
+                self.state.potential_export_requested = false;
+            }
+
+            if self.state.path_recording_toggled {
+                self.toggle_path_recording();
+                self.state.path_recording_toggled = false;
+            }
+
+            if self.state.capture_save_requested {
+                self.save_capture(context);
+                self.state.capture_save_requested = false;
+            }
+
+            if self.state.palette_save_requested {
+                self.save_palette();
+                self.state.palette_save_requested = false;
+            }
+
+            if self.state.palette_load_requested {
+                self.load_palette();
+                self.state.palette_load_requested = false;
+            }
+
+            if self.state.help_requested {
+                println!("\n{}", help_lines().join("\n"));
+                self.state.help_requested = false;
+            }
+
+            let checkpoint_due = match &self.checkpoint_log {
+                Some(log) => checkpoint_time.elapsed() >= log.interval(),
+                None => false,
+            };
+
+            if checkpoint_due {
+                if self.state.data != checkpoint_state.data
+                    || self.state.export_dimensions != checkpoint_state.export_dimensions
+                {
+                    self.checkpoint();
+                    checkpoint_state = self.state;
+                }
+                checkpoint_time = Instant::now();
+            }
         }
 
+        if self.state.data != checkpoint_state.data
+            || self.state.export_dimensions != checkpoint_state.export_dimensions
+        {
+            self.checkpoint();
+        }
+
+        if self.state.pending_exports > 0 {
+            println!(
+                "{}",
+                tr(Message::WaitingForExports, &[&self.state.pending_exports])
+            );
+        }
+        self.finish_exports(true);
+
         Ok(())
     }
 }
@@ -753,5 +1935,29 @@ impl_error! {
         VkWinCreationErr(vulkano_win::CreationError),
         VkSwapchainCreationErr(SwapchainCreationError),
         VkSwapchainAcquireErr(AcquireError),
+        MonitorErr(NoSuchMonitor),
+        JuliaHudErr(JuliaHudError),
+        JuliaGuidesErr(JuliaGuidesError),
+        JuliaStripErr(JuliaStripError),
     }
 }
+
+/// A monitor index that is out of range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NoSuchMonitor(pub usize);
+
+impl Display for NoSuchMonitor {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "no monitor number {}", self.0)
+    }
+}
+
+impl Error for NoSuchMonitor {}
+
+/// The names of the available monitors, in the order that `WindowOptions::monitor` indexes them.
+pub fn monitor_names() -> Vec<String> {
+    EventsLoop::new()
+        .get_available_monitors()
+        .map(|m| m.get_name().unwrap_or_default())
+        .collect()
+}
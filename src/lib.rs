@@ -4,18 +4,19 @@ use vulkano::instance::{
     Instance, InstanceCreationError, InstanceExtensions, PhysicalDevice, PhysicalDeviceType,
     QueueFamily,
 };
-use vulkano::pipeline::{ComputePipeline, ComputePipelineCreationError};
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract, ComputePipelineCreationError};
 use vulkano::OomError;
 
-use palette::Srgba;
-
 #[macro_use]
 extern crate gramit;
-use gramit::{Vec2, Vec4};
+use gramit::{Vec2, Vec3, Vec4};
 
+use std::cell::{Ref, RefCell};
 use std::fmt::{self, Debug, Display, Formatter};
+use std::io::{self, Write};
 use std::iter;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
 
 macro_rules! impl_error {
@@ -52,96 +53,983 @@ macro_rules! impl_error {
     };
 }
 
+mod atomic;
+mod buddhabrot;
+mod equalize;
 mod export;
+mod formula;
+mod guides;
+mod hud;
+mod icc;
 mod image;
+mod pcg;
 mod render;
 mod shaders;
+mod sidecar;
+mod strip;
+mod trap;
 
+pub mod animated;
+pub mod animation;
+pub mod checkpoint;
+pub mod colormap;
+pub mod contact_sheet;
+pub mod contour;
+pub mod dzi;
+pub mod embedded;
+pub mod escape_time;
+pub mod farm;
+pub mod ggr;
+pub mod gradient;
+pub mod heatmap;
+pub mod history;
+pub mod hybrid;
+pub mod iim;
 pub mod interface;
+pub mod locale;
+pub mod mesh;
+pub mod name_template;
+pub mod orbit;
+pub mod palette_expr;
+pub mod palette_file;
+pub mod path;
+pub mod poly;
+pub mod print;
+pub mod random_palette;
+pub mod spec;
+pub mod thumbnail;
+pub mod tiled;
+pub mod variations;
+pub mod video;
+pub mod viewfile;
 
+use equalize::{Equalization, EqualizationCache};
 use export::JuliaExport;
-use shaders::julia_comp;
+use shaders::{
+    julia_buddhabrot_comp, julia_buddhabrot_tonemap_comp, julia_comp, julia_cost_comp,
+    julia_escape_comp, julia_guides_comp, julia_hud_comp, julia_iim_comp, julia_iterate_comp,
+    julia_iterate_strict_comp, julia_nebulabrot_tonemap_comp, julia_newton_comp,
+    julia_newton_strict_comp, julia_potential_comp, julia_strict_comp, julia_strip_comp,
+    julia_tonemap_comp, julia_tonemap_wide_comp,
+};
+use thumbnail::PreviewCache;
+use trap::TrapTexture;
+
+pub use atomic::write_atomic;
+pub use buddhabrot::BuddhabrotOptions;
+pub use escape_time::{EscapeValue, ParseEscapeValueError};
+pub use export::{
+    BitDepth, ColorSpace, ExportJob, FinishedExport, ImageTooLargeError, ImgDimensions,
+    JuliaExportError, OutputFormat, Overlay, OverlayPosition, OverwritePolicy,
+    ParseColorSpaceError, ParseOutputFormatError, ParseOverlayPositionError, ParseTransferError,
+    PixelRect, Progress, RegionError, Transfer, TransferStages, UnsupportedError,
+};
+pub use gradient::{Gradient, GradientStop, Interpolation};
+pub use heatmap::CostReport;
+pub use hybrid::Hybrid;
+pub use icc::IccProfile;
+pub use mesh::MeshOptions;
+pub use poly::Polynomial;
+pub use print::{PaperSize, PrintSettings};
+pub use trap::{OrbitTrap, OrbitTrapError};
 
-pub use export::ImgDimensions;
+#[cfg(feature = "avif")]
+pub use export::AvifOptions;
+#[cfg(feature = "webp")]
+pub use export::WebpOptions;
 
 type CompDesc = PipelineLayout<julia_comp::Layout>;
+type StrictDesc = PipelineLayout<julia_strict_comp::Layout>;
+type IterateDesc = PipelineLayout<julia_iterate_comp::Layout>;
+type IterateStrictDesc = PipelineLayout<julia_iterate_strict_comp::Layout>;
+type NewtonDesc = PipelineLayout<julia_newton_comp::Layout>;
+type NewtonStrictDesc = PipelineLayout<julia_newton_strict_comp::Layout>;
+type TonemapDesc = PipelineLayout<julia_tonemap_comp::Layout>;
+type TonemapWideDesc = PipelineLayout<julia_tonemap_wide_comp::Layout>;
+type PotentialDesc = PipelineLayout<julia_potential_comp::Layout>;
+type CostDesc = PipelineLayout<julia_cost_comp::Layout>;
+type EscapeDesc = PipelineLayout<julia_escape_comp::Layout>;
+type BuddhabrotDesc = PipelineLayout<julia_buddhabrot_comp::Layout>;
+type BuddhabrotTonemapDesc = PipelineLayout<julia_buddhabrot_tonemap_comp::Layout>;
+type NebulabrotTonemapDesc = PipelineLayout<julia_nebulabrot_tonemap_comp::Layout>;
+type HudDesc = PipelineLayout<julia_hud_comp::Layout>;
+type GuidesDesc = PipelineLayout<julia_guides_comp::Layout>;
+type StripDesc = PipelineLayout<julia_strip_comp::Layout>;
+type IimDesc = PipelineLayout<julia_iim_comp::Layout>;
+
+/// Which set is rendered for the polynomial `x^n + c`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FractalKind {
+    /// The Julia set of a fixed `c`; each pixel is a starting point.
+    Julia,
+    /// The Mandelbrot set; each pixel is a value of `c`, iterated from 0.
+    Mandelbrot,
+    /// The Burning Ship, which iterates `(|Re z| + i|Im z|)^n + c` over the same plane as the
+    /// Mandelbrot set.
+    BurningShip,
+    /// The Tricorn (or Mandelbar), which iterates `conj(z)^n + c` over the same plane as the
+    /// Mandelbrot set.
+    Tricorn,
+    /// Newton's method for `z^n - 1`, with each pixel a starting point. Pixels are colored by the
+    /// root their orbit converges to. Rendered by its own shader.
+    Newton,
+}
+
+impl FractalKind {
+    /// Whether each pixel is a value of `c`, as for the Mandelbrot set, rather than the starting
+    /// point of its orbit, unless `JuliaData::plane` says otherwise.
+    pub fn is_parameter_plane(self) -> bool {
+        match self {
+            FractalKind::Julia | FractalKind::Newton => false,
+            _ => true,
+        }
+    }
+
+    fn shader_id(self) -> u32 {
+        match self {
+            FractalKind::Julia => 0,
+            FractalKind::Mandelbrot => 1,
+            FractalKind::BurningShip => 2,
+            FractalKind::Tricorn => 3,
+            FractalKind::Newton => 4,
+        }
+    }
+}
+
+/// Formats the kind as its command-line name, e.g. `burning-ship`.
+impl Display for FractalKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            FractalKind::Julia => "julia",
+            FractalKind::Mandelbrot => "mandelbrot",
+            FractalKind::BurningShip => "burning-ship",
+            FractalKind::Tricorn => "tricorn",
+            FractalKind::Newton => "newton",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for FractalKind {
+    type Err = ParseFractalKindError;
+
+    fn from_str(s: &str) -> Result<FractalKind, ParseFractalKindError> {
+        match s {
+            "julia" => Ok(FractalKind::Julia),
+            "mandelbrot" => Ok(FractalKind::Mandelbrot),
+            "burning-ship" => Ok(FractalKind::BurningShip),
+            "tricorn" | "mandelbar" => Ok(FractalKind::Tricorn),
+            "newton" => Ok(FractalKind::Newton),
+            _ => Err(ParseFractalKindError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseFractalKindError;
+
+impl Display for ParseFractalKindError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown fractal, expected julia, mandelbrot, burning-ship, tricorn or newton"
+        )
+    }
+}
+
+impl std::error::Error for ParseFractalKindError {}
+
+/// Which part of the formula the pixels of the view stand for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Plane {
+    /// Each pixel is the starting point of its orbit, iterated with the fixed `c`, as for Julia
+    /// sets.
+    Dynamical,
+    /// Each pixel is a value of `c`, iterated from the fixed `z0`, as for the Mandelbrot set.
+    Parameter,
+}
+
+impl Plane {
+    fn shader_id(self) -> u32 {
+        match self {
+            Plane::Dynamical => 0,
+            Plane::Parameter => 1,
+        }
+    }
+}
+
+/// Formats the plane as its command-line name, `z` or `c`.
+impl Display for Plane {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Plane::Dynamical => "z",
+            Plane::Parameter => "c",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Plane {
+    type Err = ParsePlaneError;
+
+    fn from_str(s: &str) -> Result<Plane, ParsePlaneError> {
+        match s {
+            "z" | "dynamical" => Ok(Plane::Dynamical),
+            "c" | "parameter" => Ok(Plane::Parameter),
+            _ => Err(ParsePlaneError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParsePlaneError;
+
+impl Display for ParsePlaneError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "unknown plane, expected z or c")
+    }
+}
+
+impl std::error::Error for ParsePlaneError {}
+
+/// The stripe density used unless another is given.
+pub const DEFAULT_STRIPE_DENSITY: f32 = 5.0;
+
+/// The number of iterations a repeating gradient spans when it's turned on without another period.
+pub const DEFAULT_COLOR_PERIOD: f32 = 64.0;
+
+/// The exponent `n` must be greater than this; the escape radius and the fixed point search assume
+/// an expanding map.
+pub const MIN_EXPONENT: f32 = 1.0;
+
+/// What escaping points are colored by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColoringMode {
+    /// The smooth iteration count, scaled by `color_scale` and shifted by `color_offset`.
+    Iterations,
+    /// The estimated distance to the set, from the derivative of the orbit. Points near the
+    /// boundary take the end of the gradient, falling off to its start over `color_scale` pixels,
+    /// so thin filaments stay visible however many iterations are needed to reach them.
+    DistanceEstimate,
+    /// The stripe average: the mean over the orbit of `(1 + sin(d arg z)) / 2`, with `d` the
+    /// stripe density. It lies between 0 and 1 and is scaled and shifted into the gradient like
+    /// the iteration count, drawing smooth stripes that follow the shape of the set.
+    Stripes,
+    /// Binary decomposition: the smooth iteration count, with the gradient mirrored for points
+    /// whose last iterate lies below the real axis. The cells this splits the exterior into meet
+    /// along the external field lines, which show as stripes running out from the set.
+    BinaryDecomposition,
+    /// Red, green and blue each from a different measure of the escape instead of from the
+    /// gradient: the smooth iteration count as a fraction of the limit, the fractional escape
+    /// radius `log log |z|`, and the angle of the last iterate as a fraction of a turn. Each is
+    /// scaled and shifted by its channel's parameters in `channels`.
+    Channels,
+    /// The curvature average: the mean over the orbit of the angle it turns through at each
+    /// iterate, as a fraction of a half turn. Like the stripe average it lies between 0 and 1 and
+    /// is scaled and shifted into the gradient, drawing fine, jagged textures along the orbits'
+    /// turns that the iteration count can't show.
+    Curvature,
+}
+
+impl ColoringMode {
+    fn shader_id(self) -> u32 {
+        match self {
+            ColoringMode::Iterations => 0,
+            ColoringMode::DistanceEstimate => 1,
+            ColoringMode::Stripes => 2,
+            ColoringMode::BinaryDecomposition => 3,
+            ColoringMode::Channels => 4,
+            ColoringMode::Curvature => 5,
+        }
+    }
+}
+
+impl Default for ColoringMode {
+    fn default() -> ColoringMode {
+        ColoringMode::Iterations
+    }
+}
+
+/// Formats the mode as its command-line name, `iterations`, `distance`, `stripes`, `binary`,
+/// `channels` or `curvature`.
+impl Display for ColoringMode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            ColoringMode::Iterations => "iterations",
+            ColoringMode::DistanceEstimate => "distance",
+            ColoringMode::Stripes => "stripes",
+            ColoringMode::BinaryDecomposition => "binary",
+            ColoringMode::Channels => "channels",
+            ColoringMode::Curvature => "curvature",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ColoringMode {
+    type Err = ParseColoringModeError;
+
+    fn from_str(s: &str) -> Result<ColoringMode, ParseColoringModeError> {
+        match s {
+            "iterations" => Ok(ColoringMode::Iterations),
+            "distance" | "de" => Ok(ColoringMode::DistanceEstimate),
+            "stripes" | "stripe" => Ok(ColoringMode::Stripes),
+            "binary" | "decomposition" => Ok(ColoringMode::BinaryDecomposition),
+            "channels" | "rgb" => Ok(ColoringMode::Channels),
+            "curvature" => Ok(ColoringMode::Curvature),
+            _ => Err(ParseColoringModeError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseColoringModeError;
+
+impl Display for ParseColoringModeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown coloring mode, expected iterations, distance, stripes, binary, channels or \
+             curvature"
+        )
+    }
+}
+
+impl std::error::Error for ParseColoringModeError {}
+
+/// The mapping of the red, green and blue values of `ColoringMode::Channels` into colors. Each
+/// value, between 0 and 1, is multiplied by its channel's scale and has its offset added, and the
+/// result is clamped between 0 and 1 and taken as non-linear sRGB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Channels {
+    pub scale: Vec3,
+    pub offset: Vec3,
+}
+
+impl Channels {
+    /// Whether the values are taken as they are.
+    pub fn is_identity(&self) -> bool {
+        *self == Channels::default()
+    }
+}
+
+impl Default for Channels {
+    fn default() -> Channels {
+        Channels {
+            scale: vec3!(1.0, 1.0, 1.0),
+            offset: vec3!(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+/// The light for slope shading, which lights the image as if it were a height field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    /// The direction the light comes from in the image plane, in degrees counterclockwise from
+    /// the positive real axis.
+    pub azimuth: f32,
+    /// The angle of the light above the image plane, in degrees.
+    pub elevation: f32,
+    /// What the heights are taken from.
+    pub relief: Relief,
+}
+
+impl Default for Light {
+    fn default() -> Light {
+        Light {
+            azimuth: 45.0,
+            elevation: 45.0,
+            relief: Relief::default(),
+        }
+    }
+}
+
+/// The height field lit by slope shading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Relief {
+    /// The value being colored, which for the iteration count gives terraces that climb towards
+    /// the set.
+    Iterations,
+    /// The distance estimate, with the set as a ridge. Distance falls off at the same rate all
+    /// around the set, so its gradient gives the evenly embossed, normal-mapped look of the
+    /// boundary whatever the coloring.
+    Distance,
+}
+
+impl Relief {
+    fn shader_id(self) -> u32 {
+        match self {
+            Relief::Iterations => 1,
+            Relief::Distance => 2,
+        }
+    }
+}
+
+impl Default for Relief {
+    fn default() -> Relief {
+        Relief::Iterations
+    }
+}
+
+/// Formats the relief as its command-line name, `iterations` or `distance`.
+impl Display for Relief {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Relief::Iterations => "iterations",
+            Relief::Distance => "distance",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Relief {
+    type Err = ParseReliefError;
+
+    fn from_str(s: &str) -> Result<Relief, ParseReliefError> {
+        match s {
+            "iterations" => Ok(Relief::Iterations),
+            "distance" | "de" => Ok(Relief::Distance),
+            _ => Err(ParseReliefError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseReliefError;
+
+impl Display for ParseReliefError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "unknown relief, expected iterations or distance")
+    }
+}
+
+impl std::error::Error for ParseReliefError {}
+
+/// Adjustments to the brightness of the image, applied to its linear colors after they're taken
+/// from the gradient and shaded. Coloring modes that squeeze or stretch the range of colors, like
+/// equalization, often need them to bring the result back to a pleasing tonal range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    /// Brightening in stops: each one doubles the linear intensity.
+    pub exposure: f32,
+    /// The output is the input raised to the reciprocal of this, so values above 1 lift the
+    /// midtones and values below 1 deepen them.
+    pub gamma: f32,
+    /// Spreads intensities away from middle gray, in proportion to their logarithm, for values
+    /// above 1, and draws them towards it for values below.
+    pub contrast: f32,
+    /// How the adjusted intensities are brought into the displayable range.
+    pub curve: ToneCurve,
+}
+
+impl Tone {
+    /// Whether the adjustments leave colors as they are.
+    pub fn is_identity(&self) -> bool {
+        *self == Tone::default()
+    }
+}
+
+impl Default for Tone {
+    fn default() -> Tone {
+        Tone {
+            exposure: 0.0,
+            gamma: 1.0,
+            contrast: 1.0,
+            curve: ToneCurve::default(),
+        }
+    }
+}
+
+/// The curve mapping linear intensities, which may exceed 1 after shading or raising the
+/// exposure, into the displayable range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToneCurve {
+    /// Intensities above 1 are clipped, which leaves the rest of the image exactly as rendered.
+    Clip,
+    /// Reinhard's curve `c / (1 + c)`, which never clips but dims the whole image; raise the
+    /// exposure to make up for it.
+    Reinhard,
+    /// A fit of the ACES filmic curve, which leaves the midtones much as they are and rolls
+    /// highlights off smoothly into white.
+    Aces,
+}
+
+impl ToneCurve {
+    fn shader_id(self) -> u32 {
+        match self {
+            ToneCurve::Clip => 0,
+            ToneCurve::Reinhard => 1,
+            ToneCurve::Aces => 2,
+        }
+    }
+}
+
+impl Default for ToneCurve {
+    fn default() -> ToneCurve {
+        ToneCurve::Clip
+    }
+}
+
+/// Formats the curve as its command-line name, `clip`, `reinhard` or `aces`.
+impl Display for ToneCurve {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            ToneCurve::Clip => "clip",
+            ToneCurve::Reinhard => "reinhard",
+            ToneCurve::Aces => "aces",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ToneCurve {
+    type Err = ParseToneCurveError;
+
+    fn from_str(s: &str) -> Result<ToneCurve, ParseToneCurveError> {
+        match s {
+            "clip" => Ok(ToneCurve::Clip),
+            "reinhard" => Ok(ToneCurve::Reinhard),
+            "aces" | "filmic" => Ok(ToneCurve::Aces),
+            _ => Err(ParseToneCurveError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseToneCurveError;
+
+impl Display for ParseToneCurveError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "unknown tone curve, expected clip, reinhard or aces")
+    }
+}
+
+impl std::error::Error for ParseToneCurveError {}
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct JuliaData {
-    pub color: [Vec4; 3],
-    pub color_midpoint: [f32; 3],
+    /// The colors that escaping points are mapped into.
+    pub gradient: Gradient,
+    /// Added to the normalized iteration count of escaping points before it is mapped into the
+    /// gradient, after scaling by `color_scale`.
+    pub color_offset: f32,
+    pub color_scale: f32,
 
-    pub n: u32,
+    pub kind: FractalKind,
+    /// The exponent of `x^n`. Whole exponents are computed exactly, others with the principal
+    /// branch of the complex power.
+    pub n: f32,
     pub c: Vec2,
+    /// The starting point of orbits in the parameter plane, 0 by default. Moving it away from the
+    /// critical point 0 perturbs the set. Unused in the dynamical plane, where orbits start from
+    /// the pixel, and by Newton fractals.
+    pub z0: Vec2,
+    /// Whether the pixels are starting points or values of `c`, in place of the kind's own plane.
+    /// Viewing the Burning Ship in the dynamical plane gives its Julia sets, for instance. Ignored
+    /// for Newton fractals.
+    pub plane: Option<Plane>,
+    /// A polynomial to iterate in place of `x^n`, so that the formula becomes `p(x) + c`, or the
+    /// function whose roots are found for Newton fractals.
+    pub poly: Option<Polynomial>,
+    /// A denominator `Q`, which makes the iterated map the rational function `P(x)/Q(x) + c`,
+    /// where `P` is `poly` or `x^n`. Ignored for Newton fractals.
+    pub denom: Option<Polynomial>,
+    /// A sequence of folds to cycle through, one per iteration step, in place of the kind's own.
+    /// Ignored for Newton fractals.
+    pub hybrid: Option<Hybrid>,
+
+    /// Apply slope shading with the given light. Ignored for Newton fractals.
+    pub shading: Option<Light>,
+
+    /// The iteration limits of the red, green and blue channels of a Nebulabrot. When set,
+    /// Buddhabrot exports render a Nebulabrot, and `iters` is unused by them.
+    pub nebulabrot: Option<[u32; 3]>,
 
     pub iters: u32,
 
     pub center: Vec2,
     pub extents: Vec2,
+
+    /// Render with the strict variant of the shader, which gives bit-identical images for the
+    /// same parameters on the same device and driver.
+    pub strict: bool,
+
+    /// Stop iterating orbits once they are found to be periodic, treating their points as inside
+    /// the set. Interior pixels then take a fraction of the iteration limit instead of all of it.
+    /// Ignored for Newton fractals.
+    pub periodicity: bool,
+
+    /// Skip iterating the points of the Mandelbrot set's main cardioid and period-2 bulb, which are
+    /// known to be inside it. Only applies to the parameter plane of `x^2 + c` iterated from 0.
+    pub interior_checks: bool,
+
+    /// The angle in degrees by which the view is turned counterclockwise about its center.
+    /// `extents` are measured along the turned axes.
+    pub rotation: f32,
+
+    /// What escaping points are colored by. Ignored for Newton fractals.
+    pub coloring: ColoringMode,
+
+    /// The number of stripes per turn around the origin for stripe average coloring. Whole
+    /// numbers avoid a seam along the negative real axis.
+    pub stripe_density: f32,
+
+    /// The scales and offsets of the channels of per-channel coloring.
+    pub channels: Channels,
+
+    /// Spread the gradient evenly over the escaping pixels of the view, by the fraction of them
+    /// that escape sooner, instead of linearly over the iteration count. Keeps deep zooms, where
+    /// most pixels escape after nearly the same number of steps, from coming out one color. Only
+    /// applies to iteration count and binary decomposition coloring.
+    pub equalize: bool,
+
+    /// The number of iterations after which the gradient repeats, or `None` to stretch it once
+    /// over the iteration limit and clamp values past its ends. Deep zooms escape over a range of
+    /// iterations far wider than any one gradient reads well across, so repeating it keeps the
+    /// bands narrow. Takes the place of equalization, and only applies to iteration count and
+    /// binary decomposition coloring.
+    pub color_period: Option<f32>,
+
+    /// The fraction of its span the gradient is turned by, from 0 to 1, with the colors that pass
+    /// its end coming around again at its start. Advanced over time by color cycling in the
+    /// interactive interface. Values outside of the gradient's span keep the colors of its ends.
+    pub color_phase: f32,
+
+    /// The space the gradient's colors are blended in between stops.
+    pub interpolation: Interpolation,
+
+    /// The color of points that never escape, or never reach a root for Newton fractals, as
+    /// non-linear sRGB with alpha. A transparent color leaves the set out of exports, so that they can be
+    /// composited over other artwork. `None` colors them from the gradient: its end, or its start
+    /// for Newton fractals.
+    pub interior_color: Option<Vec4>,
+
+    /// Exposure, gamma and contrast adjustments applied to the colors of the image. Not applied to
+    /// Buddhabrots and Nebulabrots, which have their own.
+    pub tone: Tone,
+
+    /// Add an ordered dither of one quantization step to the colors before they're stored at 8
+    /// bits per channel, which breaks up the banding of smooth gradients.
+    pub dither: bool,
 }
 
 impl JuliaData {
     fn into_shader_data(self) -> julia_comp::ty::Data {
-        let mut color = [[0f32; 4]; 3];
-        for (c, orig) in color.iter_mut().zip(self.color.iter()) {
-            let nonlin = Srgba::new(orig.x, orig.y, orig.z, orig.w);
-            let lin = nonlin.into_linear();
-            let (r, g, b, a) = lin.into_components();
-            *c = [r, g, b, a];
-        }
+        let (color, color_pos) = self.gradient.shader_stops();
 
         let mut c = [0f32; 2];
         c.copy_from_slice(self.c.as_ref());
 
+        let mut z0 = [0f32; 2];
+        z0.copy_from_slice(self.z0.as_ref());
+
         let mut center = [0f32; 2];
         center.copy_from_slice(self.center.as_ref());
 
         let mut extents = [0f32; 2];
         extents.copy_from_slice(self.extents.as_ref());
 
+        let (poly, poly_len) = match &self.poly {
+            Some(p) => (p.shader_coefficients(), p.coefficients().len() as u32),
+            None => (Default::default(), 0),
+        };
+
+        let (denom, denom_len) = match &self.denom {
+            Some(q) => (q.shader_coefficients(), q.coefficients().len() as u32),
+            None => (Default::default(), 0),
+        };
+
+        let (hybrid, hybrid_len) = match &self.hybrid {
+            Some(h) => (h.shader_program(), h.steps().len() as u32),
+            None => (0, 0),
+        };
+
+        let rotation = [
+            self.rotation.to_radians().cos(),
+            self.rotation.to_radians().sin(),
+        ];
+
+        let light = match self.shading {
+            Some(l) => [l.azimuth.to_radians(), l.elevation.to_radians()],
+            None => [0.0; 2],
+        };
+
         julia_comp::ty::Data {
             color,
-            midpt: self.color_midpoint,
+            color_pos,
+            poly,
+            denom,
+            equalization: [[0.0; 4]; 4],
+            color_stops: self.gradient.stops().len() as u32,
             n: self.n,
             c,
             iters: self.iters,
-            _dummy0: [0; 4],
+            kind: self.kind.shader_id(),
             center,
             extents,
+            poly_len,
+            color_offset: self.color_offset,
+            color_scale: self.color_scale,
+            denom_len,
+            z0,
+            origin: [0; 2],
+            full_size: [0; 2],
+            light,
+            shading: self.shading.map_or(0, |l| l.relief.shader_id()),
+            hybrid,
+            hybrid_len,
+            plane: self.view_plane().shader_id(),
+            periodicity: self.periodicity as u32,
+            interior: (self.interior_checks && self.is_quadratic_mandelbrot()) as u32,
+            rotation,
+            coloring: self.coloring.shader_id(),
+            stripe_density: self.stripe_density,
+            trap: 0,
+            trap_center: [0.0; 2],
+            trap_size: [0.0; 2],
+            equalize: 0,
+            color_period: self.color_period.unwrap_or(0.0),
+            color_phase: self.color_phase,
+            interpolation: self.interpolation.shader_id(),
+            interior_color: self.interior_color.map_or([0.0; 4], gradient::to_linear),
+            interior_colored: self.interior_color.is_some() as u32,
+            exposure: self.tone.exposure.exp2(),
+            gamma: self.tone.gamma,
+            contrast: self.tone.contrast,
+            dither: self.dither as u32,
+            tone_curve: self.tone.curve.shader_id(),
+            color_space: 0,
+            transfer: 0,
+            channel_scale: [
+                self.channels.scale.x,
+                self.channels.scale.y,
+                self.channels.scale.z,
+                0.0,
+            ],
+            channel_offset: [
+                self.channels.offset.x,
+                self.channels.offset.y,
+                self.channels.offset.z,
+                0.0,
+            ],
+        }
+    }
+
+    /// Whether `other` only differs from this in how its iteration data is colored: its gradient,
+    /// color mapping, channel mapping, lighting, tone or dithering. The iteration pass of one then
+    /// serves for the other, and only the coloring pass needs to run again.
+    pub fn same_iterations(&self, other: &JuliaData) -> bool {
+        let distance_relief = |d: &JuliaData| {
+            d.coloring != ColoringMode::DistanceEstimate
+                && d.shading.map_or(false, |l| l.relief == Relief::Distance)
+        };
+
+        let recolored = JuliaData {
+            gradient: other.gradient,
+            color_offset: other.color_offset,
+            color_scale: other.color_scale,
+            shading: other.shading,
+            channels: other.channels,
+            equalize: other.equalize,
+            color_period: other.color_period,
+            color_phase: other.color_phase,
+            interpolation: other.interpolation,
+            interior_color: other.interior_color,
+            tone: other.tone,
+            dither: other.dither,
+            ..*self
+        };
+
+        recolored == *other && distance_relief(self) == distance_relief(other)
+    }
+
+    /// Whether the view is the unperturbed Mandelbrot set of `x^2 + c`, whose main components are
+    /// known in closed form.
+    fn is_quadratic_mandelbrot(&self) -> bool {
+        let plain_kind = match self.kind {
+            FractalKind::Julia | FractalKind::Mandelbrot => true,
+            _ => false,
+        };
+
+        plain_kind
+            && self.view_plane() == Plane::Parameter
+            && self.n == 2.0
+            && self.z0 == Vec2::zeros()
+            && self.poly.is_none()
+            && self.denom.is_none()
+            && self.hybrid.is_none()
+    }
+
+    /// Turns an offset along the axes of the view into the same offset in the complex plane,
+    /// undoing the view's rotation.
+    pub fn view_to_plane(&self, offset: Vec2) -> Vec2 {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        vec2!(
+            offset.x * cos - offset.y * sin,
+            offset.x * sin + offset.y * cos
+        )
+    }
+
+    /// The plane the pixels are in: `plane` if set, otherwise that of the kind.
+    pub fn view_plane(&self) -> Plane {
+        match self.plane {
+            _ if self.kind == FractalKind::Newton => Plane::Dynamical,
+            Some(plane) => plane,
+            None if self.kind.is_parameter_plane() => Plane::Parameter,
+            None => Plane::Dynamical,
+        }
+    }
+
+    /// Stretches the extents along the longer image dimension so that the given dimensions cover
+    /// the complex plane without distortion.
+    pub fn fit_to(&self, dims: ImgDimensions) -> JuliaData {
+        let ImgDimensions { width, height } = dims;
+        let mut data = *self;
+
+        if width < height {
+            data.extents.y *= height as f32 / width as f32;
+        } else if width > height {
+            data.extents.x *= width as f32 / height as f32;
         }
+
+        data
     }
 
     pub fn name(&self) -> String {
-        use palette::Srgb;
+        let kind = match self.kind {
+            FractalKind::Julia => "",
+            FractalKind::Mandelbrot => "mandelbrot_",
+            FractalKind::BurningShip => "burning_ship_",
+            FractalKind::Tricorn => "tricorn_",
+            FractalKind::Newton => "newton_",
+        };
+
+        let power = match &self.poly {
+            Some(p) => format!("p{}", p),
+            None => format!("x{}", self.n),
+        };
+        let power = match &self.denom {
+            Some(q) => format!("{}_q{}", power, q),
+            None => power,
+        };
+
+        let power = if self.z0 != Vec2::zeros() && self.view_plane() == Plane::Parameter {
+            format!("{}_z{:.5}_{:.5}i", power, self.z0.x, self.z0.y)
+        } else {
+            power
+        };
+        let power = match self.plane {
+            Some(p) if (p == Plane::Parameter) != self.kind.is_parameter_plane() => {
+                format!("{}_{}plane", power, p)
+            }
+            _ => power,
+        };
+        let power = match &self.hybrid {
+            Some(h) => format!("{}_h{}", power, h.to_string().replace('*', "x")),
+            None => power,
+        };
 
-        fn to_hex(c: Vec4) -> String {
-            let c = Srgb::new(c[0], c[1], c[2]);
-            let c = Srgb::<u8>::from_format(c);
-            format!("{:02x}{:02x}{:02x}", c.red, c.green, c.blue)
+        let mut mapping = if self.color_offset != 0.0 || self.color_scale != 1.0 {
+            format!("_s{:.2}-{:.2}", self.color_scale, self.color_offset)
+        } else {
+            String::new()
+        };
+        if let Some(l) = self.shading {
+            mapping.push_str(&format!("_l{:.0}-{:.0}", l.azimuth, l.elevation));
+            if l.relief == Relief::Distance {
+                mapping.push_str("de");
+            }
+        }
+        match self.coloring {
+            ColoringMode::Iterations => (),
+            ColoringMode::DistanceEstimate => mapping.push_str("_de"),
+            ColoringMode::Stripes => mapping.push_str(&format!("_st{:.1}", self.stripe_density)),
+            ColoringMode::BinaryDecomposition => mapping.push_str("_bd"),
+            ColoringMode::Curvature => mapping.push_str("_cv"),
+            ColoringMode::Channels => {
+                mapping.push_str("_ch");
+                if !self.channels.is_identity() {
+                    let Channels { scale, offset } = self.channels;
+                    mapping.push_str(&format!(
+                        "{:.2}-{:.2}-{:.2}_{:.2}-{:.2}-{:.2}",
+                        scale.x, scale.y, scale.z, offset.x, offset.y, offset.z
+                    ));
+                }
+            }
+        }
+        if let Some(p) = self.color_period {
+            mapping.push_str(&format!("_p{:.1}", p));
+        } else if self.equalize {
+            mapping.push_str("_eq");
+        }
+        if self.color_phase != 0.0 {
+            mapping.push_str(&format!("_ph{:.2}", self.color_phase));
+        }
+        if self.interpolation != Interpolation::Rgb {
+            mapping.push_str(&format!("_{}", self.interpolation));
+        }
+        if let Some(c) = self.interior_color {
+            mapping.push_str(&format!("_in{}", gradient::to_hex(c)));
         }
+        if !self.tone.is_identity() {
+            let t = self.tone;
+            mapping.push_str(&format!(
+                "_t{:.2}-{:.2}-{:.2}",
+                t.exposure, t.gamma, t.contrast
+            ));
+            if t.curve != ToneCurve::Clip {
+                mapping.push_str(&format!("-{}", t.curve));
+            }
+        }
+        if self.dither {
+            mapping.push_str("_dither");
+        }
+
+        let rotation = if self.rotation != 0.0 {
+            format!("_r{:.2}", self.rotation)
+        } else {
+            String::new()
+        };
+
+        let gradient = self
+            .gradient
+            .stops()
+            .iter()
+            .map(|s| format!("{}-{:.2}", gradient::to_hex(s.color), s.position))
+            .collect::<Vec<_>>()
+            .join("-");
 
-        format!("x{}_{:.5}_{:.5}i_m{}_o{:.4}-{:.4}_e{:.8}_c{}-{}-{}-{:.2}-{:.2}-{:.2}",
-            self.n,
+        format!("{}{}_{:.5}_{:.5}i_m{}_o{:.4}-{:.4}_e{:.8}{}_c{}{}",
+            kind,
+            power,
             self.c.x,
             self.c.y,
             self.iters,
             self.center.x,
             self.center.y,
             f32::max(self.extents.x, self.extents.y),
-            to_hex(self.color[0]),
-            to_hex(self.color[1]),
-            to_hex(self.color[2]),
-            self.color_midpoint[0],
-            self.color_midpoint[1],
-            self.color_midpoint[2],
+            rotation,
+            gradient,
+            mapping,
         )
     }
 }
 
+/// The Vulkan state julia renders with, and the exports made with it. Exports are written to a
+/// temporary file next to their target and renamed over it once complete, so an interrupted
+/// export never leaves a truncated image behind.
 #[derive(Debug)]
 pub struct JuliaContext {
     vk_data: JuliaVkData,
     export: JuliaExport,
+    trap: RefCell<TrapTexture>,
+    previews: RefCell<PreviewCache>,
+    equalization: RefCell<EqualizationCache>,
 }
 
 impl JuliaContext {
@@ -177,38 +1065,101 @@ impl JuliaContext {
 
         let (physical, queue_family) =
             find_best_physical_device(&instance).ok_or(JuliaCreationError::DeviceDiscovery)?;
+        let export_family = async_compute_family(physical, queue_family);
 
         let device_extensions = DeviceExtensions {
             khr_swapchain: true,
             ..DeviceExtensions::none()
         };
 
+        // 16-bit storage images are an extended format, needed only for 16-bit exports
+        let features = Features {
+            shader_storage_image_extended_formats: physical
+                .supported_features()
+                .shader_storage_image_extended_formats,
+            ..Features::none()
+        };
+
         let (device, mut queues) = Device::new(
             physical,
-            &Features::none(),
+            &features,
             &device_extensions,
-            iter::once((queue_family, 0.5)),
+            iter::once((queue_family, 0.5)).chain(export_family.map(|f| (f, 0.5))),
         )
         .map_err(JuliaCreationError::DeviceCreation)?;
         let queue = queues.next().unwrap();
+        let export_queue = queues.next().unwrap_or_else(|| queue.clone());
+
+        macro_rules! load_pipeline {
+            ($module:ident) => {{
+                let shader = $module::Shader::load(device.clone())
+                    .map_err(JuliaCreationError::ShaderLoad)?;
+                Arc::new(
+                    ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
+                        .map_err(JuliaCreationError::ComputePipelineCreation)?,
+                )
+            }};
+        }
 
-        let shader =
-            julia_comp::Shader::load(device.clone()).map_err(JuliaCreationError::ShaderLoad)?;
-        let pipeline = Arc::new(
-            ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
-                .map_err(JuliaCreationError::ComputePipelineCreation)?,
-        );
+        let pipeline = load_pipeline!(julia_comp);
+        let strict_pipeline = load_pipeline!(julia_strict_comp);
+        let iterate_pipeline = load_pipeline!(julia_iterate_comp);
+        let iterate_strict_pipeline = load_pipeline!(julia_iterate_strict_comp);
+        let newton_pipeline = load_pipeline!(julia_newton_comp);
+        let newton_strict_pipeline = load_pipeline!(julia_newton_strict_comp);
+        let potential_pipeline = load_pipeline!(julia_potential_comp);
+        let cost_pipeline = load_pipeline!(julia_cost_comp);
+        let escape_pipeline = load_pipeline!(julia_escape_comp);
+        let buddhabrot_pipeline = load_pipeline!(julia_buddhabrot_comp);
+        let buddhabrot_tonemap_pipeline = load_pipeline!(julia_buddhabrot_tonemap_comp);
+        let nebulabrot_tonemap_pipeline = load_pipeline!(julia_nebulabrot_tonemap_comp);
+        let hud_pipeline = load_pipeline!(julia_hud_comp);
+        let guides_pipeline = load_pipeline!(julia_guides_comp);
+        let strip_pipeline = load_pipeline!(julia_strip_comp);
+        let iim_pipeline = load_pipeline!(julia_iim_comp);
+        let tonemap_pipeline = load_pipeline!(julia_tonemap_comp);
+        let tonemap_wide_pipeline = if features.shader_storage_image_extended_formats {
+            Some(load_pipeline!(julia_tonemap_wide_comp))
+        } else {
+            None
+        };
 
         let export = JuliaExport::new();
+        let trap =
+            TrapTexture::new(None, &device, &queue).map_err(JuliaCreationError::TrapUpload)?;
 
         let vk_data = JuliaVkData {
             instance,
             device,
             queue,
+            export_queue,
             pipeline,
+            strict_pipeline,
+            iterate_pipeline,
+            iterate_strict_pipeline,
+            newton_pipeline,
+            newton_strict_pipeline,
+            potential_pipeline,
+            cost_pipeline,
+            escape_pipeline,
+            buddhabrot_pipeline,
+            buddhabrot_tonemap_pipeline,
+            nebulabrot_tonemap_pipeline,
+            hud_pipeline,
+            guides_pipeline,
+            strip_pipeline,
+            iim_pipeline,
+            tonemap_pipeline,
+            tonemap_wide_pipeline,
         };
 
-        Ok(JuliaContext { vk_data, export })
+        Ok(JuliaContext {
+            vk_data,
+            export,
+            trap: RefCell::new(trap),
+            previews: RefCell::new(PreviewCache::default()),
+            equalization: RefCell::new(EqualizationCache::default()),
+        })
     }
 
     pub fn instance(&self) -> &Arc<Instance> {
@@ -219,16 +1170,426 @@ impl JuliaContext {
         &self.vk_data.device
     }
 
+    /// The queue used for interactive rendering and presentation.
     pub fn queue(&self) -> &Arc<Queue> {
         &self.vk_data.queue
     }
 
+    /// The queue used for exports. This is a separate compute-only queue if the device has one, so
+    /// that exports can run asynchronously to interactive rendering, and otherwise the same as
+    /// `queue()`.
+    pub fn export_queue(&self) -> &Arc<Queue> {
+        &self.vk_data.export_queue
+    }
+
     pub fn pipeline(&self) -> &Arc<ComputePipeline<CompDesc>> {
         &self.vk_data.pipeline
     }
 
-    pub fn export(&self, dims: ImgDimensions, data: &JuliaData, filename: &Path) {
-        self.export.export(dims, data, filename, self);
+    pub fn strict_pipeline(&self) -> &Arc<ComputePipeline<StrictDesc>> {
+        &self.vk_data.strict_pipeline
+    }
+
+    /// The color pipeline to render `data` with, depending on its kind and strictness. All of them
+    /// share a descriptor set layout: the image they render into, the uniform data, the orbit trap
+    /// picture, and the iteration data. For escape-time fractals this is the coloring pass, which
+    /// colors the iteration data found by `iteration_pipeline`.
+    pub fn color_pipeline(
+        &self,
+        data: &JuliaData,
+    ) -> Arc<dyn ComputePipelineAbstract + Send + Sync> {
+        let vk = &self.vk_data;
+        match (data.kind, data.strict) {
+            (FractalKind::Newton, false) => vk.newton_pipeline.clone(),
+            (FractalKind::Newton, true) => vk.newton_strict_pipeline.clone(),
+            (_, false) => vk.pipeline.clone(),
+            (_, true) => vk.strict_pipeline.clone(),
+        }
+    }
+
+    /// The pass that finds the iteration data that the color pipeline of `data` colors, or `None`
+    /// for Newton fractals, which are iterated and colored in one pass. It shares the color
+    /// pipelines' descriptor set layout.
+    pub fn iteration_pipeline(
+        &self,
+        data: &JuliaData,
+    ) -> Option<Arc<dyn ComputePipelineAbstract + Send + Sync>> {
+        let vk = &self.vk_data;
+        match (data.kind, data.strict) {
+            (FractalKind::Newton, _) => None,
+            (_, false) => Some(vk.iterate_pipeline.clone()),
+            (_, true) => Some(vk.iterate_strict_pipeline.clone()),
+        }
+    }
+
+    /// The pipeline that maps the floating-point image rendered by a color pipeline into an 8-bit
+    /// image, with the tone adjustments of the view. It shares the color pipelines' uniform data,
+    /// and takes the floating-point image at binding 2.
+    pub fn tonemap_pipeline(&self) -> &Arc<ComputePipeline<TonemapDesc>> {
+        &self.vk_data.tonemap_pipeline
+    }
+
+    /// Like `tonemap_pipeline`, but writing a 16-bit image, or `None` if the device doesn't
+    /// support 16-bit storage images.
+    pub fn tonemap_wide_pipeline(&self) -> Option<&Arc<ComputePipeline<TonemapWideDesc>>> {
+        self.vk_data.tonemap_wide_pipeline.as_ref()
+    }
+
+    /// The largest width or height, in pixels, of an image the device can render at once: the
+    /// smaller of its largest 2D image and the most 8-pixel work groups it can dispatch along an
+    /// axis. Exports beyond it fail with `JuliaExportError::TooLargeErr`, but can be rendered in
+    /// tiles with `render_region`.
+    pub fn max_image_dimension(&self) -> u32 {
+        let limits = self.device().physical_device().limits();
+        let groups = limits.max_compute_work_group_count();
+        let dispatchable = u32::min(groups[0], groups[1]).saturating_mul(8);
+        u32::min(limits.max_image_dimension_2d(), dispatchable)
+    }
+
+    /// Whether color images can be exported with the given number of bits per channel.
+    pub fn supports_bit_depth(&self, depth: BitDepth) -> bool {
+        match depth {
+            BitDepth::Eight => true,
+            BitDepth::Sixteen => self.tonemap_wide_pipeline().is_some(),
+        }
+    }
+
+    pub fn potential_pipeline(&self) -> &Arc<ComputePipeline<PotentialDesc>> {
+        &self.vk_data.potential_pipeline
+    }
+
+    pub fn cost_pipeline(&self) -> &Arc<ComputePipeline<CostDesc>> {
+        &self.vk_data.cost_pipeline
+    }
+
+    pub fn escape_pipeline(&self) -> &Arc<ComputePipeline<EscapeDesc>> {
+        &self.vk_data.escape_pipeline
+    }
+
+    pub fn buddhabrot_pipeline(&self) -> &Arc<ComputePipeline<BuddhabrotDesc>> {
+        &self.vk_data.buddhabrot_pipeline
+    }
+
+    pub fn buddhabrot_tonemap_pipeline(&self) -> &Arc<ComputePipeline<BuddhabrotTonemapDesc>> {
+        &self.vk_data.buddhabrot_tonemap_pipeline
+    }
+
+    pub fn nebulabrot_tonemap_pipeline(&self) -> &Arc<ComputePipeline<NebulabrotTonemapDesc>> {
+        &self.vk_data.nebulabrot_tonemap_pipeline
+    }
+
+    pub fn hud_pipeline(&self) -> &Arc<ComputePipeline<HudDesc>> {
+        &self.vk_data.hud_pipeline
+    }
+
+    pub fn guides_pipeline(&self) -> &Arc<ComputePipeline<GuidesDesc>> {
+        &self.vk_data.guides_pipeline
+    }
+
+    pub fn strip_pipeline(&self) -> &Arc<ComputePipeline<StripDesc>> {
+        &self.vk_data.strip_pipeline
+    }
+
+    pub fn iim_pipeline(&self) -> &Arc<ComputePipeline<IimDesc>> {
+        &self.vk_data.iim_pipeline
+    }
+
+    /// Renders `data` at `dims` and writes it to `filename`. Errors in rendering or writing, such
+    /// as running out of GPU memory or disk space, are returned rather than panicking.
+    pub fn export(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        filename: &Path,
+    ) -> Result<(), JuliaExportError> {
+        self.export.export(dims, data, filename, self)
+    }
+
+    /// Renders `data` like `export`, but writes it to `out` as a stream in `format` rather than to
+    /// a file. Only formats that `OutputFormat::streams` can be written this way.
+    pub fn export_to<W: Write>(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        format: OutputFormat,
+        out: &mut W,
+    ) -> Result<(), JuliaExportError> {
+        self.export.export_to(dims, data, format, out, self)
+    }
+
+    /// Renders `data` like `export`, but returns its pixels as rows of 8-bit RGBA values rather
+    /// than writing them, for encoders that take raw frames.
+    pub fn render_rgba(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+    ) -> Result<Vec<u8>, JuliaExportError> {
+        self.export.render_rgba(dims, data, self)
+    }
+
+    #[cfg(feature = "avif")]
+    pub fn set_avif_options(&self, options: AvifOptions) {
+        self.export.set_avif_options(options);
+    }
+
+    #[cfg(feature = "webp")]
+    pub fn set_webp_options(&self, options: WebpOptions) {
+        self.export.set_webp_options(options);
+    }
+
+    /// Sets the format every export is written in, or chooses it by the extension of each file
+    /// name, as it is by default.
+    pub fn set_format(&self, format: Option<OutputFormat>) {
+        self.export.set_format(format);
+    }
+
+    /// Sets the quality of JPEG exports, from 1 to 100. Defaults to 90.
+    pub fn set_jpeg_quality(&self, quality: u8) {
+        self.export.set_jpeg_quality(quality);
+    }
+
+    /// Sets whether every export is followed by a sidecar file, its name with `.json` appended,
+    /// holding its dimensions and parameters. Off by default.
+    pub fn set_sidecar(&self, sidecar: bool) {
+        self.export.set_sidecar(sidecar);
+    }
+
+    /// Sets what happens when an export's file already exists. Defaults to replacing it.
+    pub fn set_overwrite_policy(&self, policy: OverwritePolicy) {
+        self.export.set_overwrite_policy(policy);
+    }
+
+    /// The file an export to `filename` is written to under the overwrite policy.
+    pub fn target(&self, filename: &Path) -> PathBuf {
+        self.export.target(filename)
+    }
+
+    pub fn set_icc_profile(&self, profile: Option<IccProfile>) {
+        self.export.set_icc_profile(profile);
+    }
+
+    /// Sets the caption or watermark stamped onto color exports, or `None` for neither.
+    pub fn set_overlay(&self, overlay: Option<Overlay>) {
+        self.export.set_overlay(overlay);
+    }
+
+    pub fn set_print_settings(&self, settings: Option<PrintSettings>) {
+        self.export.set_print_settings(settings);
+    }
+
+    /// Sets the bit depth of exported color images. Defaults to 8 bits; check
+    /// `supports_bit_depth` before choosing 16.
+    pub fn set_bit_depth(&self, depth: BitDepth) {
+        self.export.set_bit_depth(depth);
+    }
+
+    /// Sets the color space of exported color images. Defaults to sRGB.
+    pub fn set_color_space(&self, space: ColorSpace) {
+        self.export.set_color_space(space);
+    }
+
+    /// Sets the transfer functions exported colors are encoded with. By default they are encoded
+    /// with the sRGB transfer function on the GPU, as the interactive view is.
+    pub fn set_transfer(&self, stages: TransferStages) {
+        self.export.set_transfer(stages);
+    }
+
+    /// Uploads an image to stamp onto orbits in color renders, or removes it. There is none by
+    /// default.
+    pub fn set_orbit_trap(&self, trap: Option<&OrbitTrap>) -> Result<(), OrbitTrapError> {
+        let texture = TrapTexture::new(trap, self.device(), self.queue())?;
+        self.trap.replace(texture);
+        self.export.clear_caches();
+        self.previews.borrow_mut().clear();
+        Ok(())
+    }
+
+    pub(crate) fn trap_texture(&self) -> Ref<TrapTexture> {
+        self.trap.borrow()
+    }
+
+    /// The histogram equalization to color `data` with, if it asks for one and anything in it
+    /// escapes. Rendering the histogram takes a small potential render, which is only redone when
+    /// the view changes.
+    pub(crate) fn equalization(
+        &self,
+        data: &JuliaData,
+    ) -> Result<Option<Equalization>, JuliaExportError> {
+        self.equalization.borrow_mut().get(data, self)
+    }
+
+    pub fn export_potential(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        filename: &Path,
+    ) -> Result<(), JuliaExportError> {
+        self.export.export_potential(dims, data, filename, self)
+    }
+
+    /// Exports a heatmap of the iterations spent on each pixel of `data`, and reports where the
+    /// view is most expensive. Not for Newton fractals.
+    pub fn export_heatmap(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        filename: &Path,
+    ) -> Result<CostReport, JuliaExportError> {
+        self.export.export_heatmap(dims, data, filename, self)
+    }
+
+    /// Exports the escape time of every pixel of `data` as raw numbers, as a NumPy `.npy` array or
+    /// as CSV if the file name ends in `.csv`. Not for Newton fractals.
+    pub fn export_escape_time(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        value: EscapeValue,
+        filename: &Path,
+    ) -> Result<(), JuliaExportError> {
+        self.export
+            .export_escape_time(dims, data, value, filename, self)
+    }
+
+    /// Exports vector contours of the smooth escape time of `data` at each of `thresholds`, in
+    /// iterations, as an SVG with a path per threshold. A threshold at the iteration limit traces
+    /// the boundary of the set. Not for Newton fractals.
+    pub fn export_contours(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        thresholds: &[f32],
+        filename: &Path,
+    ) -> Result<(), JuliaExportError> {
+        self.export
+            .export_contours(dims, data, thresholds, filename, self)
+    }
+
+    /// Exports the smooth escape time of `data` as a heightmap mesh for 3D printing, closed into a
+    /// solid: as binary STL if the file name ends in `.stl`, and as OBJ otherwise. Not for Newton
+    /// fractals.
+    pub fn export_mesh(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        options: &MeshOptions,
+        filename: &Path,
+    ) -> Result<(), JuliaExportError> {
+        self.export.export_mesh(dims, data, options, filename, self)
+    }
+
+    /// Exports a Buddhabrot of `data`: the escaping orbits of randomly sampled points, with each
+    /// pixel colored by how many of them pass through it, or a Nebulabrot if `data.nebulabrot` is
+    /// set. Not supported for Newton fractals.
+    pub fn export_buddhabrot(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        options: &BuddhabrotOptions,
+        filename: &Path,
+    ) -> Result<(), JuliaExportError> {
+        self.export
+            .export_buddhabrot(dims, data, options, filename, self)
+    }
+
+    /// Exports the Julia set of `data` drawn by inverse iteration, with `options.samples` points
+    /// plotted per pixel on average. Only the Julia sets of `x^n + c` with whole exponents are
+    /// supported; see `iim::supports`.
+    pub fn export_iim(
+        &self,
+        dims: ImgDimensions,
+        data: &JuliaData,
+        options: &BuddhabrotOptions,
+        filename: &Path,
+    ) -> Result<(), JuliaExportError> {
+        self.export.export_iim(dims, data, options, filename, self)
+    }
+
+    /// Renders only the pixels of `rect` within an image of `full_dims`, as rows of 8-bit sRGBA
+    /// values. They're the same as the pixels in that rectangle of a full render of `data` at
+    /// `full_dims`, so an image can be rendered piece by piece. Fails with
+    /// `JuliaExportError::RegionErr` if `rect` is empty, doesn't lie within `full_dims`, or its
+    /// dimensions aren't multiples of 8.
+    pub fn render_region(
+        &self,
+        full_dims: ImgDimensions,
+        rect: PixelRect,
+        data: &JuliaData,
+    ) -> Result<Vec<u8>, JuliaExportError> {
+        JuliaExport::render_region(full_dims, rect, data, self)
+    }
+
+    /// A quick, small render of `data` for thumbnails in user interfaces, with the aspect ratio of
+    /// its extents and its longer side `max_dim` pixels, rounded down to a multiple of 8. At most
+    /// `thumbnail::PREVIEW_MAX_ITERS` iterations are used. The most recently requested previews
+    /// are kept in memory, so asking for the same one again is free.
+    pub fn preview(
+        &self,
+        data: &JuliaData,
+        max_dim: u32,
+    ) -> Result<::image::RgbaImage, JuliaExportError> {
+        self.previews.borrow_mut().get(data, max_dim, self)
+    }
+
+    /// Writes 8-bit sRGBA pixels, such as those of `render_region`, in the format given by the
+    /// file extension and with the settings of other exports.
+    pub fn write_image(
+        &self,
+        filename: &Path,
+        dims: ImgDimensions,
+        srgba: &[u8],
+    ) -> io::Result<()> {
+        self.export.write_srgb(filename, dims, srgba)
+    }
+
+    /// Exports every job in turn, with up to `max_in_flight` of them rendering on the GPU while
+    /// earlier ones are encoded. Stops at the first that fails.
+    pub fn export_batch<I>(&self, jobs: I, max_in_flight: usize) -> Result<(), JuliaExportError>
+    where
+        I: IntoIterator<Item = ExportJob>,
+    {
+        self.export.export_batch(jobs, max_in_flight, self)
+    }
+
+    /// Submits `job` and returns without waiting for it, leaving a worker thread to write it.
+    /// Returns the file it is written to.
+    pub fn export_in_background(&self, job: ExportJob) -> Result<PathBuf, JuliaExportError> {
+        self.export.export_in_background(job, self)
+    }
+
+    /// The background exports finished since the last call, each with the error that kept it
+    /// from being written, if any. Never blocks.
+    pub fn finished_exports(&self) -> Vec<FinishedExport> {
+        self.export.finished_exports()
+    }
+
+    /// Waits for every background export to finish, and returns those finished since
+    /// `finished_exports` was last called.
+    pub fn wait_for_exports(&self) -> Vec<FinishedExport> {
+        self.export.wait_for_exports()
+    }
+
+    /// The number of background exports not yet written.
+    pub fn pending_exports(&self) -> usize {
+        self.export.pending_exports()
+    }
+
+    /// The progress of the first background export not yet written, if it has been reported.
+    pub fn export_progress(&self) -> Option<Progress> {
+        self.export.export_progress()
+    }
+
+    /// Exports `job`, calling `progress` as each strip of the image is rendered. Returns the file
+    /// it is written to.
+    pub fn export_with_progress<F>(
+        &self,
+        job: ExportJob,
+        progress: F,
+    ) -> Result<PathBuf, JuliaExportError>
+    where
+        F: FnMut(Progress),
+    {
+        self.export.export_with_progress(job, self, progress)
     }
 }
 
@@ -237,7 +1598,25 @@ struct JuliaVkData {
     instance: Arc<Instance>,
     device: Arc<Device>,
     queue: Arc<Queue>,
+    export_queue: Arc<Queue>,
     pipeline: Arc<ComputePipeline<CompDesc>>,
+    strict_pipeline: Arc<ComputePipeline<StrictDesc>>,
+    iterate_pipeline: Arc<ComputePipeline<IterateDesc>>,
+    iterate_strict_pipeline: Arc<ComputePipeline<IterateStrictDesc>>,
+    newton_pipeline: Arc<ComputePipeline<NewtonDesc>>,
+    newton_strict_pipeline: Arc<ComputePipeline<NewtonStrictDesc>>,
+    potential_pipeline: Arc<ComputePipeline<PotentialDesc>>,
+    cost_pipeline: Arc<ComputePipeline<CostDesc>>,
+    escape_pipeline: Arc<ComputePipeline<EscapeDesc>>,
+    buddhabrot_pipeline: Arc<ComputePipeline<BuddhabrotDesc>>,
+    buddhabrot_tonemap_pipeline: Arc<ComputePipeline<BuddhabrotTonemapDesc>>,
+    nebulabrot_tonemap_pipeline: Arc<ComputePipeline<NebulabrotTonemapDesc>>,
+    hud_pipeline: Arc<ComputePipeline<HudDesc>>,
+    guides_pipeline: Arc<ComputePipeline<GuidesDesc>>,
+    strip_pipeline: Arc<ComputePipeline<StripDesc>>,
+    iim_pipeline: Arc<ComputePipeline<IimDesc>>,
+    tonemap_pipeline: Arc<ComputePipeline<TonemapDesc>>,
+    tonemap_wide_pipeline: Option<Arc<ComputePipeline<TonemapWideDesc>>>,
 }
 
 #[derive(Debug)]
@@ -247,6 +1626,7 @@ pub enum JuliaCreationError {
     DeviceCreation(DeviceCreationError),
     ShaderLoad(OomError),
     ComputePipelineCreation(ComputePipelineCreationError),
+    TrapUpload(OrbitTrapError),
 }
 
 impl Display for JuliaCreationError {
@@ -259,6 +1639,7 @@ impl Display for JuliaCreationError {
             JuliaCreationError::DeviceCreation(e) => write!(f, "{}", e),
             JuliaCreationError::ShaderLoad(e) => write!(f, "failed to load shader: {}", e),
             JuliaCreationError::ComputePipelineCreation(e) => write!(f, "{}", e),
+            JuliaCreationError::TrapUpload(e) => write!(f, "{}", e),
         }
     }
 }
@@ -288,14 +1669,26 @@ fn find_best_by_type(
         .max_by_key(num_compute_queues);
 
     if let Some(d) = dev {
-        d.queue_families()
-            .find(QueueFamily::supports_compute)
-            .map(|q| (d, q))
+        main_queue_family(d).map(|q| (d, q))
     } else {
         None
     }
 }
 
+/// Prefers a family that supports graphics as well as compute. Such a family can in practice also
+/// present, so the interactive path never has to transfer images between queues.
+fn main_queue_family(dev: PhysicalDevice) -> Option<QueueFamily> {
+    dev.queue_families()
+        .find(|q| q.supports_graphics() && q.supports_compute())
+        .or_else(|| dev.queue_families().find(QueueFamily::supports_compute))
+}
+
+/// A compute family without graphics support other than `main`, for exports to run on.
+fn async_compute_family<'a>(dev: PhysicalDevice<'a>, main: QueueFamily) -> Option<QueueFamily<'a>> {
+    dev.queue_families()
+        .find(|q| q.id() != main.id() && q.supports_compute() && !q.supports_graphics())
+}
+
 fn find_best_physical_device(instance: &Arc<Instance>) -> Option<(PhysicalDevice, QueueFamily)> {
     find_best_by_type(instance, PhysicalDeviceType::DiscreteGpu)
         .or_else(|| find_best_by_type(instance, PhysicalDeviceType::IntegratedGpu))
@@ -303,3 +1696,141 @@ fn find_best_physical_device(instance: &Arc<Instance>) -> Option<(PhysicalDevice
         .or_else(|| find_best_by_type(instance, PhysicalDeviceType::Cpu))
         .or_else(|| find_best_by_type(instance, PhysicalDeviceType::Other))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests that render are ignored by default, since they need a Vulkan device. Run them with
+    // `cargo test -- --ignored`.
+
+    fn context() -> JuliaContext {
+        JuliaContext::new().expect("no Vulkan device to render with")
+    }
+
+    const DIMS: ImgDimensions = ImgDimensions {
+        width: 64,
+        height: 48,
+    };
+
+    /// The rows of `rect` cut from the 8-bit RGBA pixels of an image of `DIMS`.
+    fn crop(pixels: &[u8], rect: PixelRect) -> Vec<u8> {
+        let row = DIMS.width as usize * 4;
+        pixels
+            .chunks_exact(row)
+            .skip(rect.y as usize)
+            .take(rect.height as usize)
+            .flat_map(|r| r[rect.x as usize * 4..(rect.x + rect.width) as usize * 4].to_vec())
+            .collect()
+    }
+
+    #[test]
+    #[ignore]
+    fn regions_match_the_full_render() {
+        let context = context();
+        let data = interface::default_state().fit_to(DIMS);
+        let full = context.render_rgba(DIMS, &data).unwrap();
+
+        for &rect in &[
+            PixelRect {
+                x: 0,
+                y: 0,
+                width: 64,
+                height: 48,
+            },
+            PixelRect {
+                x: 16,
+                y: 8,
+                width: 32,
+                height: 24,
+            },
+            PixelRect {
+                x: 56,
+                y: 40,
+                width: 8,
+                height: 8,
+            },
+        ] {
+            let region = context.render_region(DIMS, rect, &data).unwrap();
+            assert!(region == crop(&full, rect), "region {:?} differs", rect);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn invalid_regions_are_rejected() {
+        let context = context();
+        let data = interface::default_state().fit_to(DIMS);
+
+        for &rect in &[
+            PixelRect {
+                x: 8,
+                y: 0,
+                width: 64,
+                height: 48,
+            },
+            PixelRect {
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 8,
+            },
+        ] {
+            match context.render_region(DIMS, rect, &data) {
+                Err(JuliaExportError::RegionErr(e)) => assert_eq!(e.rect, rect),
+                other => panic!("region {:?} gave {:?}", rect, other.map(|p| p.len())),
+            }
+        }
+    }
+
+    fn same_pipeline<T: ?Sized, U: ?Sized>(a: &Arc<T>, b: &Arc<U>) -> bool {
+        &**a as *const T as *const () == &**b as *const U as *const ()
+    }
+
+    #[test]
+    #[ignore]
+    fn strict_views_select_the_strict_pipelines() {
+        let context = context();
+        let vk = &context.vk_data;
+        let mut data = interface::default_state();
+
+        data.strict = true;
+        let color = context.color_pipeline(&data);
+        assert!(same_pipeline(&color, &vk.strict_pipeline));
+        let iterate = context.iteration_pipeline(&data).unwrap();
+        assert!(same_pipeline(&iterate, &vk.iterate_strict_pipeline));
+
+        data.strict = false;
+        let color = context.color_pipeline(&data);
+        assert!(same_pipeline(&color, &vk.pipeline));
+        let iterate = context.iteration_pipeline(&data).unwrap();
+        assert!(same_pipeline(&iterate, &vk.iterate_pipeline));
+
+        data.kind = FractalKind::Newton;
+        data.strict = true;
+        let newton = context.color_pipeline(&data);
+        assert!(same_pipeline(&newton, &vk.newton_strict_pipeline));
+        assert!(context.iteration_pipeline(&data).is_none());
+    }
+
+    #[test]
+    #[ignore]
+    fn strict_renders_are_byte_identical() {
+        let context = context();
+        let mut data = interface::default_state().fit_to(DIMS);
+        data.strict = true;
+        data.c = vec2!(-0.8, 0.156);
+        data.iters = 500;
+        let rect = PixelRect {
+            x: 0,
+            y: 0,
+            width: DIMS.width,
+            height: DIMS.height,
+        };
+
+        // Regions are rendered with buffers of their own, so neither render is a cached copy
+        let first = context.render_region(DIMS, rect, &data).unwrap();
+        let second = context.render_region(DIMS, rect, &data).unwrap();
+        assert!(first == second, "strict renders differ");
+    }
+}
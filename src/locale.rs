@@ -0,0 +1,614 @@
+//! Translations of the messages shown to users of the interactive interface and the command line
+//! tool.
+//!
+//! Messages are templates with `{}` placeholders, filled in order by `tr`. The language is set
+//! once for the whole process with `set_language`, and is English until then.
+//!
+//! Some output is deliberately left in English: the details of errors from the system and from
+//! libraries, such as I/O errors and the render farm's protocol errors, which are passed as
+//! placeholders; the dump of the parsed options printed on startup, which is for debugging; and
+//! panics on internal failures, such as failing to create the Vulkan context.
+
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    English,
+    Spanish,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Spanish];
+
+    /// The language of the user's locale, from the first of `LC_ALL`, `LC_MESSAGES` and `LANG`
+    /// that is set. Falls back to English for locales without a translation.
+    pub fn from_env() -> Language {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(Language::English)
+    }
+
+    /// The ISO 639-1 code of the language.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Spanish => "es",
+        }
+    }
+
+    /// The template of `msg` in this language.
+    pub fn text(self, msg: Message) -> &'static str {
+        match self {
+            Language::English => english(msg),
+            Language::Spanish => spanish(msg),
+        }
+    }
+}
+
+impl Default for Language {
+    fn default() -> Language {
+        Language::English
+    }
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for Language {
+    type Err = ParseLanguageError;
+
+    /// Parses a language code, ignoring any territory, encoding or modifier as found in locale
+    /// names, e.g. `es`, `es_MX` or `es_ES.UTF-8`.
+    fn from_str(s: &str) -> Result<Language, ParseLanguageError> {
+        let mut code = s.split(|c| c == '_' || c == '-' || c == '.' || c == '@');
+        let code = code.next().unwrap_or("").to_lowercase();
+
+        match code.as_str() {
+            "en" | "english" | "c" | "posix" => Ok(Language::English),
+            "es" | "spanish" | "español" => Ok(Language::Spanish),
+            _ => Err(ParseLanguageError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParseLanguageError;
+
+impl Display for ParseLanguageError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "unknown language, expected one of: ")?;
+        for (i, lang) in Language::ALL.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", lang)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ParseLanguageError {}
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the language of all messages from now on.
+pub fn set_language(lang: Language) {
+    let index = Language::ALL.iter().position(|&l| l == lang).unwrap();
+    LANGUAGE.store(index as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    Language::ALL[LANGUAGE.load(Ordering::Relaxed) as usize]
+}
+
+/// The message in the current language, with its placeholders filled by `args` in order.
+pub fn tr(msg: Message, args: &[&dyn Display]) -> String {
+    fill(language().text(msg), args)
+}
+
+/// Replaces each `{}` in `template` with the next of `args`. Placeholders left over once `args`
+/// run out are removed.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut pieces = template.split("{}");
+    let mut args = args.iter();
+    let mut filled = String::from(pieces.next().unwrap_or(""));
+
+    for piece in pieces {
+        if let Some(arg) = args.next() {
+            write!(filled, "{}", arg).unwrap();
+        }
+        filled.push_str(piece);
+    }
+
+    filled
+}
+
+/// Every translated message. Each names the template it stands for; the English ones below are
+/// the reference for what each placeholder holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Message {
+    CurrentState,
+    Iterations,
+    Range,
+    ColorGradient,
+    ColorMapping,
+    ColorMappingEqualized,
+    ColorMappingRepeating,
+    ColorBlending,
+    ColorCycling,
+    CyclingSpeed,
+    Interior,
+    ToneAdjustment,
+    InteriorGradient,
+    InteriorTransparent,
+    BlendingRgb,
+    BlendingOklab,
+    BlendingLch,
+    BlendingHsv,
+    BlendingHsvLong,
+    Shading,
+    Coloring,
+    ColoringIterations,
+    ColoringDistance,
+    ColoringStripes,
+    ColoringBinary,
+    ColoringChannels,
+    ColoringCurvature,
+    ShadingLight,
+    ShadingLightDistance,
+    Off,
+    ExportDimensions,
+    JuliaFormula,
+    MandelbrotFormula,
+    BurningShipFormula,
+    TricornFormula,
+    NewtonPolynomial,
+    NewtonFormula,
+    FromZ0,
+    ParameterPlane,
+    DynamicalPlane,
+    HybridSteps,
+    NumeratorCoefficients,
+    DenominatorCoefficients,
+    Exporting,
+    ExportingPotential,
+    Exported,
+    ExportFailed,
+    PendingExports,
+    WaitingForExports,
+    RenderProgress,
+    Done,
+    RecordingPath,
+    PathSaved,
+    PathWriteFailed,
+    RenderingPath,
+    RenderingAnimation,
+    UnsupportedAnimation,
+    SpecReadFailed,
+    RenderingVariations,
+    RenderingSheet,
+    FarmListening,
+    FarmWorkerJoined,
+    FarmWorkerLost,
+    FarmImageWritten,
+    FarmProgress,
+    FarmNoPotential,
+    FarmUnsupportedOption,
+    FarmFailed,
+    WorkerFinished,
+    WorkerFailed,
+    WatchingFolder,
+    RenderingView,
+    ViewRendered,
+    ViewMoveFailed,
+    SavingCapture,
+    NoCapturedFrames,
+    CaptureDirFailed,
+    NewtonNoPotential,
+    NewtonNoHeatmap,
+    HeatmapNoBatch,
+    NewtonNoEscapeTime,
+    NewtonNoContours,
+    NewtonNoMesh,
+    EscapeTimeNoBatch,
+    StdoutUnsupported,
+    TiledPngOnly,
+    ImageTooLarge,
+    ExportingInTiles,
+    ManifestReadFailed,
+    ViewReadFailed,
+    NoEmbeddedView,
+    OpenedView,
+    CostSummary,
+    CostWorstTile,
+    NewtonNoBuddhabrot,
+    AccumulationNoBatch,
+    IimUnsupported,
+    SixteenBitUnsupported,
+    PresentUnsupported,
+    RandomColors,
+    PaletteSaved,
+    PaletteLoaded,
+    ReadFailed,
+    CreateFailed,
+    TrapUploadFailed,
+    CheckpointWriteFailed,
+    NoCheckpoints,
+    ChooseCheckpoint,
+    ThumbnailsFailed,
+    NoSuchCheckpoint,
+    OrbitPoint,
+    OrbitEscaped,
+    OrbitPeriodic,
+    Attracting,
+    Repelling,
+    Indifferent,
+    OrbitUndetermined,
+    KeyHelp,
+}
+
+fn english(msg: Message) -> &'static str {
+    use Message::*;
+
+    match msg {
+        CurrentState => "Current state",
+        Iterations => "{} Iterations",
+        Range => "Range: ({}) -- ({})",
+        ColorGradient => "Color gradient: {}",
+        ColorMapping => "Color mapping: scale {}, offset {}",
+        ColorMappingEqualized => "Color mapping: scale {}, offset {}, equalized",
+        ColorMappingRepeating => {
+            "Color mapping: scale {}, offset {}, repeating every {} iterations"
+        }
+        ColorBlending => "Gradient blending: {}",
+        ColorCycling => "Color cycling: {}",
+        CyclingSpeed => "{} turns per second, phase {}",
+        Interior => "Interior: {}",
+        ToneAdjustment => "Tone: exposure {} EV, gamma {}, contrast {}, curve {}",
+        InteriorGradient => "from the gradient",
+        InteriorTransparent => "transparent",
+        BlendingRgb => "linear RGB",
+        BlendingOklab => "Oklab",
+        BlendingLch => "Oklab LCh",
+        BlendingHsv => "HSV",
+        BlendingHsvLong => "HSV, long way around",
+        Shading => "Shading: {}",
+        Coloring => "Coloring: {}",
+        ColoringIterations => "iteration count",
+        ColoringDistance => "distance estimate",
+        ColoringStripes => "stripe average, density {}",
+        ColoringBinary => "binary decomposition",
+        ColoringChannels => "per channel, scale {}, offset {}",
+        ColoringCurvature => "curvature average",
+        ShadingLight => "azimuth {}, elevation {}",
+        ShadingLightDistance => "azimuth {}, elevation {}, distance relief",
+        Off => "off",
+        ExportDimensions => "Export dimensions: {}x{}",
+        JuliaFormula => "f(x) = {} + ({})",
+        MandelbrotFormula => "Mandelbrot set of f(x) = {} + c",
+        BurningShipFormula => "Burning Ship of f(x) = {} + c",
+        TricornFormula => "Tricorn of f(x) = {} + c",
+        NewtonPolynomial => "Newton's method for P(x)",
+        NewtonFormula => "Newton's method for f(x) = x^{} - 1",
+        FromZ0 => "{}, from z0 = {}",
+        ParameterPlane => "Parameter plane of f(x) = {} + c, from z0 = {}",
+        DynamicalPlane => "{}, Julia set for c = {}",
+        HybridSteps => "Hybrid: {}",
+        NumeratorCoefficients => "P(x) coefficients: {}",
+        DenominatorCoefficients => "Q(x) coefficients: {}",
+        Exporting => "Exporting to {} ...",
+        ExportingPotential => "Exporting potential to {} ...",
+        Exported => "Exported {}",
+        ExportFailed => "Export failed: {}",
+        PendingExports => "Exports in progress: {} (the first {} rendered)",
+        WaitingForExports => "Waiting for {} exports to finish ...",
+        RenderProgress => "Rendering ... {}",
+        Done => " Done!",
+        RecordingPath => "Recording the path of c (F2 to stop)",
+        PathSaved => "Saved {} points of the path of c to {}",
+        PathWriteFailed => "Failed to write {}: {}",
+        RenderingPath => "Rendering {} frames to {}",
+        RenderingAnimation => "Rendering {} frames into {}",
+        UnsupportedAnimation => {
+            "{} is neither a video nor an animated image; use mp4, m4v, mkv, mov, gif, apng or webp"
+        }
+        SpecReadFailed => "Failed to read the animation spec {}: {}",
+        RenderingVariations => "Rendering {} variations to {}",
+        RenderingSheet => "Rendering a contact sheet of {} views to {}",
+        FarmListening => "Waiting for workers on {} to render {} pieces",
+        FarmWorkerJoined => "Worker {} connected",
+        FarmWorkerLost => "Lost worker {}: {}",
+        FarmImageWritten => "Wrote {}",
+        FarmProgress => "{} of {} pieces done",
+        FarmNoPotential => "Potential exports can't be rendered on a farm",
+        FarmUnsupportedOption => "{} can't be used on a farm: workers render without it",
+        FarmFailed => "Render farm failed: {}",
+        WorkerFinished => "Rendered {} pieces",
+        WorkerFailed => "Lost the coordinator: {}",
+        WatchingFolder => "Watching {} for view files",
+        RenderingView => "Rendering {}",
+        ViewRendered => "Wrote {}",
+        ViewMoveFailed => "Failed to move {}: {}",
+        SavingCapture => "Saving the last {} frames to {} ...",
+        NoCapturedFrames => "No frames captured",
+        CaptureDirFailed => "Failed to create {}: {}",
+        NewtonNoPotential => "Newton fractals have no potential to export",
+        NewtonNoHeatmap => "Heatmaps of Newton fractals aren't supported",
+        HeatmapNoBatch => "Heatmaps can't be batch exported",
+        NewtonNoEscapeTime => "Newton fractals have no escape time to export",
+        NewtonNoContours => "Newton fractals have no escape time to trace contours of",
+        NewtonNoMesh => "Newton fractals have no escape time to make a mesh of",
+        EscapeTimeNoBatch => "Escape times can't be batch exported",
+        StdoutUnsupported => "Only color images can be written to standard output, as ppm or pam",
+        TiledPngOnly => "Tiled exports can only be written as png",
+        ImageTooLarge => {
+            "{}x{} is larger than this GPU can render, at most {} pixels along each side; \
+             export it as png to render it in tiles"
+        }
+        ExportingInTiles => {
+            "{}x{} is larger than this GPU can render, at most {} pixels along each side; \
+             rendering it in tiles"
+        }
+        ManifestReadFailed => "Failed to read the manifest {}: {}",
+        ViewReadFailed => "Failed to read the view embedded in {}: {}",
+        NoEmbeddedView => "{} has no embedded view; only PNGs exported by julia carry one",
+        OpenedView => "Opened the view exported {}",
+        CostSummary => "{} iteration steps per pixel on average",
+        CostWorstTile => "Most expensive: the {}x{} tile at ({}, {}), with {} steps per pixel",
+        NewtonNoBuddhabrot => "Newton fractals have no escaping orbits to accumulate",
+        AccumulationNoBatch => {
+            "Buddhabrots, Nebulabrots and inverse iteration renders can't be batch exported"
+        }
+        IimUnsupported => {
+            "Inverse iteration only draws Julia sets of x^n + c with a whole exponent of at least 2"
+        }
+        SixteenBitUnsupported => "This GPU can't render 16-bit images; exporting with 8 bits",
+        PresentUnsupported => "Warning: queue family {} may not be able to present to the window",
+        RandomColors => "Random gradient from seed {}",
+        PaletteSaved => "Saved the gradient to {}",
+        PaletteLoaded => "Loaded the gradient from {}",
+        ReadFailed => "Failed to read {}: {}",
+        CreateFailed => "Failed to create {}: {}",
+        TrapUploadFailed => "Failed to upload the trap image {}: {}",
+        CheckpointWriteFailed => "Failed to write checkpoint to {}: {}",
+        NoCheckpoints => "No checkpoints in {}",
+        ChooseCheckpoint => "Checkpoint to restore [1]: ",
+        ThumbnailsFailed => "Failed to render thumbnails in {}: {}",
+        NoSuchCheckpoint => "No checkpoint number {}",
+        OrbitPoint => "z{} = {}",
+        OrbitEscaped => "Escaped after {} iterations",
+        OrbitPeriodic => "Periodic with period {} from z{}\nMultiplier: {} (|m| = {}, {})",
+        Attracting => "attracting",
+        Repelling => "repelling",
+        Indifferent => "indifferent",
+        OrbitUndetermined => "No escape or cycle detected",
+        KeyHelp => {
+            "Keys (F1 to close)\n\
+             Tab: show or hide this display\n\
+             Ctrl+Plus, Ctrl+Minus: text size\n\
+             W A S D: pan (Shift: small steps)\n\
+             Plus, Minus: zoom\n\
+             C: recenter    Z: reset zoom\n\
+             Arrows: move c or z0 (Ctrl, Alt, Shift: step size)\n\
+             Right drag: move c or z0 (Shift: finely)\n\
+             F2: start or stop recording the path of c\n\
+             F3: show or hide composition guides    F4: gradient strip\n\
+             F5: color cycling    Alt+Period, Alt+Comma: cycling speed\n\
+             PageUp, PageDown: exponent (Shift: by 0.1)\n\
+             M: next fractal kind    Shift+M: swap z and c planes\n\
+             Shift+C: next built-in gradient    Alt+C: random gradient\n\
+             Ctrl+S: save the gradient    Ctrl+O: load it again\n\
+             [ ]: iterations (Shift: light azimuth)\n\
+             1-9: active gradient stop    Insert, Delete: add or remove a stop\n\
+             R F: hue    T G: saturation    Y H: value    N B: alpha\n\
+             U J: move the active stop\n\
+             Period, Comma: color offset (Shift: stripe density, Ctrl: gradient period)\n\
+             Apostrophe, Semicolon: color scale (Shift: light elevation)\n\
+             Ctrl+H: histogram equalization    Ctrl+P: repeating gradient\n\
+             Ctrl+I: gradient blending space    Ctrl+T: transparent interior\n\
+             Ctrl+' Ctrl+;: exposure    Ctrl+] Ctrl+[: gamma    Alt+' Alt+;: contrast\n\
+             Alt+T: tone curve\n\
+             V: slope shading    Shift+V: next coloring mode    Ctrl+V: shading relief\n\
+             I K: export width    O L: export height\n\
+             E: export image    P: export potential\n\
+             X: save the last few seconds\n\
+             Q, Esc: quit"
+        }
+    }
+}
+
+fn spanish(msg: Message) -> &'static str {
+    use Message::*;
+
+    match msg {
+        CurrentState => "Estado actual",
+        Iterations => "{} iteraciones",
+        Range => "Rango: ({}) -- ({})",
+        ColorGradient => "Degradado de color: {}",
+        ColorMapping => "Mapeo de color: escala {}, desplazamiento {}",
+        ColorMappingEqualized => "Mapeo de color: escala {}, desplazamiento {}, ecualizado",
+        ColorMappingRepeating => {
+            "Mapeo de color: escala {}, desplazamiento {}, repetido cada {} iteraciones"
+        }
+        Shading => "Sombreado: {}",
+        ColorBlending => "Mezcla del degradado: {}",
+        ColorCycling => "Ciclo de colores: {}",
+        CyclingSpeed => "{} vueltas por segundo, fase {}",
+        Interior => "Interior: {}",
+        ToneAdjustment => "Tono: exposición {} EV, gamma {}, contraste {}, curva {}",
+        InteriorGradient => "del degradado",
+        InteriorTransparent => "transparente",
+        BlendingRgb => "RGB lineal",
+        BlendingOklab => "Oklab",
+        BlendingLch => "LCh de Oklab",
+        BlendingHsv => "HSV",
+        BlendingHsvLong => "HSV, por el camino largo",
+        Coloring => "Coloreado: {}",
+        ColoringIterations => "número de iteraciones",
+        ColoringDistance => "estimación de distancia",
+        ColoringStripes => "promedio de franjas, densidad {}",
+        ColoringBinary => "descomposición binaria",
+        ColoringChannels => "por canal, escala {}, desplazamiento {}",
+        ColoringCurvature => "promedio de curvatura",
+        ShadingLight => "acimut {}, elevación {}",
+        ShadingLightDistance => "acimut {}, elevación {}, relieve de distancia",
+        Off => "desactivado",
+        ExportDimensions => "Dimensiones de exportación: {}x{}",
+        JuliaFormula => "f(x) = {} + ({})",
+        MandelbrotFormula => "Conjunto de Mandelbrot de f(x) = {} + c",
+        BurningShipFormula => "Burning Ship de f(x) = {} + c",
+        TricornFormula => "Tricorn de f(x) = {} + c",
+        NewtonPolynomial => "Método de Newton para P(x)",
+        NewtonFormula => "Método de Newton para f(x) = x^{} - 1",
+        FromZ0 => "{}, desde z0 = {}",
+        ParameterPlane => "Plano de parámetros de f(x) = {} + c, desde z0 = {}",
+        DynamicalPlane => "{}, conjunto de Julia para c = {}",
+        HybridSteps => "Híbrido: {}",
+        NumeratorCoefficients => "Coeficientes de P(x): {}",
+        DenominatorCoefficients => "Coeficientes de Q(x): {}",
+        Exporting => "Exportando a {} ...",
+        ExportingPotential => "Exportando el potencial a {} ...",
+        Exported => "Exportado {}",
+        ExportFailed => "La exportación falló: {}",
+        PendingExports => "Exportaciones en curso: {} (la primera {} renderizada)",
+        WaitingForExports => "Esperando a que terminen {} exportaciones ...",
+        RenderProgress => "Renderizando ... {}",
+        Done => " ¡Listo!",
+        RecordingPath => "Grabando el recorrido de c (F2 para parar)",
+        PathSaved => "Se guardaron {} puntos del recorrido de c en {}",
+        PathWriteFailed => "No se pudo escribir {}: {}",
+        RenderingPath => "Generando {} fotogramas en {}",
+        RenderingAnimation => "Codificando {} fotogramas en {}",
+        UnsupportedAnimation => {
+            "{} no es ni un vídeo ni una imagen animada; use mp4, m4v, mkv, mov, gif, apng o webp"
+        }
+        SpecReadFailed => "No se pudo leer la especificación de animación {}: {}",
+        RenderingVariations => "Generando {} variaciones en {}",
+        RenderingSheet => "Generando una hoja de contactos de {} vistas en {}",
+        FarmListening => "Esperando trabajadores en {} para generar {} piezas",
+        FarmWorkerJoined => "Trabajador {} conectado",
+        FarmWorkerLost => "Se perdió el trabajador {}: {}",
+        FarmImageWritten => "Escrito {}",
+        FarmProgress => "{} de {} piezas hechas",
+        FarmNoPotential => "Las exportaciones de potencial no se pueden generar en una granja",
+        FarmUnsupportedOption => {
+            "{} no se puede usar en una granja: los trabajadores generan sin él"
+        }
+        FarmFailed => "Falló la granja de render: {}",
+        WorkerFinished => "Generadas {} piezas",
+        WorkerFailed => "Se perdió el coordinador: {}",
+        WatchingFolder => "Vigilando {} en busca de archivos de vista",
+        RenderingView => "Generando {}",
+        ViewRendered => "Escrito {}",
+        ViewMoveFailed => "No se pudo mover {}: {}",
+        SavingCapture => "Guardando los últimos {} fotogramas en {} ...",
+        NoCapturedFrames => "No hay fotogramas capturados",
+        CaptureDirFailed => "No se pudo crear {}: {}",
+        NewtonNoPotential => "Los fractales de Newton no tienen potencial que exportar",
+        NewtonNoHeatmap => "No se admiten mapas de calor de fractales de Newton",
+        HeatmapNoBatch => "Los mapas de calor no se pueden exportar por lotes",
+        NewtonNoEscapeTime => "Los fractales de Newton no tienen tiempo de escape que exportar",
+        NewtonNoContours => {
+            "Los fractales de Newton no tienen tiempo de escape del que trazar contornos"
+        }
+        NewtonNoMesh => {
+            "Los fractales de Newton no tienen tiempo de escape del que hacer una malla"
+        }
+        EscapeTimeNoBatch => "Los tiempos de escape no se pueden exportar por lotes",
+        StdoutUnsupported => {
+            "Solo las imágenes en color se pueden escribir en la salida estándar, como ppm o pam"
+        }
+        TiledPngOnly => "Las exportaciones por bloques solo se pueden escribir como png",
+        ImageTooLarge => {
+            "{}x{} supera lo que esta GPU puede renderizar, como mucho {} píxeles por lado; \
+             expórtela como png para renderizarla por bloques"
+        }
+        ExportingInTiles => {
+            "{}x{} supera lo que esta GPU puede renderizar, como mucho {} píxeles por lado; \
+             se renderizará por bloques"
+        }
+        ManifestReadFailed => "No se pudo leer el manifiesto {}: {}",
+        ViewReadFailed => "No se pudo leer la vista incrustada en {}: {}",
+        NoEmbeddedView => {
+            "{} no tiene ninguna vista incrustada; solo la llevan los png exportados por julia"
+        }
+        OpenedView => "Se abrió la vista exportada el {}",
+        CostSummary => "{} pasos de iteración por píxel de media",
+        CostWorstTile => "El más costoso: el bloque de {}x{} en ({}, {}), con {} pasos por píxel",
+        NewtonNoBuddhabrot => "Los fractales de Newton no tienen órbitas que escapen",
+        AccumulationNoBatch => {
+            "Los Buddhabrot, Nebulabrot y dibujos por iteración inversa no se pueden exportar por \
+             lotes"
+        }
+        IimUnsupported => {
+            "La iteración inversa solo dibuja conjuntos de Julia de x^n + c con un exponente \
+             entero de al menos 2"
+        }
+        SixteenBitUnsupported => {
+            "Esta GPU no puede generar imágenes de 16 bits; se exportará con 8 bits"
+        }
+        PresentUnsupported => {
+            "Advertencia: puede que la familia de colas {} no pueda presentar en la ventana"
+        }
+        RandomColors => "Degradado aleatorio de la semilla {}",
+        PaletteSaved => "Se guardó el degradado en {}",
+        PaletteLoaded => "Se cargó el degradado de {}",
+        ReadFailed => "No se pudo leer {}: {}",
+        CreateFailed => "No se pudo crear {}: {}",
+        TrapUploadFailed => "No se pudo cargar en la GPU la imagen de trampa {}: {}",
+        CheckpointWriteFailed => "No se pudo escribir el punto de control en {}: {}",
+        NoCheckpoints => "No hay puntos de control en {}",
+        ChooseCheckpoint => "Punto de control a restaurar [1]: ",
+        ThumbnailsFailed => "No se pudieron generar las miniaturas en {}: {}",
+        NoSuchCheckpoint => "No existe el punto de control número {}",
+        OrbitPoint => "z{} = {}",
+        OrbitEscaped => "Escapó tras {} iteraciones",
+        OrbitPeriodic => "Periódica de periodo {} desde z{}\nMultiplicador: {} (|m| = {}, {})",
+        Attracting => "atractora",
+        Repelling => "repulsora",
+        Indifferent => "indiferente",
+        OrbitUndetermined => "No se detectó escape ni ciclo",
+        KeyHelp => {
+            "Teclas (F1 para cerrar)\n\
+             Tab: mostrar u ocultar esta pantalla\n\
+             Ctrl+Más, Ctrl+Menos: tamaño del texto\n\
+             W A S D: desplazar (Mayús: pasos pequeños)\n\
+             Más, Menos: zoom\n\
+             C: centrar    Z: restablecer zoom\n\
+             Flechas: mover c o z0 (Ctrl, Alt, Mayús: tamaño del paso)\n\
+             Arrastre derecho: mover c o z0 (Mayús: con precisión)\n\
+             F2: empezar o parar la grabación del recorrido de c\n\
+             F3: mostrar u ocultar las guías de composición    F4: tira del degradado\n\
+             F5: ciclo de colores    Alt+Punto, Alt+Coma: velocidad del ciclo\n\
+             RePág, AvPág: exponente (Mayús: de 0.1 en 0.1)\n\
+             M: siguiente tipo de fractal    Mayús+M: cambiar plano z / c\n\
+             Mayús+C: siguiente degradado incorporado    Alt+C: degradado aleatorio\n\
+             Ctrl+S: guardar el degradado    Ctrl+O: volver a cargarlo\n\
+             [ ]: iteraciones (Mayús: acimut de la luz)\n\
+             1-9: parada del degradado activa    Insertar, Suprimir: añadir o quitar una parada\n\
+             R F: tono    T G: saturación    Y H: valor    N B: alfa\n\
+             U J: mover la parada activa\n\
+             Punto, Coma: desplazamiento de color (Mayús: densidad de franjas, Ctrl: periodo del degradado)\n\
+             Apóstrofo, Punto y coma: escala de color (Mayús: elevación de la luz)\n\
+             Ctrl+H: ecualización del histograma    Ctrl+P: degradado repetido\n\
+             Ctrl+I: espacio de mezcla del degradado    Ctrl+T: interior transparente\n\
+             Ctrl+' Ctrl+;: exposición    Ctrl+] Ctrl+[: gamma    Alt+' Alt+;: contraste\n\
+             Alt+T: curva de tono\n\
+             V: sombreado    Mayús+V: siguiente modo de coloreado    Ctrl+V: relieve del sombreado\n\
+             I K: ancho de exportación    O L: alto de exportación\n\
+             E: exportar imagen    P: exportar potencial\n\
+             X: guardar los últimos segundos\n\
+             Q, Esc: salir"
+        }
+    }
+}
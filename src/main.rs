@@ -1,21 +1,55 @@
-use julia::interface::JuliaInterface;
-use julia::{ImgDimensions, JuliaContext, JuliaData};
+use julia::animated::{self, AnimatedFormat};
+use julia::animation::{
+    Animation, CircleOrbit, Easing, FramePattern, FrameRange, FrameSequence, Zoom,
+};
+use julia::checkpoint::{Checkpoint, CheckpointLog};
+use julia::contact_sheet::{self, ContactSheet, Sweep};
+use julia::dzi::DeepZoom;
+use julia::embedded;
+use julia::farm::{self, Coordinator};
+use julia::history::{HistoryOptions, ViewHistory};
+use julia::iim;
+use julia::interface::{self, Guides, JuliaInterface, WindowOptions};
+use julia::locale::{self, tr, Language, Message};
+use julia::name_template::NameTemplate;
+use julia::orbit::{self, Orbit, OrbitOutcome};
+use julia::palette_file::{self, Palette};
+use julia::path::CPath;
+use julia::random_palette;
+use julia::spec::AnimationSpec;
+use julia::thumbnail::ThumbnailCache;
+use julia::tiled::TiledExport;
+use julia::variations::{self, VariationOptions};
+use julia::video::{self, VideoOptions};
+use julia::viewfile::{self, ViewFormat};
+use julia::{
+    gradient, BitDepth, BuddhabrotOptions, Channels, ColorSpace, ColoringMode, EscapeValue,
+    ExportJob, FractalKind, Gradient, Hybrid, IccProfile, ImgDimensions, Interpolation,
+    JuliaContext, JuliaData, JuliaExportError, Light, MeshOptions, OrbitTrap, OutputFormat,
+    Overlay, OverlayPosition, OverwritePolicy, PaperSize, Plane, Polynomial, PrintSettings,
+    Progress, Relief, Tone, ToneCurve, Transfer, TransferStages, MIN_EXPONENT,
+};
 
 #[macro_use]
 extern crate gramit;
-use gramit::{Vec2, Vec4};
+use gramit::{Vec2, Vec3, Vec4};
 
+use image::RgbaImage;
 use structopt::StructOpt;
 
-use palette::named;
-use palette::Srgb;
-
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
-use std::path::PathBuf;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
 
-#[derive(Debug, StructOpt)]
+#[derive(Debug, Clone, StructOpt)]
 #[structopt(name = "julia", about = "A generator of Julia sets")]
 /// Create images of Julia sets.
 ///
@@ -23,11 +57,32 @@ use std::str::FromStr;
 ///
 ///     f(x) = x^n + c
 ///
-/// where `n` is an integer and `c` is a complex number `c_r + c_i * i`.
+/// where `n` is a real number and `c` is a complex number `c_r + c_i * i`.
 struct JuliaArgs {
-    /// The exponent n.
-    #[structopt(short = "n", long = "exponent", default_value = "2")]
-    n: u32,
+    /// The fractal to render: julia, mandelbrot, burning-ship, tricorn or newton. Mandelbrot,
+    /// burning-ship and tricorn iterate from 0 over values of `c`, and newton has no `c`, so
+    /// `-r`/`-i` only apply to julia. Defaults to julia.
+    #[structopt(long)]
+    fractal: Option<FractalKind>,
+
+    /// Render the Mandelbrot set of x^n + c instead of a Julia set. Each point of the image is then
+    /// a value of `c`, and `-r`/`-i` are ignored. Short for `--fractal mandelbrot`.
+    #[structopt(long, conflicts_with_all = &["fractal", "burning_ship"])]
+    mandelbrot: bool,
+
+    /// Render the Burning Ship, which iterates (|Re x| + i|Im x|)^n + c from 0 like the Mandelbrot
+    /// set. Short for `--fractal burning-ship`.
+    #[structopt(long, conflicts_with = "fractal")]
+    burning_ship: bool,
+
+    /// The exponent n, greater than 1. May be fractional, e.g. 2.5.
+    #[structopt(
+        short = "n",
+        long = "exponent",
+        default_value = "2",
+        parse(try_from_str = parse_exponent)
+    )]
+    n: f32,
 
     /// The real part of the complex number `c`.
     #[structopt(short = "r", long = "real-part", default_value = "0.0")]
@@ -37,6 +92,37 @@ struct JuliaArgs {
     #[structopt(short = "i", long = "imaginary-part", default_value = "0.0")]
     ci: f32,
 
+    /// The starting point of orbits in the parameter plane, given as two comma-separated decimal
+    /// values. Moving it away from 0 gives perturbed versions of the mandelbrot, burning-ship and
+    /// tricorn sets.
+    #[structopt(long, parse(try_from_str = parse_vec2), default_value = "0.0,0.0")]
+    z0: Vec2,
+
+    /// What the pixels stand for, in place of the fractal's own choice: `z` makes them starting
+    /// points iterated with the fixed `c`, as for julia, and `c` makes them values of `c` iterated
+    /// from --z0, as for mandelbrot. `--fractal burning-ship --plane z` renders Burning Ship Julia
+    /// sets, for instance. Ignored for newton.
+    #[structopt(long)]
+    plane: Option<Plane>,
+
+    /// A polynomial to iterate in place of x^n, so that the formula becomes p(x) + c. Given as
+    /// comma-separated complex coefficients, highest degree first, e.g. "1,0,-0.5,0.2i" for
+    /// x^3 - 0.5x + 0.2i. Supports degrees up to 8.
+    #[structopt(long)]
+    poly: Option<Polynomial>,
+
+    /// Cycle through the folds of several kinds, one per iteration step, e.g.
+    /// "mandelbrot*2,burning-ship" for two steps of x^n + c and then one Burning Ship step. Up to
+    /// 16 steps of mandelbrot, burning-ship and tricorn. Whether pixels are starting points or
+    /// values of c still depends on --fractal.
+    #[structopt(long)]
+    hybrid: Option<Hybrid>,
+
+    /// A denominator polynomial Q, in the same form as --poly, to iterate the rational map
+    /// P(x)/Q(x) + c, where P is the --poly polynomial or x^n. Ignored for newton.
+    #[structopt(long)]
+    denom: Option<Polynomial>,
+
     /// The number of iterations to compute.
     #[structopt(short = "m", long = "iters", default_value = "100")]
     iters: u32,
@@ -49,16 +135,136 @@ struct JuliaArgs {
     #[structopt(short, long, default_value = "800")]
     height: u32,
 
-    /// The color gradient. Consists of at least two and at most three comma-separated color names,
-    /// and an optional comma-separated value between 0 and 1. Valid color names are those from the
-    /// CSS3 specification.
-    #[structopt(short, long, parse(try_from_str = parse_gradient),
-        default_value = "black,white")]
-    colors: [Vec4; 3],
+    /// The color gradient, as between 2 and 16 comma-separated stops. Each is a color, either a
+    /// name from the CSS3 specification or a hex code `#rrggbb`, or `#rrggbbaa` to give it an
+    /// alpha value, optionally followed by `@` and its position. Stops without a position are
+    /// spread evenly between their neighbors, and the first and last default to 0 and 1. It may
+    /// also be `expr:` and expressions in `t` for red, green, blue and optionally alpha.
+    #[structopt(short, long, default_value = "black@0,white@0.5")]
+    colors: Gradient,
+
+    /// A file to take the color gradient from instead of `--colors`: a palette file (`.toml` or
+    /// `.json`) as saved with Ctrl+S, or a GIMP gradient (`.ggr`), whose segments that don't blend
+    /// linearly in RGB are approximated. An interpolation given in a palette file takes the place
+    /// of `--interpolation`.
+    #[structopt(long, parse(try_from_str = load_palette_file))]
+    palette_file: Option<Palette>,
+
+    /// The palette file that Ctrl+S saves the gradient to and Ctrl+O loads it from, as JSON if its
+    /// name ends in `.json` and as TOML otherwise.
+    #[structopt(long, parse(from_os_str), default_value = "palette.toml")]
+    palette_path: PathBuf,
+
+    /// Use a random gradient instead of `--colors`, made from the given seed, or from the clock if
+    /// none is given. The seed is printed so that the gradient can be made again.
+    #[structopt(long)]
+    random_colors: Option<Option<u32>>,
+
+    /// The space the gradient's colors are blended in between stops: `rgb`, linear RGB, `oklab`,
+    /// the perceptual Oklab space, `lch`, its polar form, which keeps blends between saturated
+    /// hues saturated, or `hsv` and `hsv-long`, which turn the hue of the sRGB colors the short or
+    /// the long way around.
+    #[structopt(long, default_value = "rgb")]
+    interpolation: Interpolation,
+
+    /// The color of points that never escape, or never reach a root for newton: a CSS3 color name,
+    /// a hex code `#rrggbb` or `#rrggbbaa`, or `transparent` to leave the set out of exported
+    /// images. By default they take the end of the gradient, or its start for newton.
+    #[structopt(long, parse(try_from_str = gradient::parse_color))]
+    interior_color: Option<Vec4>,
+
+    /// Brightens the colored image by this many stops, each doubling its linear intensity.
+    /// Applied after the gradient and shading; Buddhabrots use `--exposure` instead.
+    #[structopt(long, default_value = "0.0")]
+    tone_exposure: f32,
+
+    /// Raises the colored image's linear intensities to the reciprocal of this. Values above 1
+    /// lift the midtones, and values below 1 deepen them.
+    #[structopt(long, default_value = "1.0")]
+    tone_gamma: f32,
 
-    #[structopt(short = "g", long, parse(try_from_str = parse_midpts),
-        default_value = "0.5")]
-    midpts: [f32; 3],
+    /// Spreads the colored image's intensities away from middle gray for values above 1, and draws
+    /// them towards it for values below.
+    #[structopt(long, default_value = "1.0")]
+    tone_contrast: f32,
+
+    /// How the colored image's intensities are brought into the displayable range: clip, to cut
+    /// off everything brighter than white, reinhard, to compress them without ever clipping, or
+    /// aces, for a filmic curve with soft highlights.
+    #[structopt(long, default_value = "clip")]
+    tone_curve: ToneCurve,
+
+    /// Dither the colors with an ordered pattern before they're stored at 8 bits per channel,
+    /// which breaks up the bands of smooth gradients.
+    #[structopt(long)]
+    dither: bool,
+
+    /// Multiplies the normalized iteration counts of escaping points before they are mapped into
+    /// the gradient.
+    #[structopt(long, default_value = "1.0")]
+    color_scale: f32,
+
+    /// Added to the normalized iteration counts of escaping points, after scaling, before they are
+    /// mapped into the gradient.
+    #[structopt(long, default_value = "0.0")]
+    color_offset: f32,
+
+    /// What escaping points are colored by: `iterations`, the smooth iteration count,
+    /// `distance`, the estimated distance to the set, `stripes`, the stripe average, `binary`,
+    /// the iteration count with the gradient mirrored below the real axis, `channels`, red, green
+    /// and blue from three measures of the escape in place of the gradient, or `curvature`, the
+    /// average angle the orbit turns through at each iterate. With `distance`, points
+    /// on the boundary take the end of the gradient and fade to its start over `--color-scale`
+    /// pixels, which keeps thin filaments crisp at any iteration count. Ignored for newton.
+    #[structopt(long, default_value = "iterations")]
+    coloring: ColoringMode,
+
+    /// The number of stripes per turn for `--coloring stripes`. Whole numbers avoid a seam along
+    /// the negative real axis.
+    #[structopt(long, default_value = "5")]
+    stripe_density: f32,
+
+    /// Multiplies the red, green and blue values of `--coloring channels`, given as three
+    /// comma-separated numbers. The values are the iteration count as a fraction of `--iters`, the
+    /// fractional escape radius and the angle of the last iterate, each between 0 and 1.
+    #[structopt(long, parse(try_from_str = parse_vec3), default_value = "1,1,1")]
+    channel_scale: Vec3,
+
+    /// Added to the red, green and blue values of `--coloring channels` after scaling, given as
+    /// three comma-separated numbers.
+    #[structopt(long, parse(try_from_str = parse_vec3), default_value = "0,0,0")]
+    channel_offset: Vec3,
+
+    /// Spread the gradient evenly over the escaping pixels, by the fraction of them that escape
+    /// sooner, instead of linearly over the iteration count. Keeps deep zooms from coming out one
+    /// color. Only for `--coloring iterations` and `binary`.
+    #[structopt(long)]
+    equalize: bool,
+
+    /// Repeat the gradient every this many iterations instead of stretching it once over
+    /// `--iters`. Keeps deep zooms, which escape over a huge range of iterations, in narrow bands.
+    /// Takes the place of `--equalize`. Only for `--coloring iterations` and `binary`.
+    #[structopt(long)]
+    color_period: Option<f32>,
+
+    /// Turn the gradient by this fraction of its span, wrapping the colors that pass its end
+    /// around to its start, as color cycling in the interactive interface does. From 0 to 1.
+    #[structopt(long, default_value = "0")]
+    color_phase: f32,
+
+    /// Light the image as if it were a height field, for an embossed look. Ignored for newton.
+    #[structopt(long)]
+    shading: bool,
+
+    /// The direction of the light for --shading, given as comma-separated azimuth and elevation in
+    /// degrees. The azimuth is counterclockwise from the positive real axis.
+    #[structopt(long, parse(try_from_str = parse_vec2), default_value = "45.0,45.0")]
+    light: Vec2,
+
+    /// The height field lit by --shading: `iterations`, the value being colored, or `distance`,
+    /// the distance estimate, which raises the set as a ridge and embosses its boundary evenly.
+    #[structopt(long, default_value = "iterations")]
+    relief: Relief,
 
     /// The complex number at the center of the image, given as two comma-separated decimal values.
     #[structopt(short = "O", long, parse(try_from_str = parse_vec2),
@@ -69,212 +275,2574 @@ struct JuliaArgs {
     #[structopt(short, long, default_value = "3.6")]
     extent: f32,
 
-    /// The name of the output image.
+    /// The angle in degrees to turn the view counterclockwise by, about its center.
+    #[structopt(long, default_value = "0")]
+    rotation: f32,
+
+    /// Export an image and exit instead of starting the interactive viewer. The file name is
+    /// generated from the parameters if not given. `-` writes a PAM stream to standard output, or
+    /// PPM with `--format ppm`.
     #[structopt(short = "o", long = "output")]
-    file: Option<PathBuf>,
+    file: Option<Option<PathBuf>>,
+
+    /// A template for generated file names, such as `julia_{n}_{cr}{ci}i_{width}x{height}_{date}`,
+    /// in place of a name made from every parameter. Names exports from the interactive viewer
+    /// too. See the README for its placeholders.
+    #[structopt(long)]
+    name_template: Option<NameTemplate>,
+
+    /// Render with strict shader math, so that the same parameters always give a bit-identical image
+    /// on the same device and driver. Slightly slower than the default.
+    #[structopt(long)]
+    strict: bool,
+
+    /// Iterate every interior point up to the iteration limit, instead of stopping once its orbit
+    /// is found to be periodic.
+    #[structopt(long)]
+    no_periodicity: bool,
+
+    /// Iterate the points of the Mandelbrot set's main cardioid and period-2 bulb like any other,
+    /// instead of recognizing them as inside the set up front.
+    #[structopt(long)]
+    no_interior_checks: bool,
+
+    /// Export the continuous potential of the set as a grayscale image rather than a colored one.
+    /// Produces a 16-bit PNG, or raw 32-bit floats if the output name ends in `.pfm`.
+    #[structopt(long)]
+    potential: bool,
+
+    /// Export a Buddhabrot: color each pixel by how many escaping orbits of randomly sampled points
+    /// pass through it, rather than by how quickly it escapes.
+    #[structopt(long, conflicts_with = "potential")]
+    buddhabrot: bool,
+
+    /// Export a Nebulabrot: three Buddhabrots in the red, green and blue channels, made with the
+    /// given iteration limits. Given as three comma-separated counts.
+    #[structopt(long, conflicts_with = "potential", parse(try_from_str = parse_iter_limits))]
+    nebulabrot: Option<[u32; 3]>,
+
+    /// Export a Julia set drawn by the inverse iteration method: random walks backwards from a
+    /// point of the set, plotting every point reached. Only for Julia sets of `x^n + c` with a
+    /// whole exponent. `--samples`, `--exposure`, `--gamma` and `--seed` apply as for Buddhabrots.
+    #[structopt(long, conflicts_with_all = &["potential", "buddhabrot", "nebulabrot"])]
+    iim: bool,
+
+    /// Export a heatmap of the iteration steps spent on each pixel instead of the image, from dark
+    /// purple for none to yellow for the iteration limit, and report the most expensive tile. Shows
+    /// why a view is slow and where the time goes. Not for newton.
+    #[structopt(long, conflicts_with_all = &["potential", "buddhabrot", "nebulabrot", "iim"])]
+    heatmap: bool,
+
+    /// Export the escape time of every pixel as raw numbers instead of the image: `count` for the
+    /// number of iterations, or `smooth` for the smooth iteration count. Written as a NumPy `.npy`
+    /// array, or as CSV if the output name ends in `.csv`. Not for newton.
+    #[structopt(
+        long,
+        conflicts_with_all = &["potential", "buddhabrot", "nebulabrot", "iim", "heatmap"]
+    )]
+    escape_time: Option<EscapeValue>,
+
+    /// Render the image in square tiles of this many pixels, a multiple of 8, one at a time, for
+    /// images too large for the GPU to render at once. The tiles are kept next to the output
+    /// until they are stitched into it. Only for color PNGs.
+    #[structopt(
+        long,
+        conflicts_with_all = &[
+            "potential", "buddhabrot", "nebulabrot", "iim", "heatmap", "escape_time"
+        ]
+    )]
+    tiled: Option<u32>,
+
+    /// Export the image as a Deep Zoom pyramid for viewers such as OpenSeadragon, in square tiles
+    /// of the given number of pixels, a multiple of 8, or 256 if none is given. The output name
+    /// has `.dzi` in place of its extension, and the tiles are written next to it. Only for color
+    /// images.
+    #[structopt(
+        long,
+        conflicts_with_all = &[
+            "potential", "buddhabrot", "nebulabrot", "iim", "heatmap", "escape_time", "tiled",
+            "resume", "sizes", "palette_set"
+        ]
+    )]
+    dzi: Option<Option<u32>>,
+
+    /// Export vector contours of the escape time at each of these comma-separated thresholds, in
+    /// iterations, as an SVG for laser cutters and plotters. A threshold at the iteration limit
+    /// traces the boundary of the set. The output name has `.svg` in place of its extension.
+    #[structopt(
+        long,
+        use_delimiter = true,
+        conflicts_with_all = &[
+            "potential", "buddhabrot", "nebulabrot", "iim", "heatmap", "escape_time", "tiled",
+            "dzi", "resume", "sizes", "palette_set"
+        ]
+    )]
+    contours: Vec<f32>,
+
+    /// Export the smooth escape time as a heightmap mesh for 3D printing, closed into a solid with
+    /// walls and a floor: binary STL if the output name ends in `.stl`, and OBJ otherwise, with
+    /// `.obj` in place of any other extension.
+    #[structopt(
+        long,
+        conflicts_with_all = &[
+            "potential", "buddhabrot", "nebulabrot", "iim", "heatmap", "escape_time", "tiled",
+            "dzi", "contours", "resume", "sizes", "palette_set"
+        ]
+    )]
+    mesh: bool,
+
+    /// The height of a mesh's relief, as a fraction of the image's width.
+    #[structopt(long, default_value = "0.1")]
+    mesh_scale: f32,
+
+    /// The distance in pixels between the vertices of a mesh. Larger steps make coarser meshes
+    /// with fewer triangles.
+    #[structopt(long, default_value = "1")]
+    mesh_step: u32,
+
+    /// Export the view at each of these sizes in one run, given as comma-separated
+    /// `WIDTHxHEIGHT` pairs, such as `1920x1080,3840x2160,800x800`. Each is named as it would be
+    /// with `--width` and `--height` set to it; an output name given with `--output` has the size
+    /// added before its extension.
+    #[structopt(
+        long,
+        use_delimiter = true,
+        parse(try_from_str = parse_size),
+        conflicts_with_all = &[
+            "buddhabrot", "nebulabrot", "iim", "heatmap", "escape_time", "tiled", "resume", "dpi",
+            "paper"
+        ]
+    )]
+    sizes: Vec<(u32, u32)>,
+
+    /// Export the view once per palette of a palette set: a TOML file of palettes, each a table
+    /// under its name, as in `[fire]` followed by its `colors`, or a `.json` object of them, in
+    /// order of name. Each export has the palette's name
+    /// added before its extension. The fractal is computed only once, and recolored for each
+    /// palette.
+    #[structopt(
+        long,
+        parse(from_os_str),
+        conflicts_with_all = &[
+            "potential", "buddhabrot", "nebulabrot", "iim", "heatmap", "escape_time", "tiled",
+            "resume", "sizes", "palette_file", "random_colors"
+        ]
+    )]
+    palette_set: Option<PathBuf>,
+
+    /// Resume a tiled export that was interrupted, from the manifest in its tile directory,
+    /// rendering only the tiles that are missing. The view and tile size are read from the
+    /// manifest; a trap image has to be given again.
+    #[structopt(long, parse(from_os_str))]
+    resume: Option<PathBuf>,
+
+    /// The number of orbits sampled per pixel of a Buddhabrot, or points plotted per pixel by
+    /// inverse iteration.
+    #[structopt(long, default_value = "50")]
+    samples: u32,
+
+    /// Multiplies the normalized hit counts of a Buddhabrot before they're mapped into the gradient.
+    #[structopt(long, default_value = "1.0")]
+    exposure: f32,
+
+    /// The exponent applied to the normalized hit counts of a Buddhabrot. Lower values bring out
+    /// rarely visited pixels.
+    #[structopt(long, default_value = "0.5")]
+    gamma: f32,
+
+    /// Seeds the random sampling of a Buddhabrot.
+    #[structopt(long, default_value = "0")]
+    seed: u32,
+
+    /// An image to stamp onto the fractal as an orbit trap. Each point takes the color of the
+    /// image where its orbit first lands on an opaque part of it.
+    #[structopt(long, parse(from_os_str))]
+    trap_image: Option<PathBuf>,
+
+    /// The point the trap image is centered on, given as two comma-separated decimal values.
+    #[structopt(long, parse(try_from_str = parse_vec2), default_value = "0.0,0.0")]
+    trap_center: Vec2,
+
+    /// The width of the trap image in the complex plane. Its height follows its aspect ratio.
+    #[structopt(long, default_value = "1.0")]
+    trap_width: f32,
+
+    /// An ICC profile to embed in exported PNGs instead of the default sRGB profile.
+    #[structopt(long, parse(from_os_str))]
+    icc_profile: Option<PathBuf>,
+
+    /// Don't embed an ICC profile in exported PNGs.
+    #[structopt(long)]
+    no_icc: bool,
+
+    /// Export color images with 16 bits per channel. PNGs, TIFFs, PPMs and PAMs keep all 16 bits;
+    /// other formats are reduced to 8 bits after rendering.
+    #[structopt(long)]
+    sixteen_bit: bool,
+
+    /// The color space of exported images, srgb or p3 (Display P3). Gradients are blended in its
+    /// gamut, and PNGs are tagged with it.
+    #[structopt(long, default_value = "srgb")]
+    colorspace: ColorSpace,
+
+    /// The transfer function the GPU encodes exported colors with, linear or srgb. With linear,
+    /// colors are encoded for the file after they're read back, as they used to be.
+    #[structopt(long, default_value = "srgb")]
+    render_transfer: Transfer,
+
+    /// The transfer function of exported files, linear or srgb. Linear PNGs are tagged as such.
+    #[structopt(long, default_value = "srgb")]
+    file_transfer: Transfer,
+
+    /// The format of exported images: png, jpeg, tiff, bmp, webp, avif, ppm or pam. Chosen by the
+    /// extension of the output file name if not given; generated names take its extension.
+    #[structopt(long)]
+    format: Option<OutputFormat>,
+
+    /// The quality of JPEG exports, from 1 to 100.
+    #[structopt(long, default_value = "90")]
+    jpeg_quality: u8,
+
+    /// Never replace existing files when exporting: number the new ones instead, as
+    /// `julia_001.png`, `julia_002.png` and so on.
+    #[structopt(long)]
+    no_overwrite: bool,
+
+    /// Write a JSON file holding the dimensions and parameters of every exported image next to
+    /// it, named after it with `.json` appended.
+    #[structopt(long)]
+    sidecar: bool,
+
+    /// A caption to stamp onto exported color images, with the placeholders of
+    /// `--name-template`, such as `z^{n} + {cr}{ci}i`. It is drawn in white on a dark box.
+    #[structopt(long, parse(try_from_str = NameTemplate::parse_caption))]
+    caption: Option<NameTemplate>,
+
+    /// An image to stamp onto exported color images as a watermark, at its own size, above the
+    /// caption if there is one.
+    #[structopt(long, parse(try_from_str = Overlay::open_watermark))]
+    watermark: Option<Arc<RgbaImage>>,
+
+    /// Where the caption and watermark are placed: top-left, top-right, bottom-left,
+    /// bottom-right or center.
+    #[structopt(long, default_value = "bottom-right")]
+    overlay_position: OverlayPosition,
+
+    /// How opaque the caption and watermark are, from 0 to 1.
+    #[structopt(long, default_value = "1")]
+    overlay_opacity: f32,
+
+    /// How many times the caption's 5x7 pixel font is magnified, up to 8. By default it grows by
+    /// one step for every 400 pixels of the image's height.
+    #[structopt(long)]
+    caption_size: Option<u32>,
+
+    /// The quality of WebP exports, from 0 to 100.
+    #[cfg(feature = "webp")]
+    #[structopt(long, default_value = "80")]
+    webp_quality: f32,
+
+    /// The quality of AVIF exports, from 1 to 100.
+    #[cfg(feature = "avif")]
+    #[structopt(long, default_value = "80")]
+    avif_quality: f32,
+
+    /// The encoder speed for AVIF exports, from 1 (slow, smallest files) to 10 (fast).
+    #[cfg(feature = "avif")]
+    #[structopt(long, default_value = "4")]
+    avif_speed: u8,
+
+    /// How often, in minutes, the interactive viewer saves its state to the checkpoint file. 0
+    /// disables checkpoints.
+    #[structopt(long, default_value = "2")]
+    checkpoint_interval: f64,
+
+    /// The file checkpoints are appended to. Defaults to `julia/checkpoints` in the user's data
+    /// directory.
+    #[structopt(long, parse(from_os_str))]
+    checkpoint_file: Option<PathBuf>,
+
+    /// Start the interactive viewer from a saved checkpoint instead of the given parameters. With
+    /// no value, lists the most recent checkpoints and asks which one to load; otherwise loads the
+    /// given one, counting from 1 for the most recent.
+    #[structopt(long)]
+    restore_checkpoint: Option<Option<usize>>,
+
+    /// Don't render thumbnails of the checkpoints listed by --restore-checkpoint.
+    #[structopt(long)]
+    no_thumbnails: bool,
+
+    /// How many seconds of the interactive view to keep for saving with X. 0 disables frame
+    /// capture.
+    #[structopt(long, default_value = "10")]
+    capture_seconds: f32,
+
+    /// How many frames per second of the interactive view to keep.
+    #[structopt(long, default_value = "15")]
+    capture_fps: u32,
+
+    /// Frames saved with X are this many times smaller than the window in each dimension.
+    #[structopt(long, default_value = "2")]
+    capture_scale: u32,
+
+    /// The monitor to open the interactive viewer on, counting from 0 in the order listed by
+    /// `--list-monitors`. Defaults to the primary monitor.
+    #[structopt(long)]
+    monitor: Option<usize>,
+
+    /// The initial size of the interactive window in logical pixels, given as `WIDTHxHEIGHT`.
+    /// Defaults to a square of 80% of the monitor's smaller dimension.
+    #[structopt(long, parse(try_from_str = parse_size))]
+    window_size: Option<(u32, u32)>,
+
+    /// Open the interactive viewer fullscreen.
+    #[structopt(long)]
+    fullscreen: bool,
+
+    /// Keep the interactive window above all others.
+    #[structopt(long)]
+    always_on_top: bool,
+
+    /// Show the heads-up display over the interactive view from the start. Tab toggles it.
+    #[structopt(long)]
+    hud: bool,
+
+    /// The magnification of the heads-up display's text, from 1 to 8.
+    #[structopt(long, default_value = "2")]
+    hud_scale: u32,
+
+    /// Show composition guides over the interactive view from the start, given as a
+    /// comma-separated list of `thirds`, `center` and `margins`. The outline of the export is
+    /// drawn whenever the guides are shown and its shape differs from the window's. F3 toggles
+    /// them, showing all three if this isn't given.
+    #[structopt(long, parse(try_from_str = parse_guides))]
+    guides: Option<Guides>,
+
+    /// The print resolution in dots per inch, recorded in exported PNGs. 300 if only `--paper` is
+    /// given.
+    #[structopt(long)]
+    dpi: Option<f32>,
+
+    /// The paper the export is to be printed on: a3, a4, a5, letter, legal or tabloid, optionally
+    /// followed by `-landscape`, or a size such as `200x300mm` or `8x10in`. Sets the export size
+    /// to cover the paper and its bleed at the print resolution, overriding `-w` and `-h`.
+    #[structopt(long)]
+    paper: Option<PaperSize>,
+
+    /// How far the printed image extends past the edges of the paper, in millimeters.
+    #[structopt(long, default_value = "3")]
+    bleed: f32,
+
+    /// How far inside the edges of the paper important content should stay, in millimeters.
+    #[structopt(long, default_value = "5")]
+    safe_margin: f32,
+
+    /// List the available monitors and exit.
+    #[structopt(long)]
+    list_monitors: bool,
+
+    /// The language of messages, as a language code such as `en` or `es`. Defaults to the language
+    /// of the locale, from `LC_ALL`, `LC_MESSAGES` or `LANG`.
+    #[structopt(long)]
+    lang: Option<Language>,
+
+    #[structopt(subcommand)]
+    cmd: Option<JuliaCommand>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-struct ParseGradientError;
+/// Where and how `animate`, `zoom` and `circle` write their frames.
+#[derive(Debug, Clone, StructOpt)]
+struct SequenceOpts {
+    /// The file to write: a video encoded as H.264 in the container its extension names, `.mp4`,
+    /// `.m4v`, `.mkv` or `.mov`; or a looping `.gif`, `.apng` (or `.png`) or `.webp`. Videos and
+    /// WebPs need ffmpeg installed.
+    #[structopt(long, parse(from_os_str))]
+    output: Option<PathBuf>,
 
-impl Display for ParseGradientError {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "failed to parse color gradient")
-    }
+    /// The quality of the video: the constant rate factor of the H.264 encoder, from 0 for
+    /// lossless to 51. Lower is better and larger.
+    #[structopt(long, default_value = "18")]
+    crf: u32,
+
+    /// The quality of animated WebPs, from 0 to 100.
+    #[structopt(long, default_value = "80")]
+    webp_quality: f32,
+
+    /// The directory to write the frames to without `--output`. Defaults to the name of the spec
+    /// or path file for `animate`, or of the output file otherwise, with `_frames` in place of its
+    /// extension.
+    #[structopt(long, parse(from_os_str))]
+    dir: Option<PathBuf>,
+
+    /// The file names of the frames, with `%d` for the frame number counted from 1, or `%05d` for
+    /// the number padded with zeros to 5 digits.
+    #[structopt(long, default_value = "frame_%05d.png")]
+    pattern: FramePattern,
+
+    /// The frames to render, as `FIRST..LAST` with both included. Either end may be left out.
+    #[structopt(long, default_value = "1..")]
+    range: FrameRange,
+
+    /// How many frames may be rendering on the GPU at once, when writing frames.
+    #[structopt(short = "j", long, default_value = "2")]
+    in_flight: usize,
 }
 
-impl Error for ParseGradientError {}
+#[derive(Debug, Clone, StructOpt)]
+enum JuliaCommand {
+    /// Print the forward orbit of a point under f and report whether it escapes or becomes
+    /// periodic. The formula parameters are taken from the options given before `orbit`.
+    Orbit {
+        /// The starting point of the orbit, given as two comma-separated decimal values.
+        #[structopt(short = "z", long, parse(try_from_str = parse_vec2),
+            default_value = "0.0,0.0")]
+        start: Vec2,
 
-impl From<std::num::ParseFloatError> for ParseGradientError {
-    fn from(_: std::num::ParseFloatError) -> ParseGradientError {
-        ParseGradientError
-    }
+        /// How close the orbit must return to an earlier point to be considered periodic.
+        #[structopt(short, long, default_value = "1e-5")]
+        tolerance: f32,
+    },
+
+    /// Export every image listed in a file, one per line. Each line holds the options for one
+    /// image as they would be given on the command line, e.g. `-r -0.8 -i 0.156 -o a.png`. Empty
+    /// lines and lines starting with `#` are skipped. ICC, AVIF and trap image options are taken
+    /// from the options given before `batch`.
+    Batch {
+        /// The file listing the jobs.
+        #[structopt(parse(from_os_str))]
+        jobs: PathBuf,
+
+        /// How many jobs may be rendering on the GPU at once, so that it keeps working while
+        /// finished images are encoded. Each one needs its own buffers.
+        #[structopt(short = "j", long, default_value = "2")]
+        in_flight: usize,
+    },
+
+    /// Render a path of `c` recorded in the interactive viewer (with F2) as a numbered sequence of
+    /// frames, keeping the pace of the recording. Every other parameter is taken from the options
+    /// given before `path`.
+    Path {
+        /// The recorded path.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+
+        /// Frames per second of recording time.
+        #[structopt(long, default_value = "30")]
+        fps: f32,
+
+        /// The file names of the frames, with `%d` for the frame number counted from 1, or `%05d`
+        /// for the number padded with zeros to 5 digits.
+        #[structopt(long, default_value = "frame_%05d.png")]
+        pattern: FramePattern,
+
+        /// The frames to render, as `FIRST..LAST` with both included. Either end may be left out.
+        #[structopt(long, default_value = "1..")]
+        range: FrameRange,
+
+        /// How much to even out the jitter of the recording: the standard deviation, in seconds,
+        /// of a Gaussian average over nearby points. 0 follows the recording exactly.
+        #[structopt(long, default_value = "0.1")]
+        smooth: f32,
+
+        /// The directory to write the frames to. Defaults to the name of the path file with
+        /// `_frames` in place of its extension.
+        #[structopt(long, parse(from_os_str))]
+        dir: Option<PathBuf>,
+
+        /// How many frames may be rendering on the GPU at once.
+        #[structopt(short = "j", long, default_value = "2")]
+        in_flight: usize,
+    },
+
+    /// Render an animation straight into a video file encoded by ffmpeg or an animated image, or
+    /// without `--output` as a numbered sequence of frames. The animation is read from a spec file,
+    /// or follows a path of `c` recorded in the interactive viewer (with F2); every other parameter
+    /// is taken from the options given before `animate`.
+    Animate {
+        /// A spec file describing the animation with keyframes, in TOML, or in JSON if its name
+        /// ends in `.json`. Its resolution takes the place of `--width` and `--height`, and its
+        /// frame rate and output are used unless `--fps` and `--output` are given.
+        #[structopt(parse(from_os_str), required_unless = "path")]
+        spec: Option<PathBuf>,
+
+        /// A recorded path to follow instead of a spec file.
+        #[structopt(long, parse(from_os_str), conflicts_with = "spec")]
+        path: Option<PathBuf>,
+
+        /// Frames per second of animation time. Defaults to the frame rate of the spec, or 30.
+        #[structopt(long)]
+        fps: Option<f32>,
+
+        /// How much to even out the jitter of the recording: the standard deviation, in seconds,
+        /// of a Gaussian average over nearby points. 0 follows the recording exactly.
+        #[structopt(long, default_value = "0.1")]
+        smooth: f32,
+
+        #[structopt(flatten)]
+        sequence: SequenceOpts,
+    },
+
+    /// Render a zoom toward a point into a video file encoded by ffmpeg or an animated image, or
+    /// without `--output` as a numbered sequence of frames. The extent shrinks exponentially, so
+    /// that the zoom keeps the same pace all the way in, and iterations are added as it deepens.
+    /// Every other parameter is taken from the options given before `zoom`.
+    Zoom {
+        /// The point to zoom toward, kept at the center, given as two comma-separated decimal
+        /// values.
+        #[structopt(long, parse(try_from_str = parse_vec2))]
+        target: Vec2,
+
+        /// The extent of the shorter image dimension in the complex plane at the start.
+        #[structopt(long, default_value = "3.6")]
+        from_extent: f32,
+
+        /// The extent of the shorter image dimension in the complex plane at the end.
+        #[structopt(long)]
+        to_extent: f32,
+
+        /// The number of frames the zoom takes.
+        #[structopt(long, default_value = "300")]
+        frames: u32,
+
+        /// Frames per second of animation time.
+        #[structopt(long, default_value = "30")]
+        fps: f32,
+
+        /// The iterations added to the iteration limit for every tenfold zoom in.
+        #[structopt(long, default_value = "100")]
+        iters_per_decade: f32,
+
+        /// How the zoom speeds up and slows down: `linear` keeps the same pace all the way in,
+        /// `smoothstep` or `exponential` start and stop gently, and `cubic-bezier(x1,y1,x2,y2)`
+        /// follows a timing curve as in CSS.
+        #[structopt(long, default_value = "linear")]
+        easing: Easing,
+
+        #[structopt(flatten)]
+        sequence: SequenceOpts,
+    },
+
+    /// Render `c` moving around a circle about the origin, `c = r·e^(iθ)`, into a video file
+    /// encoded by ffmpeg or an animated image, or without `--output` as a numbered sequence of
+    /// frames: the classic morphing Julia set loop. Every other parameter is taken from the
+    /// options given before `circle`.
+    Circle {
+        /// The distance of `c` from the origin.
+        #[structopt(long, default_value = "0.7885")]
+        radius: f32,
+
+        /// The angle of `c` at the start, in degrees counterclockwise from the positive real axis.
+        #[structopt(long, default_value = "0")]
+        from_angle: f32,
+
+        /// The angle `c` sweeps toward, in degrees. The last frame stops one step short of it, so
+        /// that a full turn loops without repeating a frame.
+        #[structopt(long, default_value = "360")]
+        to_angle: f32,
+
+        /// The number of frames the sweep takes.
+        #[structopt(long, default_value = "300")]
+        frames: u32,
+
+        /// How the sweep speeds up and slows down, as for `zoom`.
+        #[structopt(long, default_value = "linear")]
+        easing: Easing,
+
+        /// Frames per second of animation time.
+        #[structopt(long, default_value = "30")]
+        fps: f32,
+
+        #[structopt(flatten)]
+        sequence: SequenceOpts,
+    },
+
+    /// Export a numbered set of random variations of the view, for choosing the best of them. Each
+    /// one moves `c` (or `z0` in the parameter plane), shifts the gradient and turns the view by a
+    /// small random amount. The view is taken from the options given before `variations`.
+    Variations {
+        /// How many variations to export.
+        #[structopt(short = "n", long, default_value = "12")]
+        count: u32,
+
+        /// Seeds the random perturbations. The same seed and view give the same variations.
+        #[structopt(long, default_value = "0")]
+        seed: u32,
+
+        /// The largest distance to move `c` or `z0` by.
+        #[structopt(long, default_value = "0.01")]
+        param_jitter: f32,
+
+        /// The largest amount to shift the color offset by either way.
+        #[structopt(long, default_value = "0.25")]
+        phase_jitter: f32,
+
+        /// The largest angle in degrees to turn the view by either way.
+        #[structopt(long, default_value = "15")]
+        rotation_jitter: f32,
+
+        /// The directory to write the variations to. Defaults to the output file name with
+        /// `_variations` in place of its extension.
+        #[structopt(long, parse(from_os_str))]
+        dir: Option<PathBuf>,
+
+        /// How many variations may be rendering on the GPU at once.
+        #[structopt(short = "j", long, default_value = "2")]
+        in_flight: usize,
+    },
+
+    /// Render small previews of several views and compose them into one contact sheet PNG, each
+    /// labeled underneath, to survey candidates quickly. The views are listed in a batch file, or
+    /// are the view given by the options before `sheet` with one parameter swept across a range.
+    Sheet {
+        /// A batch file listing the views, in the format `batch` reads. Each preview is labeled
+        /// with its line of options.
+        #[structopt(long, parse(from_os_str), required_unless = "sweep")]
+        jobs: Option<PathBuf>,
+
+        /// Step one parameter evenly across the sheet, given as `PARAM=START..END`, such as
+        /// `cr=-0.8..-0.7`. PARAM is one of `cr`, `ci`, `n`, `iters`, `rotation` and
+        /// `color-offset`. Each preview is labeled with its value.
+        #[structopt(long, conflicts_with = "jobs")]
+        sweep: Option<Sweep>,
+
+        /// How many previews a sweep has.
+        #[structopt(short = "n", long, default_value = "16")]
+        count: u32,
+
+        /// The side of each square preview in pixels, rounded down to a multiple of 8.
+        #[structopt(long, default_value = "256")]
+        cell: u32,
+
+        /// How many previews each row holds. Defaults to as many as make the sheet closest to
+        /// square.
+        #[structopt(long)]
+        columns: Option<u32>,
+
+        /// The file to write the sheet to, always as a PNG. Defaults to the output file name with
+        /// `_sheet` added before its extension, and `.png` in place of it.
+        #[structopt(long, parse(from_os_str))]
+        sheet: Option<PathBuf>,
+    },
+
+    /// Coordinate a render farm: wait for workers to connect, hand them pieces of the images to
+    /// render, and write the images here as they complete. Renders the image given by the options
+    /// before `farm`, or every image of a batch file. Workers render in sRGB without a trap image
+    /// or overlay, so those options can't be used.
+    Farm {
+        /// The address to listen for workers on. Only this machine can connect by default; to
+        /// accept workers from others, give an address such as `0.0.0.0:7878` and a `--token`.
+        #[structopt(long, default_value = "127.0.0.1:7878")]
+        listen: String,
+
+        /// A shared secret workers must present to be handed pieces. Required to listen on
+        /// addresses other machines can reach.
+        #[structopt(long)]
+        token: Option<String>,
+
+        /// A batch file listing the images to render, in the format `batch` reads.
+        #[structopt(long, parse(from_os_str))]
+        jobs: Option<PathBuf>,
+
+        /// Split images into square tiles of this many pixels, a multiple of 8, instead of
+        /// handing out whole images. Spreads a single large export over several workers.
+        #[structopt(long)]
+        tile_size: Option<u32>,
+    },
+
+    /// Render pieces of images for a render farm coordinator until it has none left.
+    Worker {
+        /// The address of the coordinator, as `host:port`.
+        #[structopt(long)]
+        connect: String,
+
+        /// The token the coordinator was given, if any.
+        #[structopt(long)]
+        token: Option<String>,
+    },
+
+    /// Watch a directory for view files, the options of an image as a flat JSON object or TOML
+    /// table, and render each one as it appears. Finished view files are moved into `done/` next
+    /// to their images, and ones that can't be rendered into `failed/`. ICC and trap image options
+    /// are taken from the options given before `watch`.
+    Watch {
+        /// The directory to watch.
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+
+        /// How often to look for new files, in seconds.
+        #[structopt(long, default_value = "1")]
+        interval: f32,
+    },
+
+    /// Reopen the view embedded in a PNG exported by julia, in the interactive viewer or, with
+    /// `-o`, rendered again at the size it was exported at. The embedded view replaces the
+    /// formula, view and color options; output options are taken from the options given before
+    /// `open`.
+    Open {
+        /// The exported PNG.
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+    },
 }
 
 impl JuliaArgs {
+    fn overwrite_policy(&self) -> OverwritePolicy {
+        if self.no_overwrite {
+            OverwritePolicy::Number
+        } else {
+            OverwritePolicy::Replace
+        }
+    }
+
+    /// Whether the image is written to standard output rather than to a file.
+    fn to_stdout(&self) -> bool {
+        match &self.file {
+            Some(Some(file)) => file.as_os_str() == "-",
+            _ => false,
+        }
+    }
+
+    fn dims(&self) -> ImgDimensions {
+        if let Some(dims) = self.print_settings().and_then(|p| p.dimensions()) {
+            return dims;
+        }
+
+        ImgDimensions {
+            width: (self.width / 40) * 40,
+            height: (self.height / 40) * 40,
+        }
+    }
+
+    /// The print settings, if a resolution or paper size is given.
+    fn print_settings(&self) -> Option<PrintSettings> {
+        if self.dpi.is_none() && self.paper.is_none() {
+            return None;
+        }
+
+        Some(PrintSettings {
+            dpi: self.dpi.unwrap_or(PrintSettings::default().dpi),
+            paper: self.paper,
+            bleed_mm: self.bleed.max(0.0),
+            safe_mm: self.safe_margin.max(0.0),
+        })
+    }
+
+    /// The caption and watermark stamped onto exports, if either is given.
+    fn overlay(&self) -> Option<Overlay> {
+        if self.caption.is_none() && self.watermark.is_none() {
+            return None;
+        }
+
+        Some(Overlay {
+            caption: self.caption.clone(),
+            watermark: self.watermark.clone(),
+            position: self.overlay_position,
+            opacity: self.overlay_opacity,
+            text_scale: self.caption_size,
+        })
+    }
+
+    fn transfer_stages(&self) -> TransferStages {
+        TransferStages {
+            render: self.render_transfer,
+            file: self.file_transfer,
+        }
+    }
+
+    fn gradient(&self) -> Gradient {
+        match self.random_colors {
+            Some(Some(seed)) => random_palette::random_gradient(seed),
+            _ => self.palette_file.map_or(self.colors, |p| p.gradient),
+        }
+    }
+
+    fn interpolation(&self) -> Interpolation {
+        match self.palette_file {
+            Some(Palette {
+                interpolation: Some(interpolation),
+                ..
+            }) if self.random_colors.is_none() => interpolation,
+            _ => self.interpolation,
+        }
+    }
+
+    fn data(&self) -> JuliaData {
+        JuliaData {
+            gradient: self.gradient(),
+            color_offset: self.color_offset,
+            color_scale: self.color_scale,
+            kind: self.kind(),
+            n: self.n,
+            c: vec2!(self.cr, self.ci),
+            z0: self.z0,
+            poly: self.poly,
+            denom: self.denom,
+            hybrid: self.hybrid,
+            shading: if self.shading {
+                Some(Light {
+                    azimuth: self.light.x,
+                    elevation: self.light.y,
+                    relief: self.relief,
+                })
+            } else {
+                None
+            },
+
+            nebulabrot: self.nebulabrot,
+            plane: self.plane,
+
+            iters: self.iters,
+
+            center: self.center,
+            extents: vec2!(self.extent, self.extent),
+
+            strict: self.strict,
+            periodicity: !self.no_periodicity,
+            interior_checks: !self.no_interior_checks,
+            rotation: self.rotation,
+            coloring: self.coloring,
+            stripe_density: self.stripe_density,
+            channels: Channels {
+                scale: self.channel_scale,
+                offset: self.channel_offset,
+            },
+            equalize: self.equalize,
+            color_period: self.color_period,
+            color_phase: self.color_phase,
+            interpolation: self.interpolation(),
+            interior_color: self.interior_color,
+            tone: Tone {
+                exposure: self.tone_exposure,
+                gamma: self.tone_gamma,
+                contrast: self.tone_contrast,
+                curve: self.tone_curve,
+            },
+            dither: self.dither,
+        }
+    }
+
+    fn kind(&self) -> FractalKind {
+        if self.burning_ship {
+            FractalKind::BurningShip
+        } else if self.mandelbrot {
+            FractalKind::Mandelbrot
+        } else {
+            self.fractal.unwrap_or(FractalKind::Julia)
+        }
+    }
+
     fn filename(&self) -> PathBuf {
         match &self.file {
-            Some(path) => path.clone(),
-            None => {
-                fn to_hex(c: Vec4) -> String {
-                    let c = Srgb::new(c[0], c[1], c[2]);
-                    let c = Srgb::<u8>::from_format(c);
-                    format!("{:02x}{:02x}{:02x}", c.red, c.green, c.blue)
+            Some(Some(path)) => path.clone(),
+            _ => {
+                // Potentials are always written as PNGs, and escape times as NumPy arrays
+                let extension = match self.format {
+                    _ if self.escape_time.is_some() => "npy",
+                    Some(format) if !self.potential => format.extension(),
+                    _ => "png",
+                };
+
+                let output = if self.potential {
+                    String::from("_potential")
+                } else if let Some([r, g, b]) = self.nebulabrot {
+                    format!("_nebulabrot{}-{}-{}", r, g, b)
+                } else if self.buddhabrot {
+                    String::from("_buddhabrot")
+                } else if self.iim {
+                    String::from("_iim")
+                } else if self.heatmap {
+                    String::from("_heatmap")
+                } else if let Some(value) = self.escape_time {
+                    format!("_escape_{}", value)
+                } else {
+                    String::new()
+                };
+
+                if let Some(template) = &self.name_template {
+                    let dims = self.dims();
+                    let name = template.render(&self.data().fit_to(dims), dims, SystemTime::now());
+                    return PathBuf::from(format!("{}{}.{}", name, output, extension));
+                }
+
+                let power = match &self.poly {
+                    Some(p) => format!("p{}", p),
+                    None => format!("x{}", self.n),
+                };
+                let power = match &self.denom {
+                    Some(q) => format!("{}_q{}", power, q),
+                    None => power,
+                };
+                let parameter_plane = self.data().view_plane() == Plane::Parameter;
+                let power = if self.z0 != Vec2::zeros() && parameter_plane {
+                    format!("{}_z{}_{}i", power, self.z0.x, self.z0.y)
+                } else {
+                    power
+                };
+                let power = match self.plane {
+                    Some(p) if (p == Plane::Parameter) != self.kind().is_parameter_plane() => {
+                        format!("{}_{}plane", power, p)
+                    }
+                    _ => power,
+                };
+                let power = match &self.hybrid {
+                    Some(h) => format!("{}_h{}", power, h.to_string().replace('*', "x")),
+                    None => power,
+                };
+
+                let mut mapping = if self.color_offset != 0.0 || self.color_scale != 1.0 {
+                    format!("_s{}-{}", self.color_scale, self.color_offset)
+                } else {
+                    String::new()
+                };
+                if self.shading {
+                    mapping.push_str(&format!("_l{}-{}", self.light.x, self.light.y));
+                    if self.relief == Relief::Distance {
+                        mapping.push_str("de");
+                    }
+                }
+                match self.coloring {
+                    ColoringMode::Iterations => (),
+                    ColoringMode::DistanceEstimate => mapping.push_str("_de"),
+                    ColoringMode::Stripes => {
+                        mapping.push_str(&format!("_st{}", self.stripe_density))
+                    }
+                    ColoringMode::BinaryDecomposition => mapping.push_str("_bd"),
+                    ColoringMode::Curvature => mapping.push_str("_cv"),
+                    ColoringMode::Channels => {
+                        mapping.push_str("_ch");
+                        let (s, o) = (self.channel_scale, self.channel_offset);
+                        if s != vec3!(1.0, 1.0, 1.0) || o != vec3!(0.0, 0.0, 0.0) {
+                            mapping.push_str(&format!(
+                                "{}-{}-{}_{}-{}-{}",
+                                s.x, s.y, s.z, o.x, o.y, o.z
+                            ));
+                        }
+                    }
+                }
+                if let Some(p) = self.color_period {
+                    mapping.push_str(&format!("_p{}", p));
+                } else if self.equalize {
+                    mapping.push_str("_eq");
+                }
+                if self.color_phase != 0.0 {
+                    mapping.push_str(&format!("_ph{:.2}", self.color_phase));
+                }
+                if self.interpolation() != Interpolation::Rgb {
+                    mapping.push_str(&format!("_{}", self.interpolation()));
                 }
+                if let Some(c) = self.interior_color {
+                    mapping.push_str(&format!("_in{}", gradient::to_hex(c)));
+                }
+                if self.tone_exposure != 0.0
+                    || self.tone_gamma != 1.0
+                    || self.tone_contrast != 1.0
+                    || self.tone_curve != ToneCurve::Clip
+                {
+                    mapping.push_str(&format!(
+                        "_t{}-{}-{}",
+                        self.tone_exposure, self.tone_gamma, self.tone_contrast
+                    ));
+                    if self.tone_curve != ToneCurve::Clip {
+                        mapping.push_str(&format!("-{}", self.tone_curve));
+                    }
+                }
+                if self.dither {
+                    mapping.push_str("_dither");
+                }
+
+                let rotation = if self.rotation != 0.0 {
+                    format!("_r{}", self.rotation)
+                } else {
+                    String::new()
+                };
+
+                let gradient = self
+                    .gradient()
+                    .stops()
+                    .iter()
+                    .map(|s| format!("{}-{}", gradient::to_hex(s.color), s.position))
+                    .collect::<Vec<_>>()
+                    .join("-");
 
                 PathBuf::from(format!(
-                    "x{}_{}_{}i_m{}_c{}-{}_e{}_c{}-{}-{}-{}-{}-{}_{}x{}.png",
-                    self.n,
+                    "{}{}_{}_{}i_m{}_c{}-{}_e{}{}_c{}{}_{}x{}{}.{}",
+                    match self.kind() {
+                        FractalKind::Julia => "",
+                        FractalKind::Mandelbrot => "mandelbrot_",
+                        FractalKind::BurningShip => "burning_ship_",
+                        FractalKind::Tricorn => "tricorn_",
+                        FractalKind::Newton => "newton_",
+                    },
+                    power,
                     self.cr,
                     self.ci,
                     self.iters,
                     self.center[0],
                     self.center[1],
                     self.extent,
-                    to_hex(self.colors[0]),
-                    to_hex(self.colors[1]),
-                    to_hex(self.colors[2]),
-                    self.midpts[0],
-                    self.midpts[1],
-                    self.midpts[2],
+                    rotation,
+                    gradient,
+                    mapping,
                     self.width,
                     self.height,
+                    output,
+                    extension,
                 ))
             }
         }
     }
 }
 
-fn parse_hexcode(s: &str) -> Option<Srgb<u8>> {
-    let mut channels = Vec::new();
-    let mut current = 0u8;
-    for (i, c) in s.chars().enumerate() {
-        if channels.len() >= 3 || (i == 0 && c != '#') {
-            return None;
-        } else {
-            if i == 0 {
-                continue;
-            }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+struct ParseVecError;
 
-            current = current << 4;
-            current += c.to_digit(16)? as u8;
-            if i % 2 == 0 {
-                channels.push(current);
-                current = 0;
-            }
-        }
+impl Display for ParseVecError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "failed to parse vec2")
     }
+}
 
-    if channels.len() != 3 {
-        None
-    } else {
-        Some(Srgb::new(channels[0], channels[1], channels[2]))
+impl Error for ParseVecError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+struct ParseIterLimitsError;
+
+impl Display for ParseIterLimitsError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "failed to parse iteration limits, expected three comma-separated counts"
+        )
     }
 }
 
-fn parse_gradient(s: &str) -> Result<[Vec4; 3], ParseGradientError> {
-    let mut components = s.split(',').map(str::trim);
+impl Error for ParseIterLimitsError {}
 
-    fn must_be_color(s: Option<&str>) -> Result<Srgb<f32>, ParseGradientError> {
-        let c = s.ok_or(ParseGradientError)?;
-        let c = named::from_str(c)
-            .or_else(|| parse_hexcode(c))
-            .ok_or(ParseGradientError)?;
-        Ok(Srgb::from_format(c))
-    }
-
-    fn to_vec4(c: Srgb<f32>) -> Vec4 {
-        let (r, g, b) = c.into_components();
-        vec4!(r, g, b, 1.0)
-    }
-
-    let c1 = must_be_color(components.next())?;
-    let c2 = must_be_color(components.next())?;
-    let c3 = match components.next() {
-        None => None,
-        Some(s) => Some(must_be_color(Some(s))?),
-    }
-    .unwrap_or(Srgb::new(1.0, 1.0, 1.0));
-
-    /*
-    let (c3, midpt) = match components.next() {
-        // No third component, flat gradient between two colors
-        None => (Srgb::<f32>::new(1.0, 1.0, 1.0), 0.9999999),
-        Some(s) => match named::from_str(s).or_else(|| parse_hexcode(s)) {
-            // Third component isn't a color, truncated gradient between two colors
-            None => (c2, f32::from_str(s).map_err(|_| ParseGradientError)?),
-            // Third component is a color, see if there's a fourth
-            Some(c) => {
-                let c = Srgb::<f32>::from_format(c);
-                match components.next() {
-                    // No midpoint specified, use default
-                    None => (c, 0.25),
-                    // Midpoint specified
-                    Some(s) => (c, f32::from_str(s).map_err(|_| ParseGradientError)?),
-                }
-            }
-        },
-    };
-    */
+fn parse_iter_limits(s: &str) -> Result<[u32; 3], ParseIterLimitsError> {
+    let limits: Vec<u32> = s
+        .split(',')
+        .map(|v| v.trim().parse().map_err(|_| ParseIterLimitsError))
+        .collect::<Result<_, _>>()?;
 
-    if components.next().is_some() {
-        Err(ParseGradientError)
-    } else {
-        Ok([to_vec4(c1), to_vec4(c2), to_vec4(c3)])
+    match limits.as_slice() {
+        [r, g, b] => Ok([*r, *g, *b]),
+        _ => Err(ParseIterLimitsError),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+struct ParseExponentError;
+
+impl Display for ParseExponentError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "failed to parse exponent, expected a number greater than {}",
+            MIN_EXPONENT
+        )
     }
 }
 
-fn parse_midpts(s: &str) -> Result<[f32; 3], ParseGradientError> {
-    let components: Vec<_> = s.split(',').map(str::trim).collect();
+impl Error for ParseExponentError {}
 
-    match components.len() {
-        1 => Ok([0.0, f32::from_str(components[0])?, 1.0]),
-        2 => Ok([
-            f32::from_str(components[0])?,
-            f32::from_str(components[1])?,
-            1.0,
-        ]),
-        3 => Ok([
-            f32::from_str(components[0])?,
-            f32::from_str(components[1])?,
-            f32::from_str(components[2])?,
-        ]),
-        _ => Err(ParseGradientError),
+fn parse_exponent(s: &str) -> Result<f32, ParseExponentError> {
+    match f32::from_str(s.trim()) {
+        Ok(n) if n > MIN_EXPONENT && n.is_finite() => Ok(n),
+        _ => Err(ParseExponentError),
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-struct ParseVecError;
+struct ParseSizeError;
 
-impl Display for ParseVecError {
+impl Display for ParseSizeError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "failed to parse vec2")
+        write!(f, "failed to parse size, expected WIDTHxHEIGHT")
     }
 }
 
-impl Error for ParseVecError {}
+impl Error for ParseSizeError {}
 
-fn parse_vec2(s: &str) -> Result<Vec2, ParseVecError> {
-    let mut components = s.split(',').map(str::trim);
+fn parse_size(s: &str) -> Result<(u32, u32), ParseSizeError> {
+    let mut wh = s.splitn(2, 'x');
 
-    let x = components.next().ok_or(ParseVecError)?;
-    let x = f32::from_str(x).map_err(|_| ParseVecError)?;
+    let width = wh.next().ok_or(ParseSizeError)?;
+    let width = u32::from_str(width.trim()).map_err(|_| ParseSizeError)?;
 
-    let y = components.next().ok_or(ParseVecError)?;
-    let y = f32::from_str(y).map_err(|_| ParseVecError)?;
+    let height = wh.next().ok_or(ParseSizeError)?;
+    let height = u32::from_str(height.trim()).map_err(|_| ParseSizeError)?;
 
-    if components.next().is_some() {
-        Err(ParseVecError)
+    Ok((width, height))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+struct ParseGuidesError;
+
+impl Display for ParseGuidesError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "failed to parse guides, expected a comma-separated list of thirds, center and margins"
+        )
+    }
+}
+
+impl Error for ParseGuidesError {}
+
+fn parse_guides(s: &str) -> Result<Guides, ParseGuidesError> {
+    let mut guides = Guides::default();
+
+    for name in s.split(',').map(str::trim) {
+        match name {
+            "thirds" => guides.thirds = true,
+            "center" => guides.center = true,
+            "margins" => guides.margins = true,
+            _ => return Err(ParseGuidesError),
+        }
+    }
+
+    Ok(guides)
+}
+
+fn parse_vec2(s: &str) -> Result<Vec2, ParseVecError> {
+    let mut components = s.split(',').map(str::trim);
+
+    let x = components.next().ok_or(ParseVecError)?;
+    let x = f32::from_str(x).map_err(|_| ParseVecError)?;
+
+    let y = components.next().ok_or(ParseVecError)?;
+    let y = f32::from_str(y).map_err(|_| ParseVecError)?;
+
+    if components.next().is_some() {
+        Err(ParseVecError)
     } else {
         Ok(vec2!(x, y))
     }
 }
 
+fn parse_vec3(s: &str) -> Result<Vec3, ParseVecError> {
+    let components = s
+        .split(',')
+        .map(|c| f32::from_str(c.trim()).map_err(|_| ParseVecError))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match components.as_slice() {
+        [x, y, z] => Ok(vec3!(*x, *y, *z)),
+        _ => Err(ParseVecError),
+    }
+}
+
+/// Reads a gradient from a GIMP `.ggr` file, or a palette from a palette file otherwise.
+fn load_palette_file(s: &str) -> io::Result<Palette> {
+    let path = Path::new(s);
+    if path
+        .extension()
+        .map_or(false, |e| e.eq_ignore_ascii_case("ggr"))
+    {
+        Ok(Palette {
+            gradient: Gradient::from_ggr(path)?,
+            interpolation: None,
+        })
+    } else {
+        Palette::load(path)
+    }
+}
+
+fn fmt_complex(z: Vec2) -> String {
+    let op = if z.y < 0.0 { '-' } else { '+' };
+
+    format!("{} {} {}i", z.x, op, z.y.abs())
+}
+
+fn print_orbit(orbit: &Orbit) {
+    for (k, z) in orbit.points.iter().enumerate() {
+        println!("{}", tr(Message::OrbitPoint, &[&k, &fmt_complex(*z)]));
+    }
+
+    match orbit.outcome {
+        OrbitOutcome::Escaped { iteration } => {
+            println!("{}", tr(Message::OrbitEscaped, &[&iteration]));
+        }
+
+        OrbitOutcome::Periodic {
+            preperiod,
+            period,
+            multiplier,
+        } => {
+            let magnitude = (multiplier.x * multiplier.x + multiplier.y * multiplier.y).sqrt();
+            let kind = if orbit.outcome.is_attracting() {
+                Message::Attracting
+            } else if magnitude > 1.0 {
+                Message::Repelling
+            } else {
+                Message::Indifferent
+            };
+
+            println!(
+                "{}",
+                tr(
+                    Message::OrbitPeriodic,
+                    &[
+                        &period,
+                        &preperiod,
+                        &fmt_complex(multiplier),
+                        &magnitude,
+                        &tr(kind, &[]),
+                    ]
+                )
+            );
+        }
+
+        OrbitOutcome::Undetermined => println!("{}", tr(Message::OrbitUndetermined, &[])),
+    }
+}
+
+/// The frames of an animation read from `input` at `fps`, written to `dir` or by default to a
+/// directory named after `input` with `_frames` in place of its extension.
+fn frame_sequence(
+    input: &Path,
+    dir: Option<PathBuf>,
+    pattern: &FramePattern,
+    fps: f32,
+    range: FrameRange,
+) -> FrameSequence {
+    FrameSequence {
+        dir: dir.unwrap_or_else(|| {
+            let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+            input.with_file_name(format!("{}_frames", stem))
+        }),
+        pattern: pattern.clone(),
+        fps: fps.max(1.0),
+        range,
+    }
+}
+
+/// Export jobs for the frames of a render following the recorded path of `c` in `path`, with every
+/// other parameter taken from `args`.
+fn path_jobs(
+    args: &JuliaArgs,
+    path: &Path,
+    smooth: f32,
+    sequence: &FrameSequence,
+) -> Vec<ExportJob> {
+    let c_path = or_exit_with(CPath::read(path), Message::ReadFailed, path);
+    if c_path.is_empty() {
+        return Vec::new();
+    }
+    or_exit_with(
+        fs::create_dir_all(&sequence.dir),
+        Message::CreateFailed,
+        &sequence.dir,
+    );
+
+    let dims = args.dims();
+    let data = args.data().fit_to(dims);
+    let jobs = sequence.jobs(&c_path.smoothed(smooth), &data, dims, args.potential);
+    println!(
+        "{}",
+        tr(
+            Message::RenderingPath,
+            &[&jobs.len(), &sequence.dir.display()]
+        )
+    );
+
+    jobs
+}
+
+/// The views of the frames of a render following the recorded path of `c` in `path`, with every
+/// other parameter taken from `args`.
+fn path_frames(
+    args: &JuliaArgs,
+    path: &Path,
+    smooth: f32,
+    sequence: &FrameSequence,
+) -> Vec<JuliaData> {
+    let c_path = or_exit_with(CPath::read(path), Message::ReadFailed, path);
+    if c_path.is_empty() {
+        return Vec::new();
+    }
+
+    let dims = args.dims();
+    let data = args.data().fit_to(dims);
+    sequence
+        .frames(&c_path.smoothed(smooth), &data)
+        .into_iter()
+        .map(|(_, data)| data)
+        .collect()
+}
+
+/// A zoom from `from_extent` to `to_extent` toward `target` that takes `frames` frames at `fps`.
+fn zoom_animation(
+    target: Vec2,
+    from_extent: f32,
+    to_extent: f32,
+    frames: u32,
+    fps: f32,
+    iters_per_decade: f32,
+    easing: Easing,
+) -> Zoom {
+    Zoom {
+        target,
+        from_extent,
+        to_extent,
+        duration: frames.saturating_sub(1) as f32 / fps,
+        iters_per_decade,
+        easing,
+    }
+}
+
+/// A circular orbit of `c` from `from_angle` to `to_angle` that takes `frames` frames at `fps`,
+/// ending one frame short of `to_angle`.
+fn circle_animation(
+    radius: f32,
+    from_angle: f32,
+    to_angle: f32,
+    frames: u32,
+    fps: f32,
+    easing: Easing,
+) -> CircleOrbit {
+    CircleOrbit {
+        radius,
+        from_angle,
+        to_angle,
+        sweep: frames as f32 / fps,
+        duration: frames.saturating_sub(1) as f32 / fps,
+        easing,
+    }
+}
+
+/// Reads the spec file given to `animate`, if any, or exits if it can't be read. Its resolution
+/// replaces the width and height in `args`, and its frame rate and output fill in those that
+/// aren't given.
+fn read_animation_spec(args: &mut JuliaArgs) -> Option<AnimationSpec> {
+    let (spec, fps, output) = match &mut args.cmd {
+        Some(JuliaCommand::Animate {
+            spec: Some(path),
+            fps,
+            sequence: SequenceOpts { output, .. },
+            ..
+        }) => match AnimationSpec::load(path.as_path()) {
+            Ok(spec) => (spec, fps, output),
+            Err(e) => {
+                eprintln!("{}", tr(Message::SpecReadFailed, &[&path.display(), &e]));
+                std::process::exit(1);
+            }
+        },
+        _ => return None,
+    };
+
+    if fps.is_none() {
+        *fps = spec.fps;
+    }
+    if output.is_none() {
+        *output = spec.output.clone();
+    }
+    if let Some((width, height)) = spec.resolution() {
+        args.width = width;
+        args.height = height;
+    }
+    Some(spec)
+}
+
+/// Export jobs for the frames of `animation`, with every other parameter taken from `args`.
+fn animation_jobs<A: Animation>(
+    args: &JuliaArgs,
+    animation: &A,
+    sequence: &FrameSequence,
+) -> Vec<ExportJob> {
+    or_exit_with(
+        fs::create_dir_all(&sequence.dir),
+        Message::CreateFailed,
+        &sequence.dir,
+    );
+
+    let dims = args.dims();
+    let data = args.data().fit_to(dims);
+    let jobs = sequence.jobs(animation, &data, dims, args.potential);
+    println!(
+        "{}",
+        tr(
+            Message::RenderingPath,
+            &[&jobs.len(), &sequence.dir.display()]
+        )
+    );
+
+    jobs
+}
+
+/// The views of the frames of `animation`, with every other parameter taken from `args`.
+fn animation_frames<A: Animation>(
+    args: &JuliaArgs,
+    animation: &A,
+    sequence: &FrameSequence,
+) -> Vec<JuliaData> {
+    let data = args.data().fit_to(args.dims());
+    sequence
+        .frames(animation, &data)
+        .into_iter()
+        .map(|(_, data)| data)
+        .collect()
+}
+
+/// Renders `frames` at `dims` into `output`, as a video or an animated image as its extension
+/// names. Exits if it names neither, or the file can't be written.
+fn export_animation(
+    context: &JuliaContext,
+    dims: ImgDimensions,
+    frames: &[JuliaData],
+    output: &Path,
+    options: &VideoOptions,
+) {
+    let format = AnimatedFormat::from_path(output);
+    if format.is_none() && !video::is_video(output) {
+        eprintln!(
+            "{}",
+            tr(Message::UnsupportedAnimation, &[&output.display()])
+        );
+        std::process::exit(1);
+    }
+    if frames.is_empty() {
+        return;
+    }
+
+    println!(
+        "{}",
+        tr(
+            Message::RenderingAnimation,
+            &[&frames.len(), &output.display()]
+        )
+    );
+
+    let output = context.target(output);
+    let result = match format {
+        Some(format) => animated::export_animated(
+            context,
+            dims,
+            frames,
+            &output,
+            format,
+            options.fps,
+            show_progress,
+        ),
+        None => video::export_video(context, dims, frames, &output, options, show_progress),
+    };
+    if let Err(e) = result {
+        let output = output.display();
+        eprintln!("{}", tr(Message::PathWriteFailed, &[&output, &e]));
+        std::process::exit(1);
+    }
+}
+
+/// Jobs exporting random variations of the view as `variation_000.png`, `variation_001.png`, ...
+/// The parameters of each are also written to a checkpoint log named `variations` in the same
+/// directory, last first, so that `--restore-checkpoint N` with that log reopens variation `N - 1`.
+fn variation_jobs(
+    args: &JuliaArgs,
+    options: &VariationOptions,
+    dir: Option<PathBuf>,
+) -> Vec<ExportJob> {
+    let dir = dir.unwrap_or_else(|| {
+        let filename = args.filename();
+        let stem = filename.file_stem().unwrap_or_default().to_string_lossy();
+        filename.with_file_name(format!("{}_variations", stem))
+    });
+    or_exit_with(fs::create_dir_all(&dir), Message::CreateFailed, &dir);
+
+    let dims = args.dims();
+    let views = variations::variations(&args.data(), options);
+    println!(
+        "{}",
+        tr(
+            Message::RenderingVariations,
+            &[&views.len(), &dir.display()]
+        )
+    );
+
+    let log = CheckpointLog::new(dir.join("variations"), Duration::from_secs(0));
+    if log.path().exists() {
+        or_exit_with(
+            fs::remove_file(log.path()),
+            Message::PathWriteFailed,
+            log.path(),
+        );
+    }
+    for data in views.iter().rev() {
+        or_exit_with(log.append(data, dims), Message::PathWriteFailed, log.path());
+    }
+
+    views
+        .into_iter()
+        .enumerate()
+        .map(|(i, data)| ExportJob {
+            dims,
+            data: data.fit_to(dims),
+            filename: dir.join(format!("variation_{:03}.png", i)),
+            potential: args.potential,
+        })
+        .collect()
+}
+
+/// Picks a checkpoint from the log, either the `index`-th most recent or by asking on stdin.
+/// When listing, shows the path of a cached thumbnail under each checkpoint if `thumbnails` is
+/// given.
+fn choose_checkpoint(
+    log: &CheckpointLog,
+    index: Option<usize>,
+    thumbnails: Option<(&ThumbnailCache, &JuliaContext)>,
+) -> Option<Checkpoint> {
+    let mut checkpoints = or_exit_with(log.read(), Message::ReadFailed, log.path());
+    checkpoints.reverse();
+
+    if checkpoints.is_empty() {
+        eprintln!("{}", tr(Message::NoCheckpoints, &[&log.path().display()]));
+        return None;
+    }
+
+    let index = match index {
+        Some(i) => i,
+        None => {
+            let listed = &checkpoints[..checkpoints.len().min(20)];
+            let thumbnail_paths = thumbnails.and_then(|(cache, context)| {
+                let views: Vec<_> = listed.iter().map(|c| c.data).collect();
+                match cache.render(&views, context) {
+                    Ok(paths) => Some(paths),
+                    Err(e) => {
+                        eprintln!(
+                            "{}",
+                            tr(Message::ThumbnailsFailed, &[&cache.dir().display(), &e])
+                        );
+                        None
+                    }
+                }
+            });
+
+            for (i, checkpoint) in listed.iter().enumerate() {
+                println!("{:3}: {}", i + 1, checkpoint);
+                if let Some(paths) = &thumbnail_paths {
+                    println!("     {}", paths[i].display());
+                }
+            }
+            print!("{}", tr(Message::ChooseCheckpoint, &[]));
+            io::stdout().flush().unwrap();
+
+            let mut line = String::new();
+            io::stdin().lock().read_line(&mut line).unwrap();
+            let line = line.trim();
+            if line.is_empty() {
+                1
+            } else {
+                line.parse().unwrap_or(0)
+            }
+        }
+    };
+
+    if index == 0 || index > checkpoints.len() {
+        eprintln!("{}", tr(Message::NoSuchCheckpoint, &[&index]));
+        None
+    } else {
+        Some(checkpoints[index - 1])
+    }
+}
+
+/// Reads the jobs of a batch file, exiting with an error if any of them is invalid.
+fn read_batch(path: &Path) -> Vec<ExportJob> {
+    read_batch_with(path, |args, _| export_job(&args))
+}
+
+/// Reads the options on each line of a batch file and turns them into an item with `f`, which is
+/// also given the line. Exits with an error naming the line if any can't be read.
+fn read_batch_with<T, F>(path: &Path, f: F) -> Vec<T>
+where
+    F: Fn(JuliaArgs, &str) -> Result<T, String>,
+{
+    let contents = or_exit_with(fs::read_to_string(path), Message::ReadFailed, path);
+    let mut items = Vec::new();
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let argv = iter::once("julia").chain(line.split_whitespace());
+        let item = JuliaArgs::from_iter_safe(argv)
+            .map_err(|e| e.message)
+            .and_then(|args| f(args, line));
+
+        match item {
+            Ok(item) => items.push(item),
+            Err(e) => {
+                eprintln!("{}:{}: {}", path.display(), i + 1, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    items
+}
+
+/// One export per size of `--sizes`, exiting with an error if they can't be exported.
+fn size_jobs(args: &JuliaArgs) -> Vec<ExportJob> {
+    let jobs = args.sizes.iter().map(|&(width, height)| {
+        let mut sized = args.clone();
+        sized.width = width;
+        sized.height = height;
+        if let Some(Some(path)) = &args.file {
+            let dims = sized.dims();
+            sized.file = Some(Some(with_suffix(
+                path,
+                &format!("{}x{}", dims.width, dims.height),
+            )));
+        }
+        export_job(&sized)
+    });
+
+    match jobs.collect() {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `path` with `_` and `suffix` added before its extension.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, suffix, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, suffix),
+    };
+
+    path.with_file_name(name)
+}
+
+/// Exports the view once per palette of the set at `path`. The exports run one after another
+/// through the context's cache, so that every palette after the first only recolors the samples
+/// of the first.
+fn export_palette_set(args: &JuliaArgs, path: &Path, context: &JuliaContext) {
+    let palettes = match palette_file::load_set(path) {
+        Ok(palettes) => palettes,
+        Err(e) => {
+            eprintln!("{}", tr(Message::ReadFailed, &[&path.display(), &e]));
+            std::process::exit(1);
+        }
+    };
+
+    let dims = args.dims();
+    let data = args.data().fit_to(dims);
+    let filename = args.filename();
+    for (name, palette) in palettes {
+        let data = JuliaData {
+            gradient: palette.gradient,
+            interpolation: palette.interpolation.unwrap_or(data.interpolation),
+            ..data
+        };
+        let filename = context.target(&with_suffix(&filename, &name));
+        println!("{}", tr(Message::Exporting, &[&filename.display()]));
+        or_exit(context.export(dims, &data, &filename), &filename);
+    }
+}
+
+/// The export the options describe, if it is one that batches support.
+fn export_job(args: &JuliaArgs) -> Result<ExportJob, String> {
+    if args.buddhabrot || args.nebulabrot.is_some() || args.iim {
+        return Err(tr(Message::AccumulationNoBatch, &[]));
+    }
+
+    if args.heatmap {
+        return Err(tr(Message::HeatmapNoBatch, &[]));
+    }
+
+    if args.escape_time.is_some() {
+        return Err(tr(Message::EscapeTimeNoBatch, &[]));
+    }
+
+    if args.potential && args.kind() == FractalKind::Newton {
+        return Err(tr(Message::NewtonNoPotential, &[]));
+    }
+
+    let dims = args.dims();
+    Ok(ExportJob {
+        dims,
+        data: args.data().fit_to(dims),
+        filename: args.filename(),
+        potential: args.potential,
+    })
+}
+
+/// Exports a color image, or the potential if `potential` is set, showing how far the rendering
+/// has come on standard error.
+fn export_with_progress(
+    context: &JuliaContext,
+    dims: ImgDimensions,
+    data: JuliaData,
+    filename: PathBuf,
+    potential: bool,
+) {
+    let job = ExportJob {
+        dims,
+        data,
+        filename,
+        potential,
+    };
+
+    let filename = job.filename.clone();
+    or_exit(context.export_with_progress(job, show_progress), &filename);
+}
+
+/// The value of an export to `filename`, or exits with an error if it failed.
+fn or_exit<T>(result: Result<T, JuliaExportError>, filename: &Path) -> T {
+    result.unwrap_or_else(|e| {
+        let filename = filename.display();
+        eprintln!("{}", tr(Message::PathWriteFailed, &[&filename, &e]));
+        std::process::exit(1);
+    })
+}
+
+/// The value of `result`, or exits after printing `msg` with `path` and the error.
+fn or_exit_with<T, E: Display>(result: Result<T, E>, msg: Message, path: &Path) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", tr(msg, &[&path.display(), &e]));
+        std::process::exit(1);
+    })
+}
+
+/// Renders the tiles of `export` that are missing and stitches them, exiting with an error if
+/// they can't be written.
+fn run_tiled(mut export: TiledExport, context: &JuliaContext) {
+    if let Err(e) = export.run(context, show_progress) {
+        let filename = export.filename().display();
+        eprintln!("{}", tr(Message::PathWriteFailed, &[&filename, &e]));
+        std::process::exit(1);
+    }
+}
+
+/// Renders the contact sheet of the `sheet` command.
+fn export_sheet(
+    sheet: &ContactSheet,
+    views: Vec<(JuliaData, String)>,
+    filename: &Path,
+    context: &JuliaContext,
+) {
+    println!(
+        "{}",
+        tr(
+            Message::RenderingSheet,
+            &[&views.len(), &filename.display()]
+        )
+    );
+    let image = match sheet.render(&views, context, show_progress) {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("{}", tr(Message::ExportFailed, &[&e]));
+            std::process::exit(1);
+        }
+    };
+
+    let filename = context.target(filename);
+    if let Err(e) = contact_sheet::write(&image, &filename) {
+        eprintln!(
+            "{}",
+            tr(Message::PathWriteFailed, &[&filename.display(), &e])
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Shows how far an export has been rendered on standard error, overwriting the last progress
+/// shown.
+fn show_progress(progress: Progress) {
+    eprint!("\r{}", tr(Message::RenderProgress, &[&progress]));
+    if progress.done == progress.total {
+        eprintln!();
+    }
+}
+
+/// Renders every view file that appears in `dir`, one at a time, and moves each into `done/` with
+/// its image, or into `failed/` if it can't be rendered. Runs until interrupted.
+fn watch(dir: &Path, interval: Duration, context: &JuliaContext) {
+    let done = dir.join("done");
+    let failed = dir.join("failed");
+    or_exit_with(fs::create_dir_all(&done), Message::CreateFailed, &done);
+    or_exit_with(fs::create_dir_all(&failed), Message::CreateFailed, &failed);
+
+    println!("{}", tr(Message::WatchingFolder, &[&dir.display()]));
+
+    // The sizes of the view files found on the last pass. Files are only read once their size
+    // holds between passes, so that ones still being written aren't picked up.
+    let mut sizes = HashMap::new();
+
+    loop {
+        let mut paths: Vec<PathBuf> = or_exit_with(fs::read_dir(dir), Message::ReadFailed, dir)
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && ViewFormat::from_path(path).is_some())
+            .collect();
+        paths.sort();
+
+        let mut pending = HashMap::new();
+        for path in paths {
+            let size = match fs::metadata(&path) {
+                Ok(metadata) => metadata.len(),
+                Err(_) => continue,
+            };
+
+            if sizes.get(&path) == Some(&size) {
+                render_view_file(&path, &done, &failed, context);
+            } else {
+                pending.insert(path, size);
+            }
+        }
+
+        sizes = pending;
+        thread::sleep(interval);
+    }
+}
+
+fn render_view_file(path: &Path, done: &Path, failed: &Path, context: &JuliaContext) {
+    let name = path.file_name().unwrap_or_default();
+
+    let target = match view_job(path, done) {
+        Ok(job) => {
+            println!("{}", tr(Message::RenderingView, &[&path.display()]));
+            let filename = context.target(&job.filename);
+            match context.export_batch(iter::once(job), 1) {
+                Ok(()) => {
+                    println!("{}", tr(Message::ViewRendered, &[&filename.display()]));
+                    done.join(name)
+                }
+                Err(e) => {
+                    let filename = filename.display();
+                    eprintln!("{}", tr(Message::PathWriteFailed, &[&filename, &e]));
+                    failed.join(name)
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            failed.join(name)
+        }
+    };
+
+    if let Err(e) = fs::rename(path, &target) {
+        eprintln!("{}", tr(Message::ViewMoveFailed, &[&path.display(), &e]));
+    }
+}
+
+/// The export a view file describes. Its image goes in `done`, named after the view file unless
+/// the view gives an output file.
+fn view_job(path: &Path, done: &Path) -> Result<ExportJob, String> {
+    // Only files with one of the extensions are picked up
+    let format = ViewFormat::from_path(path).expect("not a view file");
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let entries = viewfile::parse(&contents, format)?;
+
+    let argv = iter::once(String::from("julia")).chain(viewfile::to_args(&entries));
+    let args = JuliaArgs::from_iter_safe(argv).map_err(|e| e.message)?;
+    let mut job = export_job(&args)?;
+
+    job.filename = match &args.file {
+        Some(Some(file)) => done.join(file),
+        _ => {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            done.join(format!("{}.png", stem))
+        }
+    };
+    Ok(job)
+}
+
+/// The export dimensions and view embedded in the PNG at `path`, exiting if it has none.
+fn open_view(args: &JuliaArgs, path: &Path) -> (ImgDimensions, JuliaData) {
+    match embedded::read_view(path) {
+        Ok(Some(view)) => {
+            // Standard output carries the image itself when streaming
+            if args.to_stdout() {
+                eprintln!("{}", tr(Message::OpenedView, &[&view]));
+            } else {
+                println!("{}", tr(Message::OpenedView, &[&view]));
+            }
+            (view.export_dimensions, view.data)
+        }
+        Ok(None) => {
+            eprintln!("{}", tr(Message::NoEmbeddedView, &[&path.display()]));
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}", tr(Message::ViewReadFailed, &[&path.display(), &e]));
+            std::process::exit(1);
+        }
+    }
+}
+
 fn main() {
-    let args = JuliaArgs::from_args();
-    println!("{:#?}", args);
-    println!("Computed filename: {:?}", args.filename());
+    let mut args = JuliaArgs::from_args();
+    locale::set_language(args.lang.unwrap_or_else(Language::from_env));
+
+    if let Some(seed) = args.random_colors {
+        let seed = seed.unwrap_or_else(random_palette::time_seed);
+        // Standard output carries the image itself when streaming
+        if args.to_stdout() {
+            eprintln!("{}", tr(Message::RandomColors, &[&seed]));
+        } else {
+            println!("{}", tr(Message::RandomColors, &[&seed]));
+        }
+        args.random_colors = Some(Some(seed));
+    }
+
+    let animation_spec = read_animation_spec(&mut args);
+
+    let (dims, data) = match &args.cmd {
+        Some(JuliaCommand::Open { image }) => open_view(&args, image),
+        _ => (args.dims(), args.data()),
+    };
+
+    if let Some(JuliaCommand::Orbit { start, tolerance }) = args.cmd {
+        print_orbit(&orbit::analyze(&data, start, tolerance));
+        return;
+    }
+
+    if args.list_monitors {
+        for (i, name) in interface::monitor_names().iter().enumerate() {
+            println!("{}: {}", i, name);
+        }
+        return;
+    }
+
+    let batch = match &args.cmd {
+        Some(JuliaCommand::Batch { jobs, in_flight }) => Some((read_batch(jobs), *in_flight)),
+        Some(JuliaCommand::Path {
+            path,
+            fps,
+            smooth,
+            dir,
+            pattern,
+            range,
+            in_flight,
+        }) => {
+            let sequence = frame_sequence(path, dir.clone(), pattern, *fps, *range);
+            Some((path_jobs(&args, path, *smooth, &sequence), *in_flight))
+        }
+        Some(JuliaCommand::Animate {
+            spec,
+            path,
+            fps,
+            smooth,
+            sequence:
+                SequenceOpts {
+                    output: None,
+                    dir,
+                    pattern,
+                    range,
+                    in_flight,
+                    ..
+                },
+        }) => {
+            let fps = fps.unwrap_or(30.0);
+            let jobs = match (spec, &animation_spec, path) {
+                (Some(file), Some(spec), _) => {
+                    let sequence = frame_sequence(file, dir.clone(), pattern, fps, *range);
+                    animation_jobs(&args, spec, &sequence)
+                }
+                (_, _, Some(path)) => {
+                    let sequence = frame_sequence(path, dir.clone(), pattern, fps, *range);
+                    path_jobs(&args, path, *smooth, &sequence)
+                }
+                _ => Vec::new(),
+            };
+            Some((jobs, *in_flight))
+        }
+        Some(JuliaCommand::Zoom {
+            target,
+            from_extent,
+            to_extent,
+            frames,
+            fps,
+            iters_per_decade,
+            easing,
+            sequence:
+                SequenceOpts {
+                    output: None,
+                    dir,
+                    pattern,
+                    range,
+                    in_flight,
+                    ..
+                },
+        }) => {
+            let sequence = frame_sequence(&args.filename(), dir.clone(), pattern, *fps, *range);
+            let zoom = zoom_animation(
+                *target,
+                *from_extent,
+                *to_extent,
+                *frames,
+                sequence.fps,
+                *iters_per_decade,
+                *easing,
+            );
+            Some((animation_jobs(&args, &zoom, &sequence), *in_flight))
+        }
+        Some(JuliaCommand::Circle {
+            radius,
+            from_angle,
+            to_angle,
+            frames,
+            easing,
+            fps,
+            sequence:
+                SequenceOpts {
+                    output: None,
+                    dir,
+                    pattern,
+                    range,
+                    in_flight,
+                    ..
+                },
+        }) => {
+            let sequence = frame_sequence(&args.filename(), dir.clone(), pattern, *fps, *range);
+            let circle = circle_animation(
+                *radius,
+                *from_angle,
+                *to_angle,
+                *frames,
+                sequence.fps,
+                *easing,
+            );
+            Some((animation_jobs(&args, &circle, &sequence), *in_flight))
+        }
+        Some(JuliaCommand::Variations {
+            count,
+            seed,
+            param_jitter,
+            phase_jitter,
+            rotation_jitter,
+            dir,
+            in_flight,
+        }) => {
+            let options = VariationOptions {
+                count: *count,
+                seed: *seed,
+                param_jitter: *param_jitter,
+                phase_jitter: *phase_jitter,
+                rotation_jitter: *rotation_jitter,
+            };
+            Some((variation_jobs(&args, &options, dir.clone()), *in_flight))
+        }
+        None if !args.sizes.is_empty() => Some((size_jobs(&args), 2)),
+        _ => None,
+    };
+
+    if args.to_stdout() {
+        eprintln!("{:#?}", args);
+    } else {
+        println!("{:#?}", args);
+        println!("Computed filename: {:?}", args.filename());
+    }
 
     let context = JuliaContext::new().expect("failed to create JuliaContext");
 
-    let dims = ImgDimensions {
-        width: (args.width / 40) * 40,
-        height: (args.height / 40) * 40,
+    if args.no_icc {
+        context.set_icc_profile(None);
+    } else if let Some(path) = &args.icc_profile {
+        let profile = or_exit_with(IccProfile::from_file(path), Message::ReadFailed, path);
+        context.set_icc_profile(Some(profile));
+    }
+
+    #[cfg(feature = "avif")]
+    context.set_avif_options(julia::AvifOptions {
+        quality: args.avif_quality,
+        speed: args.avif_speed,
+    });
+    #[cfg(feature = "webp")]
+    context.set_webp_options(julia::WebpOptions {
+        quality: args.webp_quality,
+    });
+    context.set_format(args.format);
+    context.set_jpeg_quality(args.jpeg_quality);
+    context.set_sidecar(args.sidecar);
+    context.set_overwrite_policy(args.overwrite_policy());
+    context.set_print_settings(args.print_settings());
+    context.set_overlay(args.overlay());
+
+    let bit_depth = if !args.sixteen_bit {
+        BitDepth::Eight
+    } else if context.supports_bit_depth(BitDepth::Sixteen) {
+        BitDepth::Sixteen
+    } else {
+        eprintln!("{}", tr(Message::SixteenBitUnsupported, &[]));
+        BitDepth::Eight
     };
+    context.set_bit_depth(bit_depth);
+    context.set_color_space(args.colorspace);
+    context.set_transfer(args.transfer_stages());
+
+    if let Some(path) = &args.trap_image {
+        let trap = or_exit_with(
+            OrbitTrap::open(path, args.trap_center, args.trap_width),
+            Message::ReadFailed,
+            path,
+        );
+        or_exit_with(
+            context.set_orbit_trap(Some(&trap)),
+            Message::TrapUploadFailed,
+            path,
+        );
+    }
+
+    if let Some((jobs, in_flight)) = batch {
+        if let Err(e) = context.export_batch(jobs, in_flight) {
+            eprintln!("{}", tr(Message::ExportFailed, &[&e]));
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    match &args.cmd {
+        Some(JuliaCommand::Farm {
+            listen,
+            token,
+            jobs,
+            tile_size,
+        }) => {
+            let unsupported = [
+                ("--trap-image", args.trap_image.is_some()),
+                ("--sixteen-bit", args.sixteen_bit),
+                ("--colorspace", args.colorspace != ColorSpace::Srgb),
+                ("--render-transfer", args.render_transfer != Transfer::Srgb),
+                ("--file-transfer", args.file_transfer != Transfer::Srgb),
+                ("--caption/--watermark", args.overlay().is_some()),
+            ];
+            if let Some((option, _)) = unsupported.iter().find(|(_, used)| *used) {
+                eprintln!("{}", tr(Message::FarmUnsupportedOption, &[option]));
+                std::process::exit(1);
+            }
+
+            let jobs = match jobs {
+                Some(path) => read_batch(path),
+                None => vec![ExportJob {
+                    dims,
+                    data: data.fit_to(dims),
+                    filename: args.filename(),
+                    potential: args.potential,
+                }],
+            };
+            if jobs.iter().any(|job| job.potential) {
+                eprintln!("{}", tr(Message::FarmNoPotential, &[]));
+                std::process::exit(1);
+            }
+
+            let coordinator = Coordinator::new(jobs, *tile_size);
+            if let Err(e) = coordinator.run(
+                listen.as_str(),
+                token.as_ref().map(String::as_str),
+                &context,
+            ) {
+                eprintln!("{}", tr(Message::FarmFailed, &[&e]));
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(JuliaCommand::Sheet {
+            jobs,
+            sweep,
+            count,
+            cell,
+            columns,
+            sheet,
+        }) => {
+            let views = match (jobs, sweep) {
+                (Some(path), _) => {
+                    read_batch_with(path, |args, line| Ok((args.data(), line.to_string())))
+                }
+                (None, Some(sweep)) => sweep.views(&args.data(), *count),
+                (None, None) => unreachable!("structopt requires --jobs or --sweep"),
+            };
+            let filename = sheet
+                .clone()
+                .unwrap_or_else(|| with_suffix(&args.filename(), "sheet").with_extension("png"));
+
+            let layout = ContactSheet::new(*cell, *columns);
+            export_sheet(&layout, views, &filename, &context);
+            return;
+        }
+        Some(JuliaCommand::Watch { dir, interval }) => {
+            watch(dir, Duration::from_secs_f32(interval.max(0.1)), &context);
+            return;
+        }
+        Some(JuliaCommand::Worker { connect, token }) => {
+            if args.trap_image.is_some() {
+                eprintln!("{}", tr(Message::FarmUnsupportedOption, &[&"--trap-image"]));
+                std::process::exit(1);
+            }
+
+            match farm::work(
+                connect.as_str(),
+                token.as_ref().map(String::as_str),
+                &context,
+            ) {
+                Ok(rendered) => println!("{}", tr(Message::WorkerFinished, &[&rendered])),
+                Err(e) => {
+                    eprintln!("{}", tr(Message::WorkerFailed, &[&e]));
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(JuliaCommand::Animate {
+            path,
+            fps,
+            smooth,
+            sequence:
+                SequenceOpts {
+                    output: Some(output),
+                    crf,
+                    webp_quality,
+                    pattern,
+                    range,
+                    ..
+                },
+            ..
+        }) => {
+            let sequence = frame_sequence(output, None, pattern, fps.unwrap_or(30.0), *range);
+            let options = VideoOptions {
+                fps: sequence.fps,
+                crf: *crf,
+                webp_quality: *webp_quality,
+            };
+            let frames = match (&animation_spec, path) {
+                (Some(spec), _) => animation_frames(&args, spec, &sequence),
+                (None, Some(path)) => path_frames(&args, path, *smooth, &sequence),
+                (None, None) => Vec::new(),
+            };
+            export_animation(&context, args.dims(), &frames, output, &options);
+            return;
+        }
+        Some(JuliaCommand::Zoom {
+            target,
+            from_extent,
+            to_extent,
+            frames,
+            fps,
+            iters_per_decade,
+            easing,
+            sequence:
+                SequenceOpts {
+                    output: Some(output),
+                    crf,
+                    webp_quality,
+                    pattern,
+                    range,
+                    ..
+                },
+        }) => {
+            let sequence = frame_sequence(output, None, pattern, *fps, *range);
+            let options = VideoOptions {
+                fps: sequence.fps,
+                crf: *crf,
+                webp_quality: *webp_quality,
+            };
+            let zoom = zoom_animation(
+                *target,
+                *from_extent,
+                *to_extent,
+                *frames,
+                sequence.fps,
+                *iters_per_decade,
+                *easing,
+            );
+            let frames = animation_frames(&args, &zoom, &sequence);
+            export_animation(&context, args.dims(), &frames, output, &options);
+            return;
+        }
+        Some(JuliaCommand::Circle {
+            radius,
+            from_angle,
+            to_angle,
+            frames,
+            easing,
+            fps,
+            sequence:
+                SequenceOpts {
+                    output: Some(output),
+                    crf,
+                    webp_quality,
+                    pattern,
+                    range,
+                    ..
+                },
+        }) => {
+            let sequence = frame_sequence(output, None, pattern, *fps, *range);
+            let options = VideoOptions {
+                fps: sequence.fps,
+                crf: *crf,
+                webp_quality: *webp_quality,
+            };
+            let circle = circle_animation(
+                *radius,
+                *from_angle,
+                *to_angle,
+                *frames,
+                sequence.fps,
+                *easing,
+            );
+            let frames = animation_frames(&args, &circle, &sequence);
+            export_animation(&context, args.dims(), &frames, output, &options);
+            return;
+        }
+        _ => {}
+    }
+
+    if let Some(manifest) = &args.resume {
+        match TiledExport::from_manifest(manifest) {
+            Ok(export) => run_tiled(export, &context),
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    tr(Message::ManifestReadFailed, &[&manifest.display(), &e])
+                );
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(path) = &args.palette_set {
+        export_palette_set(&args, path, &context);
+        return;
+    }
+
+    if args.to_stdout() {
+        let format = args.format.unwrap_or(OutputFormat::Pam);
+        let color = !args.potential
+            && !args.buddhabrot
+            && args.nebulabrot.is_none()
+            && !args.iim
+            && !args.heatmap
+            && args.escape_time.is_none()
+            && args.tiled.is_none()
+            && args.dzi.is_none()
+            && args.contours.is_empty()
+            && !args.mesh;
+        if !color || !format.streams() {
+            eprintln!("{}", tr(Message::StdoutUnsupported, &[]));
+            std::process::exit(1);
+        }
 
-    let data = JuliaData {
-        color: args.colors,
-        color_midpoint: args.midpts,
-        n: args.n,
-        c: vec2!(args.cr, args.ci),
+        let stdout = io::stdout();
+        let mut out = io::BufWriter::new(stdout.lock());
+        let data = data.fit_to(dims);
+        if let Err(e) = context
+            .export_to(dims, &data, format, &mut out)
+            .and_then(|()| out.flush().map_err(JuliaExportError::from))
+        {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-        iters: args.iters,
+    if args.file.is_some() {
+        let filename = args.filename();
+        let data = data.fit_to(dims);
+        let max = context.max_image_dimension();
 
-        center: args.center,
-        extents: vec2!(args.extent, args.extent),
+        if args.potential {
+            if data.kind == FractalKind::Newton {
+                eprintln!("{}", tr(Message::NewtonNoPotential, &[]));
+                std::process::exit(1);
+            }
+            export_with_progress(&context, dims, data, filename, true);
+        } else if args.buddhabrot || args.nebulabrot.is_some() {
+            if data.kind == FractalKind::Newton {
+                eprintln!("{}", tr(Message::NewtonNoBuddhabrot, &[]));
+                std::process::exit(1);
+            }
+            let options = BuddhabrotOptions {
+                samples: args.samples,
+                exposure: args.exposure,
+                gamma: args.gamma,
+                seed: args.seed,
+            };
+            let result = context.export_buddhabrot(dims, &data, &options, &filename);
+            or_exit(result, &filename);
+        } else if args.iim {
+            if !iim::supports(&data) {
+                eprintln!("{}", tr(Message::IimUnsupported, &[]));
+                std::process::exit(1);
+            }
+            let options = BuddhabrotOptions {
+                samples: args.samples,
+                exposure: args.exposure,
+                gamma: args.gamma,
+                seed: args.seed,
+            };
+            let result = context.export_iim(dims, &data, &options, &filename);
+            or_exit(result, &filename);
+        } else if args.heatmap {
+            if data.kind == FractalKind::Newton {
+                eprintln!("{}", tr(Message::NewtonNoHeatmap, &[]));
+                std::process::exit(1);
+            }
+            let report = or_exit(context.export_heatmap(dims, &data, &filename), &filename);
+            let tile = report.worst_tile;
+            println!(
+                "{}",
+                tr(Message::CostSummary, &[&format!("{:.1}", report.mean())])
+            );
+            println!(
+                "{}",
+                tr(
+                    Message::CostWorstTile,
+                    &[
+                        &tile.width,
+                        &tile.height,
+                        &tile.x,
+                        &tile.y,
+                        &format!("{:.1}", report.worst_tile_mean()),
+                    ]
+                )
+            );
+        } else if let Some(value) = args.escape_time {
+            if data.kind == FractalKind::Newton {
+                eprintln!("{}", tr(Message::NewtonNoEscapeTime, &[]));
+                std::process::exit(1);
+            }
+            let result = context.export_escape_time(dims, &data, value, &filename);
+            or_exit(result, &filename);
+        } else if !args.contours.is_empty() {
+            if data.kind == FractalKind::Newton {
+                eprintln!("{}", tr(Message::NewtonNoContours, &[]));
+                std::process::exit(1);
+            }
+            let filename = filename.with_extension("svg");
+            let result = context.export_contours(dims, &data, &args.contours, &filename);
+            or_exit(result, &filename);
+        } else if args.mesh {
+            if data.kind == FractalKind::Newton {
+                eprintln!("{}", tr(Message::NewtonNoMesh, &[]));
+                std::process::exit(1);
+            }
+            let options = MeshOptions {
+                scale: args.mesh_scale,
+                step: args.mesh_step,
+            };
+            let filename = match filename.extension() {
+                Some(e) if e.eq_ignore_ascii_case("stl") || e.eq_ignore_ascii_case("obj") => {
+                    filename.clone()
+                }
+                _ => filename.with_extension("obj"),
+            };
+            let result = context.export_mesh(dims, &data, &options, &filename);
+            or_exit(result, &filename);
+        } else if let Some(tile_size) = args.tiled {
+            let png = args.format.or_else(|| OutputFormat::from_path(&filename))
+                == Some(OutputFormat::Png);
+            if !png {
+                eprintln!("{}", tr(Message::TiledPngOnly, &[]));
+                std::process::exit(1);
+            }
+
+            let filename = context.target(&filename);
+            run_tiled(TiledExport::new(dims, data, tile_size, filename), &context);
+        } else if let Some(tile_size) = args.dzi {
+            let filename = context.target(&filename.with_extension("dzi"));
+            let tile_size = tile_size.unwrap_or(DeepZoom::DEFAULT_TILE_SIZE);
+            let export = DeepZoom::new(dims, data, tile_size, filename);
+            if let Err(e) = export.run(&context, show_progress) {
+                let filename = export.filename().display();
+                eprintln!("{}", tr(Message::PathWriteFailed, &[&filename, &e]));
+                std::process::exit(1);
+            }
+        } else if dims.width > max || dims.height > max {
+            // Too large to render at once, but tiles of it aren't
+            let png = args.format.or_else(|| OutputFormat::from_path(&filename))
+                == Some(OutputFormat::Png);
+            let size: [&dyn Display; 3] = [&dims.width, &dims.height, &max];
+            if !png {
+                eprintln!("{}", tr(Message::ImageTooLarge, &size));
+                std::process::exit(1);
+            }
+
+            eprintln!("{}", tr(Message::ExportingInTiles, &size));
+            let filename = context.target(&filename);
+            let tile_size = u32::min(TiledExport::DEFAULT_TILE_SIZE, max);
+            run_tiled(TiledExport::new(dims, data, tile_size, filename), &context);
+        } else {
+            export_with_progress(&context, dims, data, filename, false);
+        }
+
+        return;
+    }
+
+    let checkpoint_log = CheckpointLog::new(
+        args.checkpoint_file
+            .clone()
+            .unwrap_or_else(CheckpointLog::default_path),
+        Duration::from_secs_f64(args.checkpoint_interval.max(0.0) * 60.0),
+    );
+
+    let thumbnail_cache =
+        ThumbnailCache::new(ThumbnailCache::default_dir(), ThumbnailCache::DEFAULT_SIZE);
+    let thumbnails = if args.no_thumbnails {
+        None
+    } else {
+        Some((&thumbnail_cache, &context))
     };
 
-    let mut interface = JuliaInterface::new(&context, Some(data), Some(dims))
-        .expect("failed to create JuliaInterface");
+    let (data, dims) = match args.restore_checkpoint {
+        Some(index) => match choose_checkpoint(&checkpoint_log, index, thumbnails) {
+            Some(checkpoint) => (checkpoint.data, checkpoint.export_dimensions),
+            None => return,
+        },
+        None => (data, dims),
+    };
+
+    let window_options = WindowOptions {
+        monitor: args.monitor,
+        size: args.window_size,
+        fullscreen: args.fullscreen,
+        always_on_top: args.always_on_top,
+    };
+
+    let mut interface =
+        JuliaInterface::with_window_options(&context, Some(data), Some(dims), &window_options)
+            .expect("failed to create JuliaInterface");
+    interface.set_hud_visible(args.hud);
+    interface.set_hud_scale(args.hud_scale);
+    interface.set_print_settings(args.print_settings());
+    interface.set_bit_depth(bit_depth);
+    interface.set_color_space(args.colorspace);
+    interface.set_transfer(args.transfer_stages());
+    if let Some(format) = args.format {
+        interface.set_format(format);
+    }
+    interface.set_jpeg_quality(args.jpeg_quality);
+    interface.set_sidecar(args.sidecar);
+    interface.set_overwrite_policy(args.overwrite_policy());
+    #[cfg(feature = "webp")]
+    interface.set_webp_options(julia::WebpOptions {
+        quality: args.webp_quality,
+    });
+    interface.set_palette_path(args.palette_path.clone());
+    interface.set_name_template(args.name_template.clone());
+    let all_guides = Guides {
+        thirds: true,
+        center: true,
+        margins: true,
+    };
+    interface.set_guides(args.guides.unwrap_or(all_guides), args.guides.is_some());
+    if args.checkpoint_interval > 0.0 {
+        interface.set_checkpoint_log(Some(checkpoint_log));
+    }
+    if args.capture_seconds > 0.0 {
+        interface.set_view_history(Some(ViewHistory::new(HistoryOptions {
+            seconds: args.capture_seconds,
+            fps: args.capture_fps,
+            scale: args.capture_scale,
+        })));
+    }
     interface.run(&context).unwrap();
 }
@@ -0,0 +1,206 @@
+//! Heightmap meshes for 3D printing. The smooth escape time of every pixel is taken as a height,
+//! from the floor for points that escape at once to the full relief for points that never escape,
+//! and the surface is closed into a solid with walls around its edges and a flat floor, so that
+//! slicers can print it as it is.
+//!
+//! Files ending in `.stl` are written as binary STL, and anything else as Wavefront OBJ. Lengths
+//! are in pixels of the image, with `x` to the right, `y` up the image and `z` up from the floor.
+
+use crate::atomic::write_atomic;
+use crate::ImgDimensions;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// How a heightmap mesh is built from the escape time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshOptions {
+    /// The height of the relief as a fraction of the image's width.
+    pub scale: f32,
+    /// The distance in pixels between neighboring vertices. Larger steps make smaller meshes.
+    pub step: u32,
+}
+
+impl Default for MeshOptions {
+    fn default() -> MeshOptions {
+        MeshOptions {
+            scale: 0.1,
+            step: 1,
+        }
+    }
+}
+
+/// The thickness of the solid below the lowest point of the surface, as a fraction of the image's
+/// width.
+const BASE: f32 = 0.02;
+
+/// The vertices of a heightmap and the order its border runs in.
+struct Heightmap {
+    /// The vertices of the surface in rows from the top, then one on the floor under each point of
+    /// the border, then the center of the floor.
+    vertices: Vec<[f32; 3]>,
+    columns: u32,
+    rows: u32,
+    /// The surface vertices around the edge, counterclockwise seen from above.
+    border: Vec<u32>,
+}
+
+impl Heightmap {
+    /// The heightmap of `values`, one per pixel in rows from the top, each out of `iters`.
+    fn new(dims: ImgDimensions, values: &[f32], iters: u32, options: &MeshOptions) -> Heightmap {
+        let ImgDimensions { width, height } = dims;
+        let step = options.step.max(1);
+        let columns = (width - 1 + step - 1) / step + 1;
+        let rows = (height - 1 + step - 1) / step + 1;
+        let relief = options.scale * width as f32;
+        let base = BASE * width as f32;
+
+        let mut vertices = Vec::with_capacity((columns * rows) as usize);
+        for row in 0..rows {
+            let y = u32::min(row * step, height - 1);
+            for col in 0..columns {
+                let x = u32::min(col * step, width - 1);
+                let t = values[(y * width + x) as usize] / iters as f32;
+                vertices.push([
+                    x as f32,
+                    (height - 1 - y) as f32,
+                    base + t.max(0.0).min(1.0) * relief,
+                ]);
+            }
+        }
+
+        // Down the left side, along the bottom, up the right side and back along the top
+        let index = |col: u32, row: u32| row * columns + col;
+        let mut border: Vec<u32> = (0..rows).map(|row| index(0, row)).collect();
+        border.extend((1..columns).map(|col| index(col, rows - 1)));
+        border.extend((0..rows - 1).rev().map(|row| index(columns - 1, row)));
+        border.extend((1..columns - 1).rev().map(|col| index(col, 0)));
+
+        for &i in &border {
+            let [x, y, _] = vertices[i as usize];
+            vertices.push([x, y, 0.0]);
+        }
+        vertices.push([(width - 1) as f32 / 2.0, (height - 1) as f32 / 2.0, 0.0]);
+
+        Heightmap {
+            vertices,
+            columns,
+            rows,
+            border,
+        }
+    }
+
+    fn triangle_count(&self) -> usize {
+        let surface = 2 * (self.columns - 1) as usize * (self.rows - 1) as usize;
+        surface + 3 * self.border.len()
+    }
+
+    /// Calls `emit` with every triangle of the solid, its vertices counterclockwise seen from
+    /// outside.
+    fn triangles<F>(&self, mut emit: F) -> io::Result<()>
+    where
+        F: FnMut([u32; 3]) -> io::Result<()>,
+    {
+        let columns = self.columns;
+        for row in 0..self.rows - 1 {
+            for col in 0..columns - 1 {
+                let top_left = row * columns + col;
+                let bottom_left = top_left + columns;
+                emit([top_left, bottom_left, bottom_left + 1])?;
+                emit([top_left, bottom_left + 1, top_left + 1])?;
+            }
+        }
+
+        let floor = (columns * self.rows) as usize;
+        let center = (self.vertices.len() - 1) as u32;
+        let n = self.border.len();
+        for i in 0..n {
+            let j = (i + 1) % n;
+            let (top_p, top_q) = (self.border[i], self.border[j]);
+            let (floor_p, floor_q) = ((floor + i) as u32, (floor + j) as u32);
+
+            emit([floor_p, floor_q, top_q])?;
+            emit([floor_p, top_q, top_p])?;
+            emit([center, floor_q, floor_p])?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the heightmap of `values`, one per pixel in rows from the top, as binary STL if
+/// `filename` ends in `.stl` and as OBJ otherwise. The file is replaced atomically.
+pub(crate) fn write(
+    filename: &Path,
+    dims: ImgDimensions,
+    values: &[f32],
+    iters: u32,
+    options: &MeshOptions,
+) -> io::Result<()> {
+    let stl = filename
+        .extension()
+        .map_or(false, |e| e.eq_ignore_ascii_case("stl"));
+    let heightmap = Heightmap::new(dims, values, iters, options);
+
+    write_atomic(filename, |path| {
+        let mut out = BufWriter::new(File::create(path)?);
+        if stl {
+            write_stl(&mut out, &heightmap)?;
+        } else {
+            write_obj(&mut out, &heightmap)?;
+        }
+        out.flush()
+    })
+}
+
+fn write_obj<W: Write>(out: &mut W, heightmap: &Heightmap) -> io::Result<()> {
+    writeln!(out, "# julia heightmap")?;
+    for [x, y, z] in &heightmap.vertices {
+        writeln!(out, "v {} {} {}", x, y, z)?;
+    }
+
+    // OBJ counts vertices from 1
+    heightmap.triangles(|[a, b, c]| writeln!(out, "f {} {} {}", a + 1, b + 1, c + 1))
+}
+
+/// Writes a binary STL: an 80-byte header, the number of triangles, then each triangle's normal
+/// and vertices as little-endian floats followed by two unused bytes.
+fn write_stl<W: Write>(out: &mut W, heightmap: &Heightmap) -> io::Result<()> {
+    let mut header = [b' '; 80];
+    header[..15].copy_from_slice(b"julia heightmap");
+    out.write_all(&header)?;
+    out.write_all(&(heightmap.triangle_count() as u32).to_le_bytes())?;
+
+    let vertices = &heightmap.vertices;
+    heightmap.triangles(|[a, b, c]| {
+        let [a, b, c] = [
+            vertices[a as usize],
+            vertices[b as usize],
+            vertices[c as usize],
+        ];
+        for v in normal(a, b, c).iter().chain(&a).chain(&b).chain(&c) {
+            out.write_all(&v.to_le_bytes())?;
+        }
+        out.write_all(&[0, 0])
+    })
+}
+
+/// The unit normal of the triangle `a`, `b`, `c`, facing the side its vertices run
+/// counterclockwise on.
+fn normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let n = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+
+    let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+    if length > 0.0 {
+        [n[0] / length, n[1] / length, n[2] / length]
+    } else {
+        [0.0; 3]
+    }
+}
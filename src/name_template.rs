@@ -0,0 +1,191 @@
+//! Templates for the names of exported files, as in `julia_{n}_{cr}{ci}i_{width}x{height}_{date}`.
+//! Placeholders in braces are replaced by the parameters of the image, and everything else is
+//! kept as it is. `{{` and `}}` stand for literal braces. The extension is added after the
+//! rendered template, and isn't part of it. The same templates caption exported images, where
+//! they may also contain path separators.
+//!
+//! The placeholders are:
+//!
+//! - `{name}`: the name julia generates when there is no template, with every parameter in it
+//! - `{kind}`: the fractal kind, as `--fractal` takes it
+//! - `{n}`: the exponent
+//! - `{cr}`, `{ci}`: the real and imaginary parts of `c`, the imaginary part always signed so
+//!   that `{cr}{ci}i` reads as a complex number
+//! - `{x}`, `{y}`: the center of the view
+//! - `{extent}`: the larger of the view's width and height in the complex plane
+//! - `{rotation}`: the angle the view is turned by, in degrees
+//! - `{iters}`: the iteration limit
+//! - `{width}`, `{height}`: the dimensions of the image in pixels
+//! - `{date}`, `{time}`: when the image is exported, as `YYYY-MM-DD` and `hhmmss` in UTC
+
+use crate::checkpoint;
+use crate::{ImgDimensions, JuliaData};
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// A parsed file name template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    Name,
+    Kind,
+    N,
+    Cr,
+    Ci,
+    X,
+    Y,
+    Extent,
+    Rotation,
+    Iters,
+    Width,
+    Height,
+    Date,
+    Time,
+}
+
+impl Placeholder {
+    fn from_name(name: &str) -> Option<Placeholder> {
+        Some(match name {
+            "name" => Placeholder::Name,
+            "kind" => Placeholder::Kind,
+            "n" => Placeholder::N,
+            "cr" => Placeholder::Cr,
+            "ci" => Placeholder::Ci,
+            "x" => Placeholder::X,
+            "y" => Placeholder::Y,
+            "extent" => Placeholder::Extent,
+            "rotation" => Placeholder::Rotation,
+            "iters" => Placeholder::Iters,
+            "width" => Placeholder::Width,
+            "height" => Placeholder::Height,
+            "date" => Placeholder::Date,
+            "time" => Placeholder::Time,
+            _ => return None,
+        })
+    }
+}
+
+impl NameTemplate {
+    /// Parses a template for the caption of an image rather than a file name, which unlike a file
+    /// name may contain `/` and `\`.
+    pub fn parse_caption(s: &str) -> Result<NameTemplate, ParseNameTemplateError> {
+        NameTemplate::parse(s, false)
+    }
+
+    fn parse(s: &str, file_name: bool) -> Result<NameTemplate, ParseNameTemplateError> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(ParseNameTemplateError::UnmatchedBrace),
+                        }
+                    }
+
+                    let placeholder = Placeholder::from_name(&name)
+                        .ok_or(ParseNameTemplateError::UnknownPlaceholder(name))?;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::replace(&mut text, String::new())));
+                    }
+                    parts.push(Part::Placeholder(placeholder));
+                }
+                '}' => return Err(ParseNameTemplateError::UnmatchedBrace),
+                '/' | '\\' if file_name => return Err(ParseNameTemplateError::PathSeparator),
+                _ => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(NameTemplate { parts })
+    }
+
+    /// The file name, without an extension, of an image of `dims` rendered from `data` at `time`.
+    pub fn render(&self, data: &JuliaData, dims: ImgDimensions, time: SystemTime) -> String {
+        let [year, month, day, hour, minute, second] = checkpoint::utc(time);
+
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Placeholder(p) => match p {
+                    Placeholder::Name => data.name(),
+                    Placeholder::Kind => data.kind.to_string(),
+                    Placeholder::N => data.n.to_string(),
+                    Placeholder::Cr => format!("{:.5}", data.c.x),
+                    Placeholder::Ci => format!("{:+.5}", data.c.y),
+                    Placeholder::X => format!("{:.5}", data.center.x),
+                    Placeholder::Y => format!("{:.5}", data.center.y),
+                    Placeholder::Extent => format!("{:.8}", data.extents.x.max(data.extents.y)),
+                    Placeholder::Rotation => format!("{:.2}", data.rotation),
+                    Placeholder::Iters => data.iters.to_string(),
+                    Placeholder::Width => dims.width.to_string(),
+                    Placeholder::Height => dims.height.to_string(),
+                    Placeholder::Date => format!("{:04}-{:02}-{:02}", year, month, day),
+                    Placeholder::Time => format!("{:02}{:02}{:02}", hour, minute, second),
+                },
+            })
+            .collect()
+    }
+}
+
+impl FromStr for NameTemplate {
+    type Err = ParseNameTemplateError;
+
+    fn from_str(s: &str) -> Result<NameTemplate, ParseNameTemplateError> {
+        NameTemplate::parse(s, true)
+    }
+}
+
+/// A malformed file name template.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ParseNameTemplateError {
+    UnknownPlaceholder(String),
+    UnmatchedBrace,
+    /// Templates name files in the output directory, so they can't contain `/` or `\`.
+    PathSeparator,
+}
+
+impl Display for ParseNameTemplateError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ParseNameTemplateError::UnknownPlaceholder(name) => {
+                write!(f, "unknown placeholder {{{}}}", name)
+            }
+            ParseNameTemplateError::UnmatchedBrace => write!(f, "unmatched brace"),
+            ParseNameTemplateError::PathSeparator => {
+                write!(f, "file name templates can't contain path separators")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseNameTemplateError {}
@@ -0,0 +1,98 @@
+//! Forward orbit analysis for single points, using the same iteration as the renderer.
+
+use gramit::Vec2;
+
+use crate::formula::{
+    complex_abs, complex_mul, escape_radius, iteration_derivative, mandelbrot_iteration,
+    orbit_start,
+};
+use crate::JuliaData;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Orbit {
+    /// The computed iterates, starting with the initial iterate.
+    pub points: Vec<Vec2>,
+    pub outcome: OrbitOutcome,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrbitOutcome {
+    /// The orbit left the escape radius at the given iteration.
+    Escaped { iteration: usize },
+
+    /// The orbit returned to within the tolerance of an earlier point. The cycle starts at
+    /// `preperiod` and repeats every `period` iterations; `multiplier` is the derivative of the
+    /// `period`-th iterate along the cycle.
+    Periodic {
+        preperiod: usize,
+        period: usize,
+        multiplier: Vec2,
+    },
+
+    /// Neither escape nor periodicity was detected within the iteration limit.
+    Undetermined,
+}
+
+impl OrbitOutcome {
+    /// Whether a detected cycle is attracting, i.e. its multiplier lies inside the unit disk.
+    pub fn is_attracting(&self) -> bool {
+        match self {
+            OrbitOutcome::Periodic { multiplier, .. } => complex_abs(*multiplier) < 1.0,
+            _ => false,
+        }
+    }
+}
+
+/// Iterates the orbit of the point `p` under the formula described by `data` for at most
+/// `data.iters` steps, stopping early if the orbit escapes or comes back within `tolerance` of an
+/// earlier point. In the dynamical plane `p` is the initial iterate; in the parameter plane it is
+/// `c`.
+pub fn analyze(data: &JuliaData, p: Vec2, tolerance: f32) -> Orbit {
+    let radius = escape_radius(data);
+    let (start, c) = orbit_start(data, p);
+    let steps = data.hybrid.map(|h| h.steps().len()).unwrap_or(1);
+    let mut points = vec![start];
+
+    for k in 0..data.iters as usize {
+        let z = points[k];
+        if complex_abs(z) > radius {
+            return Orbit {
+                points,
+                outcome: OrbitOutcome::Escaped { iteration: k },
+            };
+        }
+
+        let next = mandelbrot_iteration(data, c, z, k);
+
+        // Search backwards so the shortest period is found first. A hybrid only repeats once its
+        // steps come around again, so the period must be a multiple of its length.
+        let cycle_start = points
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|&(j, &prev)| (k + 1 - j) % steps == 0 && complex_abs(prev - next) < tolerance)
+            .map(|(j, _)| j);
+        points.push(next);
+
+        if let Some(j) = cycle_start {
+            let period = k + 1 - j;
+            let multiplier = (j..=k).fold(vec2!(1.0, 0.0), |m, i| {
+                complex_mul(m, iteration_derivative(data, points[i], i))
+            });
+
+            return Orbit {
+                points,
+                outcome: OrbitOutcome::Periodic {
+                    preperiod: j,
+                    period,
+                    multiplier,
+                },
+            };
+        }
+    }
+
+    Orbit {
+        points,
+        outcome: OrbitOutcome::Undetermined,
+    }
+}
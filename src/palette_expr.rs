@@ -0,0 +1,293 @@
+//! Gradients defined by expressions, as in `expr:sin(6.28*t), sin(6.28*t+2.1), sin(6.28*t+4.2)`.
+//! The expressions give the red, green and blue channels, and optionally alpha, as functions of
+//! the position `t` from 0 to 1. They're evaluated once, when the gradient is parsed, at
+//! `MAX_STOPS` evenly spaced points that become an ordinary gradient, with each channel clamped
+//! between 0 and 1.
+//!
+//! Expressions are made of numbers, `t`, the constants `pi` and `tau`, the operators `+`, `-`,
+//! `*`, `/` and `^` (a power), parentheses, and the functions `sin`, `cos`, `tan`, `abs`, `sqrt`,
+//! `exp`, `ln`, `floor`, `fract`, `min`, `max` and `pow`.
+
+use crate::gradient::{Gradient, MAX_STOPS};
+
+use std::f32::consts::PI;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A parsed expression in `t`.
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f32),
+    T,
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+    Floor,
+    Fract,
+    Min,
+    Max,
+    Pow,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        Some(match name {
+            "sin" => Function::Sin,
+            "cos" => Function::Cos,
+            "tan" => Function::Tan,
+            "abs" => Function::Abs,
+            "sqrt" => Function::Sqrt,
+            "exp" => Function::Exp,
+            "ln" => Function::Ln,
+            "floor" => Function::Floor,
+            "fract" => Function::Fract,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            "pow" => Function::Pow,
+            _ => return None,
+        })
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Function::Min | Function::Max | Function::Pow => 2,
+            _ => 1,
+        }
+    }
+
+    fn apply(self, args: &[f32]) -> f32 {
+        let x = args[0];
+        match self {
+            Function::Sin => x.sin(),
+            Function::Cos => x.cos(),
+            Function::Tan => x.tan(),
+            Function::Abs => x.abs(),
+            Function::Sqrt => x.sqrt(),
+            Function::Exp => x.exp(),
+            Function::Ln => x.ln(),
+            Function::Floor => x.floor(),
+            Function::Fract => x - x.floor(),
+            Function::Min => x.min(args[1]),
+            Function::Max => x.max(args[1]),
+            Function::Pow => x.powf(args[1]),
+        }
+    }
+}
+
+impl Expr {
+    fn eval(&self, t: f32) -> f32 {
+        match self {
+            Expr::Number(n) => *n,
+            Expr::T => t,
+            Expr::Neg(e) => -e.eval(t),
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.eval(t), b.eval(t));
+                match op {
+                    Op::Add => a + b,
+                    Op::Sub => a - b,
+                    Op::Mul => a * b,
+                    Op::Div => a / b,
+                    Op::Pow => a.powf(b),
+                }
+            }
+            Expr::Call(f, args) => {
+                let args: Vec<_> = args.iter().map(|e| e.eval(t)).collect();
+                f.apply(&args)
+            }
+        }
+    }
+}
+
+/// The gradient given by three or four comma-separated expressions, for red, green, blue and
+/// alpha, or `None` if they don't parse.
+pub fn gradient(s: &str) -> Option<Gradient> {
+    let mut parser = Parser {
+        chars: s.chars().peekable(),
+    };
+    let channels = parser.list()?;
+    parser.skip_space();
+    if parser.chars.peek().is_some() || channels.len() < 3 || channels.len() > 4 {
+        return None;
+    }
+
+    let colors: Vec<_> = (0..MAX_STOPS)
+        .map(|i| {
+            let t = i as f32 / (MAX_STOPS - 1) as f32;
+            let mut c = [1.0; 4];
+            for (channel, e) in c.iter_mut().zip(&channels) {
+                let value = e.eval(t);
+                // NaN, from a root or logarithm of a negative number, is taken as 0
+                *channel = if value.is_nan() {
+                    0.0
+                } else {
+                    value.max(0.0).min(1.0)
+                };
+            }
+            vec4!(c[0], c[1], c[2], c[3])
+        })
+        .collect();
+
+    Gradient::even(&colors)
+}
+
+/// A recursive descent parser, with the usual precedence: `^` binds tightest and to the right,
+/// then negation, then `*` and `/`, then `+` and `-`.
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_space(&mut self) {
+        while self.chars.peek().map_or(false, |c| c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    /// Consumes `c` if it is the next character after any whitespace.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        if self.chars.peek() == Some(&c) {
+            self.chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Comma-separated expressions.
+    fn list(&mut self) -> Option<Vec<Expr>> {
+        let mut exprs = vec![self.sum()?];
+        while self.eat(',') {
+            exprs.push(self.sum()?);
+        }
+        Some(exprs)
+    }
+
+    fn sum(&mut self) -> Option<Expr> {
+        let mut lhs = self.product()?;
+        loop {
+            let op = if self.eat('+') {
+                Op::Add
+            } else if self.eat('-') {
+                Op::Sub
+            } else {
+                return Some(lhs);
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Option<Expr> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else {
+                return Some(lhs);
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Option<Expr> {
+        if self.eat('-') {
+            Some(Expr::Neg(Box::new(self.unary()?)))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Option<Expr> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            // The exponent may itself be negated, as in `t^-2`
+            let exponent = self.unary()?;
+            Some(Expr::Binary(Op::Pow, Box::new(base), Box::new(exponent)))
+        } else {
+            Some(base)
+        }
+    }
+
+    fn atom(&mut self) -> Option<Expr> {
+        if self.eat('(') {
+            let e = self.sum()?;
+            return if self.eat(')') { Some(e) } else { None };
+        }
+
+        self.skip_space();
+        let c = *self.chars.peek()?;
+        if c.is_ascii_digit() || c == '.' {
+            self.number()
+        } else if c.is_ascii_alphabetic() {
+            let mut name = String::new();
+            while let Some(&c) = self.chars.peek() {
+                if !c.is_ascii_alphanumeric() && c != '_' {
+                    break;
+                }
+                name.push(c);
+                self.chars.next();
+            }
+            self.name(&name.to_ascii_lowercase())
+        } else {
+            None
+        }
+    }
+
+    fn number(&mut self) -> Option<Expr> {
+        let mut digits = String::new();
+        while let Some(&c) = self.chars.peek() {
+            let exponent_sign =
+                (c == '-' || c == '+') && digits.ends_with(|d| d == 'e' || d == 'E');
+            if !c.is_ascii_digit() && c != '.' && c != 'e' && c != 'E' && !exponent_sign {
+                break;
+            }
+            digits.push(c);
+            self.chars.next();
+        }
+        digits.parse().ok().map(Expr::Number)
+    }
+
+    /// A variable, constant or function call starting with `name`.
+    fn name(&mut self, name: &str) -> Option<Expr> {
+        match name {
+            "t" => Some(Expr::T),
+            "pi" => Some(Expr::Number(PI)),
+            "tau" => Some(Expr::Number(2.0 * PI)),
+            _ => {
+                let function = Function::from_name(name)?;
+                if !self.eat('(') {
+                    return None;
+                }
+                let args = self.list()?;
+                if !self.eat(')') || args.len() != function.arity() {
+                    return None;
+                }
+                Some(Expr::Call(function, args))
+            }
+        }
+    }
+}
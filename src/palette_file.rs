@@ -0,0 +1,199 @@
+//! Palette files: a gradient on its own, saved from the interactive interface and loaded again in
+//! later sessions or with `--palette-file`. A palette is a flat JSON object or TOML table holding
+//! the stops' colors and, optionally, their positions and the space they're blended in:
+//!
+//! ```toml
+//! colors = ["#000000", "#ff8000", "#ffffff"]
+//! positions = [0, 0.25, 1]
+//! interpolation = "hsv"
+//! ```
+//!
+//! Colors are hex codes or CSS3 names as `--colors` takes them. Without `positions`, the colors
+//! are spread evenly from 0 to 1. The interpolation is named as `--interpolation` takes it.
+//!
+//! A palette set, as `--palette-set` takes it, is a TOML file of several palettes, each a table
+//! under its name, or a JSON object of them:
+//!
+//! ```toml
+//! [fire]
+//! colors = ["#000000", "#ff8000", "#ffffff"]
+//!
+//! [ice]
+//! colors = ["navy", "white"]
+//! interpolation = "oklab"
+//! ```
+
+use crate::atomic::write_atomic;
+use crate::gradient::{self, Gradient, GradientStop, Interpolation};
+use crate::viewfile::ViewFormat;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A gradient together with the space its colors are blended in, if the palette gives one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub gradient: Gradient,
+    pub interpolation: Option<Interpolation>,
+}
+
+impl Palette {
+    /// Reads a palette file, as JSON if the path ends in `.json` and as TOML otherwise.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Palette> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        let format = ViewFormat::from_path(path).unwrap_or(ViewFormat::Toml);
+
+        parse(&contents, format).map_err(|reason| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), reason),
+            )
+        })
+    }
+
+    /// Writes the palette to a file, as JSON if the path ends in `.json` and as TOML otherwise.
+    /// The file is replaced atomically.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let format = ViewFormat::from_path(path).unwrap_or(ViewFormat::Toml);
+        let contents = to_string(self, format)
+            .map_err(|reason| io::Error::new(io::ErrorKind::InvalidData, reason))?;
+
+        write_atomic(path, |tmp| fs::write(tmp, &contents))
+    }
+}
+
+/// A palette as it's written in a file, before its colors and interpolation are parsed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PaletteFile {
+    colors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    positions: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interpolation: Option<String>,
+}
+
+impl TryFrom<PaletteFile> for Palette {
+    type Error = String;
+
+    fn try_from(file: PaletteFile) -> Result<Palette, String> {
+        let colors = file
+            .colors
+            .iter()
+            .map(|s| gradient::parse_color(s).map_err(|_| format!("bad color {}", s)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let positions = match file.positions {
+            Some(positions) => positions,
+            None => {
+                let last = colors.len().saturating_sub(1).max(1) as f32;
+                (0..colors.len()).map(|i| i as f32 / last).collect()
+            }
+        };
+
+        if positions.len() != colors.len() {
+            return Err(String::from("`colors` and `positions` differ in length"));
+        }
+        if let Some(p) = positions.iter().find(|p| !p.is_finite()) {
+            return Err(format!("bad position {}", p));
+        }
+
+        let interpolation = match file.interpolation {
+            Some(s) => Some(s.parse().map_err(|_| format!("bad interpolation {}", s))?),
+            None => None,
+        };
+
+        let stops: Vec<_> = colors
+            .into_iter()
+            .zip(positions)
+            .map(|(color, position)| GradientStop { color, position })
+            .collect();
+
+        let gradient = Gradient::new(&stops).ok_or_else(|| {
+            String::from("a palette needs 2 to 16 stops with their positions in order")
+        })?;
+
+        Ok(Palette {
+            gradient,
+            interpolation,
+        })
+    }
+}
+
+impl From<&Palette> for PaletteFile {
+    fn from(palette: &Palette) -> PaletteFile {
+        let stops = palette.gradient.stops();
+
+        PaletteFile {
+            colors: stops
+                .iter()
+                .map(|s| format!("#{}", gradient::to_hex(s.color)))
+                .collect(),
+            positions: Some(stops.iter().map(|s| s.position).collect()),
+            interpolation: palette.interpolation.map(|i| i.to_string()),
+        }
+    }
+}
+
+/// Parses the contents of a palette file.
+pub fn parse(contents: &str, format: ViewFormat) -> Result<Palette, String> {
+    let file: PaletteFile = match format {
+        ViewFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string())?,
+        ViewFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string())?,
+    };
+
+    Palette::try_from(file)
+}
+
+/// Reads a palette set, as JSON if the path ends in `.json` and as TOML otherwise, returning its
+/// palettes with their names in order of name.
+pub fn load_set<P: AsRef<Path>>(path: P) -> io::Result<Vec<(String, Palette)>> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)?;
+    let format = ViewFormat::from_path(path).unwrap_or(ViewFormat::Toml);
+
+    parse_set(&contents, format).map_err(|reason| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), reason),
+        )
+    })
+}
+
+/// Parses the contents of a palette set, a table of palette files by name.
+pub fn parse_set(contents: &str, format: ViewFormat) -> Result<Vec<(String, Palette)>, String> {
+    let set: BTreeMap<String, PaletteFile> = match format {
+        ViewFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string())?,
+        ViewFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string())?,
+    };
+
+    if set.is_empty() {
+        return Err(String::from("the set holds no palettes"));
+    }
+
+    set.into_iter()
+        .map(|(name, file)| {
+            let palette = Palette::try_from(file).map_err(|e| format!("{}: {}", name, e))?;
+            Ok((name, palette))
+        })
+        .collect()
+}
+
+/// The contents of a palette file holding `palette`.
+pub fn to_string(palette: &Palette, format: ViewFormat) -> Result<String, String> {
+    let file = PaletteFile::from(palette);
+
+    match format {
+        ViewFormat::Toml => toml::to_string(&file).map_err(|e| e.to_string()),
+        ViewFormat::Json => serde_json::to_string_pretty(&file)
+            .map(|json| json + "\n")
+            .map_err(|e| e.to_string()),
+    }
+}
@@ -0,0 +1,213 @@
+//! Paths of `c` recorded while exploring interactively, which can be smoothed and resampled into
+//! the frames of an offline render that follows the exploration.
+//!
+//! Paths are saved as plain text, one point per line: the time in seconds since recording started
+//! and the real and imaginary parts of `c`, separated by whitespace. Lines starting with `#` are
+//! comments.
+
+use gramit::Vec2;
+
+use crate::atomic::write_atomic;
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A point of a path: the value of `c` at some time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathPoint {
+    /// Seconds since the start of the path.
+    pub time: f32,
+    pub c: Vec2,
+}
+
+/// A path of `c` through time, with its points in time order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CPath {
+    points: Vec<PathPoint>,
+}
+
+impl CPath {
+    pub fn new() -> CPath {
+        CPath::default()
+    }
+
+    /// A path through the given points, which are sorted by time.
+    pub fn from_points(mut points: Vec<PathPoint>) -> CPath {
+        points.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+        CPath { points }
+    }
+
+    pub fn points(&self) -> &[PathPoint] {
+        &self.points
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The time of the last point.
+    pub fn duration(&self) -> f32 {
+        self.points.last().map(|p| p.time).unwrap_or(0.0)
+    }
+
+    /// Appends a point, which must be no earlier than the last one.
+    pub fn push(&mut self, time: f32, c: Vec2) {
+        assert!(
+            time >= self.duration(),
+            "path points must be added in time order"
+        );
+        self.points.push(PathPoint { time, c });
+    }
+
+    /// The value of `c` at the given time, interpolated linearly between points and held constant
+    /// before the first and after the last.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path is empty.
+    pub fn at(&self, time: f32) -> Vec2 {
+        let next = self.points.iter().position(|p| p.time > time);
+
+        match next {
+            Some(0) => self.points[0].c,
+            Some(i) => {
+                let (a, b) = (self.points[i - 1], self.points[i]);
+                let t = (time - a.time) / (b.time - a.time);
+                a.c + t * (b.c - a.c)
+            }
+            None => self.points.last().expect("the path is empty").c,
+        }
+    }
+
+    /// The path with the jitter of a hand-drawn movement evened out: every point is replaced by
+    /// the average of the points around it, weighted by a Gaussian of the time between them with
+    /// the given standard deviation in seconds. Timing is kept as it is.
+    pub fn smoothed(&self, sigma: f32) -> CPath {
+        if sigma <= 0.0 {
+            return self.clone();
+        }
+
+        let points = self
+            .points
+            .iter()
+            .map(|p| {
+                let mut sum = Vec2::zeros();
+                let mut weights = 0.0;
+
+                for q in &self.points {
+                    let dt = q.time - p.time;
+                    if dt.abs() <= 3.0 * sigma {
+                        let w = (-dt * dt / (2.0 * sigma * sigma)).exp();
+                        sum += w * q.c;
+                        weights += w;
+                    }
+                }
+
+                PathPoint {
+                    time: p.time,
+                    c: (1.0 / weights) * sum,
+                }
+            })
+            .collect();
+
+        CPath { points }
+    }
+
+    /// Values of `c` at `fps` evenly spaced times per second, from the start of the path to its
+    /// end, for a render that keeps the pace of the recording.
+    pub fn resample(&self, fps: f32) -> Vec<Vec2> {
+        if self.points.is_empty() {
+            return Vec::new();
+        }
+
+        let frames = (self.duration() * fps).floor() as usize + 1;
+        (0..frames).map(|i| self.at(i as f32 / fps)).collect()
+    }
+
+    /// Reads a path saved by `write`.
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<CPath> {
+        let file = BufReader::new(File::open(path)?);
+        let mut points = Vec::new();
+
+        for (i, line) in file.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let values: Option<Vec<f32>> =
+                line.split_whitespace().map(|v| v.parse().ok()).collect();
+            match values.as_ref().map(Vec::as_slice) {
+                Some([time, re, im]) => points.push(PathPoint {
+                    time: *time,
+                    c: vec2!(*re, *im),
+                }),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {}: expected a time and two parts of c", i + 1),
+                    ))
+                }
+            }
+        }
+
+        Ok(CPath::from_points(points))
+    }
+
+    /// Writes the path to a file, replacing it atomically.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        write_atomic(path.as_ref(), |tmp| {
+            let mut file = BufWriter::new(File::create(tmp)?);
+
+            writeln!(file, "# time re(c) im(c)")?;
+            for p in &self.points {
+                writeln!(file, "{} {} {}", p.time, p.c.x, p.c.y)?;
+            }
+
+            file.flush()
+        })
+    }
+}
+
+/// Records `c` as it changes in the interactive interface.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathRecorder {
+    start: Instant,
+    last_recorded: Option<Instant>,
+    path: CPath,
+}
+
+impl PathRecorder {
+    /// Points are recorded no more often than this.
+    pub const INTERVAL: Duration = Duration::from_millis(10);
+
+    /// Starts recording at `now`.
+    pub fn new(now: Instant) -> PathRecorder {
+        PathRecorder {
+            start: now,
+            last_recorded: None,
+            path: CPath::new(),
+        }
+    }
+
+    /// Records the value of `c` at `now`, unless a point was recorded less than `INTERVAL` before.
+    pub fn record(&mut self, c: Vec2, now: Instant) {
+        if let Some(last) = self.last_recorded {
+            if now.duration_since(last) < Self::INTERVAL {
+                return;
+            }
+        }
+
+        let time = now.duration_since(self.start).as_secs_f32();
+        self.path.push(time, c);
+        self.last_recorded = Some(now);
+    }
+
+    /// Stops recording, returning the recorded path.
+    pub fn finish(self) -> CPath {
+        self.path
+    }
+}
@@ -0,0 +1,32 @@
+//! The PCG hash used by the shaders, stepped as a generator for seeded randomness on the CPU.
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Pcg {
+    state: u32,
+}
+
+impl Pcg {
+    pub(crate) fn new(seed: u32) -> Pcg {
+        Pcg { state: seed }
+    }
+
+    pub(crate) fn next_u32(&mut self) -> u32 {
+        self.state = self
+            .state
+            .wrapping_mul(747_796_405)
+            .wrapping_add(2_891_336_453);
+        let word =
+            ((self.state >> ((self.state >> 28) + 4)) ^ self.state).wrapping_mul(277_803_737);
+        (word >> 22) ^ word
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / 16_777_216.0
+    }
+
+    /// Uniform in `[-1, 1)`.
+    pub(crate) fn next_signed(&mut self) -> f32 {
+        2.0 * self.next_f32() - 1.0
+    }
+}
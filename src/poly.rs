@@ -0,0 +1,167 @@
+//! Complex polynomials used in place of `x^n` in the iteration.
+
+use gramit::Vec2;
+
+use crate::formula::complex_mul;
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The highest degree of polynomial the shaders accept.
+pub const MAX_DEGREE: usize = 8;
+
+/// A complex polynomial of degree at most `MAX_DEGREE`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Polynomial {
+    /// Coefficients, constant term first. Entries past `len` are zero.
+    coefficients: [Vec2; MAX_DEGREE + 1],
+    len: usize,
+}
+
+impl Polynomial {
+    /// Creates a polynomial from its coefficients, constant term first. Returns `None` if its
+    /// degree is higher than `MAX_DEGREE`, ignoring leading zero coefficients.
+    pub fn new(coefficients: &[Vec2]) -> Option<Polynomial> {
+        let len = coefficients
+            .iter()
+            .rposition(|c| c.x != 0.0 || c.y != 0.0)
+            .map(|i| i + 1)
+            .unwrap_or(1);
+
+        if len > MAX_DEGREE + 1 {
+            return None;
+        }
+
+        let mut poly = Polynomial {
+            coefficients: [vec2!(0.0, 0.0); MAX_DEGREE + 1],
+            len,
+        };
+        for (c, orig) in poly.coefficients.iter_mut().zip(coefficients) {
+            *c = *orig;
+        }
+
+        Some(poly)
+    }
+
+    /// The coefficients up to the degree of the polynomial, constant term first.
+    pub fn coefficients(&self) -> &[Vec2] {
+        &self.coefficients[..self.len]
+    }
+
+    pub fn degree(&self) -> usize {
+        self.len - 1
+    }
+
+    pub fn eval(&self, z: Vec2) -> Vec2 {
+        self.coefficients()
+            .iter()
+            .rev()
+            .fold(vec2!(0.0, 0.0), |acc, &c| complex_mul(acc, z) + c)
+    }
+
+    pub fn derivative(&self) -> Polynomial {
+        let mut coefficients = [vec2!(0.0, 0.0); MAX_DEGREE];
+        for (i, c) in self.coefficients().iter().enumerate().skip(1) {
+            coefficients[i - 1] = i as f32 * *c;
+        }
+
+        // Can't fail, the degree only decreases
+        Polynomial::new(&coefficients).unwrap()
+    }
+
+    /// The coefficients packed two to a `vec4` for the shader's uniform block.
+    pub(crate) fn shader_coefficients(&self) -> [[f32; 4]; (MAX_DEGREE + 2) / 2] {
+        let mut packed = [[0f32; 4]; (MAX_DEGREE + 2) / 2];
+        for (i, c) in self.coefficients.iter().enumerate() {
+            packed[i / 2][2 * (i % 2)] = c.x;
+            packed[i / 2][2 * (i % 2) + 1] = c.y;
+        }
+
+        packed
+    }
+}
+
+/// Formats the coefficients highest degree first, separated by commas, in the same form that is
+/// parsed, e.g. `1,0,-0.5,0.2i`.
+impl Display for Polynomial {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (i, c) in self.coefficients().iter().rev().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+
+            if c.y == 0.0 {
+                write!(f, "{}", c.x)?;
+            } else if c.x == 0.0 {
+                write!(f, "{}i", c.y)?;
+            } else {
+                let op = if c.y < 0.0 { '-' } else { '+' };
+                write!(f, "{}{}{}i", c.x, op, c.y.abs())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Polynomial {
+    type Err = ParsePolynomialError;
+
+    /// Parses comma-separated complex coefficients, highest degree first. Each is a real number,
+    /// an imaginary number such as `0.2i`, or both such as `1-0.2i`.
+    fn from_str(s: &str) -> Result<Polynomial, ParsePolynomialError> {
+        let mut coefficients = s
+            .split(',')
+            .map(|c| parse_complex(c.trim()).ok_or(ParsePolynomialError))
+            .collect::<Result<Vec<_>, _>>()?;
+        coefficients.reverse();
+
+        Polynomial::new(&coefficients).ok_or(ParsePolynomialError)
+    }
+}
+
+fn parse_complex(s: &str) -> Option<Vec2> {
+    fn imaginary(s: &str) -> Option<f32> {
+        match s {
+            "" | "+" => Some(1.0),
+            "-" => Some(-1.0),
+            _ => s.parse().ok(),
+        }
+    }
+
+    if !s.ends_with('i') {
+        return Some(vec2!(s.parse().ok()?, 0.0));
+    }
+    let s = &s[..s.len() - 1];
+
+    // The real and imaginary parts are split at the last sign that isn't leading or part of an
+    // exponent.
+    let split = s
+        .char_indices()
+        .filter(|&(i, ch)| {
+            (ch == '+' || ch == '-') && i > 0 && !s[..i].ends_with(|c| c == 'e' || c == 'E')
+        })
+        .map(|(i, _)| i)
+        .last();
+
+    match split {
+        Some(i) => Some(vec2!(s[..i].parse().ok()?, imaginary(&s[i..])?)),
+        None => Some(vec2!(0.0, imaginary(s)?)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParsePolynomialError;
+
+impl Display for ParsePolynomialError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "failed to parse polynomial of degree at most {}",
+            MAX_DEGREE
+        )
+    }
+}
+
+impl Error for ParsePolynomialError {}
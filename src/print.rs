@@ -0,0 +1,196 @@
+//! Settings for images destined for print: the resolution, the paper they are trimmed to, and the
+//! bleed and safe margins around its edges. Exports record them in their metadata, and the
+//! interactive view can draw the margins as guides.
+
+use crate::ImgDimensions;
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+const MM_PER_INCH: f32 = 25.4;
+
+/// The size of a sheet of paper, in millimeters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaperSize {
+    /// The standard name of the size, if it has one.
+    pub name: Option<&'static str>,
+    pub width_mm: f32,
+    pub height_mm: f32,
+}
+
+impl PaperSize {
+    /// The named sizes, in portrait orientation.
+    pub const NAMED: [PaperSize; 6] = [
+        PaperSize::named("A3", 297.0, 420.0),
+        PaperSize::named("A4", 210.0, 297.0),
+        PaperSize::named("A5", 148.0, 210.0),
+        PaperSize::named("Letter", 215.9, 279.4),
+        PaperSize::named("Legal", 215.9, 355.6),
+        PaperSize::named("Tabloid", 279.4, 431.8),
+    ];
+
+    const fn named(name: &'static str, width_mm: f32, height_mm: f32) -> PaperSize {
+        PaperSize {
+            name: Some(name),
+            width_mm,
+            height_mm,
+        }
+    }
+
+    /// The same size turned sideways.
+    pub fn rotated(self) -> PaperSize {
+        PaperSize {
+            width_mm: self.height_mm,
+            height_mm: self.width_mm,
+            ..self
+        }
+    }
+}
+
+/// Formats the size as its name, or as `WIDTHxHEIGHTmm`, followed by `-landscape` if a named
+/// size is turned sideways.
+impl Display for PaperSize {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.name {
+            Some(name) if self.width_mm > self.height_mm => write!(f, "{}-landscape", name),
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "{}x{}mm", self.width_mm, self.height_mm),
+        }
+    }
+}
+
+impl FromStr for PaperSize {
+    type Err = ParsePaperSizeError;
+
+    /// Parses a named size such as `a4` or `letter`, optionally followed by `-landscape`, or an
+    /// explicit size such as `200x300mm` or `8x10in`.
+    fn from_str(s: &str) -> Result<PaperSize, ParsePaperSizeError> {
+        let lower = s.to_lowercase();
+        let (base, landscape) = match lower.trim_end_matches("-landscape") {
+            base if base.len() < lower.len() => (base, true),
+            base => (base, false),
+        };
+
+        let named = PaperSize::NAMED
+            .iter()
+            .find(|p| p.name.unwrap().to_lowercase() == base);
+        if let Some(&paper) = named {
+            return Ok(if landscape { paper.rotated() } else { paper });
+        }
+
+        let (dims, mm_per_unit) = if base.ends_with("mm") {
+            (&base[..base.len() - 2], 1.0)
+        } else if base.ends_with("in") {
+            (&base[..base.len() - 2], MM_PER_INCH)
+        } else {
+            return Err(ParsePaperSizeError);
+        };
+
+        let mut parts = dims.splitn(2, 'x');
+        let mut next = || -> Result<f32, ParsePaperSizeError> {
+            let v: f32 = parts
+                .next()
+                .and_then(|v| v.trim().parse().ok())
+                .ok_or(ParsePaperSizeError)?;
+            if v > 0.0 {
+                Ok(v * mm_per_unit)
+            } else {
+                Err(ParsePaperSizeError)
+            }
+        };
+
+        Ok(PaperSize {
+            name: None,
+            width_mm: next()?,
+            height_mm: next()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ParsePaperSizeError;
+
+impl Display for ParsePaperSizeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown paper size, expected a3, a4, a5, letter, legal, tabloid (optionally with \
+             -landscape), or a size such as 200x300mm or 8x10in"
+        )
+    }
+}
+
+impl Error for ParsePaperSizeError {}
+
+/// How an image is to be printed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrintSettings {
+    /// Printed pixels per inch.
+    pub dpi: f32,
+    /// The paper the print is trimmed to, if known.
+    pub paper: Option<PaperSize>,
+    /// How far the image extends past the trim line on every side, in millimeters, so that no
+    /// unprinted edge shows if the cut is off.
+    pub bleed_mm: f32,
+    /// How far inside the trim line important content should stay, in millimeters.
+    pub safe_mm: f32,
+}
+
+impl Default for PrintSettings {
+    fn default() -> PrintSettings {
+        PrintSettings {
+            dpi: 300.0,
+            paper: None,
+            bleed_mm: 3.0,
+            safe_mm: 5.0,
+        }
+    }
+}
+
+impl PrintSettings {
+    /// The number of printed pixels spanning the given length.
+    pub fn mm_to_pixels(&self, mm: f32) -> f32 {
+        mm / MM_PER_INCH * self.dpi
+    }
+
+    /// The image size that covers the paper and its bleed at the print resolution, rounded down to
+    /// multiples of 8 as the renderer requires. `None` if no paper is set.
+    pub fn dimensions(&self) -> Option<ImgDimensions> {
+        let paper = self.paper?;
+        let pixels = |mm: f32| {
+            let px = self.mm_to_pixels(mm + 2.0 * self.bleed_mm).max(8.0) as u32;
+            px / 8 * 8
+        };
+
+        Some(ImgDimensions {
+            width: pixels(paper.width_mm),
+            height: pixels(paper.height_mm),
+        })
+    }
+
+    /// The print resolution in pixels per meter, as PNG records it.
+    pub fn pixels_per_meter(&self) -> u32 {
+        (self.dpi / MM_PER_INCH * 1000.0).round() as u32
+    }
+}
+
+/// Describes the settings in words, e.g. `A4 (210 x 297 mm), 300 dpi, 3 mm bleed, 5 mm safe
+/// margin`.
+impl Display for PrintSettings {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if let Some(paper) = &self.paper {
+            write!(
+                f,
+                "{} ({} x {} mm), ",
+                paper, paper.width_mm, paper.height_mm
+            )?;
+        }
+
+        write!(
+            f,
+            "{} dpi, {} mm bleed, {} mm safe margin",
+            self.dpi, self.bleed_mm, self.safe_mm
+        )
+    }
+}
@@ -0,0 +1,83 @@
+//! Random gradients. Stops are picked in Oklch, the polar form of the Oklab perceptual space, with
+//! their lightness climbing steadily from dark to light and their hues turning by moderate steps,
+//! so that any seed gives a gradient that reads as a ramp rather than as noise.
+
+use gramit::Vec4;
+use palette::{LinSrgb, Srgb};
+
+use crate::gradient::Gradient;
+use crate::pcg::Pcg;
+
+use std::f32::consts::PI;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The fewest and most stops a random gradient has.
+const MIN_STOPS: u32 = 3;
+const MAX_STOPS: u32 = 6;
+
+/// A random gradient of opaque colors, the same for the same seed.
+pub fn random_gradient(seed: u32) -> Gradient {
+    let mut rng = Pcg::new(seed);
+
+    let len = MIN_STOPS + rng.next_u32() % (MAX_STOPS - MIN_STOPS + 1);
+    let dark = 0.15 + 0.15 * rng.next_f32();
+    let light = 0.8 + 0.15 * rng.next_f32();
+    let chroma = 0.08 + 0.1 * rng.next_f32();
+
+    // Hues turn the same way between every pair of stops, by between 20 and 80 degrees
+    let turn = if rng.next_f32() < 0.5 { 1.0 } else { -1.0 };
+    let mut hue = 2.0 * PI * rng.next_f32();
+
+    let colors: Vec<_> = (0..len)
+        .map(|i| {
+            let t = i as f32 / (len - 1) as f32;
+            let lightness = dark + t * (light - dark) + 0.03 * rng.next_signed();
+            let color = oklch_to_srgb(lightness, chroma * (0.7 + 0.6 * rng.next_f32()), hue);
+            hue += turn * (20.0 + 60.0 * rng.next_f32()).to_radians();
+            color
+        })
+        .collect();
+
+    Gradient::even(&colors).unwrap()
+}
+
+/// The non-linear sRGB color with the given Oklch coordinates, with its chroma reduced until it
+/// lies inside the sRGB gamut.
+fn oklch_to_srgb(lightness: f32, mut chroma: f32, hue: f32) -> Vec4 {
+    let lightness = lightness.max(0.0).min(1.0);
+
+    loop {
+        let [r, g, b] = oklab_to_linear(lightness, chroma * hue.cos(), chroma * hue.sin());
+        let in_gamut = [r, g, b].iter().all(|&c| c >= 0.0 && c <= 1.0);
+
+        if in_gamut || chroma < 1e-3 {
+            let clamp = |c: f32| c.max(0.0).min(1.0);
+            let (r, g, b) =
+                Srgb::from_linear(LinSrgb::new(clamp(r), clamp(g), clamp(b))).into_components();
+            return vec4!(r, g, b, 1.0);
+        }
+
+        chroma *= 0.9;
+    }
+}
+
+/// Oklab to linear sRGB, as in the gradient shader.
+fn oklab_to_linear(l: f32, a: f32, b: f32) -> [f32; 3] {
+    let l_ = l + 0.396_337_78 * a + 0.215_803_76 * b;
+    let m_ = l - 0.105_561_35 * a - 0.063_854_17 * b;
+    let s_ = l - 0.089_484_18 * a - 1.291_485_5 * b;
+    let (l, m, s) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+
+    [
+        4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+        -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+        -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+    ]
+}
+
+/// A seed taken from the clock, for when none is given.
+pub fn time_seed() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos() ^ d.as_secs() as u32)
+}
@@ -6,6 +6,142 @@ pub mod julia_comp {
     }
 }
 
+pub mod julia_strict_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_strict.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_iterate_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_iterate.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_iterate_strict_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_iterate_strict.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_newton_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_newton.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_newton_strict_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_newton_strict.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_tonemap_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_tonemap.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_tonemap_wide_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_tonemap_wide.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_potential_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_potential.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_escape_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_escape.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_cost_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_cost.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_buddhabrot_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_buddhabrot.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_buddhabrot_tonemap_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_buddhabrot_tonemap.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_nebulabrot_tonemap_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_nebulabrot_tonemap.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_hud_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_hud.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_guides_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_guides.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_strip_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_strip.comp",
+        //dump: true
+    }
+}
+
+pub mod julia_iim_comp {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "src/shaders/julia_iim.comp",
+        //dump: true
+    }
+}
+
 pub mod julia_vert {
     use gramit::{Vec2, Vec3};
 
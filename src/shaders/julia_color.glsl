@@ -0,0 +1,317 @@
+// The smooth iteration count of p, or -1 if its orbit doesn't escape. z is set to the last
+// iterate.
+float julia(vec2 p, float R, out vec2 z) {
+  uint i = escape(p, z, R);
+
+  if (i < data.iters) {
+    uint k = i + 1;
+
+    PRECISE float mu = log(log(length(z)) / log(R)) / log(degree());
+    if (mu < 0.0) {
+      mu = 0.0;
+    } else if (mu > 1.0) {
+      mu = 1.0;
+    }
+
+    PRECISE float nu = float(k) - mu;
+    return nu;
+  } else {
+    return -1.0;
+  }
+}
+
+// The estimated distance from p to the set in pixels, or -1 if its orbit doesn't escape. Uses the
+// standard exterior estimate |z| log|z| / |dz|, halved as for quadratic maps.
+float distance_estimate(vec2 p, float R, float pixel) {
+  vec2 z;
+  vec2 dz;
+  uint i = escape_with_derivative(p, z, dz, R, true);
+
+  if (i < data.iters) {
+    float r = length(z);
+    PRECISE float d = 0.5 * r * log(r) / length(dz);
+    return d / pixel;
+  } else {
+    return -1.0;
+  }
+}
+
+// The stripe average of p, between 0 and 1, or -1 if its orbit doesn't escape. The first iterate
+// is left out of the mean, since it only depends on the starting point. To keep the mean from
+// jumping where the iteration count does, it is blended with the mean without the last iterate
+// by the fractional part of the smooth iteration count.
+float stripe_average(vec2 p, float R) {
+  vec2 z;
+  vec2 c;
+  orbit_start(p, z, c);
+
+  if (data.interior != 0 && in_main_components(c)) {
+    return -1.0;
+  }
+
+  PRECISE float sum = 0.0;
+  PRECISE float last = 0.0;
+  uint count = 0;
+
+  uint i;
+  for (i = 0; i < data.iters; i += 1) {
+    if (length(z) > R) {
+      break;
+    }
+    z = mandelbrot_iteration(c, z, i);
+
+    if (i > 0) {
+      last = 0.5 + 0.5 * sin(data.stripe_density * atan(z.y, z.x));
+      sum += last;
+      count += 1;
+    }
+  }
+
+  if (i >= data.iters || count == 0) {
+    return -1.0;
+  }
+
+  PRECISE float mu = clamp(log(log(length(z)) / log(R)) / log(degree()), 0.0, 1.0);
+  PRECISE float mean = sum / float(count);
+  PRECISE float previous = count > 1 ? (sum - last) / float(count - 1) : mean;
+  PRECISE float stripes = mix(mean, previous, mu);
+  return stripes;
+}
+
+// The curvature average of p, between 0 and 1, or -1 if its orbit doesn't escape: the mean over
+// the orbit of the angle it turns through at each iterate, between the steps into and out of it,
+// as a fraction of a half turn. Like the stripe average, it leaves out the first iterate, which has
+// no step into it, and is blended with the mean without the last iterate by the fractional part of
+// the smooth iteration count.
+float curvature_average(vec2 p, float R) {
+  vec2 z;
+  vec2 c;
+  orbit_start(p, z, c);
+
+  if (data.interior != 0 && in_main_components(c)) {
+    return -1.0;
+  }
+
+  PRECISE float sum = 0.0;
+  PRECISE float last = 0.0;
+  uint count = 0;
+  vec2 prev_step = vec2(0.0);
+
+  uint i;
+  for (i = 0; i < data.iters; i += 1) {
+    if (length(z) > R) {
+      break;
+    }
+    vec2 next = mandelbrot_iteration(c, z, i);
+    PRECISE vec2 next_step = next - z;
+
+    // Orbits that stand still for a step don't turn
+    if (i > 0 && prev_step != vec2(0.0) && next_step != vec2(0.0)) {
+      PRECISE float turn = prev_step.x * next_step.y - prev_step.y * next_step.x;
+      last = abs(atan(turn, dot(prev_step, next_step))) / HALF_TURN;
+      sum += last;
+      count += 1;
+    }
+
+    prev_step = next_step;
+    z = next;
+  }
+
+  if (i >= data.iters || count == 0) {
+    return -1.0;
+  }
+
+  PRECISE float mu = clamp(log(log(length(z)) / log(R)) / log(degree()), 0.0, 1.0);
+  PRECISE float mean = sum / float(count);
+  PRECISE float previous = count > 1 ? (sum - last) / float(count - 1) : mean;
+  PRECISE float curvature = mix(mean, previous, mu);
+  return curvature;
+}
+
+// The value colored for p under the current coloring mode: the smooth iteration count, the
+// distance estimate, or the stripe or curvature average, -1 inside the set in every case. last is
+// set to the last iterate for the modes that color by the smooth iteration count, and left at 0
+// otherwise.
+float coloring_value(vec2 p, float R, float pixel, out vec2 last) {
+  last = vec2(0.0);
+  if (data.coloring == COLORING_DISTANCE) {
+    return distance_estimate(p, R, pixel);
+  } else if (data.coloring == COLORING_STRIPES) {
+    return stripe_average(p, R);
+  } else if (data.coloring == COLORING_CURVATURE) {
+    return curvature_average(p, R);
+  } else {
+    return julia(p, R, last);
+  }
+}
+
+// The fraction of the view's escaping points that escape sooner than one with smooth iteration
+// count nu, interpolated between quantiles of their log potential. The log potential falls as the
+// count grows: log G = log log R - (nu - 1) log d.
+float equalized(float nu, float R) {
+  PRECISE float g = log(log(R)) - (nu - 1.0) * log(degree());
+
+  float previous = data.equalization[0][0];
+  if (g >= previous) {
+    return 0.0;
+  }
+
+  for (uint k = 1; k < 16; k += 1) {
+    float stop = data.equalization[k / 4][k % 4];
+    if (g >= stop) {
+      PRECISE float f = (previous - g) / max(previous - stop, 1e-20);
+      return (float(k - 1) + f) / 15.0;
+    }
+    previous = stop;
+  }
+  return 1.0;
+}
+
+float interpolant(float nu, float R) {
+  if (nu < 0.0) {
+    return 1.0;
+  }
+
+  if (data.coloring == COLORING_DISTANCE) {
+    // Distances of 0 take the end of the gradient, like the interior
+    PRECISE float t = exp(-nu / max(data.color_scale, 1e-3)) + data.color_offset;
+    return t;
+  } else if (data.coloring == COLORING_STRIPES || data.coloring == COLORING_CURVATURE) {
+    PRECISE float t = data.color_scale * nu + data.color_offset;
+    return t;
+  }
+
+  if (data.color_period > 0.0) {
+    PRECISE float t = fract(data.color_scale * nu / data.color_period + data.color_offset);
+    return t;
+  }
+
+  PRECISE float spread = data.equalize != 0 ? equalized(nu, R) : nu / float(data.iters);
+  PRECISE float t = data.color_scale * spread + data.color_offset;
+  return t;
+}
+
+// The color of an escaping sample under COLORING_CHANNELS, with smooth iteration count nu and last
+// iterate z: its red, green and blue are the iteration count as a fraction of the limit, the
+// fractional part of the escape measured by log log |z|, and the angle of z as a fraction of a
+// turn. Each is scaled and shifted by its channel's parameters and taken as non-linear sRGB.
+vec4 channel_color(float nu, vec2 z, float R) {
+  PRECISE float count = nu / float(data.iters);
+  PRECISE float radius = clamp(log(log(length(z)) / log(R)) / log(degree()), 0.0, 1.0);
+  PRECISE float angle = atan(z.y, z.x) / (2.0 * HALF_TURN) + 0.5;
+
+  PRECISE vec3 rgb = data.channel_scale.rgb * vec3(count, radius, angle) + data.channel_offset.rgb;
+  return vec4(srgb_to_linear(clamp(rgb, 0.0, 1.0)), 1.0);
+}
+
+// The color of a sample with colored value nu and last iterate z: the interior color inside the
+// set if one is set, and the gradient's otherwise. Under binary decomposition, escaping samples
+// whose last iterate lies below the real axis take the gradient mirrored, so neighboring cells
+// contrast and the field lines between them show.
+vec4 sample_color(float nu, vec2 z, float R) {
+  if (nu < 0.0 && data.interior_colored != 0) {
+    return data.interior_color;
+  }
+  if (nu >= 0.0 && data.coloring == COLORING_CHANNELS) {
+    return channel_color(nu, z, R);
+  }
+
+  bool below = data.coloring == COLORING_BINARY && nu >= 0.0 && z.y < 0.0;
+  PRECISE float t = interpolant(nu, R);
+  if (below) {
+    t = 1.0 - t;
+  }
+  return interpolate_color(cycle_interpolant(t));
+}
+
+// The height of the relief at a sample with colored value nu: nu itself, or the distance estimate
+// for SHADING_DISTANCE, which is only estimated again if it isn't what's colored. -1 inside the set
+// either way.
+float relief_height(vec2 p, float nu, float R, float pixel) {
+  if (data.shading != SHADING_DISTANCE || data.coloring == COLORING_DISTANCE) {
+    return nu;
+  }
+  return distance_estimate(p, R, pixel);
+}
+
+// Lights the color as if the relief heights were a height field, with a Lambertian term and a
+// Blinn-Phong highlight. The gradient comes from the four samples of the pixel, which lie on a
+// grid a third of a pixel apart, so the relief doesn't depend on the zoom. Distance estimates are
+// in pixels, so their slopes don't depend on the zoom either.
+vec3 shade(vec3 color, float h1, float h2, float h3, float h4) {
+  // Interior points have no slope, and the boundary has no useful one
+  if (h1 < 0.0 || h2 < 0.0 || h3 < 0.0 || h4 < 0.0) {
+    return color;
+  }
+
+  // Samples 2 and 4 are lower in the image, which is higher on the imaginary axis
+  PRECISE float dx = 1.5 * ((h3 + h4) - (h1 + h2));
+  PRECISE float dy = 1.5 * ((h1 + h3) - (h2 + h4));
+
+  // Distance grows away from the set, so it's turned upside down to make the set the ridge
+  if (data.shading == SHADING_DISTANCE) {
+    dx = -dx;
+    dy = -dy;
+  }
+
+  vec3 normal = normalize(vec3(-dx, -dy, 1.0));
+
+  vec2 light = data.light;
+  vec3 dir = vec3(cos(light.y) * cos(light.x), cos(light.y) * sin(light.x), sin(light.y));
+  vec3 halfway = normalize(dir + vec3(0.0, 0.0, 1.0));
+
+  float diffuse = max(dot(normal, dir), 0.0);
+  float specular = pow(max(dot(normal, halfway), 0.0), 32.0);
+
+  PRECISE vec3 lit = color * (0.25 + 0.75 * diffuse) + vec3(0.3 * specular);
+  return lit;
+}
+
+#ifdef JULIA_ITERATE
+
+// The iteration pass: finds the colored value, relief height and last iterate of each sample,
+// which is all the coloring pass needs of their orbits.
+void main() {
+  float R = 250.0 * degree();
+  ivec2 size = imageSize(img);
+  float pixel = pixel_size(size);
+
+  PixelSamples s;
+  for (uint k = 0; k < 4; k += 1) {
+    vec2 z = get_z(sample_offset(k), size);
+    vec2 last;
+    float nu = coloring_value(z, R, pixel, last);
+
+    s.values[k] = nu;
+    s.heights[k] = relief_height(z, nu, R, pixel);
+    s.last[k] = last;
+  }
+
+  samples.pixels[pixel_index(size)] = s;
+}
+
+#else
+
+// The coloring pass: colors the samples found by the iteration pass, composites the trap picture
+// over them and shades them. Only the trap needs the orbits again.
+void main() {
+  float R = 250.0 * degree();
+  ivec2 size = imageSize(img);
+  PixelSamples s = samples.pixels[pixel_index(size)];
+
+  vec4 colors[4];
+  for (uint k = 0; k < 4; k += 1) {
+    colors[k] = sample_color(s.values[k], s.last[k], R);
+    if (data.trap != 0) {
+      colors[k] = apply_trap(colors[k], get_z(sample_offset(k), size), R);
+    }
+  }
+
+  PRECISE vec4 color = average_samples(colors[0], colors[1], colors[2], colors[3]);
+  if (data.shading != SHADING_NONE) {
+    color.rgb = shade(color.rgb, s.heights[0], s.heights[1], s.heights[2], s.heights[3]);
+  }
+  imageStore(img, ivec2(gl_GlobalInvocationID.xy), color);
+}
+
+#endif
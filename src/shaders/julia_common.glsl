@@ -0,0 +1,461 @@
+layout(set = 0, binding = 1) uniform Data {
+  // Gradient stops: linear colors, and their positions four to a vec4. Entries past color_stops
+  // repeat the last stop.
+  vec4 color[16];
+  vec4 color_pos[4];
+  // Polynomial coefficients, constant term first, two to a vec4
+  vec4 poly[5];
+  // Denominator coefficients, packed the same way
+  vec4 denom[5];
+  // Quantiles of the view's log potential for equalization, sixteen of them from highest to
+  // lowest, four to an element
+  vec4 equalization[4];
+
+  uint color_stops;
+  float n;
+  vec2 c;
+
+  uint iters;
+  uint kind;
+
+  vec2 center;
+  vec2 extents;
+
+  // Number of polynomial coefficients, or 0 to iterate x^n
+  uint poly_len;
+
+  // Applied to normalized iteration counts before they are mapped into the gradient
+  float color_offset;
+  float color_scale;
+
+  // Number of denominator coefficients, or 0 to iterate a polynomial
+  uint denom_len;
+
+  // The starting point of orbits for the kinds that take c from the pixel
+  vec2 z0;
+
+  // The position of the rendered image within a larger one, and the size of that image, or zero
+  // to render a whole image
+  uvec2 origin;
+  uvec2 full_size;
+
+  // Azimuth and elevation of the slope shading light, in radians
+  vec2 light;
+  // The height field lit by slope shading, SHADING_NONE for no shading
+  uint shading;
+
+  // Hybrid formula program: the fold before each step, two bits per step with the first lowest,
+  // repeating every hybrid_len steps. 0 means no hybrid, so the kind decides the fold.
+  uint hybrid;
+  uint hybrid_len;
+
+  // Whether pixels are starting points (PLANE_DYNAMICAL) or values of c (PLANE_PARAMETER)
+  uint plane;
+
+  // Nonzero to stop iterating orbits that are found to be periodic
+  uint periodicity;
+
+  // Nonzero to skip iterating points of the main cardioid and period-2 bulb of the Mandelbrot set.
+  // Only set for the unperturbed quadratic parameter plane.
+  uint interior;
+
+  // Cosine and sine of the angle the view is turned counterclockwise by
+  vec2 rotation;
+
+  // What escaping points are colored by, one of the COLORING_* constants
+  uint coloring;
+
+  // Nonzero to stamp the trap image onto orbits, centered on trap_center and trap_size across
+  uint trap;
+  vec2 trap_center;
+  vec2 trap_size;
+
+  // Stripes per turn for COLORING_STRIPES
+  float stripe_density;
+
+  // Nonzero to equalize the iteration count by the quantiles in equalization
+  uint equalize;
+
+  // Iterations after which the gradient repeats, or 0 to clamp it
+  float color_period;
+
+  // The space gradient stops are blended in, one of the INTERPOLATION_* constants
+  uint interpolation;
+
+  // The linear color of points that don't escape, used if interior_colored is nonzero
+  vec4 interior_color;
+  uint interior_colored;
+
+  // Tone adjustments: a linear multiplier, the reciprocal of an exponent, and the exponent of
+  // intensities relative to middle gray
+  float exposure;
+  float gamma;
+  float contrast;
+
+  // Nonzero to dither colors before they're quantized
+  uint dither;
+
+  // How linear intensities are brought into the displayable range, one of the CURVE_* constants
+  uint tone_curve;
+
+  // The space exported colors are mapped into, one of the SPACE_* constants. Colors are always
+  // computed with sRGB primaries; in wider spaces they may lie outside of [0, 1].
+  uint color_space;
+
+  // The transfer function the tonemapping pass and the overlays encode the colors they store with,
+  // one of the TRANSFER_* constants
+  uint transfer;
+
+  // Scales and offsets of the red, green and blue values of COLORING_CHANNELS; w is unused
+  vec4 channel_scale;
+  vec4 channel_offset;
+
+  // The fraction of its span the gradient is turned by, for color cycling
+  float color_phase;
+} data;
+
+const uint KIND_JULIA = 0;
+const uint KIND_MANDELBROT = 1;
+const uint KIND_BURNING_SHIP = 2;
+const uint KIND_TRICORN = 3;
+
+const uint PLANE_DYNAMICAL = 0;
+const uint PLANE_PARAMETER = 1;
+
+const uint COLORING_ITERATIONS = 0;
+const uint COLORING_DISTANCE = 1;
+const uint COLORING_STRIPES = 2;
+const uint COLORING_BINARY = 3;
+const uint COLORING_CHANNELS = 4;
+const uint COLORING_CURVATURE = 5;
+
+const uint SHADING_NONE = 0;
+const uint SHADING_ITERATIONS = 1;
+const uint SHADING_DISTANCE = 2;
+
+const uint FOLD_NONE = 0;
+const uint FOLD_ABS = 1;
+const uint FOLD_CONJ = 2;
+
+const uint SPACE_SRGB = 0;
+const uint SPACE_DISPLAY_P3 = 1;
+
+const uint TRANSFER_LINEAR = 0;
+const uint TRANSFER_SRGB = 1;
+
+// Conversions between linear colors with the sRGB and the Display P3 primaries, which share the
+// D65 white point. The matrices are given by columns.
+const mat3 SRGB_TO_P3 = mat3(
+    0.8224621, 0.0331941, 0.0170827,
+    0.1775380, 0.9668058, 0.0723974,
+    0.0000000, 0.0000000, 0.9105199);
+const mat3 P3_TO_SRGB = mat3(
+    1.2249401, -0.0420569, -0.0196376,
+    -0.2249404, 1.0420571, -0.0786361,
+    0.0000000, 0.0000000, 1.0982735);
+
+// In strict mode every intermediate result is declared precise, which forbids the compiler from
+// reassociating, contracting (e.g. into fused multiply-adds) or otherwise reordering the math.
+#ifdef JULIA_STRICT
+#define PRECISE precise
+#else
+#define PRECISE
+#endif
+
+vec3 srgb_to_linear(vec3 c) {
+  return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), greaterThan(c, vec3(0.04045)));
+}
+
+vec3 linear_to_srgb(vec3 c) {
+  return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, greaterThan(c, vec3(0.0031308)));
+}
+
+// Encodes a linear color with the transfer function of the image it's stored in.
+vec4 encode_transfer(vec4 c) {
+  if (data.transfer == TRANSFER_SRGB) {
+    return vec4(linear_to_srgb(max(c.rgb, vec3(0.0))), c.a);
+  }
+
+  return c;
+}
+
+vec2 complex_mul(vec2 a, vec2 b) {
+  PRECISE vec2 r = vec2(
+      a.x * b.x - a.y * b.y,
+      a.x * b.y + a.y * b.x
+  );
+  return r;
+}
+
+vec2 complex_exp(vec2 z, uint n) {
+  PRECISE vec2 zn = vec2(1.0, 0.0);
+
+  for (uint i = 0; i < n; i += 1) {
+    zn = complex_mul(zn, z);
+  }
+
+  return zn;
+}
+
+// z^n for real n. Whole powers are multiplied out, which is more accurate and matches the
+// integer-only exponents of earlier versions exactly; others use the principal branch.
+vec2 complex_pow(vec2 z, float n) {
+  if (n >= 0.0 && n == floor(n)) {
+    return complex_exp(z, uint(n));
+  } else if (z == vec2(0.0)) {
+    return vec2(0.0);
+  }
+
+  PRECISE float r = pow(length(z), n);
+  PRECISE float theta = n * atan(z.y, z.x);
+  PRECISE vec2 zn = r * vec2(cos(theta), sin(theta));
+  return zn;
+}
+
+vec2 complex_div(vec2 a, vec2 b) {
+  PRECISE vec2 r = vec2(a.x * b.x + a.y * b.y, a.y * b.x - a.x * b.y) / dot(b, b);
+  return r;
+}
+
+vec2 coeff(vec4 coeffs[5], uint i) {
+  vec4 v = coeffs[i / 2];
+  return i % 2 == 0 ? v.xy : v.zw;
+}
+
+vec2 eval_coeffs(vec4 coeffs[5], uint len, vec2 z) {
+  PRECISE vec2 r = vec2(0.0);
+
+  for (uint i = len; i > 0; i -= 1) {
+    r = complex_mul(r, z) + coeff(coeffs, i - 1);
+  }
+
+  return r;
+}
+
+vec2 poly_eval(vec2 z) {
+  return eval_coeffs(data.poly, data.poly_len, z);
+}
+
+vec2 eval_coeffs_derivative(vec4 coeffs[5], uint len, vec2 z) {
+  PRECISE vec2 r = vec2(0.0);
+
+  for (uint i = len; i > 1; i -= 1) {
+    r = complex_mul(r, z) + float(i - 1) * coeff(coeffs, i - 1);
+  }
+
+  return r;
+}
+
+vec2 poly_derivative(vec2 z) {
+  return eval_coeffs_derivative(data.poly, data.poly_len, z);
+}
+
+// The degree of the iterated polynomial, which determines how fast escaping orbits grow. For a
+// rational map this is the difference of the degrees, but at least 1.
+float degree() {
+  float p = data.poly_len > 0 ? float(data.poly_len - 1) : data.n;
+  if (data.denom_len > 0) {
+    float q = float(data.denom_len - 1);
+    return max(p - q, 1.0);
+  }
+  return p;
+}
+
+// The fold applied before the i-th iteration step.
+uint fold_at(uint i) {
+  if (data.hybrid_len > 0) {
+    return (data.hybrid >> (2 * (i % data.hybrid_len))) & 3;
+  } else if (data.kind == KIND_BURNING_SHIP) {
+    return FOLD_ABS;
+  } else if (data.kind == KIND_TRICORN) {
+    return FOLD_CONJ;
+  } else {
+    return FOLD_NONE;
+  }
+}
+
+// The i-th iteration step, counting from 0.
+vec2 mandelbrot_iteration(vec2 c, vec2 z, uint i) {
+  uint fold = fold_at(i);
+  if (fold == FOLD_ABS) {
+    z = abs(z);
+  } else if (fold == FOLD_CONJ) {
+    z.y = -z.y;
+  }
+
+  PRECISE vec2 r;
+  if (data.poly_len > 0) {
+    r = poly_eval(z);
+  } else {
+    r = complex_pow(z, data.n);
+  }
+
+  if (data.denom_len > 0) {
+    r = complex_div(r, eval_coeffs(data.denom, data.denom_len, z));
+  }
+
+  r = r + c;
+  return r;
+}
+
+// The derivative of the i-th iteration step at z, applied to dz, the derivative of z with respect
+// to the pixel. Folds aren't holomorphic, so for them dz is folded alongside z, which keeps its
+// length and is close enough for distance estimates.
+vec2 iteration_derivative(vec2 z, vec2 dz, uint i) {
+  uint fold = fold_at(i);
+  if (fold == FOLD_ABS) {
+    dz *= vec2(z.x < 0.0 ? -1.0 : 1.0, z.y < 0.0 ? -1.0 : 1.0);
+    z = abs(z);
+  } else if (fold == FOLD_CONJ) {
+    z.y = -z.y;
+    dz.y = -dz.y;
+  }
+
+  vec2 d;
+  if (data.poly_len > 0) {
+    d = poly_derivative(z);
+  } else {
+    d = data.n * complex_pow(z, data.n - 1.0);
+  }
+
+  if (data.denom_len > 0) {
+    // (P/Q)' = (P'Q - PQ') / Q^2
+    vec2 p = data.poly_len > 0 ? poly_eval(z) : complex_pow(z, data.n);
+    vec2 q = eval_coeffs(data.denom, data.denom_len, z);
+    vec2 dq = eval_coeffs_derivative(data.denom, data.denom_len, z);
+    d = complex_div(complex_mul(d, q) - complex_mul(p, dq), complex_mul(q, q));
+  }
+
+  return complex_mul(d, dz);
+}
+
+// The initial iterate and the constant for the orbit of the point p. In the dynamical plane p is
+// iterated itself with a fixed c, while in the parameter plane orbits start from z0 (the critical
+// point 0 by default) with p as c.
+void orbit_start(vec2 p, out vec2 z, out vec2 c) {
+  if (data.plane == PLANE_DYNAMICAL) {
+    z = p;
+    c = data.c;
+  } else {
+    z = data.z0;
+    c = p;
+  }
+}
+
+// Whether c lies in the main cardioid or the period-2 bulb of the Mandelbrot set, where every
+// orbit is bounded.
+bool in_main_components(vec2 c) {
+  vec2 d = c - vec2(0.25, 0.0);
+  float q = dot(d, d);
+  if (q * (q + d.x) <= 0.25 * c.y * c.y) {
+    return true;
+  }
+
+  vec2 e = c + vec2(1.0, 0.0);
+  return dot(e, e) <= 0.0625;
+}
+
+// The number of iteration steps taken by the last call to escape, which is fewer than it reports
+// for orbits found to be periodic, and none for points known to be inside up front.
+uint escape_cost = 0;
+
+// How close an orbit must come back to an earlier iterate to be taken as periodic.
+const float PERIODICITY_TOLERANCE = 1e-6;
+
+// Iterates the orbit of p until it leaves the disk of radius R or the iteration limit is reached.
+// Returns the number of iterations performed, leaving the final iterate in z. Points known to be
+// inside the set without iterating are reported as reaching the limit straight away.
+//
+// With periodicity checking, orbits that return to an earlier iterate stop early and are reported
+// as reaching the limit. After Brent, the iterate compared against is replaced at doubling
+// intervals, so that cycles of any length are found within a small multiple of the iterations it
+// takes to fall into them. Hybrids only compare iterates at the start of their step schedule,
+// since the same point recurs with a different fold otherwise.
+//
+// With derivative set, dz is left holding the derivative of the final iterate with respect to p.
+// Otherwise it is left at its starting value.
+uint escape_with_derivative(vec2 p, out vec2 z, out vec2 dz, float R, bool derivative) {
+  vec2 c;
+  orbit_start(p, z, c);
+
+  // In the parameter plane p enters through c, which adds 1 to the derivative at every step
+  bool parameter = data.plane == PLANE_PARAMETER;
+  dz = parameter ? vec2(0.0) : vec2(1.0, 0.0);
+
+  escape_cost = 0;
+  if (data.interior != 0 && in_main_components(c)) {
+    return data.iters;
+  }
+
+  uint step = max(data.hybrid_len, 1);
+  vec2 saved = z;
+  uint interval = 1;
+  uint since_saved = 0;
+
+  uint i;
+  for (i = 0; i < data.iters; i += 1) {
+    if (length(z) > R) {
+      break;
+    } else {
+      if (derivative) {
+        dz = iteration_derivative(z, dz, i) + (parameter ? vec2(1.0, 0.0) : vec2(0.0));
+      }
+      z = mandelbrot_iteration(c, z, i);
+    }
+
+    if (data.periodicity != 0 && (i + 1) % step == 0) {
+      if (distance(z, saved) < PERIODICITY_TOLERANCE) {
+        escape_cost = i + 1;
+        return data.iters;
+      }
+
+      since_saved += 1;
+      if (since_saved == interval) {
+        saved = z;
+        since_saved = 0;
+        interval *= 2;
+      }
+    }
+  }
+
+  escape_cost = i;
+  return i;
+}
+
+uint escape(vec2 p, out vec2 z, float R) {
+  vec2 dz;
+  return escape_with_derivative(p, z, dz, R, false);
+}
+
+// The side of a pixel in the complex plane.
+float pixel_size(ivec2 size) {
+  vec2 full = data.full_size.x > 0 ? vec2(data.full_size) : vec2(size);
+  return data.extents.x / full.x;
+}
+
+// The color of a pixel from its four samples. Samples are weighted by their alpha, so that
+// transparent ones thin out the pixel without darkening the others.
+vec4 average_samples(vec4 c1, vec4 c2, vec4 c3, vec4 c4) {
+  PRECISE float alpha = c1.a + c2.a + c3.a + c4.a;
+  if (alpha <= 0.0) {
+    return vec4(0.0);
+  }
+
+  PRECISE vec3 rgb = (c1.rgb * c1.a + c2.rgb * c2.a + c3.rgb * c3.a + c4.rgb * c4.a) / alpha;
+  return vec4(rgb, 0.25 * alpha);
+}
+
+vec2 get_z(vec2 offs, ivec2 size) {
+  vec2 full = data.full_size.x > 0 ? vec2(data.full_size) : vec2(size);
+
+  PRECISE vec2 z =
+    (((gl_GlobalInvocationID.xy + data.origin + offs) * 2.0 / full)) - vec2(1.0);
+  z.y = -z.y;
+
+  PRECISE vec2 r = z * (data.extents / 2.0);
+  PRECISE vec2 p = data.center + vec2(
+      r.x * data.rotation.x - r.y * data.rotation.y,
+      r.x * data.rotation.y + r.y * data.rotation.x
+  );
+  return p;
+}
@@ -0,0 +1,176 @@
+const uint INTERPOLATION_RGB = 0;
+const uint INTERPOLATION_OKLAB = 1;
+const uint INTERPOLATION_LCH = 2;
+const uint INTERPOLATION_HSV = 3;
+const uint INTERPOLATION_HSV_LONG = 4;
+
+const float HALF_TURN = 3.14159265358979;
+
+float cbrt(float x) {
+  return sign(x) * pow(abs(x), 1.0 / 3.0);
+}
+
+vec3 linear_to_oklab(vec3 c) {
+  float l = cbrt(0.4122214708 * c.r + 0.5363325363 * c.g + 0.0514459929 * c.b);
+  float m = cbrt(0.2119034982 * c.r + 0.6806995451 * c.g + 0.1073969566 * c.b);
+  float s = cbrt(0.0883024619 * c.r + 0.2817188376 * c.g + 0.6299787005 * c.b);
+
+  return vec3(
+      0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+      1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+      0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s
+  );
+}
+
+vec3 oklab_to_linear(vec3 c) {
+  float l = c.x + 0.3963377774 * c.y + 0.2158037573 * c.z;
+  float m = c.x - 0.1055613458 * c.y - 0.0638541728 * c.z;
+  float s = c.x - 0.0894841775 * c.y - 1.2914855480 * c.z;
+  l = l * l * l;
+  m = m * m * m;
+  s = s * s * s;
+
+  return vec3(
+      4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+      -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+      -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s
+  );
+}
+
+// Non-linear sRGB to hue, saturation and value, with the hue in turns from 0 to 1.
+vec3 srgb_to_hsv(vec3 c) {
+  float value = max(c.r, max(c.g, c.b));
+  float chroma = value - min(c.r, min(c.g, c.b));
+  if (chroma <= 0.0) {
+    return vec3(0.0, 0.0, value);
+  }
+
+  float hue;
+  if (value == c.r) {
+    hue = (c.g - c.b) / chroma;
+  } else if (value == c.g) {
+    hue = (c.b - c.r) / chroma + 2.0;
+  } else {
+    hue = (c.r - c.g) / chroma + 4.0;
+  }
+
+  return vec3(fract(hue / 6.0), chroma / value, value);
+}
+
+vec3 hsv_to_srgb(vec3 c) {
+  vec3 k = mod(vec3(5.0, 3.0, 1.0) + c.x * 6.0, 6.0);
+  return c.z - c.z * c.y * clamp(min(k, 4.0 - k), 0.0, 1.0);
+}
+
+// Blends two linear colors in HSV of their sRGB encodings, as the interface edits them, with the
+// hue turning the short way around, or the long way if long_way is set.
+vec3 blend_hsv(vec3 c0, vec3 c1, float t, bool long_way) {
+  vec3 hsv0 = srgb_to_hsv(linear_to_srgb(clamp(c0, 0.0, 1.0)));
+  vec3 hsv1 = srgb_to_hsv(linear_to_srgb(clamp(c1, 0.0, 1.0)));
+  // Grays have no hue of their own, so they take the other color's
+  if (hsv0.y <= 1e-4) {
+    hsv0.x = hsv1.x;
+  } else if (hsv1.y <= 1e-4) {
+    hsv1.x = hsv0.x;
+  }
+
+  float turn = hsv1.x - hsv0.x;
+  if (turn > 0.5) {
+    turn -= 1.0;
+  } else if (turn < -0.5) {
+    turn += 1.0;
+  }
+  if (long_way && turn != 0.0) {
+    turn -= sign(turn);
+  }
+
+  PRECISE vec3 hsv = vec3(fract(hsv0.x + t * turn), mix(hsv0.yz, hsv1.yz, t));
+  return srgb_to_linear(hsv_to_srgb(hsv));
+}
+
+// Clamps a linear color into the gamut of the export color space.
+vec3 clamp_to_gamut(vec3 c) {
+  if (data.color_space == SPACE_DISPLAY_P3) {
+    PRECISE vec3 p3 = clamp(SRGB_TO_P3 * c, 0.0, 1.0);
+    return P3_TO_SRGB * p3;
+  }
+
+  return clamp(c, 0.0, 1.0);
+}
+
+// Blends two linear colors a fraction t of the way from c0 to c1 in the interpolation space. Alpha
+// is always blended linearly, and blends outside the gamut of the color space are clamped into
+// it.
+vec4 blend_colors(vec4 c0, vec4 c1, float t) {
+  if (data.interpolation == INTERPOLATION_RGB) {
+    PRECISE vec4 c = mix(c0, c1, t);
+    return c;
+  }
+
+  if (data.interpolation == INTERPOLATION_HSV || data.interpolation == INTERPOLATION_HSV_LONG) {
+    bool long_way = data.interpolation == INTERPOLATION_HSV_LONG;
+    return vec4(blend_hsv(c0.rgb, c1.rgb, t, long_way), mix(c0.a, c1.a, t));
+  }
+
+  vec3 lab0 = linear_to_oklab(c0.rgb);
+  vec3 lab1 = linear_to_oklab(c1.rgb);
+  PRECISE vec3 lab = mix(lab0, lab1, t);
+
+  if (data.interpolation == INTERPOLATION_LCH) {
+    float chroma0 = length(lab0.yz);
+    float chroma1 = length(lab1.yz);
+    // Grays have no hue of their own, so they take the other color's
+    float hue0 = chroma0 > 1e-4 ? atan(lab0.z, lab0.y) : atan(lab1.z, lab1.y);
+    float hue1 = chroma1 > 1e-4 ? atan(lab1.z, lab1.y) : hue0;
+
+    float turn = hue1 - hue0;
+    if (turn > HALF_TURN) {
+      turn -= 2.0 * HALF_TURN;
+    } else if (turn < -HALF_TURN) {
+      turn += 2.0 * HALF_TURN;
+    }
+
+    PRECISE float hue = hue0 + t * turn;
+    PRECISE float chroma = mix(chroma0, chroma1, t);
+    lab.yz = chroma * vec2(cos(hue), sin(hue));
+  }
+
+  PRECISE vec3 rgb = clamp_to_gamut(oklab_to_linear(lab));
+  return vec4(rgb, mix(c0.a, c1.a, t));
+}
+
+// The position of gradient stop i.
+float stop_position(uint i) {
+  return data.color_pos[i / 4][i % 4];
+}
+
+// Turns a value inside the gradient's span by color_phase of the span, wrapping around from its end
+// to its start, for color cycling. Values outside of the span are left alone.
+float cycle_interpolant(float interpolant) {
+  float start = stop_position(0);
+  float span = stop_position(data.color_stops - 1) - start;
+  if (data.color_phase == 0.0 || interpolant < start || interpolant >= start + span) {
+    return interpolant;
+  }
+
+  PRECISE float t = start + mod(interpolant - start + data.color_phase * span, span);
+  return t;
+}
+
+// Maps a value into the gradient. Values beyond the first and last stops take their colors.
+vec4 interpolate_color(float interpolant) {
+  if (interpolant < stop_position(0)) {
+    return data.color[0];
+  }
+
+  for (uint i = 1; i < data.color_stops; i += 1) {
+    float end = stop_position(i);
+    if (interpolant < end) {
+      float start = stop_position(i - 1);
+      PRECISE float t = (interpolant - start) / (end - start);
+      return blend_colors(data.color[i - 1], data.color[i], t);
+    }
+  }
+
+  return data.color[data.color_stops - 1];
+}
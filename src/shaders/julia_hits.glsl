@@ -0,0 +1,35 @@
+// Hit counting shared by the accumulation renderers, which add one hit to each pixel a point lands
+// in. Expects a push constant block named `sampling` with the image size in `size`.
+
+layout(set = 0, binding = 0) buffer Hits {
+  uint max_count;
+  uint counts[];
+} hits;
+
+// PCG hash, after Jarzynski and Olano, "Hash Functions for GPU Rendering".
+uint pcg(inout uint state) {
+  state = state * 747796405u + 2891336453u;
+  uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+  return (word >> 22u) ^ word;
+}
+
+float rand(inout uint state) {
+  return float(pcg(state)) / 4294967296.0;
+}
+
+void record(vec2 z) {
+  vec2 d = z - data.center;
+  vec2 turned = vec2(
+      d.x * data.rotation.x + d.y * data.rotation.y,
+      d.y * data.rotation.x - d.x * data.rotation.y
+  );
+  vec2 rel = turned / (data.extents / 2.0);
+  rel.y = -rel.y;
+  vec2 px = (rel + vec2(1.0)) * 0.5 * vec2(sampling.size);
+
+  if (all(greaterThanEqual(px, vec2(0.0))) && all(lessThan(px, vec2(sampling.size)))) {
+    uvec2 p = uvec2(px);
+    uint prev = atomicAdd(hits.counts[p.y * sampling.size.x + p.x], 1);
+    atomicMax(hits.max_count, prev + 1);
+  }
+}
@@ -0,0 +1,76 @@
+// Newton's method for the given polynomial, or f(z) = z^n - 1 if there is none. Points are
+// colored by the argument of the root their orbit converges to, so the roots of unity are evenly
+// spaced around the color wheel, fading towards the first gradient color the longer convergence
+// takes.
+
+const float PI = 3.14159265358979;
+const float NEWTON_TOLERANCE = 1e-5;
+
+vec2 newton_f(vec2 z) {
+  PRECISE vec2 f;
+  if (data.poly_len > 0) {
+    f = poly_eval(z);
+  } else {
+    f = complex_pow(z, max(data.n, 1.0)) - vec2(1.0, 0.0);
+  }
+  return f;
+}
+
+vec2 newton_df(vec2 z) {
+  PRECISE vec2 df;
+  if (data.poly_len > 0) {
+    df = poly_derivative(z);
+  } else {
+    float n = max(data.n, 1.0);
+    df = n * complex_pow(z, n - 1.0);
+  }
+  return df;
+}
+
+vec3 hue_to_rgb(float hue) {
+  vec3 k = mod(vec3(5.0, 3.0, 1.0) + hue * 6.0, 6.0);
+  return vec3(1.0) - clamp(min(k, 4.0 - k), 0.0, 1.0);
+}
+
+vec4 newton(vec2 p) {
+  PRECISE vec2 z = p;
+  PRECISE float dist = length(newton_f(z));
+  float prev_dist = dist;
+
+  uint i;
+  for (i = 0; i < data.iters && dist > NEWTON_TOLERANCE; i += 1) {
+    z = z - complex_div(newton_f(z), newton_df(z));
+    prev_dist = dist;
+    dist = length(newton_f(z));
+  }
+
+  if (dist > NEWTON_TOLERANCE) {
+    return data.interior_colored != 0 ? data.interior_color : data.color[0];
+  }
+
+  // Convergence is quadratic, so the fraction of the last step needed to reach the tolerance
+  // follows from how many times its logarithm doubled.
+  float frac = 1.0;
+  if (i > 0 && prev_dist < 1.0) {
+    frac = clamp(log2(log(NEWTON_TOLERANCE) / log(prev_dist)), 0.0, 1.0);
+  }
+  PRECISE float nu =
+    data.color_scale * (float(i) - 1.0 + frac) / float(data.iters) + data.color_offset;
+
+  float hue = fract(atan(z.y, z.x) / (2.0 * PI));
+  vec4 root_color = vec4(srgb_to_linear(hue_to_rgb(hue)), 1.0);
+  PRECISE vec4 color = mix(root_color, data.color[0], clamp(nu, 0.0, 1.0));
+  return color;
+}
+
+void main() {
+  ivec2 size = imageSize(img);
+
+  vec4 color1 = newton(get_z(vec2(1.0/3.0, 1.0/3.0), size));
+  vec4 color2 = newton(get_z(vec2(1.0/3.0, 2.0/3.0), size));
+  vec4 color3 = newton(get_z(vec2(2.0/3.0, 1.0/3.0), size));
+  vec4 color4 = newton(get_z(vec2(2.0/3.0, 2.0/3.0), size));
+
+  PRECISE vec4 color = average_samples(color1, color2, color3, color4);
+  imageStore(img, ivec2(gl_GlobalInvocationID.xy), color);
+}
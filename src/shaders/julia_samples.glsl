@@ -0,0 +1,27 @@
+// The iteration data of a pixel: what the iteration pass finds for each of its four samples, so
+// that the coloring pass can color them again without iterating.
+struct PixelSamples {
+  // The colored value of each sample, -1 inside the set
+  vec4 values;
+  // The relief height of each sample
+  vec4 heights;
+  // The last iterate of each sample, for the modes that color by the smooth iteration count
+  vec2 last[4];
+};
+
+// Every color pipeline binds the iteration data so that they share a layout, but only the passes
+// of julia_color.glsl use it.
+layout(set = 0, binding = 3, std430) buffer Samples {
+  PixelSamples pixels[];
+} samples;
+
+// The index of the invocation's pixel in the iteration data, in rows from the top.
+uint pixel_index(ivec2 size) {
+  return gl_GlobalInvocationID.y * uint(size.x) + gl_GlobalInvocationID.x;
+}
+
+// The offset of sample k within its pixel. The samples lie on a grid a third of a pixel apart,
+// in columns from the top left.
+vec2 sample_offset(uint k) {
+  return vec2(float(1 + k / 2), float(1 + k % 2)) / 3.0;
+}
@@ -0,0 +1,59 @@
+// The tonemapping pass: takes the linear colors the color pipelines render, which may lie outside
+// of the displayable range, and adjusts and quantizes them for display or export.
+
+layout(set = 0, binding = 2, rgba32f) uniform readonly image2D hdr;
+
+const uint CURVE_CLIP = 0;
+const uint CURVE_REINHARD = 1;
+const uint CURVE_ACES = 2;
+
+// Maps linear intensities from [0, inf) into [0, 1]: by clipping them, with Reinhard's curve
+// c / (1 + c), which never clips but dims everything, or with Narkowicz's fit of the ACES filmic
+// curve, which keeps the midtones and rolls highlights off smoothly.
+vec3 tone_curve(vec3 c) {
+  if (data.tone_curve == CURVE_REINHARD) {
+    return c / (vec3(1.0) + c);
+  } else if (data.tone_curve == CURVE_ACES) {
+    PRECISE vec3 mapped = (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14);
+    return clamp(mapped, 0.0, 1.0);
+  }
+
+  return clamp(c, 0.0, 1.0);
+}
+
+// Converts a linear color to the primaries of the color space, then applies the exposure, gamma and
+// contrast adjustments and brings it into the displayable range with the tone curve.
+vec4 adjust_tone(vec4 color) {
+  vec3 rgb = data.color_space == SPACE_DISPLAY_P3 ? SRGB_TO_P3 * color.rgb : color.rgb;
+  PRECISE vec3 c = max(rgb * data.exposure, vec3(0.0));
+  c = pow(c, vec3(1.0 / max(data.gamma, 1e-3)));
+  c = 0.18 * pow(c / 0.18, vec3(data.contrast));
+  return vec4(tone_curve(c), color.a);
+}
+
+// The rank of a pixel in the 8x8 Bayer matrix, from 0 to 63: the bits of x ^ y and y interleaved
+// and reversed.
+uint bayer8(uvec2 p) {
+  uint y = p.y & 7;
+  uint xy = (p.x & 7) ^ y;
+  return ((xy & 1) << 5) | ((y & 1) << 4) | ((xy & 2) << 2) | ((y & 2) << 1) | ((xy & 4) >> 1)
+    | ((y & 4) >> 2);
+}
+
+// Offsets the color by an ordered dither of up to half an 8-bit step either way, if dithering is
+// on. The color is already encoded, so the steps are those of the stored values. The pattern
+// follows the pixel's place in the whole image, so tiles line up.
+vec4 dither(vec4 color) {
+  if (data.dither == 0) {
+    return color;
+  }
+
+  uvec2 p = gl_GlobalInvocationID.xy + data.origin;
+  PRECISE float offset = ((float(bayer8(p)) + 0.5) / 64.0 - 0.5) / 255.0;
+  return vec4(color.rgb + vec3(offset), color.a);
+}
+
+void main() {
+  ivec2 p = ivec2(gl_GlobalInvocationID.xy);
+  imageStore(img, p, dither(encode_transfer(adjust_tone(imageLoad(hdr, p)))));
+}
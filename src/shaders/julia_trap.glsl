@@ -0,0 +1,38 @@
+// The picture of an image orbit trap. Every color pipeline binds one so that they share a layout,
+// but only julia_color.glsl samples it.
+layout(set = 0, binding = 2) uniform sampler2D trap_image;
+
+// The color of the trap picture where the orbit of p first lands on an opaque part of it, or
+// transparent if the orbit misses it before escaping or no trap is set.
+vec4 trap_color(vec2 p, float R) {
+  if (data.trap == 0) {
+    return vec4(0.0);
+  }
+
+  vec2 z;
+  vec2 c;
+  orbit_start(p, z, c);
+
+  for (uint i = 0; i < data.iters && length(z) <= R; i += 1) {
+    z = mandelbrot_iteration(c, z, i);
+
+    // Picture rows run down, against the imaginary axis
+    vec2 uv = (z - data.trap_center) / data.trap_size + vec2(0.5);
+    uv.y = 1.0 - uv.y;
+
+    if (all(greaterThanEqual(uv, vec2(0.0))) && all(lessThanEqual(uv, vec2(1.0)))) {
+      vec4 t = textureLod(trap_image, uv, 0.0);
+      if (t.a > 0.0) {
+        return t;
+      }
+    }
+  }
+
+  return vec4(0.0);
+}
+
+// The trap picture composited over the color of p.
+vec4 apply_trap(vec4 color, vec2 p, float R) {
+  vec4 t = trap_color(p, R);
+  return vec4(mix(color.rgb, t.rgb, t.a), color.a);
+}
@@ -0,0 +1,161 @@
+//! Sidecar parameter files: a JSON file written next to an exported image, named after it with
+//! `.json` appended, holding the dimensions and every parameter of the view it was rendered from.
+//! Unlike PNG text chunks, sidecars work for every format, including ones that can't carry
+//! metadata at all. Colors are written as non-linear sRGBA channels from 0 to 1, so that they keep
+//! their full precision. Numbers that aren't finite are written as `null`.
+
+use gramit::{Vec2, Vec3, Vec4};
+use serde::Serialize;
+
+use crate::atomic::write_atomic;
+use crate::{ImgDimensions, JuliaData};
+
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The contents of a sidecar file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Sidecar {
+    width: u32,
+    height: u32,
+    data: SidecarData,
+}
+
+/// The parameters of a view, with values that have a command-line form written in it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct SidecarData {
+    kind: String,
+    n: f32,
+    c: [f32; 2],
+    z0: [f32; 2],
+    plane: Option<String>,
+    poly: Option<String>,
+    denom: Option<String>,
+    hybrid: Option<String>,
+    iters: u32,
+    center: [f32; 2],
+    extents: [f32; 2],
+    rotation: f32,
+    strict: bool,
+    periodicity: bool,
+    interior_checks: bool,
+    colors: Vec<[f32; 4]>,
+    positions: Vec<f32>,
+    interpolation: String,
+    color_offset: f32,
+    color_scale: f32,
+    color_period: Option<f32>,
+    color_phase: f32,
+    equalize: bool,
+    coloring: String,
+    stripe_density: f32,
+    channel_scale: [f32; 3],
+    channel_offset: [f32; 3],
+    light: Option<SidecarLight>,
+    nebulabrot: Option<[u32; 3]>,
+    interior_color: Option<[f32; 4]>,
+    tone_exposure: f32,
+    tone_gamma: f32,
+    tone_contrast: f32,
+    tone_curve: String,
+    dither: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct SidecarLight {
+    azimuth: f32,
+    elevation: f32,
+    relief: String,
+}
+
+impl Sidecar {
+    /// The sidecar of an image of `dims` rendered from `data`.
+    fn new(dims: ImgDimensions, data: &JuliaData) -> Sidecar {
+        let stops = data.gradient.stops();
+
+        Sidecar {
+            width: dims.width,
+            height: dims.height,
+            data: SidecarData {
+                kind: data.kind.to_string(),
+                n: data.n,
+                c: vec2(data.c),
+                z0: vec2(data.z0),
+                plane: named(data.plane.as_ref()),
+                poly: named(data.poly.as_ref()),
+                denom: named(data.denom.as_ref()),
+                hybrid: named(data.hybrid.as_ref()),
+                iters: data.iters,
+                center: vec2(data.center),
+                extents: vec2(data.extents),
+                rotation: data.rotation,
+                strict: data.strict,
+                periodicity: data.periodicity,
+                interior_checks: data.interior_checks,
+                colors: stops.iter().map(|s| vec4(s.color)).collect(),
+                positions: stops.iter().map(|s| s.position).collect(),
+                interpolation: data.interpolation.to_string(),
+                color_offset: data.color_offset,
+                color_scale: data.color_scale,
+                color_period: data.color_period,
+                color_phase: data.color_phase,
+                equalize: data.equalize,
+                coloring: data.coloring.to_string(),
+                stripe_density: data.stripe_density,
+                channel_scale: vec3(data.channels.scale),
+                channel_offset: vec3(data.channels.offset),
+                light: data.shading.map(|l| SidecarLight {
+                    azimuth: l.azimuth,
+                    elevation: l.elevation,
+                    relief: l.relief.to_string(),
+                }),
+                nebulabrot: data.nebulabrot,
+                interior_color: data.interior_color.map(vec4),
+                tone_exposure: data.tone.exposure,
+                tone_gamma: data.tone.gamma,
+                tone_contrast: data.tone.contrast,
+                tone_curve: data.tone.curve.to_string(),
+                dither: data.dither,
+            },
+        }
+    }
+}
+
+/// The sidecar file of the image at `image`: its whole name with `.json` appended.
+pub(crate) fn path(image: &Path) -> PathBuf {
+    let mut name = OsString::from(image.as_os_str());
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+/// Writes the sidecar file of the image at `image`, replacing it atomically.
+pub(crate) fn write(image: &Path, dims: ImgDimensions, data: &JuliaData) -> io::Result<()> {
+    let sidecar = Sidecar::new(dims, data);
+
+    write_atomic(&path(image), |tmp| {
+        let mut out = BufWriter::new(File::create(tmp)?);
+        serde_json::to_writer_pretty(&mut out, &sidecar)?;
+        writeln!(out)?;
+        out.flush()
+    })
+}
+
+/// The value's command-line form, if there is a value.
+fn named<T: Display>(value: Option<&T>) -> Option<String> {
+    value.map(T::to_string)
+}
+
+fn vec2(v: Vec2) -> [f32; 2] {
+    [v.x, v.y]
+}
+
+fn vec3(v: Vec3) -> [f32; 3] {
+    [v.x, v.y, v.z]
+}
+
+fn vec4(v: Vec4) -> [f32; 4] {
+    [v.x, v.y, v.z, v.w]
+}
@@ -0,0 +1,288 @@
+//! Animation spec files: an animation described in a TOML or JSON document, for animations too
+//! intricate to give on the command line, or generated by other tools. A spec holds the views the
+//! animation passes through as keyframes, each a `[[keyframe]]` table giving its time in seconds
+//! and any of the animated parameters. Each parameter moves from one keyframe that sets it to the
+//! next, along the `easing` of the first, named as `Easing` parses it, and holds before the first
+//! and after the last. Parameters that no keyframe sets are taken from the command line. The
+//! extent of the shorter image dimension is interpolated exponentially, so that zooms keep their
+//! pace.
+//!
+//! ```toml
+//! width = 1280
+//! height = 720
+//! fps = 30
+//! output = "dive.mp4"
+//!
+//! [[keyframe]]
+//! time = 0
+//! center = [-0.5, 0.0]
+//! extent = 3.6
+//! iters = 100
+//! easing = "smoothstep"
+//!
+//! [[keyframe]]
+//! time = 10
+//! center = [-0.743, 0.131]
+//! extent = 0.001
+//! iters = 600
+//! ```
+//!
+//! `c` can instead follow a path through waypoints, each a `[[waypoint]]` table giving the time
+//! that `c` passes through it and its value, with optional Bezier control points `in` and `out`
+//! shaping the curve into and out of it, and an `easing` for the way to the next waypoint:
+//!
+//! ```toml
+//! [[waypoint]]
+//! time = 0
+//! c = [-0.75, 0.1]
+//! out = [-0.6, 0.5]
+//! easing = "smoothstep"
+//!
+//! [[waypoint]]
+//! time = 4
+//! c = [0.25, 0.5]
+//! in = [0.05, 0.7]
+//! ```
+//!
+//! In JSON, the arrays of tables are arrays of objects under the same names:
+//!
+//! ```json
+//! { "fps": 30, "keyframe": [{ "time": 0, "extent": 3.6 }, { "time": 10, "extent": 0.001 }] }
+//! ```
+//!
+//! Documents are read and written with serde, by the `toml` and `serde_json` crates.
+
+use gramit::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::animation::{self, optional_point, Animation, Easing, WaypointPath};
+use crate::atomic::write_atomic;
+use crate::viewfile::ViewFormat;
+use crate::JuliaData;
+
+use std::cmp::Ordering;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// An animation read from a spec file, or built to be written as one.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnimationSpec {
+    /// The width of the frames in pixels, given together with `height`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// The height of the frames in pixels, given together with `width`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Frames per second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fps: Option<f32>,
+    /// The video or animated image to write.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+    /// The keyframes, in order of their times.
+    #[serde(rename = "keyframe", default, skip_serializing_if = "Vec::is_empty")]
+    pub keyframes: Vec<Keyframe>,
+    /// The path `c` follows, in place of any `c` of the keyframes.
+    #[serde(rename = "waypoint", default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<WaypointPath>,
+}
+
+/// The parameters of the view at one time. Those left out follow the keyframes around it.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keyframe {
+    /// The time of the keyframe, in seconds.
+    pub time: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "optional_point")]
+    pub center: Option<Vec2>,
+    /// The extent of the shorter image dimension.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extent: Option<f32>,
+    /// The angle in degrees by which the view is turned counterclockwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "optional_point")]
+    pub c: Option<Vec2>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "optional_point")]
+    pub z0: Option<Vec2>,
+    /// The exponent of `x^n`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iters: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_offset: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_phase: Option<f32>,
+    /// How the parameters the keyframe sets move on to the next keyframe that sets each of them.
+    #[serde(default, skip_serializing_if = "animation::is_linear")]
+    pub easing: Easing,
+}
+
+impl AnimationSpec {
+    /// Reads a spec file, as JSON if the path ends in `.json` and as TOML otherwise.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<AnimationSpec> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        let format = ViewFormat::from_path(path).unwrap_or(ViewFormat::Toml);
+
+        parse(&contents, format)
+            .map_err(|reason| io::Error::new(io::ErrorKind::InvalidData, reason))
+    }
+
+    /// Writes the spec to a file, as JSON if the path ends in `.json` and as TOML otherwise. The
+    /// file is replaced atomically.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let format = ViewFormat::from_path(path).unwrap_or(ViewFormat::Toml);
+        let contents = to_string(self, format)
+            .map_err(|reason| io::Error::new(io::ErrorKind::InvalidInput, reason))?;
+
+        write_atomic(path, |tmp| fs::write(tmp, &contents))
+    }
+
+    /// The width and height of the frames, if the spec gives them.
+    pub fn resolution(&self) -> Option<(u32, u32)> {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => Some((width, height)),
+            _ => None,
+        }
+    }
+}
+
+/// The animation lasts until its last keyframe or waypoint.
+impl Animation for AnimationSpec {
+    fn duration(&self) -> f32 {
+        let keyframes = self.keyframes.iter().map(|k| k.time);
+        let path = self.path.as_ref().map(|path| path.duration());
+        keyframes.chain(path).fold(0.0, f32::max)
+    }
+
+    fn view(&self, base: &JuliaData, time: f32) -> JuliaData {
+        let keyframes = &self.keyframes;
+        let mut data = *base;
+
+        if let Some(center) = track(keyframes, time, |k| k.center, lerp2) {
+            data.center = center;
+        }
+        if let Some(extent) = track(keyframes, time, |k| k.extent, geometric) {
+            // The longer dimension keeps its share of the extents of the base view
+            let shorter = f32::min(base.extents.x, base.extents.y);
+            data.extents = (extent / shorter) * base.extents;
+        }
+        if let Some(rotation) = track(keyframes, time, |k| k.rotation, lerp) {
+            data.rotation = rotation;
+        }
+        if let Some(z0) = track(keyframes, time, |k| k.z0, lerp2) {
+            data.z0 = z0;
+        }
+        if let Some(n) = track(keyframes, time, |k| k.n, lerp) {
+            data.n = n;
+        }
+        if let Some(iters) = track(keyframes, time, |k| k.iters.map(|i| i as f32), lerp) {
+            data.iters = iters.round().max(1.0) as u32;
+        }
+        if let Some(offset) = track(keyframes, time, |k| k.color_offset, lerp) {
+            data.color_offset = offset;
+        }
+        if let Some(phase) = track(keyframes, time, |k| k.color_phase, lerp) {
+            data.color_phase = phase;
+        }
+
+        match &self.path {
+            Some(path) => path.view(&data, time),
+            None => {
+                if let Some(c) = track(keyframes, time, |k| k.c, lerp2) {
+                    data.c = c;
+                }
+                data
+            }
+        }
+    }
+}
+
+/// The value of one parameter at `time`, eased between the keyframes around it that set it with
+/// `mix`, or `None` if none of them do.
+fn track<T, V, M>(keyframes: &[Keyframe], time: f32, value: V, mix: M) -> Option<T>
+where
+    T: Copy,
+    V: Fn(&Keyframe) -> Option<T>,
+    M: Fn(T, T, f32) -> T,
+{
+    let mut before: Option<(&Keyframe, T)> = None;
+
+    for keyframe in keyframes {
+        let v = match value(keyframe) {
+            Some(v) => v,
+            None => continue,
+        };
+
+        if keyframe.time > time {
+            return Some(match before {
+                Some((a, va)) => {
+                    let t = (time - a.time) / (keyframe.time - a.time);
+                    mix(va, v, a.easing.apply(t))
+                }
+                None => v,
+            });
+        }
+        before = Some((keyframe, v));
+    }
+
+    before.map(|(_, v)| v)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn lerp2(a: Vec2, b: Vec2, t: f32) -> Vec2 {
+    a + t * (b - a)
+}
+
+/// Moves by the same factor in equal steps of `t`, for extents. Falls back on `lerp` unless both
+/// ends are positive.
+fn geometric(a: f32, b: f32, t: f32) -> f32 {
+    if a > 0.0 && b > 0.0 {
+        a * (b / a).powf(t)
+    } else {
+        lerp(a, b, t)
+    }
+}
+
+/// Parses the contents of a spec file.
+pub fn parse(contents: &str, format: ViewFormat) -> Result<AnimationSpec, String> {
+    let mut spec: AnimationSpec = match format {
+        ViewFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string())?,
+        ViewFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string())?,
+    };
+
+    if spec.width.is_some() != spec.height.is_some() {
+        return Err(String::from("`width` and `height` must be given together"));
+    }
+    if spec.path.as_ref().map_or(false, WaypointPath::is_empty) {
+        spec.path = None;
+    }
+    if spec.keyframes.is_empty() && spec.path.is_none() {
+        return Err(String::from("the spec has no keyframes or waypoints"));
+    }
+
+    spec.keyframes
+        .sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(Ordering::Equal));
+    Ok(spec)
+}
+
+/// The contents of a spec file holding `spec`.
+pub fn to_string(spec: &AnimationSpec, format: ViewFormat) -> Result<String, String> {
+    match format {
+        ViewFormat::Toml => toml::to_string(spec).map_err(|e| e.to_string()),
+        ViewFormat::Json => serde_json::to_string_pretty(spec)
+            .map(|json| json + "\n")
+            .map_err(|e| e.to_string()),
+    }
+}
@@ -0,0 +1,126 @@
+//! The gradient strip drawn over the interactive view: the gradient as it is blended, with ticks
+//! marking its stops and the active one, for seeing the whole of it while editing colors.
+
+use vulkano::buffer::{BufferUsage, CpuBufferPool};
+use vulkano::command_buffer::{
+    AutoCommandBuffer, AutoCommandBufferBuilder, BuildError, CommandBufferExecError,
+    CommandBufferExecFuture, DispatchError,
+};
+use vulkano::descriptor::descriptor_set::{
+    FixedSizeDescriptorSetsPool, PersistentDescriptorSetBuildError,
+};
+use vulkano::image::ImageAccess;
+use vulkano::memory::DeviceMemoryAllocError;
+use vulkano::pipeline::ComputePipeline;
+use vulkano::sync::GpuFuture;
+use vulkano::OomError;
+
+use crate::image::JuliaImage;
+use crate::shaders::{julia_comp, julia_strip_comp};
+use crate::{JuliaContext, JuliaData, StripDesc};
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::sync::Arc;
+
+/// Heights of the band of ticks and of the gradient below it, and the gap between the strip and
+/// the bottom of the view, in pixels before magnification.
+const TICKS: u32 = 3;
+const HEIGHT: u32 = 8;
+const MARGIN: u32 = 8;
+
+pub struct JuliaStrip {
+    buffer_pool: CpuBufferPool<julia_comp::ty::Data>,
+    desc_set_pool: RefCell<FixedSizeDescriptorSetsPool<Arc<ComputePipeline<StripDesc>>>>,
+}
+
+impl JuliaStrip {
+    pub fn new(context: &JuliaContext) -> JuliaStrip {
+        JuliaStrip {
+            buffer_pool: CpuBufferPool::new(
+                context.device().clone(),
+                BufferUsage {
+                    uniform_buffer: true,
+                    ..BufferUsage::none()
+                },
+            ),
+            desc_set_pool: RefCell::new(FixedSizeDescriptorSetsPool::new(
+                context.strip_pipeline().clone(),
+                0,
+            )),
+        }
+    }
+
+    /// Draws the gradient of `data` along the bottom of `image`, centered and half as wide as it,
+    /// with the `active` stop marked. The strip is magnified `scale` times, like the HUD, and its
+    /// colors are encoded like the rest of the image.
+    pub fn draw_after<F: GpuFuture>(
+        &self,
+        future: F,
+        image: &JuliaImage,
+        data: &JuliaData,
+        active: usize,
+        scale: u32,
+        context: &JuliaContext,
+    ) -> Result<CommandBufferExecFuture<F, AutoCommandBuffer>, JuliaStripError> {
+        let [width, height] = image.image().dimensions().width_height();
+
+        let mut shader_data = data.into_shader_data();
+        shader_data.transfer = image.transfer().shader_id();
+        let buffer = self.buffer_pool.next(shader_data)?;
+        let desc_set = self
+            .desc_set_pool
+            .borrow_mut()
+            .next()
+            .add_image(image.image().clone())
+            .unwrap()
+            .add_buffer(buffer)
+            .unwrap()
+            .build()?;
+
+        let bottom = height.saturating_sub(MARGIN * scale);
+        let top = bottom.saturating_sub((TICKS + HEIGHT) * scale);
+        let strip = julia_strip_comp::ty::Strip {
+            rect: [
+                (width / 4) as i32,
+                top as i32,
+                (width - width / 4) as i32,
+                bottom as i32,
+            ],
+            ticks: (TICKS * scale) as i32,
+            active: active as u32,
+        };
+
+        let cmd_buf = AutoCommandBufferBuilder::primary_one_time_submit(
+            context.device().clone(),
+            context.queue().family(),
+        )?
+        .dispatch(
+            [(width + 7) / 8, (height + 7) / 8, 1],
+            context.strip_pipeline().clone(),
+            desc_set,
+            strip,
+        )?
+        .build()?;
+
+        Ok(future.then_execute(context.queue().clone(), cmd_buf)?)
+    }
+}
+
+impl Debug for JuliaStrip {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("JuliaStrip").finish()
+    }
+}
+
+impl_error! {
+    pub enum JuliaStripError {
+        VkAllocErr(DeviceMemoryAllocError),
+        VkDescSetErr(PersistentDescriptorSetBuildError),
+        VkOomErr(OomError),
+        VkDispatchErr(DispatchError),
+        VkCmdBufBuildErr(BuildError),
+        VkExecErr(CommandBufferExecError),
+    }
+}
@@ -0,0 +1,199 @@
+//! Small previews of saved views, such as checkpoints, so that they can be told apart at a glance.
+//! Thumbnails are rendered on the GPU and cached as PNGs named after an FNV-1a hash of the
+//! parameters in checkpoint form, which is stable across builds, so each one is only rendered
+//! once. In-memory previews for host applications are cached by the
+//! context instead; see `JuliaContext::preview`.
+
+use image::RgbaImage;
+
+use crate::checkpoint::{self, Checkpoint};
+use crate::{ExportJob, ImgDimensions, JuliaContext, JuliaData, JuliaExportError, PixelRect};
+
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// A directory of cached thumbnails of one size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThumbnailCache {
+    dir: PathBuf,
+    dims: ImgDimensions,
+}
+
+impl ThumbnailCache {
+    /// The default thumbnail size, in pixels along each side.
+    pub const DEFAULT_SIZE: u32 = 128;
+
+    /// A cache of square thumbnails in `dir`. The size is rounded down to a multiple of 8, as the
+    /// renderer requires, and is at least 8.
+    pub fn new<P: Into<PathBuf>>(dir: P, size: u32) -> ThumbnailCache {
+        let size = u32::max(8, size / 8 * 8);
+        ThumbnailCache {
+            dir: dir.into(),
+            dims: ImgDimensions {
+                width: size,
+                height: size,
+            },
+        }
+    }
+
+    /// `$XDG_CACHE_HOME/julia/thumbnails`, falling back to `~/.cache/julia/thumbnails`, or to
+    /// `.julia-thumbnails` in the working directory if neither variable is set.
+    pub fn default_dir() -> PathBuf {
+        let cache_home = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| Path::new(&h).join(".cache")));
+
+        match cache_home {
+            Some(dir) => dir.join("julia").join("thumbnails"),
+            None => PathBuf::from(".julia-thumbnails"),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn dimensions(&self) -> ImgDimensions {
+        self.dims
+    }
+
+    /// Where the thumbnail of `data` is cached, whether or not it has been rendered.
+    pub fn path(&self, data: &JuliaData) -> PathBuf {
+        // Every parameter that affects the image is written to checkpoints, and the thumbnail
+        // size takes the place of the export size
+        let key = checkpoint::encode(&Checkpoint {
+            time: UNIX_EPOCH,
+            data: *data,
+            export_dimensions: self.dims,
+        });
+
+        self.dir.join(format!("{:016x}.png", fnv1a(key.as_bytes())))
+    }
+
+    /// Renders the thumbnails of the given views that aren't cached yet, and returns the paths of
+    /// all of them in the same order.
+    pub fn render(&self, views: &[JuliaData], context: &JuliaContext) -> io::Result<Vec<PathBuf>> {
+        fs::create_dir_all(&self.dir)?;
+
+        let paths: Vec<_> = views.iter().map(|data| self.path(data)).collect();
+        let jobs: Vec<_> = views
+            .iter()
+            .zip(&paths)
+            .filter(|(_, path)| !path.exists())
+            .map(|(data, path)| ExportJob {
+                dims: self.dims,
+                data: data.fit_to(self.dims),
+                filename: path.clone(),
+                potential: false,
+            })
+            .collect();
+
+        context
+            .export_batch(jobs, 2)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        Ok(paths)
+    }
+}
+
+/// The 64-bit FNV-1a hash of `bytes`, which unlike `DefaultHasher` is the same in every build.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// The most iterations previews are rendered with, to keep them fast.
+pub const PREVIEW_MAX_ITERS: u32 = 250;
+
+/// How many previews the context keeps.
+const PREVIEW_CACHE_SIZE: usize = 64;
+
+/// Recently rendered previews, least recently used first.
+#[derive(Debug, Default)]
+pub(crate) struct PreviewCache {
+    entries: VecDeque<(JuliaData, u32, RgbaImage)>,
+}
+
+impl PreviewCache {
+    /// The preview of `data` at most `max_dim` pixels across, rendered unless it is cached.
+    pub(crate) fn get(
+        &mut self,
+        data: &JuliaData,
+        max_dim: u32,
+        context: &JuliaContext,
+    ) -> Result<RgbaImage, JuliaExportError> {
+        let cached = self
+            .entries
+            .iter()
+            .position(|(d, m, _)| d == data && *m == max_dim);
+
+        let entry = match cached.and_then(|i| self.entries.remove(i)) {
+            Some(entry) => entry,
+            None => (*data, max_dim, render_preview(data, max_dim, context)?),
+        };
+
+        let image = entry.2.clone();
+        self.entries.push_back(entry);
+        if self.entries.len() > PREVIEW_CACHE_SIZE {
+            self.entries.pop_front();
+        }
+
+        Ok(image)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Renders `data` with the aspect ratio of its extents, with the longer side `max_dim` pixels
+/// rounded down to a multiple of 8, and at most `PREVIEW_MAX_ITERS` iterations.
+fn render_preview(
+    data: &JuliaData,
+    max_dim: u32,
+    context: &JuliaContext,
+) -> Result<RgbaImage, JuliaExportError> {
+    let long = u32::max(8, max_dim / 8 * 8);
+    let aspect = data.extents.x / data.extents.y;
+
+    let dims = if aspect >= 1.0 {
+        ImgDimensions {
+            width: long,
+            height: u32::max(8, (long as f32 / aspect / 8.0).round() as u32 * 8),
+        }
+    } else {
+        ImgDimensions {
+            width: u32::max(8, (long as f32 * aspect / 8.0).round() as u32 * 8),
+            height: long,
+        }
+    };
+
+    // Keep the longer side's extent, and match the other to the rounded dimensions
+    let mut data = *data;
+    let ratio = dims.height as f32 / dims.width as f32;
+    if aspect >= 1.0 {
+        data.extents.y = data.extents.x * ratio;
+    } else {
+        data.extents.x = data.extents.y / ratio;
+    }
+    data.iters = data.iters.min(PREVIEW_MAX_ITERS);
+
+    let rect = PixelRect {
+        x: 0,
+        y: 0,
+        width: dims.width,
+        height: dims.height,
+    };
+    let pixels = context.render_region(dims, rect, &data)?;
+
+    RgbaImage::from_raw(dims.width, dims.height, pixels).ok_or_else(|| {
+        JuliaExportError::from(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "rendered preview has the wrong size",
+        ))
+    })
+}
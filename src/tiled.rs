@@ -0,0 +1,317 @@
+//! Tiled exports of images too large to render in one piece, such as posters 32K pixels and more
+//! across. A color export renders the whole image at once, and needs a storage image and a
+//! readback buffer for all of it, which fails once they outgrow the GPU. A tiled export instead
+//! renders one square tile at a time as a region of the whole image, so that every tile is
+//! exactly the part of the single render it replaces. Each tile is written to its own PNG in a
+//! directory next to the output as it completes, and the tiles are then stitched into the output
+//! a band of rows at a time, so that no more than one band is ever held in memory.
+//!
+//! Tiled exports are always 8-bit sRGB PNGs. The tile directory is removed once the output is
+//! written.
+//!
+//! Every time a tile is written, a manifest in the tile directory, `manifest.json`, is rewritten
+//! with the view, the tile size and the tiles completed so far. An export that is interrupted
+//! can be picked up from its manifest, rendering only the tiles that are missing:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "output": "poster.png",
+//!   "tile_size": 4096,
+//!   "view": "<the view, as a line of the checkpoint log>",
+//!   "done": [0, 1, 2]
+//! }
+//! ```
+//!
+//! Tiles are numbered in rows from the top. The output is always written next to the tile
+//! directory, so both can be moved together.
+
+use crate::atomic::write_atomic;
+use crate::checkpoint::{self, Checkpoint};
+use crate::embedded;
+use crate::{ImgDimensions, JuliaContext, JuliaData, PixelRect, Progress};
+
+use serde::{Deserialize, Serialize};
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const MANIFEST_VERSION: u32 = 1;
+
+/// The contents of a manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    /// The file name of the output, next to the tile directory.
+    output: String,
+    tile_size: u32,
+    /// The view, as a line of the checkpoint log.
+    view: String,
+    /// The indices of the tiles written so far.
+    done: Vec<usize>,
+}
+
+/// An image to be rendered in tiles, and which of its tiles are already written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TiledExport {
+    dims: ImgDimensions,
+    data: JuliaData,
+    tile_size: u32,
+    filename: PathBuf,
+    /// Whether each tile is written, in the order of `tiles`.
+    done: Vec<bool>,
+}
+
+impl TiledExport {
+    /// The tile size exports too large for the device are rendered in when none is given, in
+    /// pixels along each side.
+    pub const DEFAULT_TILE_SIZE: u32 = 2048;
+
+    /// An export of `data` at `dims` to `filename`, in square tiles of `tile_size` pixels,
+    /// rounded down to a multiple of 8 as the renderer requires. The dimensions must be multiples
+    /// of 8 as well.
+    pub fn new(
+        dims: ImgDimensions,
+        data: JuliaData,
+        tile_size: u32,
+        filename: PathBuf,
+    ) -> TiledExport {
+        let mut export = TiledExport {
+            dims,
+            data,
+            tile_size: u32::max(8, tile_size / 8 * 8),
+            filename,
+            done: Vec::new(),
+        };
+        export.done = vec![false; export.tiles().len()];
+        export
+    }
+
+    /// The export that the manifest at `path` was written for, with the tiles it lists as done,
+    /// and that are still in the tile directory, left out of the rendering.
+    pub fn from_manifest(path: &Path) -> io::Result<TiledExport> {
+        let path = &fs::canonicalize(path)?;
+        let file = BufReader::new(File::open(path)?);
+        let manifest: Manifest = serde_json::from_reader(file).map_err(invalid_data)?;
+
+        if manifest.version != MANIFEST_VERSION {
+            return Err(invalid_data("unsupported manifest version"));
+        }
+        let view =
+            checkpoint::decode(&manifest.view).ok_or_else(|| invalid_data("invalid view"))?;
+
+        // The output lies next to the tile directory the manifest is in
+        let tile_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let filename = tile_dir.with_file_name(&manifest.output);
+        let mut export = TiledExport::new(
+            view.export_dimensions,
+            view.data,
+            manifest.tile_size,
+            filename,
+        );
+
+        let tiles = export.tiles();
+        for i in manifest.done {
+            if i < tiles.len() && export.tile_path(tiles[i]).exists() {
+                export.done[i] = true;
+            }
+        }
+
+        Ok(export)
+    }
+
+    /// The tiles of the image, in rows from the top.
+    pub fn tiles(&self) -> Vec<PixelRect> {
+        let ImgDimensions { width, height } = self.dims;
+        let size = self.tile_size;
+
+        let mut tiles = Vec::new();
+        for y in (0..height).step_by(size as usize) {
+            for x in (0..width).step_by(size as usize) {
+                tiles.push(PixelRect {
+                    x,
+                    y,
+                    width: size.min(width - x),
+                    height: size.min(height - y),
+                });
+            }
+        }
+        tiles
+    }
+
+    /// The directory the tiles are written to: the output's name with `.tiles` appended.
+    pub fn tile_dir(&self) -> PathBuf {
+        let mut name = OsString::from(self.filename.as_os_str());
+        name.push(".tiles");
+        PathBuf::from(name)
+    }
+
+    /// The manifest recording the progress of the export: `manifest.json` in the tile directory.
+    pub fn manifest_path(&self) -> PathBuf {
+        self.tile_dir().join("manifest.json")
+    }
+
+    /// The file the export is written to.
+    pub fn filename(&self) -> &Path {
+        &self.filename
+    }
+
+    fn tile_path(&self, tile: PixelRect) -> PathBuf {
+        self.tile_dir()
+            .join(format!("tile_{}_{}.png", tile.x, tile.y))
+    }
+
+    /// Renders every tile not yet written, calling `progress` and updating the manifest as each
+    /// is written, then stitches them into the output and removes the tile directory.
+    pub fn run<F>(&mut self, context: &JuliaContext, mut progress: F) -> io::Result<()>
+    where
+        F: FnMut(Progress),
+    {
+        fs::create_dir_all(self.tile_dir())?;
+        self.write_manifest()?;
+
+        let tiles = self.tiles();
+        let total = tiles.len() as u32;
+        let mut done = self.done.iter().filter(|&&d| d).count() as u32;
+        for (i, &tile) in tiles.iter().enumerate() {
+            if self.done[i] {
+                continue;
+            }
+
+            let pixels = context
+                .render_region(self.dims, tile, &self.data)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            // Tiles are only kept until they are stitched, so they favor speed over size
+            write_tile(&self.tile_path(tile), tile, &pixels, png::Compression::Fast)?;
+            self.done[i] = true;
+            self.write_manifest()?;
+
+            done += 1;
+            progress(Progress { done, total });
+        }
+
+        self.stitch(&tiles)?;
+        fs::remove_dir_all(self.tile_dir())
+    }
+
+    fn write_manifest(&self) -> io::Result<()> {
+        let view = Checkpoint {
+            time: SystemTime::now(),
+            data: self.data,
+            export_dimensions: self.dims,
+        };
+        let output = self
+            .filename
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let manifest = Manifest {
+            version: MANIFEST_VERSION,
+            output,
+            tile_size: self.tile_size,
+            view: checkpoint::encode(&view),
+            done: (0..self.done.len()).filter(|&i| self.done[i]).collect(),
+        };
+
+        write_atomic(&self.manifest_path(), |path| {
+            let mut out = BufWriter::new(File::create(path)?);
+            serde_json::to_writer_pretty(&mut out, &manifest)?;
+            writeln!(out)?;
+            out.flush()
+        })
+    }
+
+    /// Writes the output from the tiles, one band of rows of tiles at a time, with the view
+    /// embedded as in other PNG exports.
+    fn stitch(&self, tiles: &[PixelRect]) -> io::Result<()> {
+        write_atomic(&self.filename, |path| {
+            let out = BufWriter::new(File::create(path)?);
+            let mut encoder = png::Encoder::new(out, self.dims.width, self.dims.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().map_err(png_err)?;
+            writer
+                .write_chunk(
+                    png::chunk::ChunkType(*b"tEXt"),
+                    &embedded::text_chunk(self.dims, &self.data),
+                )
+                .map_err(png_err)?;
+            let mut stream = writer.stream_writer().map_err(png_err)?;
+
+            for band in tiles.chunks(self.tiles_across()) {
+                let pixels = band
+                    .iter()
+                    .map(|&tile| read_tile(&self.tile_path(tile), tile))
+                    .collect::<io::Result<Vec<_>>>()?;
+
+                for row in 0..band[0].height as usize {
+                    for (tile, pixels) in band.iter().zip(&pixels) {
+                        let stride = tile.width as usize * 4;
+                        stream.write_all(&pixels[row * stride..(row + 1) * stride])?;
+                    }
+                }
+            }
+
+            stream.finish().map_err(png_err)
+        })
+    }
+
+    /// The number of tiles in each row.
+    fn tiles_across(&self) -> usize {
+        ((self.dims.width + self.tile_size - 1) / self.tile_size) as usize
+    }
+}
+
+/// Writes the sRGBA pixels of a tile as a PNG. The file is replaced atomically.
+pub(crate) fn write_tile(
+    path: &Path,
+    tile: PixelRect,
+    pixels: &[u8],
+    compression: png::Compression,
+) -> io::Result<()> {
+    write_atomic(path, |tmp| {
+        let out = BufWriter::new(File::create(tmp)?);
+        let mut encoder = png::Encoder::new(out, tile.width, tile.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_compression(compression);
+
+        let mut writer = encoder.write_header().map_err(png_err)?;
+        writer.write_image_data(pixels).map_err(png_err)
+    })
+}
+
+/// Reads back the sRGBA pixels of a tile, checking that it has the tile's dimensions.
+pub(crate) fn read_tile(path: &Path, tile: PixelRect) -> io::Result<Vec<u8>> {
+    let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    let mut reader = decoder.read_info().map_err(invalid_data)?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(invalid_data)?;
+
+    if (info.width, info.height) != (tile.width, tile.height)
+        || info.color_type != png::ColorType::Rgba
+        || info.bit_depth != png::BitDepth::Eight
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} isn't a tile of this export", path.display()),
+        ));
+    }
+
+    pixels.truncate(info.buffer_size());
+    Ok(pixels)
+}
+
+fn png_err(err: png::EncodingError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
+
+fn invalid_data<E>(err: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
@@ -0,0 +1,143 @@
+//! Image orbit traps, which stamp a picture onto the fractal. The picture is placed somewhere in
+//! the plane, and each point takes its color from wherever the orbit of that point first lands on
+//! an opaque part of it, so it reappears distorted all through the set.
+
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, ImageCreationError, ImmutableImage};
+use vulkano::sampler::Sampler;
+use vulkano::sync::{FlushError, GpuFuture};
+
+use gramit::Vec2;
+
+use crate::shaders::julia_comp;
+use crate::ImgDimensions;
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A picture to stamp onto the fractal and where it lies in the plane.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrbitTrap {
+    rgba: Vec<u8>,
+    dims: ImgDimensions,
+    /// The point of the plane the picture is centered on.
+    pub center: Vec2,
+    /// The width of the picture in the plane. Its height follows from its aspect ratio.
+    pub width: f32,
+}
+
+impl OrbitTrap {
+    /// Reads the picture from an image file in any format the `image` crate supports.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        center: Vec2,
+        width: f32,
+    ) -> Result<OrbitTrap, image::ImageError> {
+        let rgba = image::open(path)?.to_rgba();
+        let dims = ImgDimensions {
+            width: rgba.width(),
+            height: rgba.height(),
+        };
+
+        Ok(OrbitTrap::from_rgba(rgba.into_raw(), dims, center, width))
+    }
+
+    /// A trap from 8-bit sRGBA pixels, given in rows from the top.
+    pub fn from_rgba(rgba: Vec<u8>, dims: ImgDimensions, center: Vec2, width: f32) -> OrbitTrap {
+        assert_eq!(
+            rgba.len(),
+            (dims.width * dims.height * 4) as usize,
+            "trap pixels don't match its dimensions"
+        );
+
+        OrbitTrap {
+            rgba,
+            dims,
+            center,
+            width,
+        }
+    }
+
+    pub fn dimensions(&self) -> ImgDimensions {
+        self.dims
+    }
+
+    /// The size of the picture in the plane.
+    pub fn extents(&self) -> Vec2 {
+        let aspect = self.dims.height as f32 / self.dims.width.max(1) as f32;
+        vec2!(self.width, self.width * aspect)
+    }
+}
+
+/// A trap uploaded for the color pipelines, which always bind one. Without a trap this is a
+/// single transparent pixel that the shaders never sample.
+#[derive(Debug)]
+pub(crate) struct TrapTexture {
+    image: Arc<ImmutableImage<Format>>,
+    sampler: Arc<Sampler>,
+    placement: Option<(Vec2, Vec2)>,
+}
+
+impl TrapTexture {
+    /// Uploads `trap`, waiting for the upload to finish.
+    pub(crate) fn new(
+        trap: Option<&OrbitTrap>,
+        device: &Arc<Device>,
+        queue: &Arc<Queue>,
+    ) -> Result<TrapTexture, OrbitTrapError> {
+        let (pixels, dims) = match trap {
+            Some(t) => (t.rgba.clone(), t.dims),
+            None => (
+                vec![0; 4],
+                ImgDimensions {
+                    width: 1,
+                    height: 1,
+                },
+            ),
+        };
+
+        let (image, upload) = ImmutableImage::from_iter(
+            pixels.into_iter(),
+            Dimensions::Dim2d {
+                width: dims.width,
+                height: dims.height,
+            },
+            Format::R8G8B8A8Srgb,
+            queue.clone(),
+        )?;
+        upload.then_signal_fence_and_flush()?.wait(None)?;
+
+        Ok(TrapTexture {
+            image,
+            sampler: Sampler::simple_repeat_linear_no_mipmap(device.clone()),
+            placement: trap.map(|t| (t.center, t.extents())),
+        })
+    }
+
+    pub(crate) fn image(&self) -> &Arc<ImmutableImage<Format>> {
+        &self.image
+    }
+
+    pub(crate) fn sampler(&self) -> &Arc<Sampler> {
+        &self.sampler
+    }
+
+    /// Sets the trap's placement in uniform data for the color pipelines.
+    pub(crate) fn apply(&self, data: &mut julia_comp::ty::Data) {
+        if let Some((center, extents)) = self.placement {
+            data.trap = 1;
+            data.trap_center = [center.x, center.y];
+            data.trap_size = [extents.x, extents.y];
+        }
+    }
+}
+
+impl_error! {
+    pub enum OrbitTrapError {
+        VkImageErr(ImageCreationError),
+        VkFlushErr(FlushError),
+    }
+}
@@ -0,0 +1,60 @@
+//! Random variations of a view, for generating a set of candidate images to choose from. Each
+//! variation nudges the parameter that the arrow keys move (`c`, or `z0` in the parameter plane),
+//! shifts the phase of the gradient and turns the view, by seeded random amounts.
+
+use crate::pcg::Pcg;
+use crate::{FractalKind, JuliaData, Plane};
+
+/// How many variations to make and how far they stray from the original view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VariationOptions {
+    pub count: u32,
+    /// Seeds the random perturbations, so that a set of variations can be made again.
+    pub seed: u32,
+    /// The largest distance `c` (or `z0`) is moved by.
+    pub param_jitter: f32,
+    /// The largest amount added to or subtracted from the color offset.
+    pub phase_jitter: f32,
+    /// The largest angle the view is turned by either way, in degrees.
+    pub rotation_jitter: f32,
+}
+
+impl Default for VariationOptions {
+    fn default() -> VariationOptions {
+        VariationOptions {
+            count: 12,
+            seed: 0,
+            param_jitter: 0.01,
+            phase_jitter: 0.25,
+            rotation_jitter: 15.0,
+        }
+    }
+}
+
+/// `options.count` random variations of `data`. Newton fractals have no `c`, so only their colors
+/// and rotation vary.
+pub fn variations(data: &JuliaData, options: &VariationOptions) -> Vec<JuliaData> {
+    let mut rng = Pcg::new(options.seed);
+
+    (0..options.count)
+        .map(|_| {
+            let mut data = *data;
+
+            // Uniform over the disk of radius `param_jitter`
+            let radius = options.param_jitter * rng.next_f32().sqrt();
+            let angle = 2.0 * std::f32::consts::PI * rng.next_f32();
+            let offset = vec2!(radius * angle.cos(), radius * angle.sin());
+
+            if data.kind != FractalKind::Newton {
+                match data.view_plane() {
+                    Plane::Dynamical => data.c += offset,
+                    Plane::Parameter => data.z0 += offset,
+                }
+            }
+
+            data.color_offset += options.phase_jitter * rng.next_signed();
+            data.rotation += options.rotation_jitter * rng.next_signed();
+            data
+        })
+        .collect()
+}
@@ -0,0 +1,201 @@
+//! Video exports through ffmpeg. The frames of an animation are rendered one at a time and piped
+//! as raw 8-bit RGBA to an `ffmpeg` child process, so that no frames are written to disk along the
+//! way. Videos are encoded with H.264 into the container named by the output's extension, and
+//! `.webp` outputs as looping animated WebPs, which keep alpha. `ffmpeg` must be installed and on
+//! the `PATH`.
+
+use crate::atomic::write_atomic;
+use crate::{ImgDimensions, JuliaContext, JuliaData, JuliaExportError, Progress};
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// How a video is encoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoOptions {
+    /// Frames per second.
+    pub fps: f32,
+    /// The constant rate factor of the H.264 encoder, from 0 for lossless to 51. Lower is better
+    /// and larger; 18 is close to indistinguishable from the frames.
+    pub crf: u32,
+    /// The quality of animated WebPs, from 0 to 100.
+    pub webp_quality: f32,
+}
+
+impl Default for VideoOptions {
+    fn default() -> VideoOptions {
+        VideoOptions {
+            fps: 30.0,
+            crf: 18,
+            webp_quality: 80.0,
+        }
+    }
+}
+
+/// How ffmpeg encodes a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Codec {
+    H264,
+    Webp,
+}
+
+impl Codec {
+    /// The codec for the extension of `path`: H.264 for `.mp4`, `.m4v`, `.mkv` and `.mov`, and
+    /// WebP for `.webp`.
+    fn from_path(path: &Path) -> Option<Codec> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "mp4" | "m4v" | "mkv" | "mov" => Some(Codec::H264),
+            "webp" => Some(Codec::Webp),
+            _ => None,
+        }
+    }
+
+    /// The output options that select and set up the codec.
+    fn args(self, options: &VideoOptions) -> Vec<String> {
+        let (codec, quality): (&[&str], String) = match self {
+            Codec::H264 => (
+                &["-c:v", "libx264", "-pix_fmt", "yuv420p", "-crf"],
+                options.crf.min(51).to_string(),
+            ),
+            // The WebP muxer plays animations once unless told otherwise
+            Codec::Webp => (
+                &["-c:v", "libwebp", "-loop", "0", "-quality"],
+                options.webp_quality.max(0.0).min(100.0).to_string(),
+            ),
+        };
+
+        let mut args: Vec<String> = codec.iter().map(|a| a.to_string()).collect();
+        args.push(quality);
+        args
+    }
+}
+
+/// Whether `path` names a file that ffmpeg writes: a video in `.mp4`, `.m4v`, `.mkv` or `.mov`, or
+/// an animated `.webp`.
+pub fn is_video(path: &Path) -> bool {
+    Codec::from_path(path).is_some()
+}
+
+/// An `ffmpeg` process encoding frames into a video as they are written.
+pub struct VideoEncoder {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    frame_len: usize,
+}
+
+impl VideoEncoder {
+    /// Starts `ffmpeg` encoding frames of `dims` into `filename`, replacing it, with the codec its
+    /// extension names. The dimensions must be even, as they are in every render.
+    pub fn new(
+        filename: &Path,
+        dims: ImgDimensions,
+        options: &VideoOptions,
+    ) -> io::Result<VideoEncoder> {
+        let codec = Codec::from_path(filename).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} isn't a video file ffmpeg can write", filename.display()),
+            )
+        })?;
+
+        let mut child = Command::new("ffmpeg")
+            .args(&["-y", "-loglevel", "error"])
+            .args(&["-f", "rawvideo", "-pix_fmt", "rgba"])
+            .arg("-s")
+            .arg(format!("{}x{}", dims.width, dims.height))
+            .arg("-framerate")
+            .arg(options.fps.to_string())
+            .args(&["-i", "-", "-an"])
+            .args(codec.args(options))
+            .arg(filename)
+            .stdin(Stdio::piped())
+            .spawn()?;
+
+        Ok(VideoEncoder {
+            stdin: child.stdin.take(),
+            child,
+            frame_len: dims.width as usize * dims.height as usize * 4,
+        })
+    }
+
+    /// Writes the next frame, as rows of 8-bit RGBA values.
+    ///
+    /// # Panics
+    ///
+    /// If the frame isn't the size the encoder was started with.
+    pub fn write_frame(&mut self, rgba: &[u8]) -> io::Result<()> {
+        assert_eq!(rgba.len(), self.frame_len, "frame has the wrong size");
+        match &mut self.stdin {
+            Some(stdin) => stdin.write_all(rgba),
+            None => Ok(()),
+        }
+    }
+
+    /// Ends the video and waits for `ffmpeg` to finish writing it. Fails if `ffmpeg` does.
+    pub fn finish(mut self) -> io::Result<()> {
+        drop(self.stdin.take());
+        let status = self.child.wait()?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("ffmpeg failed with {}", status),
+            ))
+        }
+    }
+}
+
+/// Stops `ffmpeg` if the encoder is dropped before the video is finished, so that it isn't left
+/// waiting for frames.
+impl Drop for VideoEncoder {
+    fn drop(&mut self) {
+        if self.stdin.take().is_some() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Renders `frames` at `dims` in order and encodes them into the video `filename`, which is
+/// replaced atomically. `progress` is called as each frame is encoded.
+pub fn export_video<F>(
+    context: &JuliaContext,
+    dims: ImgDimensions,
+    frames: &[JuliaData],
+    filename: &Path,
+    options: &VideoOptions,
+    mut progress: F,
+) -> Result<(), JuliaExportError>
+where
+    F: FnMut(Progress),
+{
+    // A failed render is kept aside, since `write_atomic` only passes I/O errors through
+    let mut render_err = None;
+
+    let res = write_atomic(filename, |path| {
+        let mut encoder = VideoEncoder::new(path, dims, options)?;
+        for (i, data) in frames.iter().enumerate() {
+            let rgba = context.render_rgba(dims, data).map_err(|e| {
+                let err = io::Error::new(io::ErrorKind::Other, e.to_string());
+                render_err = Some(e);
+                err
+            })?;
+            encoder.write_frame(&rgba)?;
+
+            progress(Progress {
+                done: i as u32 + 1,
+                total: frames.len() as u32,
+            });
+        }
+        encoder.finish()
+    });
+
+    match (res, render_err) {
+        (Err(_), Some(e)) => Err(e),
+        (res, _) => Ok(res?),
+    }
+}
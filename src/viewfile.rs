@@ -0,0 +1,145 @@
+//! View files: the command-line options of a view written as a flat JSON object or TOML table,
+//! for tools and collaborators that would rather not assemble command lines. Each key is the long
+//! name of an option, with dashes or underscores, or the letter of a short one. Strings and numbers
+//! are passed as the option's value, arrays as their elements separated by commas, and `true`
+//! gives a flag while `false` leaves it out. Nested arrays and tables are refused, as are numbers
+//! that aren't finite. For example:
+//!
+//! ```json
+//! { "real-part": -0.8, "imaginary-part": 0.156, "center": [0.1, 0.2], "shading": true }
+//! ```
+//!
+//! ```toml
+//! real_part = -0.8
+//! imaginary_part = 0.156
+//! center = [0.1, 0.2]
+//! shading = true
+//! ```
+
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+
+/// The syntax of a view file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ViewFormat {
+    Json,
+    Toml,
+}
+
+impl ViewFormat {
+    /// The format given by a path's `.json` or `.toml` extension, if either.
+    pub fn from_path(path: &Path) -> Option<ViewFormat> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "json" => Some(ViewFormat::Json),
+            "toml" => Some(ViewFormat::Toml),
+            _ => None,
+        }
+    }
+}
+
+/// A value in a view file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ViewValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<ViewItem>),
+}
+
+/// An element of an array in a view file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ViewItem {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+impl ViewValue {
+    /// Whether every number in the value is finite.
+    fn is_finite(&self) -> bool {
+        match self {
+            ViewValue::Float(x) => x.is_finite(),
+            ViewValue::Array(items) => items.iter().all(|item| match item {
+                ViewItem::Float(x) => x.is_finite(),
+                _ => true,
+            }),
+            _ => true,
+        }
+    }
+}
+
+impl Display for ViewValue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ViewValue::Bool(b) => write!(f, "{}", b),
+            ViewValue::Integer(n) => write!(f, "{}", n),
+            ViewValue::Float(x) => write!(f, "{}", x),
+            ViewValue::String(s) => write!(f, "{}", s),
+            ViewValue::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for ViewItem {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ViewItem::Bool(b) => write!(f, "{}", b),
+            ViewItem::Integer(n) => write!(f, "{}", n),
+            ViewItem::Float(x) => write!(f, "{}", x),
+            ViewItem::String(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// The keys and values of a view file, in order of key.
+pub fn parse(contents: &str, format: ViewFormat) -> Result<BTreeMap<String, ViewValue>, String> {
+    let entries: BTreeMap<String, ViewValue> = match format {
+        ViewFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string())?,
+        ViewFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string())?,
+    };
+
+    match entries.iter().find(|(_, value)| !value.is_finite()) {
+        Some((key, _)) => Err(format!("`{}` isn't a finite number", key)),
+        None => Ok(entries),
+    }
+}
+
+/// The command-line arguments that a view file's entries stand for.
+pub fn to_args(entries: &BTreeMap<String, ViewValue>) -> Vec<String> {
+    let mut args = Vec::new();
+
+    for (key, value) in entries {
+        let option = if key.chars().count() == 1 {
+            format!("-{}", key)
+        } else {
+            format!("--{}", key.replace('_', "-"))
+        };
+
+        match value {
+            ViewValue::Bool(true) => args.push(option),
+            ViewValue::Bool(false) => (),
+            value => {
+                args.push(option);
+                args.push(value.to_string());
+            }
+        }
+    }
+
+    args
+}